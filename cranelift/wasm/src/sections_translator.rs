@@ -38,7 +38,7 @@ fn entity_type(
         ImportSectionEntryType::Function(sig) => EntityType::Function(TypeIndex::from_u32(sig)),
         ImportSectionEntryType::Module(sig) => EntityType::Module(TypeIndex::from_u32(sig)),
         ImportSectionEntryType::Instance(sig) => EntityType::Instance(TypeIndex::from_u32(sig)),
-        ImportSectionEntryType::Memory(ty) => EntityType::Memory(memory(ty)),
+        ImportSectionEntryType::Memory(ty) => EntityType::Memory(memory(ty)?),
         ImportSectionEntryType::Global(ty) => {
             EntityType::Global(global(ty, environ, GlobalInit::Import)?)
         }
@@ -46,16 +46,30 @@ fn entity_type(
     })
 }
 
-fn memory(ty: MemoryType) -> Memory {
-    match ty {
+fn memory(ty: MemoryType) -> WasmResult<Memory> {
+    Ok(match ty {
         MemoryType::M32 { limits, shared } => Memory {
             minimum: limits.initial,
             maximum: limits.maximum,
             shared: shared,
+            memory64: false,
         },
-        // FIXME(#2361)
-        MemoryType::M64 { .. } => unimplemented!(),
-    }
+        // Page counts are still tracked as 32-bit numbers, which is plenty: 2^32 pages are
+        // already 256 TiB of memory.
+        MemoryType::M64 { limits, .. } => {
+            let pages = |pages: u64| {
+                u32::try_from(pages).map_err(|_| {
+                    wasm_unsupported!("64-bit memory with more than 2^32 pages: {}", pages)
+                })
+            };
+            Memory {
+                minimum: pages(limits.initial)?,
+                maximum: limits.maximum.map(pages).transpose()?,
+                shared: false,
+                memory64: true,
+            }
+        }
+    })
 }
 
 fn table(ty: TableType, environ: &mut dyn ModuleEnvironment<'_>) -> WasmResult<Table> {
@@ -220,7 +234,7 @@ pub fn parse_memory_section(
     environ.reserve_memories(memories.get_count())?;
 
     for entry in memories {
-        let memory = memory(entry?);
+        let memory = memory(entry?)?;
         environ.declare_memory(memory)?;
     }
 
@@ -386,6 +400,12 @@ pub fn parse_data_section<'data>(
                 let mut init_expr_reader = init_expr.get_binary_reader();
                 let (base, offset) = match init_expr_reader.read_operator()? {
                     Operator::I32Const { value } => (None, value as u32 as usize),
+                    Operator::I64Const { value } => (
+                        None,
+                        usize::try_from(value as u64).map_err(|_| {
+                            wasm_unsupported!("data segment offset out of range: {}", value)
+                        })?,
+                    ),
                     Operator::GlobalGet { global_index } => {
                         (Some(GlobalIndex::from_u32(global_index)), 0)
                     }
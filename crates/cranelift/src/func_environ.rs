@@ -272,6 +272,16 @@ impl<'module_environment> FuncEnvironment<'module_environment> {
         )
     }
 
+    /// The bulk memory libcalls only take 32-bit addresses and lengths.
+    fn check_bulk_memory_op(&self, index: MemoryIndex) -> WasmResult<()> {
+        if self.module.memory_plans[index].memory.memory64 {
+            return Err(WasmError::Unsupported(
+                "bulk memory operations on 64-bit memories".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Translates load of builtin function and returns a pair of values `vmctx`
     /// and address of the loaded function.
     fn translate_load_builtin_function_address(
@@ -902,6 +912,12 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
 
     fn make_heap(&mut self, func: &mut ir::Function, index: MemoryIndex) -> WasmResult<ir::Heap> {
         let pointer_type = self.pointer_type();
+        let memory64 = self.module.memory_plans[index].memory.memory64;
+        if memory64 && pointer_type != I64 {
+            return Err(WasmError::Unsupported(
+                "64-bit memories on 32-bit hosts".to_string(),
+            ));
+        }
 
         let (ptr, base_offset, current_length_offset) = {
             let vmctx = self.vmctx(func);
@@ -975,7 +991,7 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
             min_size: 0.into(),
             offset_guard_size,
             style: heap_style,
-            index_type: I32,
+            index_type: if memory64 { I64 } else { I32 },
         }))
     }
 
@@ -1171,13 +1187,37 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         _heap: ir::Heap,
         val: ir::Value,
     ) -> WasmResult<ir::Value> {
+        let memory64 = self.module.memory_plans[index].memory.memory64;
         let (func_sig, index_arg, func_idx) = self.get_memory_grow_func(&mut pos.func, index);
         let memory_index = pos.ins().iconst(I32, index_arg as i64);
         let (vmctx, func_addr) = self.translate_load_builtin_function_address(&mut pos, func_idx);
+
+        // The libcalls count pages with 32 bits. A 64-bit delta that doesn't fit is clamped to
+        // `u32::MAX`, which can never succeed either.
+        let delta = if memory64 {
+            let too_large =
+                pos.ins()
+                    .icmp_imm(IntCC::UnsignedGreaterThan, val, i64::from(u32::MAX));
+            let clamped = pos.ins().iconst(I32, i64::from(u32::MAX));
+            let delta = pos.ins().ireduce(I32, val);
+            pos.ins().select(too_large, clamped, delta)
+        } else {
+            val
+        };
         let call_inst = pos
             .ins()
-            .call_indirect(func_sig, func_addr, &[vmctx, val, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+            .call_indirect(func_sig, func_addr, &[vmctx, delta, memory_index]);
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if !memory64 {
+            return Ok(result);
+        }
+
+        // Failure is reported as `u32::MAX`, which has to become a 64-bit -1. No memory can
+        // have `u32::MAX` pages, so it's never a previous size.
+        let failed = pos.ins().icmp_imm(IntCC::Equal, result, -1);
+        let minus_one = pos.ins().iconst(I64, -1);
+        let result = pos.ins().uextend(I64, result);
+        Ok(pos.ins().select(failed, minus_one, result))
     }
 
     fn translate_memory_size(
@@ -1192,7 +1232,12 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         let call_inst = pos
             .ins()
             .call_indirect(func_sig, func_addr, &[vmctx, memory_index]);
-        Ok(*pos.func.dfg.inst_results(call_inst).first().unwrap())
+        let result = *pos.func.dfg.inst_results(call_inst).first().unwrap();
+        if self.module.memory_plans[index].memory.memory64 {
+            Ok(pos.ins().uextend(I64, result))
+        } else {
+            Ok(result)
+        }
     }
 
     fn translate_memory_copy(
@@ -1206,6 +1251,8 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        self.check_bulk_memory_op(src_index)?;
+        self.check_bulk_memory_op(dst_index)?;
         let src_index = pos.ins().iconst(I32, i64::from(src_index.as_u32()));
         let dst_index = pos.ins().iconst(I32, i64::from(dst_index.as_u32()));
 
@@ -1231,6 +1278,7 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         val: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        self.check_bulk_memory_op(memory_index)?;
         let (func_sig, memory_index, func_idx) =
             self.get_memory_fill_func(&mut pos.func, memory_index);
 
@@ -1257,6 +1305,7 @@ impl<'module_environment> cranelift_wasm::FuncEnvironment for FuncEnvironment<'m
         src: ir::Value,
         len: ir::Value,
     ) -> WasmResult<()> {
+        self.check_bulk_memory_op(memory_index)?;
         let (func_sig, func_idx) = self.get_memory_init_func(&mut pos.func);
 
         let memory_index_arg = pos.ins().iconst(I32, memory_index.index() as i64);
@@ -92,9 +92,10 @@ use crate::func_environ::{get_func_name, FuncEnvironment};
 use cranelift_codegen::ir::{self, ExternalName};
 use cranelift_codegen::machinst::buffer::MachSrcLoc;
 use cranelift_codegen::print_errors::pretty_error;
-use cranelift_codegen::{binemit, isa, Context};
+use cranelift_codegen::{binemit, isa, CodegenError, Context};
 use cranelift_wasm::{DefinedFuncIndex, FuncIndex, FuncTranslator};
 use std::convert::TryFrom;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Mutex;
 use wasmtime_environ::{
     CompileError, CompiledFunction, Compiler, FunctionAddressMap, FunctionBodyData,
@@ -415,29 +416,40 @@ impl Compiler for Cranelift {
         let mut reloc_sink = RelocSink::new(func_index);
         let mut trap_sink = TrapSink::new();
         let mut stack_map_sink = StackMapSink::default();
-        context
-            .compile_and_emit(
+        // Panics from the code generator are reported as errors for this
+        // function, together with as much of its IR as can still be printed.
+        let emitted = panic::catch_unwind(AssertUnwindSafe(|| {
+            context.compile_and_emit(
                 isa,
                 &mut code_buf,
                 &mut reloc_sink,
                 &mut trap_sink,
                 &mut stack_map_sink,
             )
-            .map_err(|error| {
-                CompileError::Codegen(pretty_error(&context.func, Some(isa), error))
-            })?;
+        }));
+        match emitted {
+            Ok(result) => {
+                result.map_err(|error| codegen_error(func_index, &context.func, isa, error))?
+            }
+            Err(payload) => {
+                let ir =
+                    panic::catch_unwind(AssertUnwindSafe(|| context.func.display(isa).to_string()))
+                        .ok();
+                return Err(CompileError::function_panicked(func_index, payload, ir));
+            }
+        };
 
-        let unwind_info = context.create_unwind_info(isa).map_err(|error| {
-            CompileError::Codegen(pretty_error(&context.func, Some(isa), error))
-        })?;
+        let unwind_info = context
+            .create_unwind_info(isa)
+            .map_err(|error| codegen_error(func_index, &context.func, isa, error))?;
 
         let address_transform =
             get_function_address_map(&context, &input, code_buf.len() as u32, isa);
 
         let ranges = if tunables.debug_info {
-            let ranges = context.build_value_labels_ranges(isa).map_err(|error| {
-                CompileError::Codegen(pretty_error(&context.func, Some(isa), error))
-            })?;
+            let ranges = context
+                .build_value_labels_ranges(isa)
+                .map_err(|error| codegen_error(func_index, &context.func, isa, error))?;
             Some(ranges)
         } else {
             None
@@ -456,3 +468,16 @@ impl Compiler for Cranelift {
         })
     }
 }
+
+fn codegen_error(
+    func_index: FuncIndex,
+    func: &ir::Function,
+    isa: &dyn isa::TargetIsa,
+    error: CodegenError,
+) -> CompileError {
+    CompileError::Function {
+        index: func_index.as_u32(),
+        message: error.to_string(),
+        ir: Some(pretty_error(func, Some(isa), error)),
+    }
+}
@@ -6,6 +6,7 @@ use cranelift_codegen::{binemit, ir, isa, isa::unwind::UnwindInfo};
 use cranelift_entity::PrimaryMap;
 use cranelift_wasm::{DefinedFuncIndex, FuncIndex, WasmError};
 use serde::{Deserialize, Serialize};
+use std::any::Any;
 use thiserror::Error;
 
 #[allow(missing_docs)]
@@ -91,6 +92,61 @@ pub enum CompileError {
     /// A compilation error occured.
     #[error("Debug info is not supported with this configuration")]
     DebugInfoNotSupported,
+
+    /// The code generator failed on, or panicked while compiling, a function
+    /// of a valid module.
+    #[error("Compilation of wasm function {index} failed: {message}")]
+    Function {
+        /// The index of the function in the module's function index space.
+        index: u32,
+        /// The code generator's description of the failure.
+        message: String,
+        /// The function's IR at the point of failure, if it's available.
+        ir: Option<String>,
+    },
+
+    /// The machine code generated for the module exceeded the configured
+    /// limit.
+    #[error("Compiled code exceeds the limit of {limit} bytes")]
+    CodeSizeLimit {
+        /// The limit, in bytes.
+        limit: usize,
+    },
+
+    /// Compiling the module took more memory than the configured limit.
+    #[error("Compilation memory exceeds the limit of {limit} bytes")]
+    MemoryLimit {
+        /// The limit, in bytes.
+        limit: usize,
+    },
+
+    /// A compilation memory limit is configured, but memory can't be
+    /// accounted for because the global allocator doesn't support it.
+    #[error("Compilation memory can't be limited without `CompilationAllocator` as the global allocator")]
+    MemoryAccountingUnavailable,
+}
+
+impl CompileError {
+    /// Creates a `CompileError::Function` for a panic with the given `payload`
+    /// which was caught while compiling the function `index`.
+    pub fn function_panicked(
+        index: FuncIndex,
+        payload: Box<dyn Any + Send>,
+        ir: Option<String>,
+    ) -> CompileError {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+        CompileError::Function {
+            index: index.as_u32(),
+            message: format!("code generator panicked: {}", message),
+            ir,
+        }
+    }
 }
 
 /// An implementation of a compiler from parsed WebAssembly module to native
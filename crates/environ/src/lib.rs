@@ -47,6 +47,10 @@ pub const WASM_PAGE_SIZE: u32 = 0x10000;
 /// The number of pages we can have before we run out of byte index space.
 pub const WASM_MAX_PAGES: u32 = 0x10000;
 
+/// The number of pages a 64-bit memory can have, which is limited by page counts being 32-bit
+/// numbers rather than by its index space.
+pub const WASM64_MAX_PAGES: u32 = u32::MAX;
+
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -1,7 +1,7 @@
 //! Data structures for representing decoded wasm modules.
 
 use crate::tunables::Tunables;
-use crate::WASM_MAX_PAGES;
+use crate::{WASM64_MAX_PAGES, WASM_MAX_PAGES};
 use cranelift_entity::{EntityRef, PrimaryMap};
 use cranelift_wasm::{
     DataIndex, DefinedFuncIndex, DefinedGlobalIndex, DefinedMemoryIndex, DefinedTableIndex,
@@ -48,8 +48,13 @@ impl MemoryStyle {
         // A heap with a maximum that doesn't exceed the static memory bound specified by the
         // tunables make it static.
         //
-        // If the module doesn't declare an explicit maximum treat it as 4GiB.
-        let maximum = memory.maximum.unwrap_or(WASM_MAX_PAGES);
+        // If the module doesn't declare an explicit maximum treat it as 4GiB, or as unbounded
+        // for 64-bit memories.
+        let maximum = memory.maximum.unwrap_or(if memory.memory64 {
+            WASM64_MAX_PAGES
+        } else {
+            WASM_MAX_PAGES
+        });
         if maximum <= tunables.static_memory_bound {
             assert_ge!(tunables.static_memory_bound, memory.minimum);
             return (
@@ -175,6 +180,7 @@ pub struct Module {
     pub table_elements: Vec<TableElements>,
 
     /// WebAssembly passive elements.
+    #[serde(serialize_with = "sorted_map_serde::serialize")]
     pub passive_elements: HashMap<ElemIndex, Box<[FuncIndex]>>,
 
     /// WebAssembly passive data segments.
@@ -182,6 +188,7 @@ pub struct Module {
     pub passive_data: HashMap<DataIndex, Arc<[u8]>>,
 
     /// WebAssembly table initializers.
+    #[serde(serialize_with = "sorted_map_serde::serialize")]
     pub func_names: HashMap<FuncIndex, String>,
 
     /// Unprocessed signatures exactly as provided by `declare_signature()`.
@@ -219,6 +226,30 @@ pub struct Module {
 
     /// WebAssembly modules.
     pub modules: PrimaryMap<ModuleIndex, TypeIndex>,
+
+    /// Indices of defined functions which can't be reached from any export,
+    /// the start function, or an element segment, in ascending order.
+    ///
+    /// This is only computed when `Tunables::report_unused_functions` is
+    /// enabled, otherwise it's empty.
+    pub unused_functions: Vec<u32>,
+
+    /// The functions each defined function's body refers to.
+    ///
+    /// This is only recorded when `Tunables::record_call_graph` is enabled,
+    /// otherwise it's empty.
+    pub callees: PrimaryMap<DefinedFuncIndex, Callees>,
+}
+
+/// The functions a function's body refers to, and so may call.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Callees {
+    /// Functions referenced with `call`, `return_call` or `ref.func`, in the
+    /// order they appear, possibly with duplicates.
+    pub direct: Vec<FuncIndex>,
+    /// Whether the body contains a `call_indirect` or `return_call_indirect`,
+    /// which may call any function placed in a table.
+    pub indirect: bool,
 }
 
 /// Different forms an instance can take in a wasm module
@@ -261,6 +292,8 @@ impl Module {
             instances: PrimaryMap::new(),
             modules: PrimaryMap::new(),
             types: PrimaryMap::new(),
+            unused_functions: Vec::new(),
+            callees: PrimaryMap::new(),
         }
     }
 
@@ -375,6 +408,28 @@ impl Default for Module {
     }
 }
 
+/// Serializes a `HashMap` with its entries sorted by key, so that serialized
+/// modules don't depend on the map's randomized iteration order.
+mod sorted_map_serde {
+    use super::HashMap;
+    use serde::{ser::SerializeMap, Serialize, Serializer};
+
+    pub(super) fn serialize<K, V, S>(data: &HashMap<K, V>, ser: S) -> Result<S::Ok, S::Error>
+    where
+        K: Ord + Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        let mut entries = data.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(k, _)| *k);
+        let mut map = ser.serialize_map(Some(entries.len()))?;
+        for (k, v) in entries {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
 mod passive_data_serde {
     use super::{Arc, DataIndex, HashMap};
     use serde::{de::MapAccess, de::Visitor, ser::SerializeMap, Deserializer, Serializer};
@@ -387,8 +442,10 @@ mod passive_data_serde {
     where
         S: Serializer,
     {
-        let mut map = ser.serialize_map(Some(data.len()))?;
-        for (k, v) in data {
+        let mut entries = data.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(k, _)| *k);
+        let mut map = ser.serialize_map(Some(entries.len()))?;
+        for (k, v) in entries {
             map.serialize_entry(k, v.as_ref())?;
         }
         map.end()
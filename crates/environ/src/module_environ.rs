@@ -1,13 +1,13 @@
-use crate::module::{MemoryPlan, Module, ModuleType, TableElements, TablePlan};
+use crate::module::{Callees, Instance, MemoryPlan, Module, ModuleType, TableElements, TablePlan};
 use crate::tunables::Tunables;
 use cranelift_codegen::ir;
 use cranelift_codegen::ir::{AbiParam, ArgumentPurpose};
 use cranelift_codegen::isa::TargetFrontendConfig;
-use cranelift_entity::PrimaryMap;
+use cranelift_entity::{EntityRef, PrimaryMap};
 use cranelift_wasm::{
     self, translate_module, DataIndex, DefinedFuncIndex, ElemIndex, EntityIndex, EntityType,
-    FuncIndex, Global, GlobalIndex, Memory, MemoryIndex, SignatureIndex, Table, TableIndex,
-    TargetEnvironment, TypeIndex, WasmError, WasmFuncType, WasmResult,
+    FuncIndex, Global, GlobalIndex, GlobalInit, Memory, MemoryIndex, SignatureIndex, Table,
+    TableIndex, TargetEnvironment, TypeIndex, WasmError, WasmFuncType, WasmResult,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -16,7 +16,7 @@ use std::mem;
 use std::path::PathBuf;
 use std::sync::Arc;
 use wasmparser::Type as WasmType;
-use wasmparser::{FuncValidator, FunctionBody, ValidatorResources, WasmFeatures};
+use wasmparser::{FuncValidator, FunctionBody, Operator, ValidatorResources, WasmFeatures};
 
 /// Object containing the standalone environment information.
 pub struct ModuleEnvironment<'data> {
@@ -506,6 +506,24 @@ impl<'data> cranelift_wasm::ModuleEnvironment<'data> for ModuleEnvironment<'data
                     params: sig.params.iter().cloned().map(|i| i.into()).collect(),
                 });
         }
+        if self.tunables.report_unused_functions || self.tunables.record_call_graph {
+            let mut callees = Callees::default();
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                match reader.read()? {
+                    Operator::Call { function_index }
+                    | Operator::ReturnCall { function_index }
+                    | Operator::RefFunc { function_index } => {
+                        callees.direct.push(FuncIndex::from_u32(function_index));
+                    }
+                    Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => {
+                        callees.indirect = true;
+                    }
+                    _ => {}
+                }
+            }
+            self.result.module.callees.push(callees);
+        }
         self.result
             .function_body_inputs
             .push(FunctionBodyData { validator, body });
@@ -640,12 +658,88 @@ and for re-adding support for interface types you can see this issue:
                 ModuleTranslation::default()
             }
         };
-        let finished = mem::replace(&mut self.result, to_continue);
+        let mut finished = mem::replace(&mut self.result, to_continue);
+        if self.tunables.report_unused_functions {
+            finished.module.unused_functions = unused_functions(&finished.module);
+        }
+        // Only keep the call graph if it was asked for, since it's serialized
+        // along with the module.
+        if !self.tunables.record_call_graph {
+            finished.module.callees = PrimaryMap::new();
+        }
         self.result.submodules.push(self.results.len());
         self.results.push(finished);
     }
 }
 
+/// Computes the indices of the defined functions in `module` which aren't
+/// transitively reachable from any of its roots: exported functions, the start
+/// function, functions placed in element segments, `ref.func` global
+/// initializers, and functions passed to nested instantiations.
+///
+/// Any function placed in an element segment is conservatively considered
+/// reachable since it may be the target of a `call_indirect`.
+fn unused_functions(module: &Module) -> Vec<u32> {
+    let mut reachable = vec![false; module.functions.len()];
+    let mut worklist = Vec::new();
+    let mut mark = |func: FuncIndex, worklist: &mut Vec<FuncIndex>| {
+        // Null element segment entries are represented with the reserved
+        // index, which lands out of bounds here.
+        if let Some(seen) = reachable.get_mut(func.index()) {
+            if !*seen {
+                *seen = true;
+                worklist.push(func);
+            }
+        }
+    };
+
+    let exported = module.exports.values();
+    let instantiated = module.instances.values().flat_map(|i| match i {
+        Instance::Instantiate { args, .. } => &args[..],
+        Instance::Import(_) => &[],
+    });
+    for entity in exported.chain(instantiated) {
+        if let EntityIndex::Function(func) = entity {
+            mark(*func, &mut worklist);
+        }
+    }
+    if let Some(func) = module.start_func {
+        mark(func, &mut worklist);
+    }
+    for segment in module.table_elements.iter() {
+        for func in segment.elements.iter() {
+            mark(*func, &mut worklist);
+        }
+    }
+    for segment in module.passive_elements.values() {
+        for func in segment.iter() {
+            mark(*func, &mut worklist);
+        }
+    }
+    for global in module.globals.values() {
+        if let GlobalInit::RefFunc(func) = global.initializer {
+            mark(func, &mut worklist);
+        }
+    }
+
+    while let Some(func) = worklist.pop() {
+        let defined = match module.defined_func_index(func) {
+            Some(defined) => defined,
+            None => continue,
+        };
+        if let Some(callees) = module.callees.get(defined) {
+            for callee in callees.direct.iter() {
+                mark(*callee, &mut worklist);
+            }
+        }
+    }
+
+    (module.num_imported_funcs..module.functions.len())
+        .filter(|i| !reachable[*i])
+        .map(|i| i as u32)
+        .collect()
+}
+
 /// Add environment-specific function parameters.
 pub fn translate_signature(mut sig: ir::Signature, pointer_type: ir::Type) -> ir::Signature {
     // Prepend the vmctx argument.
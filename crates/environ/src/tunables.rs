@@ -20,6 +20,14 @@ pub struct Tunables {
     /// calls and interrupts are implemented through the `VMInterrupts`
     /// structure, or `InterruptHandle` in the `wasmtime` crate.
     pub interruptable: bool,
+
+    /// Whether or not to compute which defined functions are unreachable from
+    /// the module's exports, start function, and element segments.
+    pub report_unused_functions: bool,
+
+    /// Whether or not to record the functions each defined function refers
+    /// to in `Module::callees`.
+    pub record_call_graph: bool,
 }
 
 impl Default for Tunables {
@@ -53,6 +61,8 @@ impl Default for Tunables {
 
             debug_info: false,
             interruptable: false,
+            report_unused_functions: false,
+            record_call_graph: false,
         }
     }
 }
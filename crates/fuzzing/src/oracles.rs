@@ -18,6 +18,7 @@ use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
 use std::time::Duration;
 use wasmtime::*;
+use wasmtime_environ::CompileError;
 use wasmtime_wast::WastContext;
 
 static CNT: AtomicUsize = AtomicUsize::new(0);
@@ -71,13 +72,7 @@ pub fn instantiate_with_config(wasm: &[u8], mut config: Config, timeout: Option<
     let engine = Engine::new(&config);
     let store = Store::new(&engine);
 
-    if let Some(timeout) = timeout {
-        let handle = store.interrupt_handle().unwrap();
-        std::thread::spawn(move || {
-            std::thread::sleep(timeout);
-            handle.interrupt();
-        });
-    }
+    let _timer = timeout.map(|timeout| store.interrupt_handle().unwrap().interrupt_after(timeout));
 
     log_wasm(wasm);
     let module = match Module::new(&engine, wasm) {
@@ -102,8 +97,8 @@ pub fn instantiate_with_config(wasm: &[u8], mut config: Config, timeout: Option<
     let _result = Instance::new(&store, &module, &imports);
 }
 
-/// Compile the Wasm buffer, and implicitly fail if we have an unexpected
-/// panic or segfault or anything else that can be detected "passively".
+/// Compile the Wasm buffer, and fail if compiling a valid module fails, or if
+/// we have a segfault or anything else that can be detected "passively".
 ///
 /// Performs initial validation, and returns early if the Wasm is invalid.
 ///
@@ -113,7 +108,15 @@ pub fn compile(wasm: &[u8], strategy: Strategy) {
 
     let engine = Engine::new(&crate::fuzz_default_config(strategy).unwrap());
     log_wasm(wasm);
-    let _ = Module::new(&engine, wasm);
+    if let Err(e) = Module::new(&engine, wasm) {
+        // Invalid modules are expected, but the code generator failing on, or
+        // panicking while compiling, a function of a valid one is a bug.
+        for cause in e.chain() {
+            if let Some(CompileError::Function { .. }) = cause.downcast_ref() {
+                panic!("failed to compile module: {:?}", e);
+            }
+        }
+    }
 }
 
 /// Instantiate the given Wasm module with each `Config` and call all of its
@@ -39,6 +39,27 @@ impl CodeMemoryEntry {
         let end = start + self.len;
         (start, end)
     }
+
+    /// Revokes execute permission from this entry's pages and zeroes them.
+    ///
+    /// This runs on drop, so if the pages can't be made writable the failure
+    /// is logged and they're unmapped without being zeroed.
+    fn wipe(&mut self) {
+        if self.mmap.is_empty() {
+            return;
+        }
+        unsafe {
+            if let Err(e) = region::protect(
+                self.mmap.as_mut_ptr(),
+                self.mmap.len(),
+                region::Protection::READ_WRITE,
+            ) {
+                log::warn!("unable to make code memory writable to wipe it: {}", e);
+                return;
+            }
+            std::ptr::write_bytes(self.mmap.as_mut_ptr(), 0, self.mmap.len());
+        }
+    }
 }
 
 impl Drop for CodeMemoryEntry {
@@ -51,6 +72,16 @@ impl Drop for CodeMemoryEntry {
     }
 }
 
+impl Drop for CodeMemory {
+    fn drop(&mut self) {
+        if self.wipe_on_drop {
+            for entry in self.current.iter_mut().chain(self.entries.iter_mut()) {
+                entry.wipe();
+            }
+        }
+    }
+}
+
 pub(crate) struct CodeMemoryObjectAllocation<'a> {
     buf: &'a mut [u8],
     funcs: BTreeMap<FuncIndex, (usize, usize)>,
@@ -86,6 +117,7 @@ pub struct CodeMemory {
     current: Option<CodeMemoryEntry>,
     entries: Vec<CodeMemoryEntry>,
     published: usize,
+    wipe_on_drop: bool,
 }
 
 fn _assert() {
@@ -100,9 +132,16 @@ impl CodeMemory {
             current: None,
             entries: Vec::new(),
             published: 0,
+            wipe_on_drop: false,
         }
     }
 
+    /// Configures whether all code is made non-executable and zeroed before
+    /// its memory is unmapped when this `CodeMemory` is dropped.
+    pub fn set_wipe_on_drop(&mut self, wipe: bool) {
+        self.wipe_on_drop = wipe;
+    }
+
     /// Allocate a continuous memory block for a single compiled function.
     /// TODO: Reorganize the code that calls this to emit code directly into the
     /// mmap region rather than into a Vec that we need to copy in.
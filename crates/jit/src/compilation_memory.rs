@@ -0,0 +1,144 @@
+//! Accounting of the memory allocated while compiling.
+//!
+//! Memory can only be accounted for if every allocation goes through
+//! [`CompilationAllocator`], so it has to be the program's global allocator.
+//! It forwards to another allocator, and while a thread is compiling a
+//! function it charges the bytes that thread allocates and frees to the
+//! [`MemoryBudget`] of the module being compiled.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+
+/// A global allocator which lets wasmtime account for the memory taken by
+/// compilation, which is what `Config::compilation_memory_limit` needs.
+///
+/// It forwards every allocation to the allocator it wraps, [`System`] by
+/// default, and adds a thread-local lookup to each one.
+///
+/// ```
+/// use std::alloc::System;
+/// use wasmtime_jit::CompilationAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CompilationAllocator = CompilationAllocator::new(System);
+/// # fn main() {}
+/// ```
+#[derive(Debug, Default)]
+pub struct CompilationAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> CompilationAllocator<A> {
+    /// Creates an allocator which forwards to `inner`.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static CURRENT: Cell<*const MemoryBudget> = Cell::new(ptr::null());
+}
+
+fn charge(bytes: isize) {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+    // The thread-local may already be gone while the thread is exiting, in
+    // which case it isn't compiling anything.
+    let _ = CURRENT.try_with(|current| {
+        let budget = current.get();
+        if !budget.is_null() {
+            // The budget outlives every `MemoryBudget::enter` which makes it
+            // current.
+            unsafe { (*budget).charge(bytes) }
+        }
+    });
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CompilationAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            charge(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            charge(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        charge(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            charge(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+/// Returns whether [`CompilationAllocator`] is the global allocator, as far
+/// as can be told from it having allocated anything.
+pub(crate) fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+/// The memory which compiling a module may take.
+///
+/// What's charged is the bytes allocated minus the bytes freed by the threads
+/// compiling the module's functions, while they compile them. Memory freed by
+/// other threads, or after compiling a function, such as that of the compiled
+/// code, isn't taken off, so the total errs on the high side.
+pub(crate) struct MemoryBudget {
+    limit: usize,
+    used: AtomicIsize,
+    exceeded: AtomicBool,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicIsize::new(0),
+            exceeded: AtomicBool::new(false),
+        }
+    }
+
+    fn charge(&self, bytes: isize) {
+        let used = self.used.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if used > 0 && used as usize > self.limit {
+            self.exceeded.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns whether the memory taken so far has ever been over the limit.
+    pub(crate) fn exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Runs `f`, charging the memory the current thread allocates and frees
+    /// while doing so to this budget.
+    pub(crate) fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Reset(*const MemoryBudget);
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                CURRENT.with(|current| current.set(self.0));
+            }
+        }
+        let _reset = Reset(CURRENT.with(|current| current.replace(self)));
+        f()
+    }
+}
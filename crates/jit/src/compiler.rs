@@ -1,20 +1,25 @@
 //! JIT compilation.
 
+use crate::compilation_memory::{self, MemoryBudget};
 use crate::instantiate::SetupError;
 use crate::object::{build_object, ObjectUnwindInfo};
 use object::write::Object;
 #[cfg(feature = "parallel-compilation")]
 use rayon::prelude::*;
+use std::fs;
 use std::hash::{Hash, Hasher};
 use std::mem;
-use wasmparser::WasmFeatures;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasmparser::{FunctionBody, WasmFeatures};
 use wasmtime_debug::{emit_dwarf, DwarfSection};
 use wasmtime_environ::entity::EntityRef;
 use wasmtime_environ::isa::{TargetFrontendConfig, TargetIsa};
-use wasmtime_environ::wasm::{DefinedMemoryIndex, MemoryIndex};
+use wasmtime_environ::wasm::{DefinedFuncIndex, DefinedMemoryIndex, FuncIndex, MemoryIndex};
 use wasmtime_environ::{
-    CompiledFunctions, Compiler as EnvCompiler, DebugInfoData, Module, ModuleMemoryOffset,
-    ModuleTranslation, Tunables, VMOffsets,
+    CompileError, CompiledFunctions, Compiler as EnvCompiler, DebugInfoData, FunctionBodyData,
+    Module, ModuleMemoryOffset, ModuleTranslation, Tunables, VMOffsets,
 };
 
 /// Select which kind of compilation to use.
@@ -45,6 +50,10 @@ pub struct Compiler {
     strategy: CompilationStrategy,
     tunables: Tunables,
     features: WasmFeatures,
+    ir_dump_dir: Option<PathBuf>,
+    max_code_size: Option<usize>,
+    compilation_memory_limit: Option<usize>,
+    parallel_compilation: bool,
 }
 
 impl Compiler {
@@ -67,8 +76,50 @@ impl Compiler {
             },
             tunables,
             features,
+            ir_dump_dir: None,
+            max_code_size: None,
+            compilation_memory_limit: None,
+            parallel_compilation: true,
         }
     }
+
+    /// Sets the directory into which functions that fail to compile are
+    /// dumped, or `None` to not dump them.
+    ///
+    /// For each such function its body, as found in the code section, is
+    /// written to `wasm-function-<index>.bin` and its IR, if it's available,
+    /// to `wasm-function-<index>.clif`.
+    pub fn set_ir_dump_dir(&mut self, dir: Option<PathBuf>) {
+        self.ir_dump_dir = dir;
+    }
+
+    /// Sets the maximum number of bytes of machine code which may be
+    /// generated for a single module, or `None` for no limit.
+    ///
+    /// Compilation fails with `CompileError::CodeSizeLimit` as soon as the
+    /// functions compiled so far exceed the limit.
+    pub fn set_max_code_size(&mut self, limit: Option<usize>) {
+        self.max_code_size = limit;
+    }
+
+    /// Sets the maximum number of bytes of memory which compiling the
+    /// functions of a single module may take, or `None` for no limit.
+    ///
+    /// The memory is accounted for by `CompilationAllocator`, which has to be
+    /// the global allocator for this to work. Compilation fails with
+    /// `CompileError::MemoryLimit` once a function finishes compiling with the
+    /// memory taken over the limit at any point so far.
+    pub fn set_compilation_memory_limit(&mut self, limit: Option<usize>) {
+        self.compilation_memory_limit = limit;
+    }
+
+    /// Sets whether functions are compiled in parallel, which they are by
+    /// default if the `parallel-compilation` feature is enabled.
+    ///
+    /// The compiled code is the same either way.
+    pub fn set_parallel_compilation(&mut self, enable: bool) {
+        self.parallel_compilation = enable;
+    }
 }
 
 fn _assert_compiler_send_sync() {
@@ -95,6 +146,26 @@ fn transform_dwarf_data(
     emit_dwarf(isa, debug_data, funcs, &memory_offset).map_err(SetupError::DebugInfo)
 }
 
+fn dump_function(dir: &Path, index: FuncIndex, body: &FunctionBody, ir: Option<&str>) {
+    let mut reader = body.get_binary_reader();
+    let bytes = reader.read_bytes(reader.bytes_remaining()).unwrap_or(&[]);
+    let path = dir.join(format!("wasm-function-{}", index.as_u32()));
+    let result = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(path.with_extension("bin"), bytes))
+        .and_then(|()| match ir {
+            Some(ir) => fs::write(path.with_extension("clif"), ir),
+            None => Ok(()),
+        });
+    if let Err(e) = result {
+        log::warn!(
+            "failed to dump wasm function {} to {}: {}",
+            index.as_u32(),
+            dir.display(),
+            e
+        );
+    }
+}
+
 #[allow(missing_docs)]
 pub struct Compilation {
     pub obj: Object,
@@ -123,6 +194,11 @@ impl Compiler {
         &self.features
     }
 
+    /// Return whether functions are compiled in parallel.
+    pub fn parallel_compilation(&self) -> bool {
+        cfg!(feature = "parallel-compilation") && self.parallel_compilation
+    }
+
     /// Compile the given function bodies.
     pub fn compile<'data>(
         &self,
@@ -130,14 +206,76 @@ impl Compiler {
     ) -> Result<Compilation, SetupError> {
         let functions = mem::take(&mut translation.function_body_inputs);
         let functions = functions.into_iter().collect::<Vec<_>>();
-        let funcs = maybe_parallel!(functions.(into_iter | into_par_iter))
-            .map(|(index, func)| {
-                self.compiler
-                    .compile_function(translation, index, func, &*self.isa, &self.tunables)
-            })
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .collect::<CompiledFunctions>();
+        let code_size = AtomicUsize::new(0);
+        let memory = match self.compilation_memory_limit {
+            Some(_) if !compilation_memory::is_installed() => {
+                return Err(CompileError::MemoryAccountingUnavailable.into());
+            }
+            Some(limit) => Some(MemoryBudget::new(limit)),
+            None => None,
+        };
+        let memory_exceeded = || match (&memory, self.compilation_memory_limit) {
+            (Some(memory), Some(limit)) if memory.exceeded() => {
+                Err(CompileError::MemoryLimit { limit })
+            }
+            _ => Ok(()),
+        };
+        let compile_function = |(index, func): (DefinedFuncIndex, FunctionBodyData<'_>)| {
+            if let Some(limit) = self.max_code_size {
+                if code_size.load(Ordering::Relaxed) > limit {
+                    return Err(CompileError::CodeSizeLimit { limit });
+                }
+            }
+            memory_exceeded()?;
+            let func_index = translation.module.func_index(index);
+            let body = func.body.clone();
+            // Compilers are expected to report failures as errors, but a
+            // panic in one is still reported as a failure of this function.
+            let compile = || {
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    self.compiler.compile_function(
+                        translation,
+                        index,
+                        func,
+                        &*self.isa,
+                        &self.tunables,
+                    )
+                }))
+                .unwrap_or_else(|payload| {
+                    Err(CompileError::function_panicked(func_index, payload, None))
+                })
+            };
+            let result = match &memory {
+                Some(memory) => memory.enter(compile),
+                None => compile(),
+            };
+            memory_exceeded()?;
+            if let (Err(CompileError::Function { ir, .. }), Some(dir)) =
+                (&result, &self.ir_dump_dir)
+            {
+                dump_function(dir, func_index, &body, ir.as_deref());
+            }
+            let func = result?;
+            let total = code_size.fetch_add(func.body.len(), Ordering::Relaxed) + func.body.len();
+            match self.max_code_size {
+                Some(limit) if total > limit => Err(CompileError::CodeSizeLimit { limit }),
+                _ => Ok(func),
+            }
+        };
+        // Functions are collected in order either way, so the compiled code
+        // doesn't depend on whether they're compiled in parallel.
+        let funcs = if self.parallel_compilation() {
+            maybe_parallel!(functions.(into_iter | into_par_iter))
+                .map(&compile_function)
+                .collect::<Result<Vec<_>, _>>()
+        } else {
+            functions
+                .into_iter()
+                .map(&compile_function)
+                .collect::<Result<Vec<_>, _>>()
+        }?
+        .into_iter()
+        .collect::<CompiledFunctions>();
 
         let dwarf_sections = if self.tunables.debug_info && !funcs.is_empty() {
             transform_dwarf_data(
@@ -168,6 +306,12 @@ impl Hash for Compiler {
             isa,
             tunables,
             features,
+            // Only used when compilation fails.
+            ir_dump_dir: _,
+            max_code_size,
+            compilation_memory_limit,
+            // Doesn't change the compiled code.
+            parallel_compilation: _,
         } = self;
 
         // Hash compiler's flags: compilation strategy, isa, frontend config,
@@ -182,6 +326,10 @@ impl Hash for Compiler {
         isa.flags().to_string().hash(hasher);
         isa.frontend_config().hash(hasher);
         tunables.hash(hasher);
+        // A module compiled without limits, or with looser ones, may not
+        // compile under these, so it mustn't be loaded from the cache.
+        max_code_size.hash(hasher);
+        compilation_memory_limit.hash(hasher);
 
         // Catch accidental bugs of reusing across crate versions.
         env!("CARGO_PKG_VERSION").hash(hasher);
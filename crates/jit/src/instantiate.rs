@@ -12,6 +12,7 @@ use object::File as ObjectFile;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::rc::Rc;
 use std::sync::Arc;
 use thiserror::Error;
 use wasmtime_debug::create_gdbjit_image;
@@ -24,9 +25,9 @@ use wasmtime_environ::{
 };
 use wasmtime_profiling::ProfilingAgent;
 use wasmtime_runtime::{
-    GdbJitImageRegistration, Imports, InstanceHandle, InstantiationError, RuntimeMemoryCreator,
-    StackMapRegistry, VMExternRefActivationsTable, VMFunctionBody, VMInterrupts,
-    VMSharedSignatureIndex, VMTrampoline,
+    GdbJitImageRegistration, Imports, InstanceHandle, InstancePool, InstantiationError,
+    ResourceLimiter, RuntimeMemoryCreator, StackMapRegistry, VMExternRefActivationsTable,
+    VMFunctionBody, VMInterrupts, VMSharedSignatureIndex, VMTrampoline,
 };
 
 /// An error condition while setting up a wasm instance, be it validation,
@@ -87,49 +88,57 @@ impl CompilationArtifacts {
         .translate(data)
         .map_err(|error| SetupError::Compile(CompileError::Wasm(error)))?;
 
-        maybe_parallel!(translations.(into_iter | into_par_iter))
-            .map(|mut translation| {
-                let Compilation {
-                    obj,
-                    unwind_info,
-                    funcs,
-                } = compiler.compile(&mut translation)?;
-
-                let ModuleTranslation {
-                    module,
-                    data_initializers,
-                    ..
-                } = translation;
-
-                let data_initializers = data_initializers
+        let build = |mut translation: ModuleTranslation<'_>| -> Result<_, SetupError> {
+            let Compilation {
+                obj,
+                unwind_info,
+                funcs,
+            } = compiler.compile(&mut translation)?;
+
+            let ModuleTranslation {
+                module,
+                data_initializers,
+                ..
+            } = translation;
+
+            let data_initializers = data_initializers
+                .into_iter()
+                .map(OwnedDataInitializer::new)
+                .collect::<Vec<_>>()
+                .into_boxed_slice();
+
+            let obj = obj.write().map_err(|_| {
+                SetupError::Instantiate(InstantiationError::Resource(
+                    "failed to create image memory".to_string(),
+                ))
+            })?;
+
+            Ok(CompilationArtifacts {
+                module,
+                obj: obj.into_boxed_slice(),
+                unwind_info: unwind_info.into_boxed_slice(),
+                data_initializers,
+                funcs: funcs
                     .into_iter()
-                    .map(OwnedDataInitializer::new)
-                    .collect::<Vec<_>>()
-                    .into_boxed_slice();
-
-                let obj = obj.write().map_err(|_| {
-                    SetupError::Instantiate(InstantiationError::Resource(
-                        "failed to create image memory".to_string(),
-                    ))
-                })?;
-
-                Ok(CompilationArtifacts {
-                    module,
-                    obj: obj.into_boxed_slice(),
-                    unwind_info: unwind_info.into_boxed_slice(),
-                    data_initializers,
-                    funcs: funcs
-                        .into_iter()
-                        .map(|(_, func)| FunctionInfo {
-                            stack_maps: func.stack_maps,
-                            traps: func.traps,
-                            address_map: func.address_map,
-                        })
-                        .collect(),
-                    debug_info: compiler.tunables().debug_info,
-                })
+                    .map(|(_, func)| FunctionInfo {
+                        stack_maps: func.stack_maps,
+                        traps: func.traps,
+                        address_map: func.address_map,
+                    })
+                    .collect(),
+                debug_info: compiler.tunables().debug_info,
             })
-            .collect::<Result<Vec<_>, SetupError>>()
+        };
+        if compiler.parallel_compilation() {
+            maybe_parallel!(translations.(into_iter | into_par_iter))
+                .map(&build)
+                .collect::<Result<Vec<_>, SetupError>>()
+        } else {
+            translations
+                .into_iter()
+                .map(&build)
+                .collect::<Result<Vec<_>, SetupError>>()
+        }
     }
 }
 
@@ -168,21 +177,26 @@ impl CompiledModule {
         artifacts: Vec<CompilationArtifacts>,
         isa: &dyn TargetIsa,
         profiler: &dyn ProfilingAgent,
+        wipe_code_on_drop: bool,
     ) -> Result<Vec<Self>, SetupError> {
         maybe_parallel!(artifacts.(into_iter | into_par_iter))
-            .map(|a| CompiledModule::from_artifacts(a, isa, profiler))
+            .map(|a| CompiledModule::from_artifacts(a, isa, profiler, wipe_code_on_drop))
             .collect()
     }
 
     /// Creates `CompiledModule` directly from `CompilationArtifacts`.
+    ///
+    /// If `wipe_code_on_drop` is set the module's code is made non-executable
+    /// and zeroed before it's unmapped.
     pub fn from_artifacts(
         artifacts: CompilationArtifacts,
         isa: &dyn TargetIsa,
         profiler: &dyn ProfilingAgent,
+        wipe_code_on_drop: bool,
     ) -> Result<Self, SetupError> {
         // Allocate all of the compiled functions into executable memory,
         // copying over their contents.
-        let (code_memory, code_range, finished_functions, trampolines) = build_code_memory(
+        let (mut code_memory, code_range, finished_functions, trampolines) = build_code_memory(
             isa,
             &artifacts.obj,
             &artifacts.module,
@@ -194,6 +208,7 @@ impl CompiledModule {
                 message
             )))
         })?;
+        code_memory.set_wipe_on_drop(wipe_code_on_drop);
 
         // Register GDB JIT images; initialize profiler and load the wasm module.
         let dbg_jit_registration = if artifacts.debug_info {
@@ -210,6 +225,16 @@ impl CompiledModule {
             profiler.module_load(&artifacts.module, &finished_functions, None);
             None
         };
+        profiler.trampolines_load(&artifacts.module, &trampolines);
+
+        let trampolines = trampolines
+            .values()
+            .map(|fat_ptr| unsafe {
+                std::mem::transmute::<*const VMFunctionBody, VMTrampoline>(
+                    *fat_ptr as *const VMFunctionBody,
+                )
+            })
+            .collect();
 
         let finished_functions = FinishedFunctions(finished_functions);
 
@@ -239,21 +264,25 @@ impl CompiledModule {
         imports: Imports<'_>,
         lookup_shared_signature: &dyn Fn(SignatureIndex) -> VMSharedSignatureIndex,
         mem_creator: Option<&dyn RuntimeMemoryCreator>,
+        table_pool: Option<&InstancePool>,
         interrupts: *const VMInterrupts,
         host_state: Box<dyn Any>,
         externref_activations_table: *mut VMExternRefActivationsTable,
         stack_map_registry: *mut StackMapRegistry,
+        limiter: Option<Rc<dyn ResourceLimiter>>,
     ) -> Result<InstanceHandle, InstantiationError> {
         InstanceHandle::new(
             self.module.clone(),
             &self.finished_functions.0,
             imports,
             mem_creator,
+            table_pool,
             lookup_shared_signature,
             host_state,
             interrupts,
             externref_activations_table,
             stack_map_registry,
+            limiter,
         )
     }
     /// Extracts `CompilationArtifacts` from the compiled module.
@@ -382,7 +411,7 @@ fn build_code_memory(
         CodeMemory,
         (*const u8, usize),
         PrimaryMap<DefinedFuncIndex, *mut [VMFunctionBody]>,
-        PrimaryMap<SignatureIndex, VMTrampoline>,
+        PrimaryMap<SignatureIndex, *mut [VMFunctionBody]>,
     ),
     String,
 > {
@@ -404,8 +433,7 @@ fn build_code_memory(
 
     let mut trampolines = PrimaryMap::new();
     for (i, fat_ptr) in allocation.trampolines() {
-        let fat_ptr =
-            unsafe { std::mem::transmute::<*const VMFunctionBody, VMTrampoline>(fat_ptr.as_ptr()) };
+        let fat_ptr: *mut [VMFunctionBody] = fat_ptr;
         assert_eq!(trampolines.push(fat_ptr), i);
     }
 
@@ -35,6 +35,7 @@ macro_rules! maybe_parallel {
 }
 
 mod code_memory;
+mod compilation_memory;
 mod compiler;
 mod instantiate;
 mod link;
@@ -45,6 +46,7 @@ pub mod native;
 pub mod trampoline;
 
 pub use crate::code_memory::CodeMemory;
+pub use crate::compilation_memory::CompilationAllocator;
 pub use crate::compiler::{Compilation, CompilationStrategy, Compiler};
 pub use crate::instantiate::{CompilationArtifacts, CompiledModule, ModuleCode, SetupError};
 pub use crate::link::link_module;
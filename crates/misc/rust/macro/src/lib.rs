@@ -50,32 +50,27 @@ fn generate_load(item: &syn::ItemTrait) -> syn::Result<TokenStream> {
         }
 
         #vis fn load_bytes(bytes: impl AsRef<[u8]>) -> #root::anyhow::Result<#name> {
-            use #root::wasmtime::{Config, Extern, Engine, Store, Instance, Module};
-            use #root::anyhow::{bail, format_err};
+            use #root::wasmtime::{Linker, Module, Store};
 
             let store = Store::default();
 
             let data = #root::wasmtime_interface_types::ModuleData::new(bytes.as_ref())?;
 
-            let module = Module::new(&engine, bytes.as_ref())?;
+            let module = Module::new(store.engine(), bytes.as_ref())?;
 
-            let mut imports: Vec<Extern> = Vec::new();
-            if let Some(module_name) = data.find_wasi_module_name() {
+            // Imports are resolved by name, and the linker's error names any
+            // which can't be. Both WASI snapshots are provided, under their
+            // own module names, so modules built against `wasi_unstable`
+            // keep working.
+            let mut linker = Linker::new(&store);
+            if data.find_wasi_module_name().is_some() {
                 let wasi_cx = #root::wasmtime_wasi::WasiCtxBuilder::new().build()?;
-                let wasi = #root::wasmtime_wasi::Wasi::new(&store, wasi_cx);
-                for i in module.imports().iter() {
-                    if i.module() != module_name {
-                        bail!("unknown import module {}", i.module());
-                    }
-                    if let Some(export) = wasi.get_export(i.name()) {
-                        imports.push(export.clone().into());
-                    } else {
-                        bail!("unknown import {}:{}", i.module(), i.name())
-                    }
-                }
+                #root::wasmtime_wasi::Wasi::new(&store, wasi_cx).add_to_linker(&mut linker)?;
+                let wasi_cx = #root::wasmtime_wasi::old::snapshot_0::WasiCtxBuilder::new().build()?;
+                #root::wasmtime_wasi::old::snapshot_0::Wasi::new(&store, wasi_cx)
+                    .add_to_linker(&mut linker)?;
             }
-            let instance =
-                Instance::new(&store, &module, &imports).map_err(|t| format_err!("instantiation trap: {:?}", t))?;
+            let instance = linker.instantiate(&module)?;
 
             Ok(#name { instance, data })
         }
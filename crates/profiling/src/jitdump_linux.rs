@@ -13,6 +13,7 @@
 
 use crate::ProfilingAgent;
 use anyhow::Result;
+use lazy_static::lazy_static;
 use object::{Object, ObjectSection};
 use scroll::{IOwrite, SizeWith, NATIVE};
 use serde::{Deserialize, Serialize};
@@ -22,11 +23,11 @@ use std::io;
 use std::io::Write;
 use std::os::unix::prelude::*;
 use std::ptr;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::{borrow, mem, process};
 use target_lexicon::Architecture;
 use wasmtime_environ::entity::PrimaryMap;
-use wasmtime_environ::wasm::DefinedFuncIndex;
+use wasmtime_environ::wasm::{DefinedFuncIndex, SignatureIndex};
 use wasmtime_environ::Module;
 use wasmtime_runtime::VMFunctionBody;
 
@@ -136,7 +137,15 @@ pub struct JitDumpAgent {
     // Note that we use a mutex internally to serialize writing out to our
     // `jitdump_file` within this process, since multiple threads may be sharing
     // this jit agent.
-    state: Mutex<State>,
+    state: Arc<Mutex<State>>,
+}
+
+lazy_static! {
+    /// The state of the `jit-<pid>.dump` file of this process, which all
+    /// agents share. It's created along with the first agent and kept until
+    /// the process exits, so that later agents don't truncate the records of
+    /// earlier ones.
+    static ref PROCESS_STATE: Mutex<Option<Arc<Mutex<State>>>> = Mutex::new(None);
 }
 
 struct State {
@@ -155,8 +164,25 @@ struct State {
 }
 
 impl JitDumpAgent {
-    /// Intialize a JitDumpAgent and write out the header
+    /// Intialize a JitDumpAgent, writing out the header if this is the first
+    /// one in this process
     pub fn new() -> Result<Self> {
+        let mut process_state = PROCESS_STATE.lock().unwrap();
+        let state = match &*process_state {
+            Some(state) => state.clone(),
+            None => {
+                let state = Arc::new(Mutex::new(State::new()?));
+                *process_state = Some(state.clone());
+                state
+            }
+        };
+        Ok(JitDumpAgent { state })
+    }
+}
+
+impl State {
+    /// Creates the jitdump file and writes out its header
+    fn new() -> Result<Self> {
         let filename = format!("./jit-{}.dump", process::id());
         let jitdump_file = OpenOptions::new()
             .read(true)
@@ -173,7 +199,7 @@ impl JitDumpAgent {
         // parse the file.
         //
         // To match what some perf examples are doing we keep this `mmap` alive
-        // until this agent goes away.
+        // until the process exits.
         let map_addr = unsafe {
             let ptr = libc::mmap(
                 ptr::null_mut(),
@@ -195,9 +221,7 @@ impl JitDumpAgent {
             dump_funcs: true,
         };
         state.write_file_header()?;
-        Ok(JitDumpAgent {
-            state: Mutex::new(state),
-        })
+        Ok(state)
     }
 }
 
@@ -213,6 +237,17 @@ impl ProfilingAgent for JitDumpAgent {
             .unwrap()
             .module_load(module, functions, dbg_image);
     }
+
+    fn trampolines_load(
+        &self,
+        module: &Module,
+        trampolines: &PrimaryMap<SignatureIndex, *mut [VMFunctionBody]>,
+    ) {
+        self.state
+            .lock()
+            .unwrap()
+            .trampolines_load(module, trampolines);
+    }
 }
 
 impl State {
@@ -326,6 +361,24 @@ impl State {
         }
     }
 
+    /// Sent when the trampolines of a module are loaded into memory.
+    pub fn trampolines_load(
+        &mut self,
+        module: &Module,
+        trampolines: &PrimaryMap<SignatureIndex, *mut [VMFunctionBody]>,
+    ) {
+        let pid = process::id();
+        let tid = pid;
+
+        for (idx, trampoline) in trampolines.iter() {
+            let (addr, len) =
+                unsafe { ((**trampoline).as_ptr() as *const u8, (**trampoline).len()) };
+            let timestamp = self.get_time_stamp();
+            let name = super::trampoline_name(module, idx);
+            self.dump_code_load_record(&name, addr, len, timestamp, pid, tid);
+        }
+    }
+
     fn dump_code_load_record(
         &mut self,
         method_name: &str,
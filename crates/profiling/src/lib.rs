@@ -1,7 +1,7 @@
 use std::error::Error;
 use std::fmt;
 use wasmtime_environ::entity::{EntityRef, PrimaryMap};
-use wasmtime_environ::wasm::DefinedFuncIndex;
+use wasmtime_environ::wasm::{DefinedFuncIndex, SignatureIndex};
 use wasmtime_environ::Module;
 use wasmtime_runtime::VMFunctionBody;
 
@@ -37,6 +37,15 @@ pub trait ProfilingAgent: Send + Sync + 'static {
         functions: &PrimaryMap<DefinedFuncIndex, *mut [VMFunctionBody]>,
         dbg_image: Option<&[u8]>,
     ) -> ();
+
+    /// Notify the profiler of the trampolines, one per signature, through
+    /// which the host calls into a new module's functions.
+    fn trampolines_load(
+        &self,
+        _module: &Module,
+        _trampolines: &PrimaryMap<SignatureIndex, *mut [VMFunctionBody]>,
+    ) {
+    }
 }
 
 /// Default agent for unsupported profiling build.
@@ -70,11 +79,32 @@ impl ProfilingAgent for NullProfilerAgent {
     }
 }
 
+/// Returns the name to record for the function `index` of `module`, which is
+/// its demangled name from the name section if it has one, and otherwise
+/// `wasm[<module>]::function[<index>]`.
 #[allow(dead_code)]
 fn debug_name(module: &Module, index: DefinedFuncIndex) -> String {
     let index = module.func_index(index);
     match module.func_names.get(&index) {
-        Some(s) => s.clone(),
-        None => format!("wasm::wasm-function[{}]", index.index()),
+        Some(s) => match rustc_demangle::try_demangle(s) {
+            Ok(name) => format!("{:#}", name),
+            Err(_) => s.clone(),
+        },
+        None => format!("{}::function[{}]", module_prefix(module), index.index()),
+    }
+}
+
+/// Returns the name to record for the trampoline for the signature `index` of
+/// `module`.
+#[allow(dead_code)]
+fn trampoline_name(module: &Module, index: SignatureIndex) -> String {
+    format!("{}::trampoline[{}]", module_prefix(module), index.index())
+}
+
+#[allow(dead_code)]
+fn module_prefix(module: &Module) -> String {
+    match &module.name {
+        Some(name) => format!("wasm[{}]", name),
+        None => "wasm".to_string(),
     }
 }
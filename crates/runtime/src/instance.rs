@@ -5,7 +5,9 @@
 use crate::export::Export;
 use crate::externref::{StackMapRegistry, VMExternRefActivationsTable};
 use crate::imports::Imports;
+use crate::limits::ResourceLimiter;
 use crate::memory::{DefaultMemoryCreator, RuntimeLinearMemory, RuntimeMemoryCreator};
+use crate::pool::InstancePool;
 use crate::table::{Table, TableElement};
 use crate::traphandlers::Trap;
 use crate::vmcontext::{
@@ -22,6 +24,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::ptr::NonNull;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::{mem, ptr, slice};
 use thiserror::Error;
@@ -62,6 +65,9 @@ pub(crate) struct Instance {
     /// Hosts can store arbitrary per-instance information here.
     host_state: Box<dyn Any>,
 
+    /// Consulted before any of this instance's memories or tables grow.
+    limiter: Option<Rc<dyn ResourceLimiter>>,
+
     /// Additional context used by compiled wasm code. This field is last, and
     /// represents a dynamically-sized array that extends beyond the nominal
     /// end of the struct (similar to a flexible array member).
@@ -344,6 +350,11 @@ impl Instance {
         &*self.host_state
     }
 
+    /// Return the resource limiter of this instance, if any.
+    pub(crate) fn limiter(&self) -> Option<&dyn ResourceLimiter> {
+        self.limiter.as_deref()
+    }
+
     /// Return the offset from the vmctx pointer to its containing Instance.
     #[inline]
     pub(crate) fn vmctx_offset() -> isize {
@@ -384,19 +395,32 @@ impl Instance {
 
     /// Grow memory by the specified amount of pages.
     ///
-    /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
-    pub(crate) fn memory_grow(&self, memory_index: DefinedMemoryIndex, delta: u32) -> Option<u32> {
-        let result = self
+    /// Returns `Ok(None)` if memory can't be grown by the specified amount
+    /// of pages, and an error if the growth was denied by the instance's
+    /// resource limiter.
+    pub(crate) fn memory_grow(
+        &self,
+        memory_index: DefinedMemoryIndex,
+        delta: u32,
+    ) -> Result<Option<u32>, String> {
+        let memory = self
             .memories
             .get(memory_index)
-            .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()))
-            .grow(delta);
+            .unwrap_or_else(|| panic!("no memory for index {}", memory_index.index()));
+
+        if let Some(limiter) = self.limiter() {
+            let current = memory.size();
+            // Growth that overflows fails below without bothering the limiter.
+            if let Some(desired) = current.checked_add(delta).filter(|_| delta != 0) {
+                limiter.memory_growing(current, desired)?;
+            }
+        }
+        let result = memory.grow(delta);
 
         // Keep current the VMContext pointers used by compiled wasm code.
         self.set_memory(memory_index, self.memories[memory_index].vmmemory());
 
-        result
+        Ok(result)
     }
 
     /// Grow imported memory by the specified amount of pages.
@@ -411,7 +435,7 @@ impl Instance {
         &self,
         memory_index: MemoryIndex,
         delta: u32,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, String> {
         let import = self.imported_memory(memory_index);
         let foreign_instance = (&*import.vmctx).instance();
         let foreign_memory = &*import.from;
@@ -450,14 +474,16 @@ impl Instance {
     /// Grow table by the specified amount of elements, filling them with
     /// `init_value`.
     ///
-    /// Returns `None` if table can't be grown by the specified amount of
-    /// elements, or if `init_value` is the wrong type of table element.
+    /// Returns `Ok(None)` if table can't be grown by the specified amount of
+    /// elements, or if `init_value` is the wrong type of table element, and
+    /// an error if the growth was denied by the resource limiter of the
+    /// instance defining the table.
     pub(crate) fn table_grow(
         &self,
         table_index: TableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, String> {
         let (defined_table_index, instance) =
             self.get_defined_table_index_and_instance(table_index);
         instance.defined_table_grow(defined_table_index, delta, init_value)
@@ -468,20 +494,28 @@ impl Instance {
         table_index: DefinedTableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
-        unsafe {
-            let orig_size = self
-                .tables
-                .get(table_index)
-                .unwrap_or_else(|| panic!("no table for index {}", table_index.index()))
-                .grow(delta, init_value)?;
-
-            // Keep the `VMContext` pointers used by compiled Wasm code up to
-            // date.
-            self.set_table(table_index, self.tables[table_index].vmtable());
+    ) -> Result<Option<u32>, String> {
+        let table = self
+            .tables
+            .get(table_index)
+            .unwrap_or_else(|| panic!("no table for index {}", table_index.index()));
 
-            Some(orig_size)
+        if let Some(limiter) = self.limiter() {
+            let current = table.size();
+            if let Some(desired) = current.checked_add(delta).filter(|_| delta != 0) {
+                limiter.table_growing(current, desired)?;
+            }
         }
+        let orig_size = match unsafe { table.grow(delta, init_value) } {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+
+        // Keep the `VMContext` pointers used by compiled Wasm code up to
+        // date.
+        self.set_table(table_index, self.tables[table_index].vmtable());
+
+        Ok(Some(orig_size))
     }
 
     pub(crate) fn defined_table_fill(
@@ -809,21 +843,30 @@ impl InstanceHandle {
     /// It is your responsibility to ensure that the given raw
     /// `externref_activations_table` and `stack_map_registry` outlive this
     /// instance.
+    ///
+    /// If `table_pool` is given the instance's tables are allocated from it,
+    /// and a slot of the pool must have been acquired for the instance.
     pub unsafe fn new(
         module: Arc<Module>,
         finished_functions: &PrimaryMap<DefinedFuncIndex, *mut [VMFunctionBody]>,
         imports: Imports,
         mem_creator: Option<&dyn RuntimeMemoryCreator>,
+        table_pool: Option<&InstancePool>,
         lookup_shared_signature: &dyn Fn(SignatureIndex) -> VMSharedSignatureIndex,
         host_state: Box<dyn Any>,
         interrupts: *const VMInterrupts,
         externref_activations_table: *mut VMExternRefActivationsTable,
         stack_map_registry: *mut StackMapRegistry,
+        limiter: Option<Rc<dyn ResourceLimiter>>,
     ) -> Result<Self, InstantiationError> {
         debug_assert!(!externref_activations_table.is_null());
         debug_assert!(!stack_map_registry.is_null());
 
-        let tables = create_tables(&module);
+        if let Some(limiter) = &limiter {
+            check_initial_sizes(&module, &**limiter)?;
+        }
+
+        let tables = create_tables(&module, table_pool)?;
         let memories = create_memories(&module, mem_creator.unwrap_or(&DefaultMemoryCreator {}))?;
 
         let vmctx_tables = tables
@@ -853,6 +896,7 @@ impl InstanceHandle {
                 passive_elements: Default::default(),
                 passive_data,
                 host_state,
+                limiter,
                 vmctx: VMContext {},
             };
             let layout = instance.alloc_layout();
@@ -1039,9 +1083,13 @@ impl InstanceHandle {
 
     /// Grow memory in this instance by the specified amount of pages.
     ///
-    /// Returns `None` if memory can't be grown by the specified amount
-    /// of pages.
-    pub fn memory_grow(&self, memory_index: DefinedMemoryIndex, delta: u32) -> Option<u32> {
+    /// Returns `Ok(None)` if memory can't be grown by the specified amount
+    /// of pages, and an error if the instance's resource limiter denied it.
+    pub fn memory_grow(
+        &self,
+        memory_index: DefinedMemoryIndex,
+        delta: u32,
+    ) -> Result<Option<u32>, String> {
         self.instance().memory_grow(memory_index, delta)
     }
 
@@ -1055,14 +1103,15 @@ impl InstanceHandle {
     /// When the table is successfully grown, returns the original size of the
     /// table.
     ///
-    /// Returns `None` if memory can't be grown by the specified amount of pages
-    /// or if the `init_value` is the incorrect table element type.
+    /// Returns `Ok(None)` if memory can't be grown by the specified amount of
+    /// pages or if the `init_value` is the incorrect table element type, and
+    /// an error if a resource limiter denied it.
     pub fn table_grow(
         &self,
         table_index: TableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, String> {
         self.instance().table_grow(table_index, delta, init_value)
     }
 
@@ -1071,14 +1120,15 @@ impl InstanceHandle {
     /// When the table is successfully grown, returns the original size of the
     /// table.
     ///
-    /// Returns `None` if memory can't be grown by the specified amount of pages
-    /// or if the `init_value` is the incorrect table element type.
+    /// Returns `Ok(None)` if memory can't be grown by the specified amount of
+    /// pages or if the `init_value` is the incorrect table element type, and
+    /// an error if the instance's resource limiter denied it.
     pub fn defined_table_grow(
         &self,
         table_index: DefinedTableIndex,
         delta: u32,
         init_value: TableElement,
-    ) -> Option<u32> {
+    ) -> Result<Option<u32>, String> {
         self.instance()
             .defined_table_grow(table_index, delta, init_value)
     }
@@ -1150,6 +1200,19 @@ impl InstanceHandle {
         ptr::drop_in_place(self.instance);
         alloc::dealloc(self.instance.cast(), layout);
     }
+
+    /// Like `dealloc`, but additionally zeroes the instance's `VMContext`,
+    /// which holds its globals among other things, before returning it to the
+    /// allocator.
+    ///
+    /// This has the same safety requirements as `dealloc`.
+    pub unsafe fn dealloc_zeroed(&self) {
+        let instance = self.instance();
+        let layout = instance.alloc_layout();
+        ptr::drop_in_place(self.instance);
+        ptr::write_bytes(self.instance.cast::<u8>(), 0, layout.size());
+        alloc::dealloc(self.instance.cast(), layout);
+    }
 }
 
 fn check_table_init_bounds(instance: &Instance) -> Result<(), InstantiationError> {
@@ -1173,14 +1236,24 @@ fn get_memory_init_start(init: &DataInitializer<'_>, instance: &Instance) -> usi
     let mut start = init.location.offset;
 
     if let Some(base) = init.location.base {
-        let val = unsafe {
+        let global = unsafe {
             if let Some(def_index) = instance.module.defined_global_index(base) {
-                *instance.global(def_index).as_u32()
+                instance.global(def_index)
             } else {
-                *(*instance.imported_global(base).from).as_u32()
+                *instance.imported_global(base).from
             }
         };
-        start += usize::try_from(val).unwrap();
+        // 64-bit memories are offset by `i64` globals.
+        let val = if instance.module.memory_plans[init.location.memory_index]
+            .memory
+            .memory64
+        {
+            unsafe { *global.as_u64() }
+        } else {
+            u64::from(unsafe { *global.as_u32() })
+        };
+        // An offset the host can't address is out of bounds regardless.
+        start = start.saturating_add(usize::try_from(val).unwrap_or(usize::MAX));
     }
 
     start
@@ -1214,7 +1287,8 @@ fn check_memory_init_bounds(
         let start = get_memory_init_start(init, instance);
         unsafe {
             let mem_slice = get_memory_slice(init, instance);
-            if mem_slice.get_mut(start..start + init.data.len()).is_none() {
+            let end = start.checked_add(init.data.len());
+            if end.and_then(|end| mem_slice.get_mut(start..end)).is_none() {
                 return Err(InstantiationError::Link(LinkError(
                     "memory out of bounds: data segment does not fit".into(),
                 )));
@@ -1225,15 +1299,42 @@ fn check_memory_init_bounds(
     Ok(())
 }
 
+/// Ask `limiter` whether the memories and tables defined by `module` may be
+/// created with their minimum sizes.
+fn check_initial_sizes(
+    module: &Module,
+    limiter: &dyn ResourceLimiter,
+) -> Result<(), InstantiationError> {
+    for plan in &module.memory_plans.values().as_slice()[module.num_imported_memories..] {
+        limiter
+            .memory_growing(0, plan.memory.minimum)
+            .map_err(InstantiationError::Resource)?;
+    }
+    for plan in &module.table_plans.values().as_slice()[module.num_imported_tables..] {
+        limiter
+            .table_growing(0, plan.table.minimum)
+            .map_err(InstantiationError::Resource)?;
+    }
+    Ok(())
+}
+
 /// Allocate memory for just the tables of the current module.
-fn create_tables(module: &Module) -> BoxedSlice<DefinedTableIndex, Table> {
+fn create_tables(
+    module: &Module,
+    table_pool: Option<&InstancePool>,
+) -> Result<BoxedSlice<DefinedTableIndex, Table>, InstantiationError> {
     let num_imports = module.num_imported_tables;
     let mut tables: PrimaryMap<DefinedTableIndex, _> =
         PrimaryMap::with_capacity(module.table_plans.len() - num_imports);
     for table in &module.table_plans.values().as_slice()[num_imports..] {
-        tables.push(Table::new(table));
+        tables.push(match table_pool {
+            Some(pool) => pool
+                .new_table(table)
+                .map_err(InstantiationError::Resource)?,
+            None => Table::new(table),
+        });
     }
-    tables.into_boxed_slice()
+    Ok(tables.into_boxed_slice())
 }
 
 /// Compute the offset for a table element initializer.
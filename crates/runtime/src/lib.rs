@@ -25,8 +25,12 @@ mod externref;
 mod imports;
 mod instance;
 mod jit_int;
+mod limits;
 mod memory;
 mod mmap;
+mod pool;
+#[cfg(unix)]
+mod sampler;
 mod table;
 mod traphandlers;
 mod vmcontext;
@@ -39,8 +43,12 @@ pub use crate::externref::*;
 pub use crate::imports::Imports;
 pub use crate::instance::{InstanceHandle, InstantiationError, LinkError};
 pub use crate::jit_int::GdbJitImageRegistration;
+pub use crate::limits::ResourceLimiter;
 pub use crate::memory::{RuntimeLinearMemory, RuntimeMemoryCreator};
 pub use crate::mmap::Mmap;
+pub use crate::pool::{InstancePool, InstanceSlot, PoolingLimits};
+#[cfg(unix)]
+pub use crate::sampler::{wasm_return_addresses, Sampler};
 pub use crate::table::{Table, TableElement};
 pub use crate::traphandlers::{
     catch_traps, init_traps, raise_lib_trap, raise_user_trap, resume_panic, with_last_info,
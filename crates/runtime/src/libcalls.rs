@@ -57,8 +57,9 @@
 //!   ```
 
 use crate::externref::VMExternRef;
+use crate::instance::Instance;
 use crate::table::Table;
-use crate::traphandlers::raise_lib_trap;
+use crate::traphandlers::{raise_lib_trap, raise_user_trap};
 use crate::vmcontext::{VMCallerCheckedAnyfunc, VMContext};
 use std::mem;
 use std::ptr::{self, NonNull};
@@ -185,18 +186,35 @@ pub extern "C" fn wasmtime_f64_nearest(x: f64) -> f64 {
     }
 }
 
+/// Turn the result of growing a memory or table into the value returned by
+/// the `grow` instruction, or into the message of a trap if growth was denied
+/// by a resource limiter which asks for one.
+fn grow_result(instance: &Instance, result: Result<Option<u32>, String>) -> Result<u32, String> {
+    let trap_on_failure = instance
+        .limiter()
+        .map_or(false, |l| l.trap_on_grow_failure());
+    match result {
+        Ok(Some(orig_size)) => Ok(orig_size),
+        Err(reason) if trap_on_failure => Err(reason),
+        Ok(None) | Err(_) => Ok(u32::max_value()),
+    }
+}
+
 /// Implementation of memory.grow for locally-defined 32-bit memories.
 pub unsafe extern "C" fn wasmtime_memory32_grow(
     vmctx: *mut VMContext,
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    let instance = (&mut *vmctx).instance();
-    let memory_index = DefinedMemoryIndex::from_u32(memory_index);
-
-    instance
-        .memory_grow(memory_index, delta)
-        .unwrap_or(u32::max_value())
+    let result = {
+        let instance = (&mut *vmctx).instance();
+        let memory_index = DefinedMemoryIndex::from_u32(memory_index);
+        grow_result(instance, instance.memory_grow(memory_index, delta))
+    };
+    match result {
+        Ok(orig_size) => orig_size,
+        Err(reason) => raise_user_trap(reason.into()),
+    }
 }
 
 /// Implementation of memory.grow for imported 32-bit memories.
@@ -205,12 +223,15 @@ pub unsafe extern "C" fn wasmtime_imported_memory32_grow(
     delta: u32,
     memory_index: u32,
 ) -> u32 {
-    let instance = (&mut *vmctx).instance();
-    let memory_index = MemoryIndex::from_u32(memory_index);
-
-    instance
-        .imported_memory_grow(memory_index, delta)
-        .unwrap_or(u32::max_value())
+    let result = {
+        let instance = (&mut *vmctx).instance();
+        let memory_index = MemoryIndex::from_u32(memory_index);
+        grow_result(instance, instance.imported_memory_grow(memory_index, delta))
+    };
+    match result {
+        Ok(orig_size) => orig_size,
+        Err(reason) => raise_user_trap(reason.into()),
+    }
 }
 
 /// Implementation of memory.size for locally-defined 32-bit memories.
@@ -241,28 +262,31 @@ pub unsafe extern "C" fn wasmtime_table_grow(
     // or is a `VMExternRef` until we look at the table type.
     init_value: *mut u8,
 ) -> u32 {
-    let instance = (&mut *vmctx).instance();
-    let table_index = TableIndex::from_u32(table_index);
-    match instance.table_element_type(table_index) {
-        TableElementType::Func => {
-            let func = init_value as *mut VMCallerCheckedAnyfunc;
-            instance
-                .table_grow(table_index, delta, func.into())
-                .unwrap_or(-1_i32 as u32)
-        }
-        TableElementType::Val(ty) => {
-            debug_assert_eq!(ty, crate::ref_type());
-
-            let init_value = if init_value.is_null() {
-                None
-            } else {
-                Some(VMExternRef::clone_from_raw(init_value))
-            };
-
-            instance
-                .table_grow(table_index, delta, init_value.into())
-                .unwrap_or(-1_i32 as u32)
-        }
+    let result = {
+        let instance = (&mut *vmctx).instance();
+        let table_index = TableIndex::from_u32(table_index);
+        let result = match instance.table_element_type(table_index) {
+            TableElementType::Func => {
+                let func = init_value as *mut VMCallerCheckedAnyfunc;
+                instance.table_grow(table_index, delta, func.into())
+            }
+            TableElementType::Val(ty) => {
+                debug_assert_eq!(ty, crate::ref_type());
+
+                let init_value = if init_value.is_null() {
+                    None
+                } else {
+                    Some(VMExternRef::clone_from_raw(init_value))
+                };
+
+                instance.table_grow(table_index, delta, init_value.into())
+            }
+        };
+        grow_result(instance, result)
+    };
+    match result {
+        Ok(orig_size) => orig_size,
+        Err(reason) => raise_user_trap(reason.into()),
     }
 }
 
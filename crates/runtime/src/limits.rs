@@ -0,0 +1,27 @@
+//! Hooks for hosts to cap the resources consumed by instances.
+
+/// Used by hosts to limit the size of linear memories and tables.
+///
+/// A limiter is consulted whenever a memory or table defined by an instance
+/// is created or grown, whether by wasm code or by the host, and can deny the
+/// request.
+pub trait ResourceLimiter {
+    /// Called before a linear memory grows from `current` to `desired` wasm
+    /// pages, including when it's first created with its minimum size.
+    ///
+    /// Returning an error denies the growth, with the error describing why.
+    fn memory_growing(&self, current: u32, desired: u32) -> Result<(), String>;
+
+    /// Called before a table grows from `current` to `desired` elements,
+    /// including when it's first created with its minimum size.
+    ///
+    /// Returning an error denies the growth, with the error describing why.
+    fn table_growing(&self, current: u32, desired: u32) -> Result<(), String>;
+
+    /// Whether growth denied while executing `memory.grow` or `table.grow`
+    /// raises a trap with the limiter's error, instead of making the
+    /// instruction return -1.
+    fn trap_on_grow_failure(&self) -> bool {
+        false
+    }
+}
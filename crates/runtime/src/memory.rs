@@ -7,7 +7,7 @@ use crate::vmcontext::VMMemoryDefinition;
 use more_asserts::{assert_ge, assert_le};
 use std::cell::RefCell;
 use std::convert::TryFrom;
-use wasmtime_environ::{MemoryPlan, MemoryStyle, WASM_MAX_PAGES, WASM_PAGE_SIZE};
+use wasmtime_environ::{MemoryPlan, MemoryStyle, WASM64_MAX_PAGES, WASM_MAX_PAGES, WASM_PAGE_SIZE};
 
 /// A memory allocator
 pub trait RuntimeMemoryCreator: Send + Sync {
@@ -49,6 +49,9 @@ pub struct MmapMemory {
     // The optional maximum size in wasm pages of this linear memory.
     maximum: Option<u32>,
 
+    // The number of pages this linear memory can never reach, as its index space would overflow.
+    index_limit: u32,
+
     // Size in bytes of extra guard pages after the end to optimize loads and stores with
     // constant offsets.
     offset_guard_size: usize,
@@ -69,9 +72,14 @@ struct WasmMmap {
 impl MmapMemory {
     /// Create a new linear memory instance with specified minimum and maximum number of wasm pages.
     pub fn new(plan: &MemoryPlan) -> Result<Self, String> {
-        // `maximum` cannot be set to more than `65536` pages.
-        assert_le!(plan.memory.minimum, WASM_MAX_PAGES);
-        assert!(plan.memory.maximum.is_none() || plan.memory.maximum.unwrap() <= WASM_MAX_PAGES);
+        // `maximum` cannot be set to more than `65536` pages, unless the memory is 64-bit.
+        let index_limit = if plan.memory.memory64 {
+            WASM64_MAX_PAGES
+        } else {
+            WASM_MAX_PAGES
+        };
+        assert_le!(plan.memory.minimum, index_limit);
+        assert!(plan.memory.maximum.is_none() || plan.memory.maximum.unwrap() <= index_limit);
 
         let offset_guard_bytes = plan.offset_guard_size as usize;
 
@@ -91,8 +99,13 @@ impl MmapMemory {
                 bound
             }
         } as usize;
-        let minimum_bytes = minimum_pages.checked_mul(WASM_PAGE_SIZE as usize).unwrap();
-        let request_bytes = minimum_bytes.checked_add(offset_guard_bytes).unwrap();
+        // 64-bit memories can ask for more than the host can address.
+        let minimum_bytes = minimum_pages
+            .checked_mul(WASM_PAGE_SIZE as usize)
+            .ok_or("memory size exceeds the host's address space")?;
+        let request_bytes = minimum_bytes
+            .checked_add(offset_guard_bytes)
+            .ok_or("memory size exceeds the host's address space")?;
         let mapped_pages = plan.memory.minimum as usize;
         let mapped_bytes = mapped_pages * WASM_PAGE_SIZE as usize;
 
@@ -104,6 +117,7 @@ impl MmapMemory {
         Ok(Self {
             mmap: mmap.into(),
             maximum: plan.memory.maximum,
+            index_limit,
             offset_guard_size: offset_guard_bytes,
             needs_signal_handlers,
         })
@@ -144,14 +158,18 @@ impl RuntimeLinearMemory for MmapMemory {
         // Wasm linear memories are never allowed to grow beyond what is
         // indexable. If the memory has no maximum, enforce the greatest
         // limit here.
-        if new_pages >= WASM_MAX_PAGES {
+        if new_pages >= self.index_limit {
             // Linear memory size would exceed the index range.
             return None;
         }
 
-        let delta_bytes = usize::try_from(delta).unwrap() * WASM_PAGE_SIZE as usize;
+        let delta_bytes = usize::try_from(delta)
+            .ok()?
+            .checked_mul(WASM_PAGE_SIZE as usize)?;
         let prev_bytes = usize::try_from(prev_pages).unwrap() * WASM_PAGE_SIZE as usize;
-        let new_bytes = usize::try_from(new_pages).unwrap() * WASM_PAGE_SIZE as usize;
+        let new_bytes = usize::try_from(new_pages)
+            .ok()?
+            .checked_mul(WASM_PAGE_SIZE as usize)?;
 
         if new_bytes > mmap.alloc.len() - self.offset_guard_size {
             // If the new size is within the declared maximum, but needs more memory than we
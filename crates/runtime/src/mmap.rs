@@ -218,6 +218,68 @@ impl Mmap {
         Ok(())
     }
 
+    /// Release the memory starting at `start` and extending for `len` bytes,
+    /// leaving the range reserved but inaccessible. The next time the range is
+    /// made accessible it reads as zeroes again. `start` and `len` must be
+    /// native page-size multiples and describe a range within `self`'s
+    /// reserved memory.
+    #[cfg(not(target_os = "windows"))]
+    pub fn decommit(&mut self, start: usize, len: usize) -> Result<(), String> {
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        // Map fresh anonymous pages over the range, which both drops the old
+        // contents and restores the `PROT_NONE` reservation.
+        let ptr = unsafe {
+            libc::mmap(
+                (self.ptr + start) as *mut libc::c_void,
+                len,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_FIXED,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1_isize {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Release the memory starting at `start` and extending for `len` bytes,
+    /// leaving the range reserved but inaccessible. The next time the range is
+    /// made accessible it reads as zeroes again. `start` and `len` must be
+    /// native page-size multiples and describe a range within `self`'s
+    /// reserved memory.
+    #[cfg(target_os = "windows")]
+    pub fn decommit(&mut self, start: usize, len: usize) -> Result<(), String> {
+        use winapi::ctypes::c_void;
+        use winapi::um::memoryapi::VirtualFree;
+        use winapi::um::winnt::MEM_DECOMMIT;
+        let page_size = region::page::size();
+        assert_eq!(start & (page_size - 1), 0);
+        assert_eq!(len & (page_size - 1), 0);
+        assert_le!(len, self.len);
+        assert_le!(start, self.len - len);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        let ptr = self.ptr as *const u8;
+        if unsafe { VirtualFree(ptr.add(start) as *mut c_void, len, MEM_DECOMMIT) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
     /// Return the allocated memory as a slice of u8.
     pub fn as_slice(&self) -> &[u8] {
         unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
@@ -0,0 +1,289 @@
+//! A pooling allocator for instances, their linear memories and their tables.
+//!
+//! `InstancePool` reserves the address space for a fixed number of linear
+//! memories, and allocates the storage for as many tables, up front and hands
+//! out instance slots against that reservation. Slots are recycled, rather
+//! than unmapped or freed, once the instance using them is deallocated.
+
+use crate::memory::{RuntimeLinearMemory, RuntimeMemoryCreator};
+use crate::mmap::Mmap;
+use crate::table::{Table, TableBuffers};
+use crate::vmcontext::VMMemoryDefinition;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex};
+use wasmtime_environ::{
+    MemoryPlan, MemoryStyle, Module, TablePlan, Tunables, WASM_MAX_PAGES, WASM_PAGE_SIZE,
+};
+
+/// Limits applied to every instance allocated from an `InstancePool`.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolingLimits {
+    /// The number of instances which may be live at once.
+    pub instance_count: u32,
+    /// The maximum number of wasm pages any linear memory may grow to.
+    pub memory_pages: u32,
+    /// The maximum number of elements any table may grow to.
+    pub table_elements: u32,
+    /// Whether acquiring a slot from an exhausted pool waits for another slot
+    /// to be released instead of failing.
+    pub block_when_exhausted: bool,
+}
+
+/// A pool of preallocated instance slots, each of which owns a reservation
+/// for one linear memory and the storage for one table.
+#[derive(Clone)]
+pub struct InstancePool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    limits: PoolingLimits,
+    // Size in bytes of each memory reservation, including guard pages.
+    memory_reservation: usize,
+    // Number of instance slots currently handed out.
+    in_use: Mutex<u32>,
+    released: Condvar,
+    memories: Mutex<Vec<Mmap>>,
+    tables: TableBuffers,
+}
+
+impl InstancePool {
+    /// Create a new pool, reserving address space for `limits.instance_count`
+    /// linear memories large enough for any memory plan produced with
+    /// `tunables`, and allocating the storage for as many tables of
+    /// `limits.table_elements` elements.
+    pub fn new(limits: PoolingLimits, tunables: &Tunables) -> Result<Self, String> {
+        if limits.memory_pages > WASM_MAX_PAGES {
+            return Err(format!(
+                "pooled memories cannot exceed {} wasm pages",
+                WASM_MAX_PAGES
+            ));
+        }
+
+        // Each reservation needs to cover a static heap in its entirety, since
+        // compiled code elides bounds checks for those, and it is always
+        // followed by at least one guard page so slots never abut.
+        let page_size = region::page::size();
+        let max_pages = u64::from(tunables.static_memory_bound.max(limits.memory_pages));
+        let guard_bytes = tunables
+            .static_memory_offset_guard_size
+            .max(tunables.dynamic_memory_offset_guard_size)
+            .max(page_size as u64);
+        let memory_reservation = max_pages
+            .checked_mul(u64::from(WASM_PAGE_SIZE))
+            .and_then(|bytes| bytes.checked_add(guard_bytes))
+            .and_then(|bytes| usize::try_from(bytes).ok())
+            .ok_or_else(|| "pooled memory reservation exceeds the address space".to_string())?;
+        let memory_reservation = (memory_reservation + page_size - 1) & !(page_size - 1);
+
+        let memories = (0..limits.instance_count)
+            .map(|_| Mmap::accessible_reserved(0, memory_reservation))
+            .collect::<Result<Vec<_>, _>>()?;
+        let tables = (0..limits.instance_count)
+            .map(|_| Vec::with_capacity(limits.table_elements as usize))
+            .collect();
+
+        Ok(Self {
+            inner: Arc::new(PoolInner {
+                limits,
+                memory_reservation,
+                in_use: Mutex::new(0),
+                released: Condvar::new(),
+                memories: Mutex::new(memories),
+                tables: Arc::new(Mutex::new(tables)),
+            }),
+        })
+    }
+
+    /// Returns the limits this pool was created with.
+    pub fn limits(&self) -> &PoolingLimits {
+        &self.inner.limits
+    }
+
+    /// Acquire an instance slot for an instance of `module`.
+    ///
+    /// Fails if `module` doesn't fit within the pool's limits, or if all slots
+    /// are in use and the pool isn't configured to block. The slot is returned
+    /// to the pool when the returned `InstanceSlot` is dropped, which must not
+    /// happen before the instance itself is deallocated.
+    pub fn acquire(&self, module: &Module) -> Result<InstanceSlot, String> {
+        let limits = &self.inner.limits;
+        let defined_memories = module.memory_plans.len() - module.num_imported_memories;
+        if defined_memories > 1 {
+            return Err(format!(
+                "module defines {} memories but pooled instances may define at most one",
+                defined_memories
+            ));
+        }
+        for plan in &module.memory_plans.values().as_slice()[module.num_imported_memories..] {
+            if plan.memory.minimum > limits.memory_pages {
+                return Err(format!(
+                    "memory minimum of {} pages exceeds the pool limit of {} pages",
+                    plan.memory.minimum, limits.memory_pages
+                ));
+            }
+        }
+        let defined_tables = module.table_plans.len() - module.num_imported_tables;
+        if defined_tables > 1 {
+            return Err(format!(
+                "module defines {} tables but pooled instances may define at most one",
+                defined_tables
+            ));
+        }
+        for plan in &module.table_plans.values().as_slice()[module.num_imported_tables..] {
+            if plan.table.minimum > limits.table_elements {
+                return Err(format!(
+                    "table minimum of {} elements exceeds the pool limit of {} elements",
+                    plan.table.minimum, limits.table_elements
+                ));
+            }
+        }
+
+        let mut in_use = self.inner.in_use.lock().unwrap();
+        while *in_use == limits.instance_count {
+            if !limits.block_when_exhausted {
+                return Err(format!(
+                    "instance pool exhausted: all {} slots are in use",
+                    limits.instance_count
+                ));
+            }
+            in_use = self.inner.released.wait(in_use).unwrap();
+        }
+        *in_use += 1;
+        Ok(InstanceSlot {
+            pool: self.inner.clone(),
+        })
+    }
+
+    /// Create a table for an instance in a slot of this pool, from the
+    /// storage set aside for that slot.
+    ///
+    /// The table can grow up to the pool's `table_elements` limit.
+    pub fn new_table(&self, plan: &TablePlan) -> Result<Table, String> {
+        Table::new_pooled(plan, &self.inner.tables, self.inner.limits.table_elements)
+    }
+}
+
+impl RuntimeMemoryCreator for InstancePool {
+    fn new_memory(&self, plan: &MemoryPlan) -> Result<Box<dyn RuntimeLinearMemory>, String> {
+        let limits = &self.inner.limits;
+        if plan.memory.minimum > limits.memory_pages {
+            return Err(format!(
+                "memory minimum of {} pages exceeds the pool limit of {} pages",
+                plan.memory.minimum, limits.memory_pages
+            ));
+        }
+        let required_pages = match plan.style {
+            MemoryStyle::Dynamic => limits.memory_pages,
+            MemoryStyle::Static { bound } => bound,
+        };
+        let required_bytes =
+            u64::from(required_pages) * u64::from(WASM_PAGE_SIZE) + plan.offset_guard_size;
+        if required_bytes > self.inner.memory_reservation as u64 {
+            return Err(format!(
+                "memory requires a {} byte reservation but pool slots are {} bytes",
+                required_bytes, self.inner.memory_reservation
+            ));
+        }
+
+        let mut mmap = self
+            .inner
+            .memories
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| "memory pool exhausted".to_string())?;
+        let minimum_bytes = plan.memory.minimum as usize * WASM_PAGE_SIZE as usize;
+        if minimum_bytes > 0 {
+            if let Err(e) = mmap.make_accessible(0, minimum_bytes) {
+                self.inner.memories.lock().unwrap().push(mmap);
+                return Err(e);
+            }
+        }
+
+        let maximum = plan
+            .memory
+            .maximum
+            .unwrap_or(WASM_MAX_PAGES)
+            .min(limits.memory_pages);
+        Ok(Box::new(PooledMemory {
+            mmap: RefCell::new(mmap),
+            size: Cell::new(plan.memory.minimum),
+            maximum,
+            pool: self.inner.clone(),
+        }))
+    }
+}
+
+/// An instance slot checked out of an `InstancePool`.
+pub struct InstanceSlot {
+    pool: Arc<PoolInner>,
+}
+
+impl Drop for InstanceSlot {
+    fn drop(&mut self) {
+        let mut in_use = self.pool.in_use.lock().unwrap();
+        *in_use -= 1;
+        self.pool.released.notify_one();
+    }
+}
+
+/// A linear memory living in one of an `InstancePool`'s reservations. It
+/// never moves, and is decommitted and handed back to the pool on drop.
+struct PooledMemory {
+    mmap: RefCell<Mmap>,
+    size: Cell<u32>,
+    maximum: u32,
+    pool: Arc<PoolInner>,
+}
+
+impl RuntimeLinearMemory for PooledMemory {
+    fn size(&self) -> u32 {
+        self.size.get()
+    }
+
+    fn grow(&self, delta: u32) -> Option<u32> {
+        let prev_pages = self.size.get();
+        if delta == 0 {
+            return Some(prev_pages);
+        }
+
+        let new_pages = prev_pages.checked_add(delta)?;
+        if new_pages > self.maximum || new_pages > WASM_MAX_PAGES {
+            return None;
+        }
+
+        let prev_bytes = prev_pages as usize * WASM_PAGE_SIZE as usize;
+        let delta_bytes = delta as usize * WASM_PAGE_SIZE as usize;
+        // The reservation always covers `maximum` pages, so growing only ever
+        // needs to change the protection of the pages in place.
+        self.mmap
+            .borrow_mut()
+            .make_accessible(prev_bytes, delta_bytes)
+            .ok()?;
+
+        self.size.set(new_pages);
+        Some(prev_pages)
+    }
+
+    fn vmmemory(&self) -> VMMemoryDefinition {
+        VMMemoryDefinition {
+            base: self.mmap.borrow_mut().as_mut_ptr(),
+            current_length: self.size.get() as usize * WASM_PAGE_SIZE as usize,
+        }
+    }
+}
+
+impl Drop for PooledMemory {
+    fn drop(&mut self) {
+        let mut mmap = mem::replace(self.mmap.get_mut(), Mmap::new());
+        let accessible = self.size.get() as usize * WASM_PAGE_SIZE as usize;
+        // If the pages can't be reset don't put the slot back, otherwise the
+        // next instance could observe this one's data.
+        if mmap.decommit(0, accessible).is_ok() {
+            self.pool.memories.lock().unwrap().push(mmap);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! A timer which periodically interrupts the thread running wasm so that the
+//! wasm call stack can be sampled.
+//!
+//! Sampling is done with `SIGPROF`: a background thread owned by a `Sampler`
+//! sends the signal to whichever thread has most recently entered wasm through
+//! `Sampler::enter`, and the signal handler hands the interrupted program
+//! counter and frame pointer to the `TrapInfo` of the innermost wasm call on
+//! that thread with `TrapInfo::record_sample`. What to do with them, if
+//! anything, is up to the `TrapInfo`, with `wasm_return_addresses` to walk
+//! the wasm stack.
+
+use crate::traphandlers;
+use std::io;
+use std::mem::{self, MaybeUninit};
+use std::sync::atomic::{spin_loop_hint, AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Condvar, Mutex, Once};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+static mut PREV_SIGPROF: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+
+/// A background timer which samples the thread executing wasm at a fixed
+/// frequency.
+pub struct Sampler {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    /// The `pthread_t` of the thread to sample, or zero, set for as long as an
+    /// outermost `enter` guard is alive. This is checked on every call into
+    /// wasm so it's an atomic rather than behind `stop`'s lock.
+    target: AtomicUsize,
+    /// Set by the sampler thread while it's sending a signal to `target`. The
+    /// guard waits for this to be clear after clearing `target`, so the
+    /// thread is still around when it's sent a signal.
+    sending: AtomicBool,
+    stop: Mutex<bool>,
+    cvar: Condvar,
+}
+
+impl Sampler {
+    /// Starts a sampler which interrupts the thread within `enter`, if any,
+    /// `frequency_hz` times a second.
+    pub fn new(frequency_hz: u32) -> Sampler {
+        init_sigprof();
+        let period = Duration::from_secs(1) / frequency_hz.max(1);
+        let shared = Arc::new(Shared {
+            target: AtomicUsize::new(0),
+            sending: AtomicBool::new(false),
+            stop: Mutex::new(false),
+            cvar: Condvar::new(),
+        });
+        let thread = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("wasm-sampler".to_string())
+                .spawn(move || {
+                    let mut stop = shared.stop.lock().unwrap();
+                    while !*stop {
+                        stop = shared.cvar.wait_timeout(stop, period).unwrap().0;
+                        if *stop {
+                            break;
+                        }
+                        shared.sending.store(true, SeqCst);
+                        let target = shared.target.load(SeqCst);
+                        if target != 0 {
+                            unsafe {
+                                libc::pthread_kill(target as libc::pthread_t, libc::SIGPROF);
+                            }
+                        }
+                        shared.sending.store(false, SeqCst);
+                    }
+                })
+                .expect("failed to spawn the sampler thread")
+        };
+        Sampler {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Marks the current thread as the one to sample until the returned guard
+    /// is dropped.
+    ///
+    /// Nested calls, which happen when wasm calls the host which calls back
+    /// into wasm, leave the outermost registration in place. Only one thread
+    /// at a time may be within `enter`.
+    pub fn enter(&self) -> impl Drop + '_ {
+        let outermost = self.shared.target.load(SeqCst) == 0;
+        if outermost {
+            let current = unsafe { libc::pthread_self() };
+            self.shared.target.store(current as usize, SeqCst);
+        }
+
+        struct Exit<'a>(Option<&'a Shared>);
+
+        impl Drop for Exit<'_> {
+            fn drop(&mut self) {
+                if let Some(shared) = self.0 {
+                    shared.target.store(0, SeqCst);
+                    while shared.sending.load(SeqCst) {
+                        spin_loop_hint();
+                    }
+                }
+            }
+        }
+
+        Exit(if outermost { Some(&self.shared) } else { None })
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        *self.shared.stop.lock().unwrap() = true;
+        self.shared.cvar.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn init_sigprof() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| unsafe {
+        let mut handler: libc::sigaction = mem::zeroed();
+        // SA_RESTART keeps a sample which lands in a blocking system call made
+        // by the host from making that call fail with EINTR.
+        handler.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART | libc::SA_ONSTACK;
+        handler.sa_sigaction = sigprof_handler as usize;
+        libc::sigemptyset(&mut handler.sa_mask);
+        if libc::sigaction(libc::SIGPROF, &handler, PREV_SIGPROF.as_mut_ptr()) != 0 {
+            panic!(
+                "unable to install signal handler: {}",
+                io::Error::last_os_error(),
+            );
+        }
+    });
+}
+
+unsafe extern "C" fn sigprof_handler(
+    signum: libc::c_int,
+    siginfo: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    let errno = errno_location().map(|errno| *errno);
+    traphandlers::record_sample(
+        traphandlers::get_pc(context) as usize,
+        traphandlers::get_fp(context),
+    );
+    if let (Some(location), Some(errno)) = (errno_location(), errno) {
+        *location = errno;
+    }
+
+    // Someone else in the process may be profiling with `SIGPROF` too, so
+    // pass the signal along to them. Their timer and ours can't be told
+    // apart, but the default disposition would terminate the process, so
+    // that's never what's forwarded to.
+    let previous = &*PREV_SIGPROF.as_ptr();
+    if previous.sa_flags & libc::SA_SIGINFO != 0 {
+        mem::transmute::<usize, extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void)>(
+            previous.sa_sigaction,
+        )(signum, siginfo, context)
+    } else if previous.sa_sigaction != libc::SIG_DFL && previous.sa_sigaction != libc::SIG_IGN {
+        mem::transmute::<usize, extern "C" fn(libc::c_int)>(previous.sa_sigaction)(signum)
+    }
+}
+
+/// Returns the location of this thread's `errno`, which the signal handler
+/// saves and restores, on platforms where it's known. Elsewhere it's left
+/// alone, which only matters if a previously installed handler changes it.
+unsafe fn errno_location() -> Option<*mut libc::c_int> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            Some(libc::__errno_location())
+        } else if #[cfg(any(target_os = "macos", target_os = "freebsd"))] {
+            Some(libc::__error())
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the return addresses of the frames in the chain of frame pointers
+/// starting at `fp`, innermost first, for as long as they're in wasm code
+/// according to `in_wasm`.
+///
+/// This only reads the stack, so it may be used in a signal handler. It
+/// relies on wasm code maintaining frame pointers, and stops at the first
+/// frame of host code, or of a trampoline, since those may not. A sample
+/// taken in a function's prologue or epilogue, before or after its own frame
+/// is set up, misses that function's caller.
+///
+/// # Unsafety
+///
+/// `fp` must be the frame pointer passed to `TrapInfo::record_sample` along
+/// with a program counter in wasm code.
+pub unsafe fn wasm_return_addresses<'a>(
+    fp: usize,
+    in_wasm: impl Fn(usize) -> bool + 'a,
+) -> impl Iterator<Item = usize> + 'a {
+    let mut fp = fp;
+    std::iter::from_fn(move || {
+        if fp == 0 || fp % mem::align_of::<usize>() != 0 {
+            return None;
+        }
+        // Every frame starts with the caller's frame pointer, followed by the
+        // return address into the caller.
+        let frame = fp as *const usize;
+        let (next_fp, ret) = (*frame, *frame.add(1));
+        if !in_wasm(ret) {
+            return None;
+        }
+        // Callers' frames are further up the stack, so anything else means
+        // the chain is broken.
+        fp = if next_fp > fp { next_fp } else { 0 };
+        Some(ret)
+    })
+}
@@ -6,15 +6,24 @@ use crate::vmcontext::{VMCallerCheckedAnyfunc, VMTableDefinition};
 use crate::{Trap, VMExternRef};
 use std::cell::RefCell;
 use std::convert::{TryFrom, TryInto};
-use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::{mem, ptr};
 use wasmtime_environ::wasm::TableElementType;
 use wasmtime_environ::{ir, TablePlan, TableStyle};
 
+/// Empty buffers with room for a fixed number of table elements, from which
+/// pooled tables take their elements' storage and to which they return it
+/// when they're dropped.
+pub(crate) type TableBuffers = Arc<Mutex<Vec<Vec<usize>>>>;
+
 /// A table instance.
 #[derive(Debug)]
 pub struct Table {
     elements: RefCell<TableElements>,
     maximum: Option<u32>,
+    // Where the storage of `elements` goes back to when the table is dropped,
+    // if it was taken from a pool.
+    pool: Option<TableBuffers>,
 }
 
 /// An element going into or coming out of a table.
@@ -47,10 +56,58 @@ impl Table {
             TableStyle::CallerChecksSignature => Self {
                 elements,
                 maximum: plan.table.maximum,
+                pool: None,
             },
         }
     }
 
+    /// Create a new table instance whose elements are stored in one of the
+    /// buffers of `pool`, each of which has room for `capacity` elements.
+    ///
+    /// The table can't grow beyond `capacity` elements, so its elements are
+    /// never reallocated, and the buffer is returned to `pool` when the table
+    /// is dropped.
+    pub(crate) fn new_pooled(
+        plan: &TablePlan,
+        pool: &TableBuffers,
+        capacity: u32,
+    ) -> Result<Self, String> {
+        if plan.table.minimum > capacity {
+            return Err(format!(
+                "table minimum of {} elements exceeds the pool limit of {} elements",
+                plan.table.minimum, capacity
+            ));
+        }
+        let buffer = pool
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| "table pool exhausted".to_string())?;
+        debug_assert!(buffer.capacity() >= capacity as usize);
+        let min = plan.table.minimum as usize;
+        let elements = match plan.table.ty {
+            TableElementType::Func => {
+                let mut elements = reuse_buffer(buffer);
+                elements.resize(min, ptr::null_mut());
+                TableElements::FuncRefs(elements)
+            }
+            TableElementType::Val(ty) => {
+                debug_assert_eq!(ty, crate::ref_type());
+                let mut elements = reuse_buffer(buffer);
+                elements.resize(min, None);
+                TableElements::ExternRefs(elements)
+            }
+        };
+        let maximum = plan.table.maximum.map_or(capacity, |max| max.min(capacity));
+        match plan.style {
+            TableStyle::CallerChecksSignature => Ok(Self {
+                elements: RefCell::new(elements),
+                maximum: Some(maximum),
+                pool: Some(pool.clone()),
+            }),
+        }
+    }
+
     /// Returns the type of the elements in this table.
     pub fn element_type(&self) -> TableElementType {
         match &*self.elements.borrow() {
@@ -221,6 +278,39 @@ impl Table {
     }
 }
 
+impl Drop for Table {
+    fn drop(&mut self) {
+        if let Some(pool) = &self.pool {
+            let elements =
+                mem::replace(self.elements.get_mut(), TableElements::FuncRefs(Vec::new()));
+            let buffer = match elements {
+                TableElements::FuncRefs(mut x) => {
+                    x.clear();
+                    reuse_buffer(x)
+                }
+                TableElements::ExternRefs(mut x) => {
+                    // Drops the references held by the table.
+                    x.clear();
+                    reuse_buffer(x)
+                }
+            };
+            pool.lock().unwrap().push(buffer);
+        }
+    }
+}
+
+/// Turns an empty buffer into one for another element type of the same size
+/// and alignment, keeping its allocation.
+fn reuse_buffer<T, U>(buffer: Vec<T>) -> Vec<U> {
+    assert_eq!(mem::size_of::<T>(), mem::size_of::<U>());
+    assert_eq!(mem::align_of::<T>(), mem::align_of::<U>());
+    assert!(buffer.is_empty());
+    let mut buffer = mem::ManuallyDrop::new(buffer);
+    // Safety: the buffer is empty, and was allocated with the same layout as
+    // a buffer of `U`s with the same capacity would have been.
+    unsafe { Vec::from_raw_parts(buffer.as_mut_ptr() as *mut U, 0, buffer.capacity()) }
+}
+
 impl TryFrom<TableElement> for *mut VMCallerCheckedAnyfunc {
     type Error = TableElement;
 
@@ -1,5 +1,18 @@
 //! WebAssembly trap handling, which is built on top of the lower-level
 //! signalhandling mechanisms.
+//!
+//! Traps never unwind through wasm frames, which compiled code doesn't have
+//! the unwind tables for, and don't rely on Rust panics at all. Instead
+//! `catch_traps` records a `setjmp` point (see `RegisterSetjmp` in
+//! `helpers.c`) before entering wasm, and a trap `longjmp`s straight back to
+//! it, whether it's raised by a signal handler for a faulting instruction or
+//! by a host function through `raise_user_trap`. All the frames in between,
+//! wasm and host alike, are discarded without running destructors, which is
+//! why the host-side entry points keep nothing needing a destructor on their
+//! stack when they raise a trap. A panic in a host function is caught before
+//! it can reach a wasm frame, carried across with the same mechanism by
+//! `resume_panic`, and resumed once `catch_traps` has returned. This all
+//! works the same when Rust panics are configured to abort.
 
 use crate::VMContext;
 use backtrace::Backtrace;
@@ -153,7 +166,7 @@ cfg_if::cfg_if! {
             }
         }
 
-        unsafe fn get_pc(cx: *mut libc::c_void) -> *const u8 {
+        pub(crate) unsafe fn get_pc(cx: *mut libc::c_void) -> *const u8 {
             cfg_if::cfg_if! {
                 if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
                     let cx = &*(cx as *const libc::ucontext_t);
@@ -175,6 +188,31 @@ cfg_if::cfg_if! {
                 }
             }
         }
+
+        /// Returns the frame pointer of the interrupted code, or zero where
+        /// it isn't known.
+        pub(crate) unsafe fn get_fp(cx: *mut libc::c_void) -> usize {
+            cfg_if::cfg_if! {
+                if #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+                    let cx = &*(cx as *const libc::ucontext_t);
+                    cx.uc_mcontext.gregs[libc::REG_RBP as usize] as usize
+                } else if #[cfg(all(target_os = "linux", target_arch = "x86"))] {
+                    let cx = &*(cx as *const libc::ucontext_t);
+                    cx.uc_mcontext.gregs[libc::REG_EBP as usize] as usize
+                } else if #[cfg(all(any(target_os = "linux", target_os = "android"), target_arch = "aarch64"))] {
+                    let cx = &*(cx as *const libc::ucontext_t);
+                    cx.uc_mcontext.regs[29] as usize
+                } else if #[cfg(target_os = "macos")] {
+                    let cx = &*(cx as *const libc::ucontext_t);
+                    (*cx.uc_mcontext).__ss.__rbp as usize
+                } else if #[cfg(all(target_os = "freebsd", target_arch = "x86_64"))] {
+                    let cx = &*(cx as *const libc::ucontext_t);
+                    cx.uc_mcontext.mc_rbp as usize
+                } else {
+                    0
+                }
+            }
+        }
     } else if #[cfg(target_os = "windows")] {
         use winapi::um::errhandlingapi::*;
         use winapi::um::winnt::*;
@@ -404,6 +442,17 @@ pub fn with_last_info<R>(func: impl FnOnce(Option<&dyn Any>) -> R) -> R {
     tls::with(|state| func(state.map(|s| s.trap_info.as_any())))
 }
 
+/// Passes `pc` and `fp`, which a `Sampler` interrupted this thread at, to the
+/// `TrapInfo` of the innermost wasm call on this thread, if there is one.
+#[cfg(unix)]
+pub(crate) fn record_sample(pc: usize, fp: usize) {
+    tls::with(|state| {
+        if let Some(state) = state {
+            state.trap_info.record_sample(pc, fp);
+        }
+    })
+}
+
 /// Temporary state stored on the stack which is registered in the `tls` module
 /// below for calls into wasm.
 pub struct CallThreadState<'a> {
@@ -436,6 +485,17 @@ pub unsafe trait TrapInfo {
     /// Returns the maximum size, in bytes, the wasm native stack is allowed to
     /// grow to.
     fn max_wasm_stack(&self) -> usize;
+
+    /// Called from the signal handler of a `Sampler` with the program counter
+    /// and frame pointer the thread was interrupted at, which may or may not
+    /// be in wasm code.
+    ///
+    /// This is run asynchronously with respect to the interrupted code, so it
+    /// must be async-signal-safe: it may not allocate, take locks or unwind,
+    /// and may only read state which can't be in the middle of being
+    /// modified. `wasm_return_addresses` walks the wasm stack from `fp` once
+    /// `pc` is known to be in wasm code.
+    fn record_sample(&self, _pc: usize, _fp: usize) {}
 }
 
 enum UnwindReason {
@@ -664,6 +664,24 @@ impl VMInterrupts {
         self.stack_limit
             .store(wasmtime_environ::INTERRUPTED, SeqCst);
     }
+
+    /// Returns whether no wasm is executing and no interrupt is pending.
+    pub fn is_idle(&self) -> bool {
+        self.stack_limit.load(SeqCst) == usize::max_value()
+    }
+
+    /// Withdraws an interrupt which hasn't been delivered yet.
+    ///
+    /// This must only be called while no wasm is executing, as otherwise the
+    /// stack limit of the executing wasm would be lost.
+    pub fn cancel_interrupt(&self) {
+        let _ = self.stack_limit.compare_exchange(
+            wasmtime_environ::INTERRUPTED,
+            usize::max_value(),
+            SeqCst,
+            SeqCst,
+        );
+    }
 }
 
 impl Default for VMInterrupts {
@@ -0,0 +1,54 @@
+//! Compiles the largest of the test programs with and without parallel
+//! compilation, after checking that both produce the same code.
+//!
+//! Run with `cargo bench --features test_programs --bench compile`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::PathBuf;
+use wasmtime::{Config, Engine, Module};
+
+fn largest_test_program() -> (String, Vec<u8>) {
+    let dir = PathBuf::from(env!("OUT_DIR")).join("wasm32-wasi/release");
+    let path = fs::read_dir(&dir)
+        .expect("reading the test programs")
+        .map(|entry| entry.expect("reading the test programs").path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "wasm"))
+        .max_by_key(|path| fs::metadata(path).map_or(0, |metadata| metadata.len()))
+        .expect("no test programs have been built");
+    let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+    (name, fs::read(&path).expect("reading a test program"))
+}
+
+fn engine(parallel: bool) -> Engine {
+    let mut config = Config::new();
+    config.parallel_compilation(parallel);
+    Engine::new(&config)
+}
+
+fn compile(c: &mut Criterion) {
+    let (name, wasm) = largest_test_program();
+    let serial = engine(false);
+    let parallel = engine(true);
+
+    let expected = Module::new(&serial, &wasm).unwrap().serialize().unwrap();
+    let actual = Module::new(&parallel, &wasm).unwrap().serialize().unwrap();
+    assert!(
+        expected == actual,
+        "compiling {} in parallel produced different code",
+        name
+    );
+
+    let mut group = c.benchmark_group(format!("compile {}", name));
+    group.sample_size(10);
+    group.bench_function("serial", |b| {
+        b.iter(|| Module::new(&serial, &wasm).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| Module::new(&parallel, &wasm).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compile);
+criterion_main!(benches);
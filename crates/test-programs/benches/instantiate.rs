@@ -0,0 +1,81 @@
+//! Instantiates a module which imports a dozen WASI functions against a
+//! `Linker` holding all of WASI, once through `Linker::instantiate`, which
+//! looks imports up by interned type, and once by finding each import's
+//! definition by comparing whole types, as `Linker` used to.
+//!
+//! Run with `cargo bench --bench instantiate`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use wasmtime::{Extern, Instance, Linker, Module, Store};
+
+const IMPORTS: &[(&str, &str)] = &[
+    ("args_get", "(param i32 i32) (result i32)"),
+    ("args_sizes_get", "(param i32 i32) (result i32)"),
+    ("environ_get", "(param i32 i32) (result i32)"),
+    ("environ_sizes_get", "(param i32 i32) (result i32)"),
+    ("clock_time_get", "(param i32 i64 i32) (result i32)"),
+    ("fd_close", "(param i32) (result i32)"),
+    ("fd_fdstat_get", "(param i32 i32) (result i32)"),
+    ("fd_prestat_get", "(param i32 i32) (result i32)"),
+    ("fd_prestat_dir_name", "(param i32 i32 i32) (result i32)"),
+    ("fd_read", "(param i32 i32 i32 i32) (result i32)"),
+    ("fd_seek", "(param i32 i64 i32 i32) (result i32)"),
+    ("fd_write", "(param i32 i32 i32 i32) (result i32)"),
+    (
+        "path_open",
+        "(param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)",
+    ),
+    ("proc_exit", "(param i32)"),
+];
+
+fn module(store: &Store) -> Module {
+    let imports = IMPORTS
+        .iter()
+        .map(|(name, sig)| {
+            format!(
+                "(import \"wasi_snapshot_preview1\" \"{}\" (func {}))\n",
+                name, sig
+            )
+        })
+        .collect::<String>();
+    Module::new(store.engine(), &format!("(module {})", imports)).unwrap()
+}
+
+/// Resolves the imports of `module` by comparing their types with those of
+/// every definition in `linker`.
+fn resolve_structurally(linker: &Linker, module: &Module) -> Vec<Extern> {
+    module
+        .imports()
+        .map(|import| {
+            linker
+                .iter()
+                .find(|(module, name, item)| {
+                    *module == import.module() && *name == import.name() && item.ty() == import.ty()
+                })
+                .map(|(_, _, item)| item)
+                .unwrap()
+        })
+        .collect()
+}
+
+fn instantiate(c: &mut Criterion) {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    let ctx = wasi_common::WasiCtxBuilder::new().build().unwrap();
+    wasmtime_wasi::Wasi::new(&store, ctx)
+        .add_to_linker(&mut linker)
+        .unwrap();
+    let module = module(&store);
+
+    let mut group = c.benchmark_group("instantiate");
+    group.bench_function("interned types", |b| {
+        b.iter(|| linker.instantiate(&module).unwrap())
+    });
+    group.bench_function("structural equality", |b| {
+        b.iter(|| Instance::new(&store, &module, &resolve_structurally(&linker, &module)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, instantiate);
+criterion_main!(benches);
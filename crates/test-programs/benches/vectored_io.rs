@@ -0,0 +1,75 @@
+//! Copies 100MiB from a guest to the host through `fd_write`, four 64KiB
+//! iovecs at a time, to measure the overhead of borrowing guest buffers.
+//!
+//! Run with `cargo bench --features test_programs --bench vectored_io`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use wasi_common::virtfs::pipe::WritePipe;
+use wasmtime::{Engine, Linker, Module, Store};
+
+const IOVEC_LEN: u64 = 64 * 1024;
+const IOVECS: u64 = 4;
+const TOTAL: u64 = 100 * 1024 * 1024;
+
+fn module(engine: &Engine) -> Module {
+    let wat = format!(
+        r#"
+        (module
+            (import "wasi_snapshot_preview1" "fd_write"
+                (func $fd_write (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 5)
+            (func (export "_start")
+                (local $i i32)
+                (local.set $i (i32.const 0))
+                (loop $iovecs
+                    (i32.store (i32.mul (local.get $i) (i32.const 8))
+                        (i32.add (i32.const 65536)
+                            (i32.mul (local.get $i) (i32.const {len}))))
+                    (i32.store offset=4 (i32.mul (local.get $i) (i32.const 8))
+                        (i32.const {len}))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $iovecs (i32.lt_u (local.get $i) (i32.const {iovecs}))))
+                (local.set $i (i32.const 0))
+                (loop $writes
+                    (if (call $fd_write (i32.const 1) (i32.const 0) (i32.const {iovecs}) (i32.const 64))
+                        (then unreachable))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $writes (i32.lt_u (local.get $i) (i32.const {writes}))))))
+        "#,
+        len = IOVEC_LEN,
+        iovecs = IOVECS,
+        writes = TOTAL / (IOVEC_LEN * IOVECS),
+    );
+    Module::new(engine, &wat).unwrap()
+}
+
+fn vectored_io(c: &mut Criterion) {
+    let engine = Engine::default();
+    let module = module(&engine);
+
+    let mut group = c.benchmark_group("vectored_io");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(TOTAL));
+    group.bench_function("fd_write", |b| {
+        b.iter(|| {
+            let store = Store::new(&engine);
+            let mut builder = wasi_common::WasiCtxBuilder::new();
+            builder.stdout(WritePipe::new(std::io::sink()));
+            let mut linker = Linker::new(&store);
+            wasmtime_wasi::Wasi::new(&store, builder.build().unwrap())
+                .add_to_linker(&mut linker)
+                .unwrap();
+            linker
+                .instantiate(&module)
+                .unwrap()
+                .get_func("_start")
+                .unwrap()
+                .call(&[])
+                .unwrap();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, vectored_io);
+criterion_main!(benches);
@@ -184,13 +184,18 @@ mod wasi_tests {
         };
         writeln!(
             out,
-            "        runtime::instantiate(&data, &bin_name, {}, {})",
+            "        let exit_code = runtime::instantiate(&data, &bin_name, {}, {})?;",
             workspace,
             match preopen_type {
                 PreopenType::OS => "PreopenType::OS",
                 PreopenType::Virtual => "PreopenType::Virtual",
             }
         )?;
+        writeln!(
+            out,
+            "        anyhow::ensure!(exit_code == 0, \"exited with status {{}}\", exit_code);"
+        )?;
+        writeln!(out, "        Ok(())")?;
         writeln!(out, "    }}")?;
         writeln!(out)?;
         Ok(())
@@ -202,8 +207,6 @@ mod wasi_tests {
             fn ignore(testsuite: &str, name: &str) -> bool {
                 if testsuite == "wasi-tests" {
                     match name {
-                        // TODO: virtfs files cannot be poll_oneoff'd yet
-                        "poll_oneoff_virtualfs" => true,
                         // TODO: virtfs does not support filetimes yet.
                         "path_filestat_virtualfs" |
                         "fd_filestat_set_virtualfs" => true,
@@ -214,12 +217,13 @@ mod wasi_tests {
                         "readlink_no_buffer_virtualfs" |
                         "dangling_symlink_virtualfs" |
                         "symlink_loop_virtualfs" |
-                        "path_symlink_trailing_slashes_virtualfs" => true,
+                        "path_symlink_trailing_slashes_virtualfs" |
+                        "symlink_policy_virtualfs" => true,
                         // TODO: virtfs does not support rename yet.
                         "path_rename_trailing_slashes_virtualfs" |
                         "path_rename_virtualfs" => true,
-                        // TODO: virtfs does not support truncation yet.
-                        "file_truncation_virtualfs" => true,
+                        // Virtual directories can't be preopened with options.
+                        "readonly_preopen_virtualfs" => true,
                         _ => false,
                     }
                 } else {
@@ -236,8 +240,6 @@ mod wasi_tests {
                         "symlink_loop" => true,
                         "truncation_rights" => true,
                         "dangling_fd" => true,
-                        // TODO: virtfs files cannot be poll_oneoff'd yet
-                        "poll_oneoff_virtualfs" => true,
                         // TODO: virtfs does not support filetimes yet.
                         "path_filestat_virtualfs" |
                         "fd_filestat_set_virtualfs" => true,
@@ -248,12 +250,13 @@ mod wasi_tests {
                         "readlink_no_buffer_virtualfs" |
                         "dangling_symlink_virtualfs" |
                         "symlink_loop_virtualfs" |
-                        "path_symlink_trailing_slashes_virtualfs" => true,
+                        "path_symlink_trailing_slashes_virtualfs" |
+                        "symlink_policy_virtualfs" => true,
                         // TODO: virtfs does not support rename yet.
                         "path_rename_trailing_slashes_virtualfs" |
                         "path_rename_virtualfs" => true,
-                        // TODO: virtfs does not support truncation yet.
-                        "file_truncation_virtualfs" => true,
+                        // Virtual directories can't be preopened with options.
+                        "readonly_preopen_virtualfs" => true,
                         _ => false,
                     }
                 } else {
@@ -267,9 +270,13 @@ mod wasi_tests {
     fn no_preopens(testsuite: &str, name: &str) -> bool {
         if testsuite == "wasi-tests" {
             match name {
+                "args_empty" => true,
+                "args_large" => true,
                 "big_random_buf" => true,
                 "clock_time_get" => true,
+                "poll_oneoff_pipe" => true,
                 "sched_yield" => true,
+                "virtual_file" => true,
                 _ => false,
             }
         } else {
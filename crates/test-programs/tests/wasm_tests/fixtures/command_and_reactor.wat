@@ -0,0 +1,4 @@
+(module
+  (memory (export "memory") 1)
+  (func (export "_start"))
+  (func (export "_initialize")))
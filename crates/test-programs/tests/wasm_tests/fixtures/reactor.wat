@@ -0,0 +1,31 @@
+(module
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "log.txt")
+  (data (i32.const 16) "x")
+  (global $log (mut i32) (i32.const -1))
+  (global $total (mut i64) (i64.const 0))
+
+  ;; Creates `log.txt` in the preopen, if there is one.
+  (func (export "_initialize")
+    (if (i32.eqz
+          (call $path_open (i32.const 3) (i32.const 0) (i32.const 0) (i32.const 7)
+            (i32.const 9) (i64.const 0x40) (i64.const 0) (i32.const 0) (i32.const 32)))
+      (then (global.set $log (i32.load (i32.const 32))))))
+
+  ;; Adds `n` to a running total, returning the total.
+  (func (export "add") (param $n i64) (result i64)
+    (global.set $total (i64.add (global.get $total) (local.get $n)))
+    (global.get $total))
+
+  (func (export "scale") (param $a f32) (param $b f64) (result f64)
+    (f64.mul (f64.promote_f32 (local.get $a)) (local.get $b)))
+
+  ;; Appends "x" to `log.txt`, returning the errno.
+  (func (export "append") (result i32)
+    (i32.store (i32.const 48) (i32.const 16))
+    (i32.store (i32.const 52) (i32.const 1))
+    (call $fd_write (global.get $log) (i32.const 48) (i32.const 1) (i32.const 56))))
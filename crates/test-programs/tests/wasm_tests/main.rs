@@ -1,4 +1,5 @@
 #![cfg(feature = "test_programs")]
+mod run_file;
 mod runtime;
 mod utils;
 
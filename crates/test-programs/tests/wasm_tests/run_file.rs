@@ -0,0 +1,81 @@
+use crate::runtime;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/wasm_tests/fixtures")
+        .join(name)
+}
+
+#[test]
+fn run_file_success() -> anyhow::Result<()> {
+    assert_eq!(runtime::run_file(&fixture("exit_success.wat"), None)?, 0);
+    Ok(())
+}
+
+#[test]
+fn run_file_exit_status() -> anyhow::Result<()> {
+    assert_eq!(runtime::run_file(&fixture("exit_status.wat"), None)?, 3);
+    Ok(())
+}
+
+#[test]
+fn run_file_trap() {
+    let err = runtime::run_file(&fixture("trap.wat"), None).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("error while testing Wasm module 'trap'"),
+        "bad error: {:?}",
+        err
+    );
+}
+
+#[test]
+fn reactor_keeps_state_between_calls() -> anyhow::Result<()> {
+    let workspace = tempfile::tempdir()?;
+    let reactor = runtime::Reactor::new(&fixture("reactor.wat"), Some(workspace.path()))?;
+    assert_eq!(reactor.invoke("add", &["40"])?[0].unwrap_i64(), 40);
+    assert_eq!(reactor.invoke("add", &["2"])?[0].unwrap_i64(), 42);
+    assert_eq!(reactor.invoke("scale", &["1.5", "3"])?[0].unwrap_f64(), 4.5);
+    assert_eq!(reactor.invoke("append", &[])?[0].unwrap_i32(), 0);
+    assert_eq!(reactor.invoke("append", &[])?[0].unwrap_i32(), 0);
+    assert_eq!(fs::read_to_string(workspace.path().join("log.txt"))?, "xx");
+    Ok(())
+}
+
+#[test]
+fn reactor_invoke_errors() -> anyhow::Result<()> {
+    let reactor = runtime::Reactor::new(&fixture("reactor.wat"), None)?;
+    let error =
+        |name: &str, args: &[&str]| format!("{:?}", reactor.invoke(name, args).unwrap_err());
+
+    let err = error("missing", &[]);
+    assert!(
+        err.contains("no exported function named `missing`"),
+        "bad error: {}",
+        err
+    );
+    let err = error("add", &[]);
+    assert!(
+        err.contains("`add` takes 1 arguments, but 0 were given"),
+        "bad error: {}",
+        err
+    );
+    let err = error("scale", &["1.5", "three"]);
+    assert!(
+        err.contains("argument 1 of `scale` isn't an f64"),
+        "bad error: {}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn reactor_rejects_commands() {
+    let err = runtime::Reactor::new(&fixture("command_and_reactor.wat"), None).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("both a Command and a Reactor"),
+        "bad error: {:?}",
+        err
+    );
+}
@@ -1,9 +1,15 @@
-use anyhow::Context;
-use std::convert::TryFrom;
-use std::fs::File;
-use std::path::Path;
-use wasi_common::{OsOther, VirtualDirEntry};
-use wasmtime::{Linker, Module, Store};
+use crate::utils;
+use anyhow::{bail, Context};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
+use wasi_common::virtfs::pipe::BoundedPipe;
+use wasi_common::virtfs::VecFileContents;
+use wasi_common::{FollowSymlinks, PreopenOptions, VirtualDirEntry};
+use wasmtime::{Config, Engine, Linker, Module, Store, TrapCode, Val, ValType};
 
 #[derive(Clone, Copy, Debug)]
 pub enum PreopenType {
@@ -13,19 +19,155 @@ pub enum PreopenType {
     Virtual,
 }
 
+/// The `i`th argument passed to `args_large`, which generates the same ones to compare against.
+fn large_arg(i: usize) -> Vec<u8> {
+    let len = (i * 37) % 2048;
+    (0..len).map(|j| ((i + j) % 255 + 1) as u8).collect()
+}
+
+/// Compiles `data`, unless the `WASMTIME_TEST_MODULE_CACHE` environment variable names a directory
+/// in which it has been compiled before, in which case the compiled module is loaded from there.
+///
+/// Most of the time spent running the test programs goes to compiling them, which this skips
+/// across runs. Modules compiled by another version of wasmtime, or with other settings, fail to
+/// deserialize and are compiled and cached again.
+fn load_module(engine: &Engine, data: &[u8]) -> anyhow::Result<Module> {
+    let cache_dir = match env::var_os("WASMTIME_TEST_MODULE_CACHE") {
+        Some(dir) => PathBuf::from(dir),
+        None => return Module::new(engine, data),
+    };
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    let path = cache_dir.join(format!("{:016x}.cwasm", hasher.finish()));
+    if let Ok(serialized) = fs::read(&path) {
+        if let Ok(module) = Module::deserialize(engine, &serialized) {
+            return Ok(module);
+        }
+    }
+    let module = Module::new(engine, data)?;
+    fs::create_dir_all(&cache_dir)
+        .with_context(|| format!("failed to create the module cache at {:?}", cache_dir))?;
+    fs::write(&path, module.serialize()?)
+        .with_context(|| format!("failed to cache the module at {:?}", path))?;
+    Ok(module)
+}
+
+/// How long a test program may run before it's interrupted and the test fails, which is read in
+/// seconds from the `WASMTIME_TEST_TIMEOUT` environment variable, so that a program which hangs
+/// fails its test rather than hanging the whole test run.
+fn timeout() -> Duration {
+    env::var("WASMTIME_TEST_TIMEOUT")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(60))
+}
+
+/// The arguments a test program is run with.
+#[derive(Clone, Copy)]
+enum Args {
+    /// The program name and `.`, the workspace.
+    Default,
+    /// No arguments at all, not even the program name.
+    Empty,
+    /// The program name followed by 1000 arguments generated by `large_arg`.
+    Large,
+}
+
+/// What a test program expects of its environment, beyond the defaults of `Args::Default`, stdio
+/// inherited, except for an empty stdin which is never closed, and the workspace preopened at `.`.
+struct TestConfig {
+    args: Args,
+    /// Whether stdout is a small pipe which the host drains from another thread once it's full.
+    drained_stdout: bool,
+    /// Whether directories preopened from the OS list their entries sorted. Virtual directories
+    /// always do.
+    sorted_readdir: bool,
+    /// Further preopens of the workspace, when it's a real directory, with the guest path and the
+    /// options of each.
+    extra_preopens: Vec<(&'static str, PreopenOptions)>,
+    /// Whether `/etc` is preopened as a virtual directory with a `hosts` file, with a virtual
+    /// `/etc/config` file added to it.
+    virtual_etc: bool,
+    /// Whether `/missing` is lazily preopened as a directory which doesn't exist.
+    lazy_missing: bool,
+    /// Whether the host makes the workspace preopen read-only once the context is built.
+    downgrade_workspace: bool,
+}
+
+impl TestConfig {
+    fn for_program(bin_name: &str) -> TestConfig {
+        let mut config = TestConfig {
+            args: Args::Default,
+            drained_stdout: false,
+            sorted_readdir: false,
+            extra_preopens: Vec::new(),
+            virtual_etc: false,
+            lazy_missing: false,
+            downgrade_workspace: false,
+        };
+        match bin_name {
+            "args_empty" => config.args = Args::Empty,
+            "args_large" => config.args = Args::Large,
+            "poll_oneoff_pipe" => config.drained_stdout = true,
+            "fd_readdir_sorted" => config.sorted_readdir = true,
+            "symlink_policy" => {
+                let mut options = PreopenOptions::new();
+                options.follow_symlinks(FollowSymlinks::Never);
+                config.extra_preopens.push(("/never", options));
+            }
+            "readonly_preopen" => {
+                let mut options = PreopenOptions::new();
+                options.read_only();
+                config.extra_preopens.push(("/readonly", options));
+            }
+            "virtual_file" => config.virtual_etc = true,
+            "preopen_lazy" => config.lazy_missing = true,
+            "fd_rights_downgrade" => config.downgrade_workspace = true,
+            _ => {}
+        }
+        config
+    }
+}
+
 pub fn instantiate(
     data: &[u8],
     bin_name: &str,
     workspace: Option<&Path>,
     preopen_type: PreopenType,
-) -> anyhow::Result<()> {
-    let store = Store::default();
+) -> anyhow::Result<i32> {
+    let store = Store::new(&Engine::new(Config::new().interruptable(true)));
+    let config = TestConfig::for_program(bin_name);
 
     // Create our wasi context with pretty standard arguments/inheritance/etc.
     // Additionally register any preopened directories if we have them.
     let mut builder = wasi_common::WasiCtxBuilder::new();
 
-    builder.arg(bin_name).arg(".").inherit_stdio();
+    match config.args {
+        Args::Default => {
+            builder.arg(bin_name).arg(".");
+        }
+        Args::Empty => {}
+        Args::Large => {
+            builder.arg(bin_name).args((0..1000).map(large_arg));
+        }
+    }
+    builder.inherit_stdio();
+
+    let drainer = if config.drained_stdout {
+        let pipe = BoundedPipe::new(4096);
+        builder.stdout(pipe.clone());
+        Some(thread::spawn(move || {
+            let start = Instant::now();
+            while pipe.len() < pipe.capacity() && start.elapsed() < Duration::from_secs(10) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            thread::sleep(Duration::from_millis(50));
+            pipe.drain();
+        }))
+    } else {
+        None
+    };
 
     if let Some(workspace) = workspace {
         match preopen_type {
@@ -33,6 +175,12 @@ pub fn instantiate(
                 let preopen_dir = wasi_common::preopen_dir(workspace)
                     .context(format!("error while preopening {:?}", workspace))?;
                 builder.preopened_dir(preopen_dir, ".");
+                builder.sorted_readdir(config.sorted_readdir);
+                for (guest_path, options) in &config.extra_preopens {
+                    let dir = wasi_common::preopen_dir(workspace)
+                        .context(format!("error while preopening {:?}", workspace))?;
+                    builder.preopened_dir_with_options(dir, guest_path, options);
+                }
             }
             PreopenType::Virtual => {
                 // we can ignore the workspace path for virtual preopens because virtual preopens
@@ -40,40 +188,163 @@ pub fn instantiate(
                 builder.preopened_virt(VirtualDirEntry::empty_directory(), ".");
             }
         }
+        if config.lazy_missing {
+            builder.preopened_dir_lazy(workspace.join("missing"), "/missing");
+        }
     }
 
     // The nonstandard thing we do with `WasiCtxBuilder` is to ensure that
-    // `stdin` is always an unreadable pipe. This is expected in the test suite
-    // where `stdin` is never ready to be read. In some CI systems, however,
-    // stdin is closed which causes tests to fail.
-    let (reader, _writer) = os_pipe::pipe()?;
-    let file = reader_to_file(reader);
-    let handle = OsOther::try_from(file).context("failed to create OsOther from PipeReader")?;
-    builder.stdin(handle);
-    let snapshot1 = wasmtime_wasi::Wasi::new(&store, builder.build()?);
+    // `stdin` is always an empty pipe which is never closed. This is expected
+    // in the test suite where `stdin` is never ready to be read. In some CI
+    // systems, however, stdin is closed which causes tests to fail.
+    builder.stdin(BoundedPipe::new(0));
+    if config.virtual_etc {
+        let mut etc = HashMap::new();
+        etc.insert(
+            "hosts".to_owned(),
+            VirtualDirEntry::File(Box::new(VecFileContents::with_content(
+                b"127.0.0.1 localhost\n".to_vec(),
+            ))),
+        );
+        builder
+            .preopened_virt(VirtualDirEntry::Directory(etc), "/etc")
+            .virtual_file("/etc/config", b"nameserver 127.0.0.1\n".to_vec());
+    }
+    let ctx = builder.build()?;
+
+    if config.downgrade_workspace && workspace.is_some() {
+        let preopen_fd = ctx
+            .preopen_fd(".")
+            .context("the workspace isn't preopened")?;
+        let read_only = PreopenOptions::new().read_only().get_rights();
+        let rights = ctx.fd_rights(preopen_fd)?;
+        ctx.set_fd_rights(
+            preopen_fd,
+            rights.base() & read_only.base(),
+            rights.inheriting() & read_only.inheriting(),
+        )?;
+    }
+
+    let snapshot1 = wasmtime_wasi::Wasi::new(&store, ctx);
 
     let mut linker = Linker::new(&store);
 
     snapshot1.add_to_linker(&mut linker)?;
 
-    let module = Module::new(store.engine(), &data).context("failed to create wasm module")?;
+    let module = load_module(store.engine(), data).context("failed to create wasm module")?;
 
-    linker
+    let start = linker
         .module("", &module)
         .and_then(|m| m.get_default(""))
         .and_then(|f| f.get0::<()>())
-        .and_then(|f| f().map_err(Into::into))
-        .context(format!("error while testing Wasm module '{}'", bin_name,))
+        .context(format!("error while testing Wasm module '{}'", bin_name,))?;
+    let timeout = timeout();
+    let timer = store.interrupt_handle()?.interrupt_after(timeout);
+    let result = start();
+    let timed_out = timer.fired();
+    drop(timer);
+    // A module exiting through `proc_exit` isn't an error, just an exit code.
+    let exit_code = match result {
+        Ok(()) => 0,
+        Err(trap) => match trap.i32_exit_status() {
+            Some(status) => status,
+            None if timed_out && trap.trap_code() == Some(TrapCode::Interrupt) => {
+                return Err(anyhow::Error::new(trap).context(format!(
+                    "Wasm module '{}' timed out after {:?}",
+                    bin_name, timeout
+                )))
+            }
+            None => {
+                return Err(anyhow::Error::new(trap)
+                    .context(format!("error while testing Wasm module '{}'", bin_name,)))
+            }
+        },
+    };
+
+    if let Some(drainer) = drainer {
+        drainer.join().unwrap();
+    }
+    Ok(exit_code)
 }
 
-#[cfg(unix)]
-fn reader_to_file(reader: os_pipe::PipeReader) -> File {
-    use std::os::unix::prelude::*;
-    unsafe { File::from_raw_fd(reader.into_raw_fd()) }
+/// Runs the wasm, or wat, file at `path` the same way as the generated tests run the test
+/// programs, with the file's stem as the program name, returning its exit code.
+///
+/// This is handy for pointing the harness at a single module while debugging.
+pub fn run_file(path: &Path, workspace: Option<&Path>) -> anyhow::Result<i32> {
+    let data = wat::parse_file(path)?;
+    let bin_name = utils::extract_exec_name_from_path(path)?;
+    instantiate(&data, &bin_name, workspace, PreopenType::OS)
 }
 
-#[cfg(windows)]
-fn reader_to_file(reader: os_pipe::PipeReader) -> File {
-    use std::os::windows::prelude::*;
-    unsafe { File::from_raw_handle(reader.into_raw_handle()) }
+/// A reactor module, which is instantiated once and then has its exports invoked any number of
+/// times, against the same instance and WASI context.
+pub struct Reactor {
+    linker: Linker,
+}
+
+impl Reactor {
+    /// Instantiates the wasm, or wat, file at `path`, with `workspace` preopened as `.`, running
+    /// its `_initialize` export if it has one. Modules which export `_start` as well are rejected.
+    pub fn new(path: &Path, workspace: Option<&Path>) -> anyhow::Result<Reactor> {
+        let data = wat::parse_file(path)?;
+        let bin_name = utils::extract_exec_name_from_path(path)?;
+        let store = Store::default();
+        let mut builder = wasi_common::WasiCtxBuilder::new();
+        builder.arg(&bin_name).inherit_stdio();
+        if let Some(workspace) = workspace {
+            let preopen_dir = wasi_common::preopen_dir(workspace)
+                .context(format!("error while preopening {:?}", workspace))?;
+            builder.preopened_dir(preopen_dir, ".");
+        }
+        let mut linker = Linker::new(&store);
+        wasmtime_wasi::Wasi::new(&store, builder.build()?).add_to_linker(&mut linker)?;
+        let module = load_module(store.engine(), &data).context("failed to create wasm module")?;
+        linker.module("", &module).context(format!(
+            "error while instantiating Wasm module '{}'",
+            bin_name
+        ))?;
+        Ok(Reactor { linker })
+    }
+
+    /// Calls the export `name` with `args`, each parsed as the type of the corresponding
+    /// parameter, returning its results.
+    pub fn invoke(&self, name: &str, args: &[&str]) -> anyhow::Result<Vec<Val>> {
+        let func = self
+            .linker
+            .get_one_by_name("", name)
+            .ok()
+            .and_then(|export| export.into_func())
+            .with_context(|| format!("no exported function named `{}`", name))?;
+        let ty = func.ty();
+        if args.len() != ty.params().len() {
+            bail!(
+                "`{}` takes {} arguments, but {} were given",
+                name,
+                ty.params().len(),
+                args.len()
+            );
+        }
+        let params = ty
+            .params()
+            .zip(args)
+            .enumerate()
+            .map(|(i, (ty, arg))| {
+                let val = match ty {
+                    ValType::I32 => arg.parse::<i32>().map(Val::from).ok(),
+                    ValType::I64 => arg.parse::<i64>().map(Val::from).ok(),
+                    ValType::F32 => arg.parse::<f32>().map(Val::from).ok(),
+                    ValType::F64 => arg.parse::<f64>().map(Val::from).ok(),
+                    _ => bail!("argument {} of `{}` has unsupported type {}", i, name, ty),
+                };
+                val.with_context(|| {
+                    format!("argument {} of `{}` isn't an {}: {:?}", i, name, ty, arg)
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let results = func
+            .call(&params)
+            .with_context(|| format!("error while invoking `{}`", name))?;
+        Ok(results.into_vec())
+    }
 }
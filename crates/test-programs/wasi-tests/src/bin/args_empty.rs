@@ -0,0 +1,17 @@
+//! Run without any arguments, not even the program name.
+
+unsafe fn test_args_empty() {
+    let (argc, buf_size) = wasi::args_sizes_get().expect("args_sizes_get");
+    assert_eq!(argc, 0, "there should be no arguments");
+    assert_eq!(buf_size, 0, "there should be no argument bytes");
+
+    // With nothing to write, zero-sized buffers are enough.
+    let mut argv = Vec::new();
+    let mut buf = Vec::new();
+    wasi::args_get(argv.as_mut_ptr(), buf.as_mut_ptr()).expect("args_get");
+}
+
+fn main() {
+    // Run the tests.
+    unsafe { test_args_empty() }
+}
@@ -0,0 +1,47 @@
+//! Run with the program name followed by 1000 arguments generated by `large_arg`, which the host
+//! generates the same way.
+use std::ffi::CStr;
+use std::ptr;
+
+const NUM_ARGS: usize = 1000;
+
+// Lengths vary from empty to a couple of KiB, adding up to far more than 64KiB, and every byte
+// value but NUL shows up, including ones which aren't valid UTF-8.
+fn large_arg(i: usize) -> Vec<u8> {
+    let len = (i * 37) % 2048;
+    (0..len).map(|j| ((i + j) % 255 + 1) as u8).collect()
+}
+
+unsafe fn test_args_large() {
+    let mut expected = vec![b"args_large".to_vec()];
+    expected.extend((0..NUM_ARGS).map(large_arg));
+    let expected_size: usize = expected.iter().map(|arg| arg.len() + 1).sum();
+
+    let (argc, buf_size) = wasi::args_sizes_get().expect("args_sizes_get");
+    assert_eq!(argc, expected.len(), "number of arguments");
+    assert_eq!(
+        buf_size, expected_size,
+        "size of the arguments including NUL terminators"
+    );
+
+    // Make the buffers larger than needed to check that `args_get` writes exactly as much as
+    // `args_sizes_get` reported.
+    let mut argv = vec![ptr::null_mut(); argc + 1];
+    let mut buf = vec![0xaa; buf_size + 16];
+    wasi::args_get(argv.as_mut_ptr(), buf.as_mut_ptr()).expect("args_get");
+    assert!(argv[argc].is_null(), "argv should not be written past argc");
+    assert!(
+        buf[buf_size..].iter().all(|b| *b == 0xaa),
+        "argv_buf should not be written past its reported size"
+    );
+
+    for (i, (arg, expected)) in argv.iter().zip(&expected).enumerate() {
+        let arg = CStr::from_ptr(*arg as *const _).to_bytes();
+        assert_eq!(arg, &expected[..], "argument {} should round-trip", i);
+    }
+}
+
+fn main() {
+    // Run the tests.
+    unsafe { test_args_large() }
+}
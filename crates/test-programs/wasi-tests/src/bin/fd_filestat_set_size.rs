@@ -0,0 +1,94 @@
+use std::{env, process};
+use wasi_tests::{drop_rights, open_scratch_directory};
+
+unsafe fn pread_all(fd: wasi::Fd) -> Vec<u8> {
+    let mut buf = [0xffu8; 32];
+    let nread = wasi::fd_pread(
+        fd,
+        &[wasi::Iovec {
+            buf: buf.as_mut_ptr(),
+            buf_len: buf.len(),
+        }],
+        0,
+    )
+    .expect("reading file content");
+    buf[..nread].to_vec()
+}
+
+unsafe fn set_size(fd: wasi::Fd, size: wasi::Filesize) {
+    wasi::fd_filestat_set_size(fd, size).expect("fd_filestat_set_size");
+    let stat = wasi::fd_filestat_get(fd).expect("fd_filestat_get");
+    assert_eq!(stat.size, size, "file size should be {}", size);
+}
+
+unsafe fn test_fd_filestat_set_size(dir_fd: wasi::Fd) {
+    let file_fd = wasi::path_open(
+        dir_fd,
+        0,
+        "file",
+        wasi::OFLAGS_CREAT,
+        wasi::RIGHTS_FD_READ
+            | wasi::RIGHTS_FD_WRITE
+            | wasi::RIGHTS_FD_FILESTAT_GET
+            | wasi::RIGHTS_FD_FILESTAT_SET_SIZE,
+        0,
+        0,
+    )
+    .expect("creating a file");
+
+    let content = b"0123456789";
+    let nwritten = wasi::fd_write(
+        file_fd,
+        &[wasi::Ciovec {
+            buf: content.as_ptr(),
+            buf_len: content.len(),
+        }],
+    )
+    .expect("writing file content");
+    assert_eq!(nwritten, content.len(), "nwritten bytes check");
+
+    // Shrinking discards the data past the new end...
+    set_size(file_fd, 4);
+    assert_eq!(pread_all(file_fd), b"0123");
+
+    // ... so extending again reads back zeros rather than the old data.
+    set_size(file_fd, 8);
+    assert_eq!(pread_all(file_fd), b"0123\0\0\0\0");
+
+    // Resizing requires its own right.
+    drop_rights(file_fd, wasi::RIGHTS_FD_FILESTAT_SET_SIZE, 0);
+    assert_eq!(
+        wasi::fd_filestat_set_size(file_fd, 0)
+            .expect_err("resizing without the right should fail")
+            .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "errno should be ERRNO_NOTCAPABLE"
+    );
+    assert_eq!(pread_all(file_fd).len(), 8, "the file should be unchanged");
+
+    wasi::fd_close(file_fd).expect("closing the file");
+    wasi::path_unlink_file(dir_fd, "file").expect("removing the file");
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_fd_filestat_set_size(dir_fd) }
+}
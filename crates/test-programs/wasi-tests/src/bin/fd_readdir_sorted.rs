@@ -0,0 +1,115 @@
+use std::{env, mem, process, slice, str};
+use wasi_tests::open_scratch_directory;
+
+// Names in the order they're created, which is neither the sorted order nor, most likely, the
+// order the host lists them in.
+const NAMES: &[&str] = &["b", "a10", "_z", "A", "c", "a2", "B0"];
+
+unsafe fn create_file(dir_fd: wasi::Fd, name: &str) {
+    let fd =
+        wasi::path_open(dir_fd, 0, name, wasi::OFLAGS_CREAT, 0, 0, 0).expect("creating a file");
+    wasi::fd_close(fd).expect("closing the file");
+}
+
+// Reads the entries starting at `cookie` with a buffer of `buf_len` bytes, returning the names
+// and cookies of those which fit.
+unsafe fn read_entries(
+    dir_fd: wasi::Fd,
+    buf_len: usize,
+    cookie: wasi::Dircookie,
+) -> Vec<(String, wasi::Dircookie)> {
+    let mut buf = vec![0; buf_len];
+    let bufused =
+        wasi::fd_readdir(dir_fd, buf.as_mut_ptr(), buf_len, cookie).expect("failed fd_readdir");
+    let mut buf = &buf[..bufused];
+    let mut entries = Vec::new();
+    while !buf.is_empty() {
+        let dirent = (buf.as_ptr() as *const wasi::Dirent).read_unaligned();
+        let namelen = dirent.d_namlen as usize;
+        let name_ptr = buf.as_ptr().add(mem::size_of::<wasi::Dirent>());
+        let name = str::from_utf8(slice::from_raw_parts(name_ptr, namelen)).expect("invalid utf8");
+        entries.push((name.to_owned(), dirent.d_next));
+        buf = &buf[mem::size_of::<wasi::Dirent>() + namelen..];
+    }
+    entries
+}
+
+unsafe fn test_fd_readdir_sorted(dir_fd: wasi::Fd) {
+    wasi::path_create_directory(dir_fd, "dir").expect("creating a directory");
+    for name in NAMES {
+        create_file(dir_fd, &format!("dir/{}", name));
+    }
+    let fd = wasi::path_open(
+        dir_fd,
+        0,
+        "dir",
+        wasi::OFLAGS_DIRECTORY,
+        wasi::RIGHTS_FD_READDIR,
+        0,
+        0,
+    )
+    .expect("opening the directory");
+
+    let expected = [".", "..", "A", "B0", "_z", "a10", "a2", "b", "c"];
+
+    // Everything fits in one call.
+    let entries = read_entries(fd, 1024, 0);
+    let names: Vec<_> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, expected, "entries should be sorted");
+
+    // Resuming from each entry's cookie continues right after it.
+    for (i, (_, cookie)) in entries.iter().enumerate() {
+        let rest = read_entries(fd, 1024, *cookie);
+        let names: Vec<_> = rest.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, &expected[i + 1..], "entries after cookie {}", cookie);
+    }
+
+    // A buffer with room for only one entry at a time lists the same entries over many calls.
+    let buf_len = mem::size_of::<wasi::Dirent>() + 3;
+    let mut names = Vec::new();
+    let mut cookie = 0;
+    loop {
+        let entries = read_entries(fd, buf_len, cookie);
+        match entries.as_slice() {
+            [] => break,
+            [(name, next)] => {
+                names.push(name.clone());
+                cookie = *next;
+            }
+            _ => panic!("expected a single entry, got {}", entries.len()),
+        }
+    }
+    assert_eq!(
+        names, expected,
+        "entries read one at a time should be sorted"
+    );
+
+    wasi::fd_close(fd).expect("closing the directory");
+    for name in NAMES {
+        wasi::path_unlink_file(dir_fd, &format!("dir/{}", name)).expect("removing a file");
+    }
+    wasi::path_remove_directory(dir_fd, "dir").expect("removing the directory");
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_fd_readdir_sorted(dir_fd) }
+}
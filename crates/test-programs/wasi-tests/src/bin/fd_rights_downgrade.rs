@@ -0,0 +1,89 @@
+//! Run with the preopened directory downgraded to read-only by the host.
+use std::{env, process};
+use wasi_tests::{drop_rights, fd_get_rights, open_scratch_directory};
+
+unsafe fn test_write_is_not_capable(dir_fd: wasi::Fd) {
+    let (base, inheriting) = fd_get_rights(dir_fd);
+    assert_eq!(
+        base & wasi::RIGHTS_PATH_CREATE_FILE,
+        0,
+        "dir should not have base RIGHTS_PATH_CREATE_FILE"
+    );
+    assert_eq!(
+        inheriting & wasi::RIGHTS_FD_WRITE,
+        0,
+        "dir should not have inheriting RIGHTS_FD_WRITE"
+    );
+
+    assert_eq!(
+        wasi::path_open(
+            dir_fd,
+            0,
+            "file",
+            wasi::OFLAGS_CREAT,
+            wasi::RIGHTS_FD_WRITE,
+            0,
+            0
+        )
+        .expect_err("creating a file should fail")
+        .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "the errno should be ENOTCAPABLE"
+    );
+    assert_eq!(
+        wasi::path_create_directory(dir_fd, "subdir")
+            .expect_err("creating a directory should fail")
+            .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "the errno should be ENOTCAPABLE"
+    );
+}
+
+unsafe fn test_rights_only_shrink(dir_fd: wasi::Fd) {
+    // Rights dropped by the host can't be regained by the guest.
+    let (base, inheriting) = fd_get_rights(dir_fd);
+    assert_eq!(
+        wasi::fd_fdstat_set_rights(dir_fd, base | wasi::RIGHTS_PATH_CREATE_FILE, inheriting)
+            .expect_err("escalating rights should fail")
+            .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "the errno should be ENOTCAPABLE"
+    );
+    assert_eq!(fd_get_rights(dir_fd), (base, inheriting));
+
+    // ... but the guest can drop more of them itself.
+    drop_rights(dir_fd, wasi::RIGHTS_PATH_OPEN, 0);
+    assert_eq!(
+        wasi::path_open(dir_fd, 0, ".", wasi::OFLAGS_DIRECTORY, 0, 0, 0)
+            .expect_err("opening a path should fail")
+            .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "the errno should be ENOTCAPABLE"
+    );
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe {
+        test_write_is_not_capable(dir_fd);
+        test_rights_only_shrink(dir_fd);
+    }
+}
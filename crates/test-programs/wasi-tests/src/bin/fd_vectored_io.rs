@@ -0,0 +1,156 @@
+use std::{env, process};
+use wasi_tests::open_scratch_directory;
+
+fn ciovec(buf: &[u8]) -> wasi::Ciovec {
+    wasi::Ciovec {
+        buf: buf.as_ptr(),
+        buf_len: buf.len(),
+    }
+}
+
+fn iovec(buf: &mut [u8]) -> wasi::Iovec {
+    wasi::Iovec {
+        buf: buf.as_mut_ptr(),
+        buf_len: buf.len(),
+    }
+}
+
+// Returns a pointer to the last two bytes of linear memory, for buffers which straddle its end.
+fn end_of_memory() -> *mut u8 {
+    (std::arch::wasm32::memory_size(0) * 65536 - 2) as *mut u8
+}
+
+unsafe fn test_fd_write(file_fd: wasi::Fd) {
+    // Empty buffers are skipped, and the same buffer may be written more than once.
+    let ab = b"ab";
+    let cd = b"cd";
+    let nwritten = wasi::fd_write(file_fd, &[ciovec(ab), ciovec(&[]), ciovec(cd), ciovec(ab)])
+        .expect("writing overlapping buffers");
+    assert_eq!(nwritten, 6, "nwritten bytes check");
+
+    // Partially overlapping buffers are fine too.
+    let hello = b"hello";
+    let nwritten = wasi::fd_pwrite(file_fd, &[ciovec(&hello[..3]), ciovec(&hello[1..])], 6)
+        .expect("writing partially overlapping buffers");
+    assert_eq!(nwritten, 7, "nwritten bytes check");
+
+    // A buffer which straddles the end of memory fails the write before anything's written.
+    let past_end = wasi::Ciovec {
+        buf: end_of_memory(),
+        buf_len: 4,
+    };
+    assert_eq!(
+        wasi::fd_write(file_fd, &[ciovec(cd), past_end])
+            .expect_err("writing a buffer past the end of memory")
+            .raw_error(),
+        wasi::ERRNO_FAULT,
+        "errno should be ERRNO_FAULT"
+    );
+    let stat = wasi::fd_filestat_get(file_fd).expect("reading the file's stats");
+    assert_eq!(stat.size, 13, "nothing should be written");
+    assert_eq!(wasi::fd_tell(file_fd).expect("telling the offset"), 6);
+}
+
+unsafe fn test_fd_read(file_fd: wasi::Fd) {
+    wasi::fd_seek(file_fd, 0, wasi::WHENCE_SET).expect("seeking to the start");
+
+    // Reads fill each buffer in turn, and a short read stops partway into the last one.
+    let mut first = [0; 4];
+    let mut second = [0; 20];
+    let nread = wasi::fd_read(
+        file_fd,
+        &[iovec(&mut first), iovec(&mut []), iovec(&mut second)],
+    )
+    .expect("reading into several buffers");
+    assert_eq!(nread, 13, "nread bytes check");
+    assert_eq!(&first, b"abcd");
+    assert_eq!(&second[..9], b"abhelello");
+    assert_eq!(
+        &second[9..],
+        &[0; 11][..],
+        "nothing should be read past the end"
+    );
+
+    // Buffers which are read into can't overlap, and fail the read before anything's read.
+    wasi::fd_seek(file_fd, 0, wasi::WHENCE_SET).expect("seeking to the start");
+    let mut buf = [0; 8];
+    let overlapping = [
+        wasi::Iovec {
+            buf: buf.as_mut_ptr(),
+            buf_len: 4,
+        },
+        wasi::Iovec {
+            buf: buf.as_mut_ptr().add(2),
+            buf_len: 4,
+        },
+    ];
+    assert_eq!(
+        wasi::fd_read(file_fd, &overlapping)
+            .expect_err("reading into overlapping buffers")
+            .raw_error(),
+        wasi::ERRNO_FAULT,
+        "errno should be ERRNO_FAULT"
+    );
+    assert_eq!(buf, [0; 8], "nothing should be read");
+
+    // The same goes for a buffer which straddles the end of memory.
+    let past_end = wasi::Iovec {
+        buf: end_of_memory(),
+        buf_len: 4,
+    };
+    assert_eq!(
+        wasi::fd_read(file_fd, &[iovec(&mut buf), past_end])
+            .expect_err("reading into a buffer past the end of memory")
+            .raw_error(),
+        wasi::ERRNO_FAULT,
+        "errno should be ERRNO_FAULT"
+    );
+    assert_eq!(buf, [0; 8], "nothing should be read");
+    assert_eq!(wasi::fd_tell(file_fd).expect("telling the offset"), 0);
+}
+
+unsafe fn test_fd_vectored_io(dir_fd: wasi::Fd) {
+    let file_fd = wasi::path_open(
+        dir_fd,
+        0,
+        "file",
+        wasi::OFLAGS_CREAT,
+        wasi::RIGHTS_FD_READ
+            | wasi::RIGHTS_FD_WRITE
+            | wasi::RIGHTS_FD_SEEK
+            | wasi::RIGHTS_FD_TELL
+            | wasi::RIGHTS_FD_FILESTAT_GET,
+        0,
+        0,
+    )
+    .expect("creating a file");
+
+    test_fd_write(file_fd);
+    test_fd_read(file_fd);
+
+    wasi::fd_close(file_fd).expect("closing the file");
+    wasi::path_unlink_file(dir_fd, "file").expect("removing the file");
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_fd_vectored_io(dir_fd) }
+}
@@ -0,0 +1,151 @@
+//! Run with stdout connected to a bounded virtual pipe, which the host drains once it has
+//! filled up.
+use more_asserts::assert_gt;
+use std::mem::MaybeUninit;
+use wasi_tests::STDOUT_FD;
+
+const CLOCK_ID: wasi::Userdata = 0x0123_45678;
+const WRITE_ID: wasi::Userdata = 0xfeed_face_dead_beef;
+const READ_ID: wasi::Userdata = u64::max_value();
+const BAD_FD_ID: wasi::Userdata = 0;
+
+unsafe fn poll_oneoff_impl(r#in: &[wasi::Subscription]) -> Vec<wasi::Event> {
+    let mut out: Vec<wasi::Event> = Vec::new();
+    out.resize_with(r#in.len(), || {
+        MaybeUninit::<wasi::Event>::zeroed().assume_init()
+    });
+    let size = wasi::poll_oneoff(r#in.as_ptr(), out.as_mut_ptr(), r#in.len())
+        .expect("poll_oneoff should succeed");
+    out.truncate(size);
+    out
+}
+
+fn subscription(
+    userdata: wasi::Userdata,
+    tag: wasi::Eventtype,
+    fd: wasi::Fd,
+) -> wasi::Subscription {
+    let fd_readwrite = wasi::SubscriptionFdReadwrite {
+        file_descriptor: fd,
+    };
+    let u = if tag == wasi::EVENTTYPE_FD_READ {
+        wasi::SubscriptionUU {
+            fd_read: fd_readwrite,
+        }
+    } else {
+        wasi::SubscriptionUU {
+            fd_write: fd_readwrite,
+        }
+    };
+    wasi::Subscription {
+        userdata,
+        u: wasi::SubscriptionU { tag, u },
+    }
+}
+
+fn clock_subscription(timeout: wasi::Timestamp) -> wasi::Subscription {
+    let clock = wasi::SubscriptionClock {
+        id: wasi::CLOCKID_MONOTONIC,
+        timeout,
+        precision: 0,
+        flags: 0,
+    };
+    wasi::Subscription {
+        userdata: CLOCK_ID,
+        u: wasi::SubscriptionU {
+            tag: wasi::EVENTTYPE_CLOCK,
+            u: wasi::SubscriptionUU { clock },
+        },
+    }
+}
+
+unsafe fn write(data: &[u8]) -> Result<usize, wasi::Error> {
+    wasi::fd_write(
+        STDOUT_FD,
+        &[wasi::Ciovec {
+            buf: data.as_ptr(),
+            buf_len: data.len(),
+        }],
+    )
+}
+
+unsafe fn fill_pipe() -> usize {
+    let chunk = [0xaa; 1000];
+    let mut total = 0;
+    loop {
+        match write(&chunk) {
+            Ok(n) => total += n,
+            Err(e) => {
+                assert_eq!(
+                    e.raw_error(),
+                    wasi::ERRNO_AGAIN,
+                    "a full pipe should fail with EAGAIN"
+                );
+                return total;
+            }
+        }
+    }
+}
+
+unsafe fn test_full_pipe_becomes_writable() {
+    assert_gt!(fill_pipe(), 0, "the pipe should accept some bytes");
+
+    // The host only drains the pipe after it has filled up, so this has to wait. The clock
+    // subscription is only a safeguard against hanging.
+    let out = poll_oneoff_impl(&[
+        subscription(WRITE_ID, wasi::EVENTTYPE_FD_WRITE, STDOUT_FD),
+        clock_subscription(10_000_000_000),
+    ]);
+    assert_eq!(out.len(), 1, "only the write subscription should fire");
+    let event = &out[0];
+    assert_eq!(
+        event.userdata, WRITE_ID,
+        "the event.userdata should round-trip exactly"
+    );
+    assert_eq!(event.error, wasi::ERRNO_SUCCESS);
+    assert_eq!(event.r#type, wasi::EVENTTYPE_FD_WRITE);
+    assert_gt!(
+        event.fd_readwrite.nbytes,
+        0,
+        "the event should report the free space of the pipe"
+    );
+
+    assert_eq!(write(b"hello").expect("writing to a drained pipe"), 5);
+}
+
+unsafe fn test_mixed_subscriptions() {
+    // The pipe now holds a few bytes, so it's both readable and writable, and a bad file
+    // descriptor only fails its own subscription.
+    let out = poll_oneoff_impl(&[
+        subscription(READ_ID, wasi::EVENTTYPE_FD_READ, STDOUT_FD),
+        subscription(WRITE_ID, wasi::EVENTTYPE_FD_WRITE, STDOUT_FD),
+        subscription(BAD_FD_ID, wasi::EVENTTYPE_FD_READ, wasi::Fd::max_value()),
+    ]);
+    assert_eq!(out.len(), 3, "every subscription should fire");
+    let find = |userdata| {
+        out.iter()
+            .find(|event| event.userdata == userdata)
+            .expect("an event for every subscription")
+    };
+
+    let read = find(READ_ID);
+    assert_eq!(read.error, wasi::ERRNO_SUCCESS);
+    assert_eq!(read.r#type, wasi::EVENTTYPE_FD_READ);
+    assert_eq!(read.fd_readwrite.nbytes, 5, "the pipe holds 5 bytes");
+
+    let write = find(WRITE_ID);
+    assert_eq!(write.error, wasi::ERRNO_SUCCESS);
+    assert_eq!(write.r#type, wasi::EVENTTYPE_FD_WRITE);
+    assert_gt!(write.fd_readwrite.nbytes, 0);
+
+    let bad = find(BAD_FD_ID);
+    assert_eq!(bad.error, wasi::ERRNO_BADF);
+    assert_eq!(bad.r#type, wasi::EVENTTYPE_FD_READ);
+}
+
+fn main() {
+    unsafe {
+        test_full_pipe_becomes_writable();
+        test_mixed_subscriptions();
+    }
+}
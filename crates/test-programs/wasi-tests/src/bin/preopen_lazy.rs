@@ -0,0 +1,53 @@
+use std::{env, process};
+use wasi_tests::{find_preopen, open_scratch_directory};
+
+unsafe fn test_preopen_lazy(dir_fd: wasi::Fd) {
+    // Finding the preopen of a missing directory and getting its fdstat works, as it isn't opened
+    // yet.
+    let missing_fd = find_preopen("/missing");
+    let fdstat = wasi::fd_fdstat_get(missing_fd).expect("the preopen's fdstat");
+    assert_eq!(fdstat.fs_filetype, wasi::FILETYPE_DIRECTORY);
+
+    // Other preopens are unaffected.
+    wasi::path_create_directory(dir_fd, "subdir").expect("creating a directory");
+    wasi::path_remove_directory(dir_fd, "subdir").expect("removing a directory");
+
+    // Using the missing directory fails once it's needed.
+    assert_eq!(
+        wasi::path_open(missing_fd, 0, "file", 0, 0, 0, 0)
+            .expect_err("opening a file in a missing directory")
+            .raw_error(),
+        wasi::ERRNO_NOENT,
+        "errno should be ERRNO_NOENT"
+    );
+    assert_eq!(
+        wasi::fd_filestat_get(missing_fd)
+            .expect_err("getting the stats of a missing directory")
+            .raw_error(),
+        wasi::ERRNO_NOENT,
+        "errno should be ERRNO_NOENT"
+    );
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_preopen_lazy(dir_fd) }
+}
@@ -0,0 +1,137 @@
+use std::{env, process};
+use wasi_tests::{create_file, fd_get_rights, find_preopen, open_scratch_directory};
+
+fn assert_notcapable<T: std::fmt::Debug>(result: Result<T, wasi::Error>, what: &str) {
+    assert_eq!(
+        result.expect_err(what).raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "errno should be ERRNO_NOTCAPABLE"
+    );
+}
+
+unsafe fn test_readonly_preopen(dir_fd: wasi::Fd) {
+    create_file(dir_fd, "file");
+    wasi::path_create_directory(dir_fd, "subdir").expect("creating a directory");
+
+    // The host preopens the scratch directory a second time, read-only.
+    let ro_fd = find_preopen("/readonly");
+    let (base, inheriting) = fd_get_rights(ro_fd);
+    assert_eq!(
+        base & (wasi::RIGHTS_PATH_CREATE_FILE | wasi::RIGHTS_PATH_UNLINK_FILE),
+        0,
+        "the preopen shouldn't have rights which modify it"
+    );
+    assert_eq!(
+        inheriting & wasi::RIGHTS_FD_WRITE,
+        0,
+        "the preopen shouldn't hand out the right to write"
+    );
+
+    // Nothing can be created, truncated or removed.
+    assert_notcapable(
+        wasi::path_open(ro_fd, 0, "new", wasi::OFLAGS_CREAT, 0, 0, 0),
+        "creating a file",
+    );
+    assert_notcapable(
+        wasi::path_open(ro_fd, 0, "file", wasi::OFLAGS_TRUNC, 0, 0, 0),
+        "truncating a file",
+    );
+    assert_notcapable(
+        wasi::path_create_directory(ro_fd, "newdir"),
+        "creating a directory",
+    );
+    assert_notcapable(wasi::path_unlink_file(ro_fd, "file"), "removing a file");
+    assert_notcapable(
+        wasi::path_remove_directory(ro_fd, "subdir"),
+        "removing a directory",
+    );
+
+    // Files can be opened for reading, but not for writing.
+    assert_notcapable(
+        wasi::path_open(ro_fd, 0, "file", 0, wasi::RIGHTS_FD_WRITE, 0, 0),
+        "opening a file for writing",
+    );
+    let file_fd = wasi::path_open(ro_fd, 0, "file", 0, wasi::RIGHTS_FD_READ, 0, 0)
+        .expect("opening a file for reading");
+    let contents = &mut [0u8; 1];
+    let iovec = wasi::Iovec {
+        buf: contents.as_mut_ptr() as *mut _,
+        buf_len: contents.len(),
+    };
+    wasi::fd_read(file_fd, &[iovec]).expect("reading from a file");
+    let data = [0u8; 1];
+    let ciovec = wasi::Ciovec {
+        buf: data.as_ptr() as *const _,
+        buf_len: data.len(),
+    };
+    assert_notcapable(wasi::fd_write(file_fd, &[ciovec]), "writing to a file");
+    wasi::fd_close(file_fd).expect("closing a file");
+
+    // Directories opened from the preopen are read-only too, and can't be reopened with more
+    // rights.
+    assert_notcapable(
+        wasi::path_open(
+            ro_fd,
+            0,
+            "subdir",
+            wasi::OFLAGS_DIRECTORY,
+            base | wasi::RIGHTS_PATH_CREATE_FILE,
+            inheriting,
+            0,
+        ),
+        "opening a directory with more rights",
+    );
+    let subdir_fd = wasi::path_open(
+        ro_fd,
+        0,
+        "subdir",
+        wasi::OFLAGS_DIRECTORY,
+        base,
+        inheriting,
+        0,
+    )
+    .expect("opening a directory");
+    assert_notcapable(
+        wasi::path_open(subdir_fd, 0, "new", wasi::OFLAGS_CREAT, 0, 0, 0),
+        "creating a file in a subdirectory",
+    );
+    assert_notcapable(
+        wasi::path_open(
+            subdir_fd,
+            0,
+            ".",
+            wasi::OFLAGS_DIRECTORY,
+            base,
+            wasi::RIGHTS_FD_WRITE,
+            0,
+        ),
+        "reopening a subdirectory with more rights",
+    );
+    wasi::fd_close(subdir_fd).expect("closing a directory");
+
+    wasi::path_remove_directory(dir_fd, "subdir").expect("removing a directory");
+    wasi::path_unlink_file(dir_fd, "file").expect("removing a file");
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_readonly_preopen(dir_fd) }
+}
@@ -0,0 +1,140 @@
+use std::io::Write;
+use std::{env, process};
+use wasi_tests::{open_scratch_directory, STDOUT_FD};
+
+unsafe fn read_file(dir_fd: wasi::Fd, filename: &str) -> Vec<u8> {
+    let fd = wasi::path_open(dir_fd, 0, filename, 0, wasi::RIGHTS_FD_READ, 0, 0)
+        .expect("opening the file for reading");
+    let mut contents = vec![0; 100];
+    let nread = wasi::fd_read(
+        fd,
+        &[wasi::Iovec {
+            buf: contents.as_mut_ptr(),
+            buf_len: contents.len(),
+        }],
+    )
+    .expect("reading the file");
+    contents.truncate(nread);
+    wasi::fd_close(fd).expect("closing the file");
+    contents
+}
+
+unsafe fn test_renumber_onto_stdout(dir_fd: wasi::Fd) {
+    let file_fd = wasi::path_open(
+        dir_fd,
+        0,
+        "stdout",
+        wasi::OFLAGS_CREAT,
+        wasi::RIGHTS_FD_READ | wasi::RIGHTS_FD_WRITE,
+        0,
+        0,
+    )
+    .expect("creating a file");
+
+    // Replace stdout with the file, after which prints land in the file.
+    wasi::fd_renumber(file_fd, STDOUT_FD).expect("renumbering a file onto stdout");
+    assert_eq!(
+        wasi::fd_close(file_fd)
+            .expect_err("closing the renumbered file descriptor")
+            .raw_error(),
+        wasi::ERRNO_BADF,
+        "errno should be ERRNO_BADF"
+    );
+    let mut stdout = std::io::stdout();
+    stdout.write_all(b"hello from stdout\n").expect("printing");
+    stdout.flush().expect("flushing stdout");
+
+    assert_eq!(read_file(dir_fd, "stdout"), b"hello from stdout\n");
+    wasi::path_unlink_file(dir_fd, "stdout").expect("removing the file");
+}
+
+unsafe fn test_renumber_errors(dir_fd: wasi::Fd) {
+    // A source which doesn't exist is a bad file descriptor, wherever it's renumbered to.
+    assert_eq!(
+        wasi::fd_renumber(wasi::Fd::max_value(), dir_fd)
+            .expect_err("renumbering a closed file descriptor")
+            .raw_error(),
+        wasi::ERRNO_BADF,
+        "errno should be ERRNO_BADF"
+    );
+
+    // Preopens can't be renumbered, or renumbered over, even onto themselves.
+    let fd =
+        wasi::path_open(dir_fd, 0, "file", wasi::OFLAGS_CREAT, 0, 0, 0).expect("creating a file");
+    for (from, to) in [(dir_fd, dir_fd), (dir_fd, fd), (fd, dir_fd)].iter() {
+        assert_eq!(
+            wasi::fd_renumber(*from, *to)
+                .expect_err("renumbering a preopen")
+                .raw_error(),
+            wasi::ERRNO_NOTSUP,
+            "errno should be ERRNO_NOTSUP"
+        );
+    }
+
+    // Renumbering any other descriptor onto itself leaves it open.
+    wasi::fd_renumber(fd, fd).expect("renumbering a descriptor onto itself");
+    wasi::fd_fdstat_get(fd).expect("the descriptor should still be open");
+    wasi::fd_close(fd).expect("closing the file");
+    wasi::path_unlink_file(dir_fd, "file").expect("removing the file");
+}
+
+unsafe fn test_renumber_onto_unused(dir_fd: wasi::Fd) {
+    let fd =
+        wasi::path_open(dir_fd, 0, "file", wasi::OFLAGS_CREAT, 0, 0, 0).expect("creating a file");
+    let unused = 50;
+    wasi::fd_renumber(fd, unused).expect("renumbering onto an unused descriptor");
+
+    // The new number is now in use, and newly opened files don't get it.
+    let mut fds = Vec::new();
+    for _ in 0..60 {
+        let fd = wasi::path_open(dir_fd, 0, "file", 0, 0, 0, 0).expect("opening the file");
+        assert_ne!(fd, unused, "the renumbered descriptor shouldn't be reused");
+        fds.push(fd);
+    }
+    for fd in fds {
+        wasi::fd_close(fd).expect("closing the file");
+    }
+    wasi::fd_close(unused).expect("closing the renumbered descriptor");
+    assert_eq!(
+        wasi::fd_close(unused)
+            .expect_err("closing an already closed descriptor")
+            .raw_error(),
+        wasi::ERRNO_BADF,
+        "errno should be ERRNO_BADF"
+    );
+    wasi::path_unlink_file(dir_fd, "file").expect("removing the file");
+}
+
+unsafe fn test_preopens_still_found() {
+    // `fd_prestat_get` still finds the preopen from fd 3 up after all of the above.
+    let prestat = wasi::fd_prestat_get(3).expect("fd 3 should still be a preopen");
+    assert_eq!(prestat.tag, wasi::PREOPENTYPE_DIR);
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe {
+        test_renumber_onto_stdout(dir_fd);
+        test_renumber_errors(dir_fd);
+        test_renumber_onto_unused(dir_fd);
+        test_preopens_still_found();
+    }
+}
@@ -0,0 +1,114 @@
+use std::{env, process};
+use wasi_tests::{create_file, find_preopen, open_scratch_directory};
+
+fn assert_loop(result: Result<wasi::Fd, wasi::Error>, what: &str) {
+    assert_eq!(
+        result.expect_err(what).raw_error(),
+        wasi::ERRNO_LOOP,
+        "errno should be ERRNO_LOOP"
+    );
+}
+
+unsafe fn test_symlink_policy(dir_fd: wasi::Fd) {
+    wasi::path_create_directory(dir_fd, "subdir").expect("creating a directory");
+    create_file(dir_fd, "subdir/file");
+    wasi::path_symlink("subdir/file", dir_fd, "link").expect("creating a symlink");
+    wasi::path_symlink("subdir", dir_fd, "dirlink").expect("creating a symlink");
+    wasi::path_symlink("file", dir_fd, "subdir/link").expect("creating a symlink");
+
+    // By default symlinks within the preopen are followed.
+    let fd = wasi::path_open(dir_fd, wasi::LOOKUPFLAGS_SYMLINK_FOLLOW, "link", 0, 0, 0, 0)
+        .expect("opening through a symlink");
+    wasi::fd_close(fd).expect("closing a file");
+    let fd = wasi::path_open(dir_fd, 0, "dirlink/file", 0, 0, 0, 0)
+        .expect("opening through a directory symlink");
+    wasi::fd_close(fd).expect("closing a file");
+
+    // With `FollowSymlinks::Never`, nothing which goes through a symlink resolves.
+    let never_fd = find_preopen("/never");
+    assert_loop(
+        wasi::path_open(
+            never_fd,
+            wasi::LOOKUPFLAGS_SYMLINK_FOLLOW,
+            "link",
+            0,
+            0,
+            0,
+            0,
+        ),
+        "opening through a symlink",
+    );
+    assert_loop(
+        wasi::path_open(never_fd, 0, "dirlink/file", 0, 0, 0, 0),
+        "opening through a directory symlink",
+    );
+    assert_loop(
+        wasi::path_open(never_fd, 0, "dirlink/", wasi::OFLAGS_DIRECTORY, 0, 0, 0),
+        "opening a directory symlink with a trailing slash",
+    );
+    assert_eq!(
+        wasi::path_filestat_get(never_fd, wasi::LOOKUPFLAGS_SYMLINK_FOLLOW, "link")
+            .expect_err("getting the stats of a symlink's target")
+            .raw_error(),
+        wasi::ERRNO_LOOP,
+        "errno should be ERRNO_LOOP"
+    );
+
+    // The symlinks themselves can still be looked at.
+    let mut buf = [0u8; 32];
+    let len = wasi::path_readlink(never_fd, "link", buf.as_mut_ptr(), buf.len())
+        .expect("reading a symlink");
+    assert_eq!(&buf[..len], b"subdir/file");
+    let stat = wasi::path_filestat_get(never_fd, 0, "link").expect("getting a symlink's stats");
+    assert_eq!(stat.filetype, wasi::FILETYPE_SYMBOLIC_LINK);
+
+    // Paths without symlinks resolve as usual.
+    let fd = wasi::path_open(never_fd, 0, "subdir/file", 0, 0, 0, 0).expect("opening a file");
+    wasi::fd_close(fd).expect("closing a file");
+
+    // Directories opened from the preopen keep its policy.
+    let subdir_fd = wasi::path_open(never_fd, 0, "subdir", wasi::OFLAGS_DIRECTORY, 0, 0, 0)
+        .expect("opening a directory");
+    assert_loop(
+        wasi::path_open(
+            subdir_fd,
+            wasi::LOOKUPFLAGS_SYMLINK_FOLLOW,
+            "link",
+            0,
+            0,
+            0,
+            0,
+        ),
+        "opening through a symlink in a subdirectory",
+    );
+    wasi::fd_close(subdir_fd).expect("closing a directory");
+
+    wasi::path_unlink_file(dir_fd, "subdir/link").expect("removing a symlink");
+    wasi::path_unlink_file(dir_fd, "dirlink").expect("removing a symlink");
+    wasi::path_unlink_file(dir_fd, "link").expect("removing a symlink");
+    wasi::path_unlink_file(dir_fd, "subdir/file").expect("removing a file");
+    wasi::path_remove_directory(dir_fd, "subdir").expect("removing a directory");
+}
+
+fn main() {
+    let mut args = env::args();
+    let prog = args.next().unwrap();
+    let arg = if let Some(arg) = args.next() {
+        arg
+    } else {
+        eprintln!("usage: {} <scratch directory>", prog);
+        process::exit(1);
+    };
+
+    // Open scratch directory
+    let dir_fd = match open_scratch_directory(&arg) {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_symlink_policy(dir_fd) }
+}
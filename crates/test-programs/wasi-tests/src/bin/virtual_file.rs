@@ -0,0 +1,88 @@
+//! Run with `/etc` preopened as a virtual directory holding a `hosts` file, and `/etc/config`
+//! added to it as a virtual file containing `CONTENTS`.
+use std::fs;
+use wasi_tests::open_scratch_directory;
+
+const CONTENTS: &[u8] = b"nameserver 127.0.0.1\n";
+
+unsafe fn test_virtual_file(dir_fd: wasi::Fd) {
+    // The file can be read like any other, through the usual path resolution.
+    let contents = fs::read("/etc/config").expect("reading the virtual file");
+    assert_eq!(contents, CONTENTS);
+
+    let file_fd = wasi::path_open(dir_fd, 0, "config", 0, wasi::RIGHTS_FD_READ, 0, 0)
+        .expect("opening the virtual file for reading");
+    let mut buf = [0u8; 64];
+    let nread = wasi::fd_read(
+        file_fd,
+        &[wasi::Iovec {
+            buf: buf.as_mut_ptr(),
+            buf_len: buf.len(),
+        }],
+    )
+    .expect("reading the virtual file");
+    assert_eq!(&buf[..nread], CONTENTS);
+    wasi::fd_close(file_fd).expect("closing the virtual file");
+
+    // Nothing can be modified. Asking for the right to write is allowed, but isn't granted.
+    let file_fd = wasi::path_open(
+        dir_fd,
+        0,
+        "config",
+        0,
+        wasi::RIGHTS_FD_READ | wasi::RIGHTS_FD_WRITE,
+        0,
+        0,
+    )
+    .expect("opening the virtual file for writing");
+    assert_eq!(
+        wasi::fd_write(
+            file_fd,
+            &[wasi::Ciovec {
+                buf: b"changed".as_ptr(),
+                buf_len: 7,
+            }],
+        )
+        .expect_err("writing to the virtual file should fail")
+        .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "errno should be ERRNO_NOTCAPABLE"
+    );
+    wasi::fd_close(file_fd).expect("closing the virtual file");
+    assert_eq!(
+        wasi::path_open(
+            dir_fd,
+            0,
+            "config",
+            wasi::OFLAGS_TRUNC,
+            wasi::RIGHTS_FD_READ,
+            0,
+            0
+        )
+        .expect_err("truncating the virtual file should fail")
+        .raw_error(),
+        wasi::ERRNO_NOTCAPABLE,
+        "errno should be ERRNO_NOTCAPABLE"
+    );
+    assert!(
+        fs::write("/etc/config", b"changed").is_err(),
+        "writing the virtual file should fail"
+    );
+    assert_eq!(fs::read("/etc/config").unwrap(), CONTENTS);
+
+    // The rest of the directory the file was added to is still there.
+    assert_eq!(fs::read("/etc/hosts").unwrap(), b"127.0.0.1 localhost\n");
+}
+
+fn main() {
+    let dir_fd = match open_scratch_directory("/etc") {
+        Ok(dir_fd) => dir_fd,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        }
+    };
+
+    // Run the tests.
+    unsafe { test_virtual_file(dir_fd) }
+}
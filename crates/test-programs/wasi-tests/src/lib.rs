@@ -36,6 +36,23 @@ pub fn open_scratch_directory(path: &str) -> Result<wasi::Fd, String> {
     }
 }
 
+/// Finds the preopen for `name` without using it, like libc does at startup.
+///
+/// Panics if there's no such preopen.
+pub unsafe fn find_preopen(name: &str) -> wasi::Fd {
+    for fd in 3.. {
+        let prestat =
+            wasi::fd_prestat_get(fd).unwrap_or_else(|e| panic!("no preopen named {}: {}", name, e));
+        assert_eq!(prestat.tag, wasi::PREOPENTYPE_DIR);
+        let mut dst = vec![0; prestat.u.dir.pr_name_len];
+        wasi::fd_prestat_dir_name(fd, dst.as_mut_ptr(), dst.len()).expect("the preopen's name");
+        if dst == name.as_bytes() {
+            return fd;
+        }
+    }
+    unreachable!()
+}
+
 pub unsafe fn create_file(dir_fd: wasi::Fd, filename: &str) {
     let file_fd =
         wasi::path_open(dir_fd, 0, filename, wasi::OFLAGS_CREAT, 0, 0, 0).expect("creating a file");
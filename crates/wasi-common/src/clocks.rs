@@ -0,0 +1,113 @@
+//! Clocks which can stand in for the host's, so that what a guest sees of time doesn't depend on
+//! when and where it runs.
+
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A source of time for a guest, installed with `WasiCtxBuilder::monotonic_clock` or
+/// `WasiCtxBuilder::wall_clock`.
+///
+/// Times are in nanoseconds: since the Unix epoch for a wall clock, and since an arbitrary point
+/// for a monotonic clock.
+pub trait WasiClock {
+    /// Returns the current time.
+    fn now(&self) -> u64;
+
+    /// Returns the resolution of the clock, which must not be zero. Defaults to a nanosecond.
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    /// Called when a guest waits for the clock to reach `time`, to move the clock straight there
+    /// if it doesn't follow real time. Returns whether it did.
+    ///
+    /// Defaults to leaving the clock alone, and the guest waits until the clock gets there by
+    /// itself.
+    fn skip_to(&self, _time: u64) -> bool {
+        false
+    }
+}
+
+/// A clock subscription of `poll_oneoff` for a clock installed with `WasiCtxBuilder`, which
+/// expires once `clock` reaches `deadline`, however long that takes.
+pub(crate) struct TimerEventData {
+    pub(crate) clock: Rc<dyn WasiClock>,
+    pub(crate) deadline: u64,
+    /// The subscription's userdata, or `None` for the host's deadline, which ends the wait
+    /// without an event.
+    pub(crate) userdata: Option<u64>,
+}
+
+/// Moves the clock of whichever of `timers` expires first to its deadline, if the clock allows
+/// it. Returns whether it did, in which case the timer has expired without waiting.
+pub(crate) fn skip_to_next_timer(timers: &[TimerEventData]) -> bool {
+    timers
+        .iter()
+        .min_by_key(|timer| timer.deadline.saturating_sub(timer.clock.now()))
+        .map_or(false, |timer| timer.clock.skip_to(timer.deadline))
+}
+
+/// A clock which only moves when it's told to.
+///
+/// Clones of a `MockClock` share its time, so the host can keep a clone to advance the clock of
+/// a running guest, from another thread if need be. A guest blocked in `poll_oneoff` on a clock
+/// subscription for a `MockClock` wakes up once the clock is advanced past its deadline, however
+/// much real time has passed.
+///
+/// A clock with a tick moves forward by the tick each time it's read, which lets time pass for
+/// the guest, deterministically, without the host having to advance it. A guest waiting on such
+/// a clock doesn't wait in real time: the clock moves straight to the deadline of the first of
+/// its timers to expire.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+    tick: u64,
+}
+
+impl MockClock {
+    /// Create a clock which reads `start` until it's advanced.
+    pub fn new(start: u64) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(start)),
+            tick: 0,
+        }
+    }
+
+    /// Make the clock move forward by `tick` each time it's read.
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = nanos(tick);
+        self
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now.fetch_add(nanos(duration), Ordering::SeqCst);
+    }
+
+    /// Set the clock to `now`.
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+}
+
+impl WasiClock for MockClock {
+    fn now(&self) -> u64 {
+        self.now.fetch_add(self.tick, Ordering::SeqCst)
+    }
+
+    fn skip_to(&self, time: u64) -> bool {
+        // Without a tick, the clock is left for the host to advance.
+        if self.tick == 0 {
+            return false;
+        }
+        self.now.fetch_max(time, Ordering::SeqCst);
+        true
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::max_value())
+}
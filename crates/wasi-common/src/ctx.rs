@@ -1,20 +1,24 @@
+use crate::clocks::WasiClock;
 use crate::entry::{Entry, EntryHandle};
 use crate::fdpool::FdPool;
-use crate::handle::Handle;
+use crate::handle::{Handle, HandleRights, RightsExt};
+use crate::random::WasiRandom;
 use crate::string_array::{PendingString, StringArray, StringArrayError};
+use crate::sys::lazydir::LazyDir;
 use crate::sys::osdir::OsDir;
 use crate::sys::stdio::NullDevice;
 use crate::sys::stdio::{Stderr, StderrExt, Stdin, StdinExt, Stdout, StdoutExt};
-use crate::virtfs::{VirtualDir, VirtualDirEntry};
-use crate::wasi::types::Fd;
+use crate::virtfs::{VecFileContents, VirtualDir, VirtualDirEntry};
+use crate::wasi::types::{Clockid, Fd, Rights};
 use crate::Error;
 use std::borrow::Borrow;
-use std::cell::RefCell;
+use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Instant;
 use std::{env, io};
 
 /// Possible errors when `WasiCtxBuilder` fails building
@@ -33,6 +37,9 @@ pub enum WasiCtxBuilderError {
     /// The root of a VirtualDirEntry tree must be a VirtualDirEntry::Directory.
     #[error("the root of a VirtualDirEntry tree at {} must be a VirtualDirEntry::Directory", .0.display())]
     VirtualDirEntryRootNotADirectory(PathBuf),
+    /// No virtual directory is preopened where a virtual file could be added.
+    #[error("the virtual file {} is not inside a preopened virtual directory", .0.display())]
+    VirtualFileNotInVirtualDir(PathBuf),
     /// `WasiCtx` has too many opened files.
     #[error("context object has too many opened files")]
     TooManyFilesOpen,
@@ -58,18 +65,104 @@ impl std::fmt::Debug for PendingEntry {
     }
 }
 
-struct PendingPreopen(Box<dyn FnOnce() -> WasiCtxBuilderResult<Box<dyn Handle>>>);
+struct PendingPreopen {
+    open: Box<dyn FnOnce() -> WasiCtxBuilderResult<Box<dyn Handle>>>,
+    follow_symlinks: FollowSymlinks,
+    rights: HandleRights,
+}
 
 impl PendingPreopen {
     fn new<F>(f: F) -> Self
     where
         F: FnOnce() -> WasiCtxBuilderResult<Box<dyn Handle>> + 'static,
     {
-        Self(Box::new(f))
+        Self {
+            open: Box::new(f),
+            follow_symlinks: FollowSymlinks::default(),
+            rights: HandleRights::new(Rights::all(), Rights::all()),
+        }
     }
 
     fn into(self) -> WasiCtxBuilderResult<Box<dyn Handle>> {
-        self.0()
+        (self.open)()
+    }
+}
+
+/// Whether path resolution inside a preopened directory follows symlinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowSymlinks {
+    /// Symlinks are followed as long as they resolve to somewhere inside the preopen, which is
+    /// the default.
+    InSandbox,
+    /// Symlinks are never followed, wherever they point. Resolving a path which goes through a
+    /// symlink fails with `ERRNO_LOOP`, as does asking to follow a symlink which is the final
+    /// component of a path.
+    ///
+    /// Symlinks themselves can still be created with `path_symlink`, read with `path_readlink`,
+    /// looked at with `path_filestat_get` and removed with `path_unlink_file`, as none of these
+    /// resolve the link, unless they're asked to follow it.
+    Never,
+}
+
+impl Default for FollowSymlinks {
+    fn default() -> Self {
+        Self::InSandbox
+    }
+}
+
+/// Options for a preopened directory added with `WasiCtxBuilder::preopened_dir_with_options`.
+#[derive(Debug, Clone)]
+pub struct PreopenOptions {
+    follow_symlinks: FollowSymlinks,
+    rights: HandleRights,
+}
+
+impl Default for PreopenOptions {
+    fn default() -> Self {
+        Self {
+            follow_symlinks: FollowSymlinks::default(),
+            rights: HandleRights::new(Rights::all(), Rights::all()),
+        }
+    }
+}
+
+impl PreopenOptions {
+    /// Options which behave the same as `WasiCtxBuilder::preopened_dir`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether paths resolved inside the preopen, and inside the directories opened from
+    /// it, follow symlinks.
+    pub fn follow_symlinks(&mut self, policy: FollowSymlinks) -> &mut Self {
+        self.follow_symlinks = policy;
+        self
+    }
+
+    /// Limits the rights the guest has on the preopen to at most `base`, and the rights it can
+    /// have on descriptors opened from it, and from those in turn, to at most `inheriting`.
+    ///
+    /// Operations needing a right which was masked out fail with `ERRNO_NOTCAPABLE`.
+    pub fn rights(&mut self, base: Rights, inheriting: Rights) -> &mut Self {
+        self.rights = HandleRights::new(base, inheriting);
+        self
+    }
+
+    /// Makes the preopen read-only, by masking out all of the rights which modify the preopened
+    /// tree, from both its base and inheriting rights.
+    ///
+    /// The guest can still open, read and stat files and directories, but it can't create,
+    /// write to, truncate, rename or remove anything.
+    pub fn read_only(&mut self) -> &mut Self {
+        self.rights(
+            self.rights.base & !Rights::mutating(),
+            self.rights.inheriting & !Rights::mutating(),
+        )
+    }
+
+    /// Returns the rights the preopen is limited to.
+    pub fn get_rights(&self) -> HandleRights {
+        self.rights
     }
 }
 
@@ -79,8 +172,21 @@ pub struct WasiCtxBuilder {
     stdout: Option<PendingEntry>,
     stderr: Option<PendingEntry>,
     preopens: Option<Vec<(PathBuf, PendingPreopen)>>,
+    virtual_files: Vec<(PathBuf, Vec<u8>)>,
     args: Option<Vec<PendingString>>,
+    max_args_size: u32,
     env: Option<HashMap<PendingString, PendingString>>,
+    sorted_readdir: bool,
+    deadline: Option<Rc<Deadline>>,
+    monotonic_clock: Option<Rc<dyn WasiClock>>,
+    wall_clock: Option<Rc<dyn WasiClock>>,
+    random: Option<Box<dyn WasiRandom>>,
+}
+
+/// The host's deadline for blocking calls, set with `WasiCtxBuilder::deadline`.
+struct Deadline {
+    deadline: Box<dyn Fn() -> Option<Instant>>,
+    expired: Box<dyn Fn()>,
 }
 
 impl WasiCtxBuilder {
@@ -95,14 +201,22 @@ impl WasiCtxBuilder {
             stdout,
             stderr,
             preopens: Some(Vec::new()),
+            virtual_files: Vec::new(),
             args: Some(Vec::new()),
+            max_args_size: u32::max_value(),
             env: Some(HashMap::new()),
+            sorted_readdir: false,
+            deadline: None,
+            monotonic_clock: None,
+            wall_clock: None,
+            random: None,
         }
     }
 
     /// Add arguments to the command-line arguments list.
     ///
-    /// Arguments must be valid UTF-8 with no NUL bytes, or else `WasiCtxBuilder::build()` will fail.
+    /// Arguments are byte strings which are passed to the guest unchanged, even if they aren't
+    /// valid UTF-8. They must not contain NUL bytes, or else `WasiCtxBuilder::build()` will fail.
     pub fn args<S: AsRef<[u8]>>(&mut self, args: impl IntoIterator<Item = S>) -> &mut Self {
         self.args
             .as_mut()
@@ -113,7 +227,8 @@ impl WasiCtxBuilder {
 
     /// Add an argument to the command-line arguments list.
     ///
-    /// Arguments must be valid UTF-8 with no NUL bytes, or else `WasiCtxBuilder::build()` will fail.
+    /// Arguments are byte strings which are passed to the guest unchanged, even if they aren't
+    /// valid UTF-8. They must not contain NUL bytes, or else `WasiCtxBuilder::build()` will fail.
     pub fn arg<S: AsRef<[u8]>>(&mut self, arg: S) -> &mut Self {
         self.args
             .as_mut()
@@ -124,8 +239,8 @@ impl WasiCtxBuilder {
 
     /// Inherit the command-line arguments from the host process.
     ///
-    /// If any arguments from the host process contain invalid UTF-8, `WasiCtxBuilder::build()` will
-    /// fail.
+    /// On Unix the arguments are passed through as bytes. On Windows, if any arguments from the
+    /// host process contain invalid UTF-16, `WasiCtxBuilder::build()` will fail.
     pub fn inherit_args(&mut self) -> &mut Self {
         let args = self.args.as_mut().unwrap();
         args.clear();
@@ -133,6 +248,17 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Limit the total size of the command-line arguments, counting the NUL terminator of each
+    /// argument, to `bytes`.
+    ///
+    /// If the arguments exceed the limit, `args_sizes_get` and `args_get` fail with `EOVERFLOW`
+    /// rather than handing the guest a truncated argument list. By default the only limit is the
+    /// one imposed by `args_sizes_get`, which reports sizes as 32-bit integers.
+    pub fn max_args_size(&mut self, bytes: u32) -> &mut Self {
+        self.max_args_size = bytes;
+        self
+    }
+
     /// Inherit stdin from the host process.
     pub fn inherit_stdin(&mut self) -> &mut Self {
         self.stdin = Some(PendingEntry::Thunk(Stdin::stdin));
@@ -161,8 +287,9 @@ impl WasiCtxBuilder {
 
     /// Inherit the environment variables from the host process.
     ///
-    /// If any environment variables from the host process contain invalid Unicode (UTF-16 for
-    /// Windows, UTF-8 for other platforms), `WasiCtxBuilder::build()` will fail.
+    /// On Unix the environment variables are passed through as bytes. On Windows, if any
+    /// environment variables from the host process contain invalid UTF-16,
+    /// `WasiCtxBuilder::build()` will fail.
     pub fn inherit_env(&mut self) -> &mut Self {
         let env = self.env.as_mut().unwrap();
         env.clear();
@@ -172,8 +299,8 @@ impl WasiCtxBuilder {
 
     /// Add an entry to the environment.
     ///
-    /// Environment variable keys and values must be valid UTF-8 with no NUL bytes, or else
-    /// `WasiCtxBuilder::build()` will fail.
+    /// Environment variable keys and values are byte strings which are passed to the guest
+    /// unchanged. They must not contain NUL bytes, or else `WasiCtxBuilder::build()` will fail.
     pub fn env<S: AsRef<[u8]>>(&mut self, k: S, v: S) -> &mut Self {
         self.env
             .as_mut()
@@ -184,8 +311,8 @@ impl WasiCtxBuilder {
 
     /// Add entries to the environment.
     ///
-    /// Environment variable keys and values must be valid UTF-8 with no NUL bytes, or else
-    /// `WasiCtxBuilder::build()` will fail.
+    /// Environment variable keys and values are byte strings which are passed to the guest
+    /// unchanged. They must not contain NUL bytes, or else `WasiCtxBuilder::build()` will fail.
     pub fn envs<S: AsRef<[u8]>, T: Borrow<(S, S)>>(
         &mut self,
         envs: impl IntoIterator<Item = T>,
@@ -217,10 +344,43 @@ impl WasiCtxBuilder {
 
     /// Add a preopened directory.
     pub fn preopened_dir<P: AsRef<Path>>(&mut self, dir: File, guest_path: P) -> &mut Self {
-        let preopen = PendingPreopen::new(move || {
+        self.preopened_dir_with_options(dir, guest_path, &PreopenOptions::new())
+    }
+
+    /// Add a preopened directory, configured with `options`.
+    pub fn preopened_dir_with_options<P: AsRef<Path>>(
+        &mut self,
+        dir: File,
+        guest_path: P,
+        options: &PreopenOptions,
+    ) -> &mut Self {
+        let mut preopen = PendingPreopen::new(move || {
             let dir = OsDir::try_from(dir).map_err(WasiCtxBuilderError::from)?;
             Ok(Box::new(dir))
         });
+        preopen.follow_symlinks = options.follow_symlinks;
+        preopen.rights = options.rights;
+        self.preopens
+            .as_mut()
+            .unwrap()
+            .push((guest_path.as_ref().to_owned(), preopen));
+        self
+    }
+
+    /// Add a preopened directory which is opened from `host_path` only once the guest first uses
+    /// it, rather than when the `WasiCtx` is built.
+    ///
+    /// Guests can find the preopen with `fd_prestat_get` and `fd_prestat_dir_name` without
+    /// opening it, so preopens which are never used never take up a host file descriptor. If
+    /// opening the directory fails, for instance because it doesn't exist, then the operation
+    /// which used the preopen fails, and the directory is opened again the next time it's used.
+    pub fn preopened_dir_lazy<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        host_path: P,
+        guest_path: Q,
+    ) -> &mut Self {
+        let host_path = host_path.as_ref().to_owned();
+        let preopen = PendingPreopen::new(move || Ok(Box::new(LazyDir::new(host_path))));
         self.preopens
             .as_mut()
             .unwrap()
@@ -268,12 +428,89 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Serve the file at `guest_path` from `contents`.
+    ///
+    /// The file is added to the directory preopened with `preopened_virt` which guests resolve
+    /// `guest_path` against, that is the one with the longest matching prefix, and the directories
+    /// leading up to it there must already exist. Otherwise `WasiCtxBuilder::build()` fails. The
+    /// guest can open and read the file, but writing to it or truncating it fails with
+    /// `ERRNO_NOTCAPABLE`.
+    pub fn virtual_file<P: AsRef<Path>>(&mut self, guest_path: P, contents: Vec<u8>) -> &mut Self {
+        self.virtual_files
+            .push((guest_path.as_ref().to_owned(), contents));
+        self
+    }
+
+    /// Make `fd_readdir` list directory entries sorted bytewise by name, with `.` and `..` first
+    /// in that order, instead of in whatever order the host lists them in.
+    ///
+    /// This makes guests whose behavior depends on the order of directory entries behave the same
+    /// on every host, at the cost of reading the whole directory on each `fd_readdir` call. It's
+    /// off by default. Virtual directories always list their entries in sorted order.
+    pub fn sorted_readdir(&mut self, enable: bool) -> &mut Self {
+        self.sorted_readdir = enable;
+        self
+    }
+
+    /// Make blocking calls return once the instant returned by `deadline`, if any, has passed.
+    ///
+    /// This is meant to share the host's deadline for running the guest, such as
+    /// `wasmtime::Store::set_deadline`, with the guest's timers, so that the two can't race.
+    /// `poll_oneoff` never waits past the deadline: when it's reached before any of the
+    /// subscriptions is ready, including a clock subscription for a later time, `expired` is
+    /// called and then the call fails with `Errno::Intr`. `expired` is where the host stops the
+    /// guest, for instance with `wasmtime::InterruptHandle::interrupt`, so that the guest is
+    /// stopped by the time it sees the error. `deadline` is called each time `poll_oneoff` is, so
+    /// the deadline can change in the meantime.
+    ///
+    /// The time left until the deadline is measured on the guest's `Clockid::Monotonic`, along
+    /// with the guest's own timers. If a clock is installed with `monotonic_clock`, the deadline
+    /// is reached once that clock has moved forward by the time which was left when `poll_oneoff`
+    /// was called, or once the deadline passes in real time, whichever comes first.
+    pub fn deadline(
+        &mut self,
+        deadline: impl Fn() -> Option<Instant> + 'static,
+        expired: impl Fn() + 'static,
+    ) -> &mut Self {
+        self.deadline = Some(Rc::new(Deadline {
+            deadline: Box::new(deadline),
+            expired: Box::new(expired),
+        }));
+        self
+    }
+
+    /// Make `Clockid::Monotonic` read `clock` instead of the host's monotonic clock.
+    ///
+    /// This applies to `clock_time_get`, `clock_res_get`, and clock subscriptions of
+    /// `poll_oneoff`, which expire once `clock` reaches their deadline rather than once enough
+    /// real time has passed. Along with `wall_clock` and `random`, this makes guests which don't
+    /// otherwise depend on the host behave the same on every run. The CPU time clocks are always
+    /// the host's.
+    pub fn monotonic_clock(&mut self, clock: impl WasiClock + 'static) -> &mut Self {
+        self.monotonic_clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// Make `Clockid::Realtime` read `clock` instead of the host's wall clock, the same way as
+    /// `monotonic_clock` does for `Clockid::Monotonic`.
+    pub fn wall_clock(&mut self, clock: impl WasiClock + 'static) -> &mut Self {
+        self.wall_clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// Make `random_get` take its bytes from `random` instead of the host's random number
+    /// generator.
+    pub fn random(&mut self, random: impl WasiRandom + 'static) -> &mut Self {
+        self.random = Some(Box::new(random));
+        self
+    }
+
     /// Build a `WasiCtx`, consuming this `WasiCtxBuilder`.
     ///
     /// If any of the arguments or environment variables in this builder cannot be converted into
     /// `CString`s, either due to NUL bytes or Unicode conversions, this will fail.
     pub fn build(&mut self) -> WasiCtxBuilderResult<WasiCtx> {
-        // Process arguments and environment variables into `String`s, failing quickly if they
+        // Process arguments and environment variables into byte strings, failing quickly if they
         // contain any NUL bytes, or if conversion from `OsString` fails.
         let args =
             StringArray::from_pending_vec(self.args.take().expect("WasiCtxBuilder has args"))
@@ -311,20 +548,65 @@ impl WasiCtxBuilder {
             tracing::debug!(fd = tracing::field::debug(fd), "WasiCtx inserted");
         }
         // Then add the preopen entries.
+        let mut preopen_dirs: Vec<(PathBuf, Option<Box<dyn Handle>>)> = Vec::new();
         for (guest_path, preopen) in self.preopens.take().unwrap() {
-            let handle = EntryHandle::from(preopen.into()?);
+            let follow_symlinks = preopen.follow_symlinks;
+            let mask = preopen.rights;
+            let handle = preopen.into()?;
+            let virtual_dir = if handle.as_any().is::<VirtualDir>() {
+                Some(handle.try_clone()?)
+            } else {
+                None
+            };
+            preopen_dirs.push((guest_path.clone(), virtual_dir));
+            let handle = EntryHandle::from(handle);
             let mut entry = Entry::new(handle);
             entry.preopen_path = Some(guest_path);
+            entry.follow_symlinks = follow_symlinks;
+            let mut rights = entry.get_rights();
+            rights.base &= mask.base;
+            rights.inheriting &= mask.inheriting;
+            entry.set_rights(rights);
             let fd = entries
                 .insert(entry)
                 .ok_or(WasiCtxBuilderError::TooManyFilesOpen)?;
             tracing::debug!(fd = tracing::field::debug(fd), "WasiCtx inserted",);
         }
+        // Guests resolve paths against the preopen with the longest matching prefix, preferring
+        // the one added last on ties, so that's where virtual files go.
+        for (guest_path, contents) in self.virtual_files.drain(..) {
+            let added = preopen_dirs
+                .iter()
+                .filter_map(|(preopen_path, dir)| {
+                    let relative = if preopen_path == Path::new(".") && guest_path.is_relative() {
+                        Some(guest_path.as_path())
+                    } else {
+                        guest_path.strip_prefix(preopen_path).ok()
+                    }?;
+                    Some((preopen_path.components().count(), relative, dir))
+                })
+                .max_by_key(|(depth, _, _)| *depth)
+                .and_then(|(_, relative, dir)| {
+                    let contents = Box::new(VecFileContents::with_content(contents));
+                    let dir = dir.as_ref()?.as_any().downcast_ref::<VirtualDir>()?;
+                    Some(dir.add_read_only_file(contents, relative))
+                })
+                .unwrap_or(false);
+            if !added {
+                return Err(WasiCtxBuilderError::VirtualFileNotInVirtualDir(guest_path));
+            }
+        }
 
         Ok(WasiCtx {
             args,
             env,
             entries: RefCell::new(entries),
+            max_args_size: self.max_args_size,
+            sorted_readdir: self.sorted_readdir,
+            deadline: self.deadline.take(),
+            monotonic_clock: self.monotonic_clock.take(),
+            wall_clock: self.wall_clock.take(),
+            random: self.random.take().map(RefCell::new),
         })
     }
 }
@@ -353,6 +635,12 @@ impl EntryTable {
     }
 
     fn insert_at(&mut self, fd: &Fd, entry: Rc<Entry>) {
+        // Replacing an open descriptor keeps its number allocated, otherwise the number has to be
+        // taken out of the pool so it isn't handed out again.
+        if !self.entries.contains_key(fd) {
+            let claimed = self.fd_pool.claim(*fd);
+            debug_assert!(claimed, "fd {:?} is free but already allocated", fd);
+        }
         self.entries.insert(*fd, entry);
     }
 
@@ -370,7 +658,13 @@ impl EntryTable {
 pub struct WasiCtx {
     entries: RefCell<EntryTable>,
     pub(crate) args: StringArray,
+    pub(crate) max_args_size: u32,
     pub(crate) env: StringArray,
+    pub(crate) sorted_readdir: bool,
+    deadline: Option<Rc<Deadline>>,
+    monotonic_clock: Option<Rc<dyn WasiClock>>,
+    wall_clock: Option<Rc<dyn WasiClock>>,
+    random: Option<RefCell<Box<dyn WasiRandom>>>,
 }
 
 impl WasiCtx {
@@ -389,6 +683,36 @@ impl WasiCtx {
             .build()
     }
 
+    /// Returns the deadline for blocking calls set with `WasiCtxBuilder::deadline`, if any.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.deadline
+            .as_ref()
+            .and_then(|deadline| (deadline.deadline)())
+    }
+
+    /// Tells the host that a blocking call gave up at the deadline set with
+    /// `WasiCtxBuilder::deadline`.
+    pub(crate) fn deadline_expired(&self) {
+        if let Some(deadline) = &self.deadline {
+            (deadline.expired)();
+        }
+    }
+
+    /// Returns the clock installed for `id` with `WasiCtxBuilder`, if any, which the guest should
+    /// see instead of the host's.
+    pub(crate) fn clock(&self, id: Clockid) -> Option<&Rc<dyn WasiClock>> {
+        match id {
+            Clockid::Realtime => self.wall_clock.as_ref(),
+            Clockid::Monotonic => self.monotonic_clock.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the source of random bytes installed with `WasiCtxBuilder::random`, if any.
+    pub(crate) fn random(&self) -> Option<RefMut<'_, Box<dyn WasiRandom>>> {
+        self.random.as_ref().map(RefCell::borrow_mut)
+    }
+
     /// Check if `WasiCtx` contains the specified raw WASI `fd`.
     pub(crate) fn contains_entry(&self, fd: Fd) -> bool {
         self.entries.borrow().contains(&fd)
@@ -411,7 +735,7 @@ impl WasiCtx {
     }
 
     /// Insert the specified `Entry` with the specified raw WASI `fd` key into the `WasiCtx`
-    /// object.
+    /// object, replacing the entry already at `fd`, if any.
     pub(crate) fn insert_entry_at(&self, fd: Fd, entry: Rc<Entry>) {
         self.entries.borrow_mut().insert_at(&fd, entry)
     }
@@ -421,6 +745,39 @@ impl WasiCtx {
         self.entries.borrow_mut().remove(fd).ok_or(Error::Badf)
     }
 
+    /// Returns the raw WASI `fd` of the directory preopened at `guest_path`, if it's still open.
+    /// If several directories were preopened there, the lowest `fd` is returned.
+    pub fn preopen_fd<P: AsRef<Path>>(&self, guest_path: P) -> Option<Fd> {
+        let guest_path = guest_path.as_ref();
+        self.entries
+            .borrow()
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.preopen_path.as_deref() == Some(guest_path))
+            .map(|(fd, _)| *fd)
+            .min_by_key(|fd| u32::from(*fd))
+    }
+
+    /// Returns the rights currently held by the raw WASI `fd`.
+    pub fn fd_rights(&self, fd: Fd) -> Result<HandleRights, Error> {
+        Ok(self.get_entry(fd)?.get_rights())
+    }
+
+    /// Restricts the rights held by the raw WASI `fd` to `base` and `inheriting`.
+    ///
+    /// Rights can only ever be dropped: if either set isn't a subset of the rights `fd`
+    /// currently holds, `Error::Notcapable` is returned and `fd` is left unchanged. This
+    /// is also what backs the guest's `fd_fdstat_set_rights`.
+    pub fn set_fd_rights(&self, fd: Fd, base: Rights, inheriting: Rights) -> Result<(), Error> {
+        let rights = HandleRights::new(base, inheriting);
+        let entry = self.get_entry(fd)?;
+        if !entry.get_rights().contains(&rights) {
+            return Err(Error::Notcapable);
+        }
+        entry.set_rights(rights);
+        Ok(())
+    }
+
     /*
     pub(crate) fn args(&self) -> &impl StringArrayWriter {
         &self.args
@@ -431,3 +788,125 @@ impl WasiCtx {
     }
     */
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{WasiCtxBuilder, WasiCtxBuilderError, WasiCtxBuilderResult};
+    use crate::entry::{Entry, EntryHandle};
+    use crate::handle::{Filetype, HandleRights};
+    use crate::sys::stdio::NullDevice;
+    use crate::virtfs::VirtualDirEntry;
+    use crate::wasi::types::Fd;
+    use crate::wasi::wasi_snapshot_preview1::WasiSnapshotPreview1;
+    use crate::Error;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    #[test]
+    fn preopens_follow_stdio() -> WasiCtxBuilderResult<()> {
+        // Preopens are numbered from 3 in the order they're added, even when they're added
+        // before stdio is overridden.
+        let ctx = WasiCtxBuilder::new()
+            .preopened_virt(VirtualDirEntry::empty_directory(), "/a")
+            .preopened_virt(VirtualDirEntry::empty_directory(), "/b")
+            .stdout(NullDevice::new())
+            .stdin(NullDevice::new())
+            .build()?;
+        for fd in 0..3 {
+            let entry = ctx.get_entry(Fd::from(fd)).expect("stdio is open");
+            assert!(entry.preopen_path.is_none());
+        }
+        for (fd, path) in [(3, "/a"), (4, "/b")].iter() {
+            let entry = ctx.get_entry(Fd::from(*fd)).expect("preopen is open");
+            assert_eq!(entry.preopen_path.as_deref(), Some(Path::new(path)));
+            assert_eq!(ctx.preopen_fd(path), Some(Fd::from(*fd)));
+        }
+        assert!(!ctx.contains_entry(Fd::from(5)));
+        assert_eq!(ctx.preopen_fd("/c"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn args_over_the_limit_overflow() -> WasiCtxBuilderResult<()> {
+        // "prog\0" and "arg\0" add up to 9 bytes.
+        let ctx = WasiCtxBuilder::new()
+            .args(&["prog", "arg"])
+            .max_args_size(9)
+            .build()?;
+        assert_eq!(ctx.args_sizes_get().unwrap(), (2, 9));
+
+        let ctx = WasiCtxBuilder::new()
+            .args(&["prog", "arg"])
+            .max_args_size(8)
+            .build()?;
+        assert!(matches!(ctx.args_sizes_get(), Err(Error::Overflow)));
+        Ok(())
+    }
+
+    #[test]
+    fn insert_at_claims_fd() -> WasiCtxBuilderResult<()> {
+        let ctx = WasiCtxBuilder::new().build()?;
+        let stdout = ctx.remove_entry(Fd::from(1)).expect("stdout is open");
+        ctx.insert_entry_at(Fd::from(10), stdout);
+        let stderr = ctx.get_entry(Fd::from(2)).expect("stderr is open");
+        ctx.insert_entry_at(Fd::from(0), stderr);
+
+        // The freed descriptor is reused, but the claimed one isn't handed out again.
+        let mut fds = Vec::new();
+        for _ in 0..10 {
+            let entry = Entry::new(EntryHandle::new(NullDevice::new()));
+            let fd = ctx.insert_entry(entry).expect("a free fd");
+            fds.push(u32::from(fd));
+        }
+        assert_eq!(fds, [1, 3, 4, 5, 6, 7, 8, 9, 11, 12]);
+        ctx.remove_entry(Fd::from(10)).expect("fd 10 is open");
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_preopens() -> WasiCtxBuilderResult<()> {
+        let missing = std::env::temp_dir().join("wasi-common-lazy-preopen-missing");
+        let ctx = WasiCtxBuilder::new()
+            .preopened_dir_lazy(&missing, "/missing")
+            .preopened_dir_lazy(std::env::temp_dir(), "/tmp")
+            .build()?;
+
+        // Nothing is opened until it's needed, so a missing directory still looks like one.
+        let entry = ctx.get_entry(Fd::from(3)).expect("preopen is open");
+        assert_eq!(entry.get_file_type(), Filetype::Directory);
+        assert_eq!(entry.preopen_path.as_deref(), Some(Path::new("/missing")));
+        let handle = entry.as_handle(&HandleRights::empty()).unwrap();
+        assert!(matches!(handle.filestat_get(), Err(Error::Noent)));
+
+        let entry = ctx.get_entry(Fd::from(4)).expect("preopen is open");
+        let handle = entry.as_handle(&HandleRights::empty()).unwrap();
+        let stat = handle.filestat_get().expect("the directory exists");
+        assert_eq!(stat.filetype, Filetype::Directory);
+        Ok(())
+    }
+
+    #[test]
+    fn virtual_files_need_a_virtual_preopen() -> WasiCtxBuilderResult<()> {
+        let mut etc = HashMap::new();
+        etc.insert("ssl".to_owned(), VirtualDirEntry::empty_directory());
+        WasiCtxBuilder::new()
+            .preopened_virt(VirtualDirEntry::Directory(etc), "/etc")
+            .virtual_file("/etc/hosts", b"127.0.0.1 localhost\n".to_vec())
+            .virtual_file("/etc/ssl/cert.pem", Vec::new())
+            .build()?;
+
+        // Files can't be added to host directories, or to directories which don't exist.
+        for path in ["/tmp/hosts", "/etc/missing/hosts", "hosts"].iter() {
+            let result = WasiCtxBuilder::new()
+                .preopened_virt(VirtualDirEntry::empty_directory(), "/etc")
+                .preopened_dir_lazy(std::env::temp_dir(), "/tmp")
+                .virtual_file(path, Vec::new())
+                .build();
+            assert!(matches!(
+                result,
+                Err(WasiCtxBuilderError::VirtualFileNotInVirtualDir(_))
+            ));
+        }
+        Ok(())
+    }
+}
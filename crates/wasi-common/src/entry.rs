@@ -1,3 +1,4 @@
+use crate::ctx::FollowSymlinks;
 use crate::handle::{Filetype, Handle, HandleRights};
 use crate::{Error, Result};
 use std::ops::Deref;
@@ -46,6 +47,8 @@ impl Deref for EntryHandle {
 pub(crate) struct Entry {
     handle: EntryHandle,
     pub(crate) preopen_path: Option<PathBuf>,
+    /// The symlink policy of the preopen this entry was opened from.
+    pub(crate) follow_symlinks: FollowSymlinks,
     // TODO: directories
 }
 
@@ -55,6 +58,7 @@ impl Entry {
         Self {
             handle,
             preopen_path,
+            follow_symlinks: FollowSymlinks::default(),
         }
     }
 
@@ -30,6 +30,9 @@ pub enum Error {
     /// Errno::Acces: Permission denied
     #[error("Acces: Permission denied")]
     Acces,
+    /// Errno::Again: Resource unavailable, or operation would block
+    #[error("Again: Resource unavailable, or operation would block")]
+    Again,
     /// Errno::Badf: Bad file descriptor
     #[error("Badf: Bad file descriptor")]
     Badf,
@@ -48,6 +51,9 @@ pub enum Error {
     /// Errno::Ilseq: Illegal byte sequence
     #[error("Ilseq: Illegal byte sequence")]
     Ilseq,
+    /// Errno::Intr: Interrupted function
+    #[error("Intr: Interrupted function")]
+    Intr,
     /// Errno::Inval: Invalid argument
     #[error("Inval: Invalid argument")]
     Inval,
@@ -159,6 +165,7 @@ impl From<io::Error> for Error {
             Some(code) => match code {
                 libc::EPIPE => Self::Pipe,
                 libc::EPERM => Self::Perm,
+                libc::EAGAIN => Self::Again,
                 libc::ENOENT => Self::Noent,
                 libc::ENOMEM => Self::Nomem,
                 libc::E2BIG => Self::TooBig,
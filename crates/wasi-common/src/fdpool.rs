@@ -2,6 +2,8 @@
 //! pool. It's intended to be mainly used within the `WasiCtx`
 //! object(s).
 
+use std::collections::BTreeSet;
+
 /// Any type wishing to be treated as a valid WASI file descriptor
 /// should implement this trait.
 ///
@@ -25,6 +27,8 @@ pub(crate) trait Fd {
 pub(crate) struct FdPool {
     next_alloc: Option<u32>,
     available: Vec<u32>,
+    /// Descriptors at or above `next_alloc` which were claimed out of order.
+    claimed: BTreeSet<u32>,
 }
 
 impl FdPool {
@@ -32,6 +36,7 @@ impl FdPool {
         Self {
             next_alloc: Some(0),
             available: Vec::new(),
+            claimed: BTreeSet::new(),
         }
     }
 
@@ -51,11 +56,31 @@ impl FdPool {
         // allocating an additional one into the pool. If we've
         // reached our max number of handles, we will fail with None
         // instead.
-        let fd = self.next_alloc.take()?;
-        // It's OK to not unpack the result of `fd.checked_add()` here which
-        // can fail since we check for `None` in the snippet above.
-        self.next_alloc = fd.checked_add(1);
-        Some(T::from_raw(fd))
+        loop {
+            let fd = self.next_alloc.take()?;
+            // It's OK to not unpack the result of `fd.checked_add()` here which
+            // can fail since we check for `None` in the snippet above.
+            self.next_alloc = fd.checked_add(1);
+            // Skip over any descriptor which was already claimed out of order.
+            if !self.claimed.remove(&fd) {
+                return Some(T::from_raw(fd));
+            }
+        }
+    }
+
+    /// Claim a specific file descriptor, such as the target of an `fd_renumber`.
+    ///
+    /// Returns `false`, leaving the pool unchanged, if `fd` is already allocated.
+    pub fn claim<T: Fd>(&mut self, fd: T) -> bool {
+        let fd = fd.as_raw();
+        if let Some(index) = self.available.iter().position(|&a| a == fd) {
+            self.available.swap_remove(index);
+            return true;
+        }
+        match self.next_alloc {
+            Some(next_alloc) if fd >= next_alloc => self.claimed.insert(fd),
+            _ => false,
+        }
     }
 
     /// Return a file descriptor back to the pool.
@@ -66,7 +91,10 @@ impl FdPool {
     pub fn deallocate<T: Fd>(&mut self, fd: T) {
         let fd = fd.as_raw();
         if let Some(next_alloc) = self.next_alloc {
-            assert!(fd < next_alloc);
+            if fd >= next_alloc {
+                assert!(self.claimed.remove(&fd));
+                return;
+            }
         }
         debug_assert!(!self.available.contains(&fd));
         self.available.push(fd);
@@ -123,6 +151,28 @@ mod test {
         fd_pool.deallocate(0u32);
     }
 
+    #[test]
+    fn claim() {
+        let mut fd_pool = FdPool::new();
+        let fd: Fd = fd_pool.allocate().expect("success allocating 0");
+        assert_eq!(*fd, 0);
+        assert!(!fd_pool.claim(0u32));
+        assert!(fd_pool.claim(2u32));
+        assert!(!fd_pool.claim(2u32));
+        let fd: Fd = fd_pool.allocate().expect("success allocating 1");
+        assert_eq!(*fd, 1);
+        let fd: Fd = fd_pool
+            .allocate()
+            .expect("success allocating 3, skipping 2");
+        assert_eq!(*fd, 3);
+        fd_pool.deallocate(2u32);
+        assert!(fd_pool.claim(2u32));
+
+        assert!(fd_pool.claim(100u32));
+        fd_pool.deallocate(100u32);
+        assert!(fd_pool.claim(100u32));
+    }
+
     #[test]
     fn max_allocation() {
         let mut fd_pool = FdPool::new();
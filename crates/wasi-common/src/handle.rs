@@ -1,3 +1,4 @@
+use crate::sched::{Eventtype, Readiness};
 pub use crate::wasi::types::{
     Advice, Dircookie, Dirent, Fdflags, Fdstat, Filedelta, Filesize, Filestat, Filetype, Fstflags,
     Lookupflags, Oflags, Prestat, PrestatDir, Rights, Size, Timestamp, Whence,
@@ -102,6 +103,12 @@ pub trait Handle {
         let required_rights = HandleRights::from_base(Rights::FD_SEEK | Rights::FD_TELL);
         file_type == Filetype::CharacterDevice && rights.contains(&required_rights)
     }
+    /// Report whether this handle is ready for an `fd_read` or `fd_write` subscription of
+    /// `poll_oneoff`. Handles backed by OS resources return `None`, and are polled through the
+    /// host OS instead.
+    fn poll_readiness(&self, _event: Eventtype) -> Option<Readiness> {
+        None
+    }
     // TODO perhaps should be a separate trait?
     // FdOps
     fn advise(&self, _advice: Advice, _offset: Filesize, _len: Filesize) -> Result<()> {
@@ -265,6 +272,7 @@ pub(crate) trait RightsExt: Sized {
     fn socket_inheriting() -> Self;
     fn tty_base() -> Self;
     fn tty_inheriting() -> Self;
+    fn mutating() -> Self;
 }
 
 impl RightsExt for Rights {
@@ -356,5 +364,25 @@ impl RightsExt for Rights {
     fn tty_inheriting() -> Self {
         Self::empty()
     }
+
+    // Operations that modify files, directories or their metadata.
+    fn mutating() -> Self {
+        Self::FD_DATASYNC
+            | Self::FD_WRITE
+            | Self::FD_ALLOCATE
+            | Self::FD_FILESTAT_SET_SIZE
+            | Self::FD_FILESTAT_SET_TIMES
+            | Self::PATH_CREATE_DIRECTORY
+            | Self::PATH_CREATE_FILE
+            | Self::PATH_LINK_SOURCE
+            | Self::PATH_LINK_TARGET
+            | Self::PATH_RENAME_SOURCE
+            | Self::PATH_RENAME_TARGET
+            | Self::PATH_FILESTAT_SET_SIZE
+            | Self::PATH_FILESTAT_SET_TIMES
+            | Self::PATH_SYMLINK
+            | Self::PATH_REMOVE_DIRECTORY
+            | Self::PATH_UNLINK_FILE
+    }
 }
 pub(crate) const DIRCOOKIE_START: Dircookie = 0;
@@ -20,6 +20,7 @@
     )
 )]
 
+pub mod clocks;
 mod ctx;
 mod entry;
 mod error;
@@ -28,6 +29,7 @@ pub mod fs;
 mod handle;
 pub mod old;
 mod path;
+pub mod random;
 mod sandboxed_tty_writer;
 pub(crate) mod sched;
 pub mod snapshots;
@@ -36,9 +38,12 @@ mod sys;
 pub mod virtfs;
 pub mod wasi;
 
-pub use ctx::{WasiCtx, WasiCtxBuilder, WasiCtxBuilderError};
+pub use clocks::{MockClock, WasiClock};
+pub use ctx::{FollowSymlinks, PreopenOptions, WasiCtx, WasiCtxBuilder, WasiCtxBuilderError};
 pub use error::{Error, Result};
 pub use handle::{Handle, HandleRights};
+pub use random::{SeededRandom, WasiRandom};
+pub use sched::Readiness;
 pub use sys::osdir::OsDir;
 pub use sys::osfile::OsFile;
 pub use sys::osother::OsOther;
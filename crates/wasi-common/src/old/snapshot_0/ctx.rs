@@ -1,12 +1,16 @@
+use crate::clocks::WasiClock;
 use crate::fdpool::FdPool;
 use crate::old::snapshot_0::entry::Entry;
 use crate::old::snapshot_0::wasi::{self, WasiError, WasiResult};
+use crate::random::WasiRandom;
 use std::borrow::Borrow;
+use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
 use std::ffi::{self, CString, OsString};
 use std::fs::File;
 use std::path::{Path, PathBuf};
-use std::{env, io, string};
+use std::rc::Rc;
+use std::{env, fmt, io, string};
 
 /// Possible errors when `WasiCtxBuilder` fails building
 /// `WasiCtx`.
@@ -39,6 +43,7 @@ type WasiCtxBuilderResult<T> = std::result::Result<T, WasiCtxBuilderError>;
 enum PendingEntry {
     Thunk(fn() -> io::Result<Entry>),
     File(File),
+    Virtual(Entry),
 }
 
 impl std::fmt::Debug for PendingEntry {
@@ -50,6 +55,7 @@ impl std::fmt::Debug for PendingEntry {
                 f as *const fn() -> io::Result<Entry>
             ),
             Self::File(f) => write!(fmt, "PendingEntry::File({:?})", f),
+            Self::Virtual(e) => write!(fmt, "PendingEntry::Virtual({:?})", e),
         }
     }
 }
@@ -99,14 +105,25 @@ impl PendingCString {
     }
 }
 
+#[derive(Debug)]
+struct PendingPreopen {
+    guest_path: PathBuf,
+    dir: File,
+    rights_base: wasi::__wasi_rights_t,
+    rights_inheriting: wasi::__wasi_rights_t,
+}
+
 /// A builder allowing customizable construction of `WasiCtx` instances.
 pub struct WasiCtxBuilder {
     stdin: Option<PendingEntry>,
     stdout: Option<PendingEntry>,
     stderr: Option<PendingEntry>,
-    preopens: Option<Vec<(PathBuf, File)>>,
+    preopens: Option<Vec<PendingPreopen>>,
     args: Option<Vec<PendingCString>>,
     env: Option<HashMap<PendingCString, PendingCString>>,
+    monotonic_clock: Option<Rc<dyn WasiClock>>,
+    wall_clock: Option<Rc<dyn WasiClock>>,
+    random: Option<Box<dyn WasiRandom>>,
 }
 
 impl WasiCtxBuilder {
@@ -119,6 +136,9 @@ impl WasiCtxBuilder {
             preopens: Some(Vec::new()),
             args: Some(Vec::new()),
             env: Some(HashMap::new()),
+            monotonic_clock: None,
+            wall_clock: None,
+            random: None,
         }
     }
 
@@ -237,12 +257,93 @@ impl WasiCtxBuilder {
         self
     }
 
+    /// Provide a reader to use as stdin, such as an in-memory buffer.
+    ///
+    /// The guest sees it as a stream which is always ready to be read.
+    pub fn stdin_reader(&mut self, reader: impl io::Read + Send + 'static) -> &mut Self {
+        let entry = Entry::virtual_reader(Box::new(reader));
+        self.stdin = Some(PendingEntry::Virtual(entry));
+        self
+    }
+
+    /// Provide a writer to use as stdout, such as an in-memory buffer.
+    ///
+    /// The guest sees it as a stream which is always ready to be written to.
+    pub fn stdout_writer(&mut self, writer: impl io::Write + Send + 'static) -> &mut Self {
+        let entry = Entry::virtual_writer(Box::new(writer));
+        self.stdout = Some(PendingEntry::Virtual(entry));
+        self
+    }
+
+    /// Provide a writer to use as stderr, such as an in-memory buffer.
+    ///
+    /// The guest sees it as a stream which is always ready to be written to.
+    pub fn stderr_writer(&mut self, writer: impl io::Write + Send + 'static) -> &mut Self {
+        let entry = Entry::virtual_writer(Box::new(writer));
+        self.stderr = Some(PendingEntry::Virtual(entry));
+        self
+    }
+
     /// Add a preopened directory.
     pub fn preopened_dir<P: AsRef<Path>>(&mut self, dir: File, guest_path: P) -> &mut Self {
-        self.preopens
-            .as_mut()
-            .unwrap()
-            .push((guest_path.as_ref().to_owned(), dir));
+        self.preopened_dir_with_rights(dir, guest_path, wasi::RIGHTS_ALL, wasi::RIGHTS_ALL)
+    }
+
+    /// Add a preopened directory, on which the guest has at most the `rights_base` rights, and
+    /// on the descriptors opened from it, and from those in turn, at most the
+    /// `rights_inheriting` rights.
+    ///
+    /// Operations needing a right which was masked out fail with `ENOTCAPABLE`.
+    pub fn preopened_dir_with_rights<P: AsRef<Path>>(
+        &mut self,
+        dir: File,
+        guest_path: P,
+        rights_base: wasi::__wasi_rights_t,
+        rights_inheriting: wasi::__wasi_rights_t,
+    ) -> &mut Self {
+        self.preopens.as_mut().unwrap().push(PendingPreopen {
+            guest_path: guest_path.as_ref().to_owned(),
+            dir,
+            rights_base,
+            rights_inheriting,
+        });
+        self
+    }
+
+    /// Add a preopened directory which the guest can open, read and stat files and directories
+    /// in, but can't create, write to, truncate, rename or remove anything in.
+    pub fn preopened_dir_read_only<P: AsRef<Path>>(
+        &mut self,
+        dir: File,
+        guest_path: P,
+    ) -> &mut Self {
+        self.preopened_dir_with_rights(
+            dir,
+            guest_path,
+            wasi::RIGHTS_ALL & !wasi::RIGHTS_MUTATING,
+            wasi::RIGHTS_ALL & !wasi::RIGHTS_MUTATING,
+        )
+    }
+
+    /// Make the monotonic clock read `clock` instead of the host's, for `clock_time_get`,
+    /// `clock_res_get` and clock subscriptions of `poll_oneoff`, the same way as
+    /// `wasi_common::WasiCtxBuilder::monotonic_clock`.
+    pub fn monotonic_clock(&mut self, clock: impl WasiClock + 'static) -> &mut Self {
+        self.monotonic_clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// Make the realtime clock read `clock` instead of the host's wall clock, the same way as
+    /// `monotonic_clock` does for the monotonic clock.
+    pub fn wall_clock(&mut self, clock: impl WasiClock + 'static) -> &mut Self {
+        self.wall_clock = Some(Rc::new(clock));
+        self
+    }
+
+    /// Make `random_get` take its bytes from `random` instead of the host's random number
+    /// generator.
+    pub fn random(&mut self, random: impl WasiRandom + 'static) -> &mut Self {
+        self.random = Some(Box::new(random));
         self
     }
 
@@ -295,10 +396,19 @@ impl WasiCtxBuilder {
                 PendingEntry::File(f) => {
                     entries.insert(fd, Entry::from(f)?);
                 }
+                PendingEntry::Virtual(e) => {
+                    entries.insert(fd, e);
+                }
             }
         }
         // Then add the preopen fds.
-        for (guest_path, dir) in self.preopens.take().unwrap() {
+        for PendingPreopen {
+            guest_path,
+            dir,
+            rights_base,
+            rights_inheriting,
+        } in self.preopens.take().unwrap()
+        {
             // We do the increment at the beginning of the loop body, so that we don't overflow
             // unnecessarily if we have exactly the maximum number of file descriptors.
             let preopen_fd = fd_pool
@@ -311,6 +421,8 @@ impl WasiCtxBuilder {
 
             let mut fe = Entry::from(dir)?;
             fe.preopen_path = Some(guest_path);
+            fe.rights_base &= rights_base;
+            fe.rights_inheriting &= rights_inheriting;
             tracing::debug!("WasiCtx inserting ({:?}, {:?})", preopen_fd, fe);
             entries.insert(preopen_fd, fe);
             tracing::debug!("WasiCtx entries = {:?}", entries);
@@ -321,16 +433,32 @@ impl WasiCtxBuilder {
             env,
             fd_pool,
             entries,
+            monotonic_clock: self.monotonic_clock.take(),
+            wall_clock: self.wall_clock.take(),
+            random: self.random.take().map(RefCell::new),
         })
     }
 }
 
-#[derive(Debug)]
 pub struct WasiCtx {
     fd_pool: FdPool,
     entries: HashMap<wasi::__wasi_fd_t, Entry>,
     pub(crate) args: Vec<CString>,
     pub(crate) env: Vec<CString>,
+    monotonic_clock: Option<Rc<dyn WasiClock>>,
+    wall_clock: Option<Rc<dyn WasiClock>>,
+    random: Option<RefCell<Box<dyn WasiRandom>>>,
+}
+
+impl fmt::Debug for WasiCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiCtx")
+            .field("fd_pool", &self.fd_pool)
+            .field("entries", &self.entries)
+            .field("args", &self.args)
+            .field("env", &self.env)
+            .finish()
+    }
 }
 
 impl WasiCtx {
@@ -349,6 +477,21 @@ impl WasiCtx {
             .build()
     }
 
+    /// Returns the clock installed for `clock_id` with `WasiCtxBuilder`, if any, which the guest
+    /// should see instead of the host's.
+    pub(crate) fn clock(&self, clock_id: wasi::__wasi_clockid_t) -> Option<&Rc<dyn WasiClock>> {
+        match clock_id {
+            wasi::__WASI_CLOCKID_REALTIME => self.wall_clock.as_ref(),
+            wasi::__WASI_CLOCKID_MONOTONIC => self.monotonic_clock.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the source of random bytes installed with `WasiCtxBuilder::random`, if any.
+    pub(crate) fn random(&self) -> Option<RefMut<'_, Box<dyn WasiRandom>>> {
+        self.random.as_ref().map(RefCell::borrow_mut)
+    }
+
     /// Check if `WasiCtx` contains the specified raw WASI `fd`.
     pub(crate) unsafe fn contains_entry(&self, fd: wasi::__wasi_fd_t) -> bool {
         self.entries.contains_key(&fd)
@@ -377,6 +520,10 @@ impl WasiCtx {
     /// Insert the specified `Entry` with the specified raw WASI `fd` key into the `WasiCtx`
     /// object.
     pub(crate) fn insert_entry_at(&mut self, fd: wasi::__wasi_fd_t, fe: Entry) -> Option<Entry> {
+        if !self.entries.contains_key(&fd) {
+            let claimed = self.fd_pool.claim(fd);
+            debug_assert!(claimed, "fd {} is free but already allocated", fd);
+        }
         self.entries.insert(fd, fe)
     }
 
@@ -389,3 +536,58 @@ impl WasiCtx {
         Ok(entry)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{WasiCtxBuilder, WasiCtxBuilderResult};
+    use crate::old::snapshot_0::hostcalls_impl;
+    use crate::old::snapshot_0::wasi::{self, WasiError};
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn virtual_stdio() -> WasiCtxBuilderResult<()> {
+        let stdout = SharedBuffer::default();
+        let mut ctx = WasiCtxBuilder::new()
+            .stdin_reader(&b"input"[..])
+            .stdout_writer(stdout.clone())
+            .build()?;
+
+        // A single iovec at offset 0 covering the 5 bytes at offset 32, with the number of bytes
+        // read or written stored at offset 16.
+        let mut memory = vec![0; 64];
+        memory[0..4].copy_from_slice(&32u32.to_le_bytes());
+        memory[4..8].copy_from_slice(&5u32.to_le_bytes());
+        memory[32..37].copy_from_slice(b"hello");
+
+        unsafe { hostcalls_impl::fd_write(&mut ctx, &mut memory, 1, 0, 1, 16) }
+            .expect("writing to stdout");
+        assert_eq!(&*stdout.0.lock().unwrap(), b"hello");
+
+        unsafe { hostcalls_impl::fd_read(&mut ctx, &mut memory, 0, 0, 1, 16) }
+            .expect("reading from stdin");
+        assert_eq!(&memory[32..37], b"input");
+        assert_eq!(&memory[16..20], &5u32.to_le_bytes());
+
+        assert_eq!(
+            unsafe {
+                hostcalls_impl::fd_seek(&mut ctx, &mut memory, 0, 0, wasi::__WASI_WHENCE_SET, 16)
+            },
+            Err(WasiError::ESPIPE)
+        );
+        Ok(())
+    }
+}
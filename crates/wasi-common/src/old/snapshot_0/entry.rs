@@ -3,27 +3,47 @@ use crate::old::snapshot_0::sys::entry_impl::{
     descriptor_as_oshandle, determine_type_and_access_rights, OsHandle,
 };
 use crate::old::snapshot_0::wasi::{self, WasiError, WasiResult};
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::mem::ManuallyDrop;
 use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
-use std::{fs, io};
+use std::{fmt, fs, io};
 
-#[derive(Debug)]
 pub(crate) enum Descriptor {
     OsHandle(OsHandle),
     Stdin,
     Stdout,
     Stderr,
+    /// A stream read from by the host, which has no OS handle behind it.
+    VirtualReader(Box<dyn Read + Send>),
+    /// A stream written to by the host, which has no OS handle behind it.
+    VirtualWriter(Box<dyn Write + Send>),
+}
+
+impl fmt::Debug for Descriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OsHandle(file) => f.debug_tuple("OsHandle").field(file).finish(),
+            Self::Stdin => f.write_str("Stdin"),
+            Self::Stdout => f.write_str("Stdout"),
+            Self::Stderr => f.write_str("Stderr"),
+            Self::VirtualReader(_) => f.write_str("VirtualReader"),
+            Self::VirtualWriter(_) => f.write_str("VirtualWriter"),
+        }
+    }
 }
 
 impl Descriptor {
     /// Return a reference to the `OsHandle` treating it as an actual file/dir, and
     /// allowing operations which require an actual file and not just a stream or
     /// socket file descriptor.
+    ///
+    /// Virtual streams can't be seeked, so `Error::ESPIPE` is returned for those.
     pub(crate) fn as_file(&self) -> WasiResult<&OsHandle> {
         match self {
             Self::OsHandle(file) => Ok(file),
+            Self::VirtualReader(_) | Self::VirtualWriter(_) => Err(WasiError::ESPIPE),
             _ => Err(WasiError::EBADF),
         }
     }
@@ -32,16 +52,41 @@ impl Descriptor {
     pub(crate) fn as_file_mut(&mut self) -> WasiResult<&mut OsHandle> {
         match self {
             Self::OsHandle(file) => Ok(file),
+            Self::VirtualReader(_) | Self::VirtualWriter(_) => Err(WasiError::ESPIPE),
             _ => Err(WasiError::EBADF),
         }
     }
 
     /// Return an `OsHandle`, which may be a stream or socket file descriptor.
-    pub(crate) fn as_os_handle<'descriptor>(&'descriptor self) -> OsHandleRef<'descriptor> {
-        descriptor_as_oshandle(self)
+    ///
+    /// Virtual streams have no `OsHandle`, so `Error::EBADF` is returned for those.
+    pub(crate) fn as_os_handle<'descriptor>(
+        &'descriptor self,
+    ) -> WasiResult<OsHandleRef<'descriptor>> {
+        if self.is_virtual() {
+            return Err(WasiError::EBADF);
+        }
+        Ok(descriptor_as_oshandle(self))
+    }
+
+    /// Whether this is a stream provided by the host rather than an OS handle.
+    pub(crate) fn is_virtual(&self) -> bool {
+        match self {
+            Self::VirtualReader(_) | Self::VirtualWriter(_) => true,
+            _ => false,
+        }
     }
 }
 
+/// Rights of virtual streams besides reading or writing. Like a pipe's, seeking is allowed, but
+/// fails with `Error::ESPIPE`, while syncing has nothing to do.
+const VIRTUAL_STREAM_RIGHTS: wasi::__wasi_rights_t = wasi::__WASI_RIGHTS_FD_DATASYNC
+    | wasi::__WASI_RIGHTS_FD_SYNC
+    | wasi::__WASI_RIGHTS_FD_FDSTAT_SET_FLAGS
+    | wasi::__WASI_RIGHTS_FD_SEEK
+    | wasi::__WASI_RIGHTS_FD_TELL
+    | wasi::__WASI_RIGHTS_POLL_FD_READWRITE;
+
 /// An abstraction struct serving as a wrapper for a host `Descriptor` object which requires
 /// certain base rights `rights_base` and inheriting rights `rights_inheriting` in order to be
 /// accessed correctly.
@@ -112,6 +157,28 @@ impl Entry {
         )
     }
 
+    /// Create an Entry which reads from `reader`, such as an in-memory buffer.
+    pub(crate) fn virtual_reader(reader: Box<dyn Read + Send>) -> Self {
+        Self {
+            file_type: wasi::__WASI_FILETYPE_UNKNOWN,
+            descriptor: Descriptor::VirtualReader(reader),
+            rights_base: wasi::__WASI_RIGHTS_FD_READ | VIRTUAL_STREAM_RIGHTS,
+            rights_inheriting: 0,
+            preopen_path: None,
+        }
+    }
+
+    /// Create an Entry which writes to `writer`, such as an in-memory buffer.
+    pub(crate) fn virtual_writer(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            file_type: wasi::__WASI_FILETYPE_UNKNOWN,
+            descriptor: Descriptor::VirtualWriter(writer),
+            rights_base: wasi::__WASI_RIGHTS_FD_WRITE | VIRTUAL_STREAM_RIGHTS,
+            rights_inheriting: 0,
+            preopen_path: None,
+        }
+    }
+
     pub(crate) fn null() -> io::Result<Self> {
         Self::from(dev_null()?)
     }
@@ -42,12 +42,15 @@ pub(crate) unsafe fn fd_datasync(
 ) -> WasiResult<()> {
     trace!("fd_datasync(fd={:?})", fd);
 
-    let fd = wasi_ctx
+    let desc = wasi_ctx
         .get_entry(fd)?
-        .as_descriptor(wasi::__WASI_RIGHTS_FD_DATASYNC, 0)?
-        .as_file()?;
+        .as_descriptor(wasi::__WASI_RIGHTS_FD_DATASYNC, 0)?;
+    if desc.is_virtual() {
+        // Writes to virtual streams are flushed as they happen.
+        return Ok(());
+    }
 
-    fd.sync_data().map_err(Into::into)
+    desc.as_file()?.sync_data().map_err(Into::into)
 }
 
 pub(crate) unsafe fn fd_pread(
@@ -172,6 +175,7 @@ pub(crate) unsafe fn fd_read(
     {
         Descriptor::OsHandle(file) => file.read_vectored(&mut iovs),
         Descriptor::Stdin => io::stdin().read_vectored(&mut iovs),
+        Descriptor::VirtualReader(reader) => reader.read_vectored(&mut iovs),
         _ => return Err(WasiError::EBADF),
     };
 
@@ -190,11 +194,7 @@ pub(crate) unsafe fn fd_renumber(
 ) -> WasiResult<()> {
     trace!("fd_renumber(from={:?}, to={:?})", from, to);
 
-    if !wasi_ctx.contains_entry(from) {
-        return Err(WasiError::EBADF);
-    }
-
-    // Don't allow renumbering over a pre-opened resource.
+    // Don't allow renumbering over a pre-opened resource, even onto itself.
     // TODO: Eventually, we do want to permit this, once libpreopen in
     // userspace is capable of removing entries from its tables as well.
     let from_fe = wasi_ctx.get_entry(from)?;
@@ -206,6 +206,9 @@ pub(crate) unsafe fn fd_renumber(
             return Err(WasiError::ENOTSUP);
         }
     }
+    if from == to {
+        return Ok(());
+    }
 
     let fe = wasi_ctx.remove_entry(from)?;
     wasi_ctx.insert_entry_at(to, fe);
@@ -281,9 +284,12 @@ pub(crate) unsafe fn fd_fdstat_get(
     trace!("fd_fdstat_get(fd={:?}, fdstat_ptr={:#x?})", fd, fdstat_ptr);
 
     let mut fdstat = dec_fdstat_byref(memory, fdstat_ptr)?;
-    let host_fd = wasi_ctx.get_entry(fd)?.as_descriptor(0, 0)?.as_os_handle();
-
-    let fs_flags = hostcalls_impl::fd_fdstat_get(&host_fd)?;
+    let desc = wasi_ctx.get_entry(fd)?.as_descriptor(0, 0)?;
+    let fs_flags = if desc.is_virtual() {
+        0
+    } else {
+        hostcalls_impl::fd_fdstat_get(&desc.as_os_handle()?)?
+    };
 
     let fe = wasi_ctx.get_entry(fd)?;
     fdstat.fs_filetype = fe.file_type;
@@ -304,9 +310,13 @@ pub(crate) unsafe fn fd_fdstat_set_flags(
 ) -> WasiResult<()> {
     trace!("fd_fdstat_set_flags(fd={:?}, fdflags={:#x?})", fd, fdflags);
 
-    let fd = wasi_ctx.get_entry(fd)?.as_descriptor(0, 0)?.as_os_handle();
+    let desc = wasi_ctx.get_entry(fd)?.as_descriptor(0, 0)?;
+    if desc.is_virtual() {
+        // Virtual streams have no flags to set.
+        return Ok(());
+    }
 
-    hostcalls_impl::fd_fdstat_set_flags(&fd, fdflags)
+    hostcalls_impl::fd_fdstat_set_flags(&desc.as_os_handle()?, fdflags)
 }
 
 pub(crate) unsafe fn fd_fdstat_set_rights(
@@ -342,11 +352,14 @@ pub(crate) unsafe fn fd_sync(
 ) -> WasiResult<()> {
     trace!("fd_sync(fd={:?})", fd);
 
-    let fd = wasi_ctx
+    let desc = wasi_ctx
         .get_entry(fd)?
-        .as_descriptor(wasi::__WASI_RIGHTS_FD_SYNC, 0)?
-        .as_file()?;
-    fd.sync_all().map_err(Into::into)
+        .as_descriptor(wasi::__WASI_RIGHTS_FD_SYNC, 0)?;
+    if desc.is_virtual() {
+        // Writes to virtual streams are flushed as they happen.
+        return Ok(());
+    }
+    desc.as_file()?.sync_all().map_err(Into::into)
 }
 
 pub(crate) unsafe fn fd_write(
@@ -380,7 +393,12 @@ pub(crate) unsafe fn fd_write(
                 file.write_vectored(&iovs)?
             }
         }
-        Descriptor::Stdin => return Err(WasiError::EBADF),
+        Descriptor::Stdin | Descriptor::VirtualReader(_) => return Err(WasiError::EBADF),
+        Descriptor::VirtualWriter(writer) => {
+            let nwritten = writer.write_vectored(&iovs)?;
+            writer.flush()?;
+            nwritten
+        }
         Descriptor::Stdout => {
             // lock for the duration of the scope
             let stdout = io::stdout();
@@ -595,6 +613,10 @@ pub(crate) unsafe fn path_open(
     let mut fe = Entry::from(fd)?;
     fe.rights_base &= max_base;
     fe.rights_inheriting &= max_inheriting;
+    // Only grant the rights which were requested, which `path_open_rights` checked the directory
+    // may hand out, so that reopening a descriptor can't escalate its rights.
+    fe.rights_base &= fs_rights_base;
+    fe.rights_inheriting &= fs_rights_inheriting;
     let guest_fd = wasi_ctx.insert_entry(fe)?;
 
     trace!("     | *fd={:?}", guest_fd);
@@ -704,8 +726,23 @@ pub(crate) unsafe fn fd_filestat_get(
         filestat_ptr
     );
 
-    let fd = wasi_ctx.get_entry(fd)?.as_descriptor(0, 0)?.as_file()?;
-    let host_filestat = hostcalls_impl::fd_filestat_get(fd)?;
+    let fe = wasi_ctx.get_entry(fd)?;
+    let desc = fe.as_descriptor(0, 0)?;
+    let host_filestat = if desc.is_virtual() {
+        // Virtual streams aren't backed by anything with a device, inode or times.
+        wasi::__wasi_filestat_t {
+            dev: 0,
+            ino: 0,
+            filetype: fe.file_type,
+            nlink: 0,
+            size: 0,
+            atim: 0,
+            mtim: 0,
+            ctim: 0,
+        }
+    } else {
+        hostcalls_impl::fd_filestat_get(desc.as_file()?)?
+    };
 
     trace!("     | *filestat_ptr={:?}", host_filestat);
 
@@ -1,4 +1,5 @@
 #![allow(non_camel_case_types)]
+use crate::clocks::{self, TimerEventData};
 use crate::old::snapshot_0::ctx::WasiCtx;
 use crate::old::snapshot_0::entry::Descriptor;
 use crate::old::snapshot_0::memory::*;
@@ -6,8 +7,14 @@ use crate::old::snapshot_0::sys::hostcalls_impl;
 use crate::old::snapshot_0::wasi::{self, WasiError, WasiResult};
 use crate::old::snapshot_0::wasi32;
 use std::convert::TryFrom;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use tracing::{error, trace};
 
+/// How often clock subscriptions for clocks installed with `WasiCtxBuilder` are checked while
+/// waiting.
+const TIMER_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 pub(crate) fn args_get(
     wasi_ctx: &WasiCtx,
     memory: &mut [u8],
@@ -131,7 +138,7 @@ pub(crate) fn environ_sizes_get(
 }
 
 pub(crate) fn random_get(
-    _wasi_ctx: &WasiCtx,
+    wasi_ctx: &WasiCtx,
     memory: &mut [u8],
     buf_ptr: wasi32::uintptr_t,
     buf_len: wasi32::size_t,
@@ -140,6 +147,10 @@ pub(crate) fn random_get(
 
     let buf = dec_slice_of_mut_u8(memory, buf_ptr, buf_len)?;
 
+    if let Some(mut random) = wasi_ctx.random() {
+        random.fill(buf);
+        return Ok(());
+    }
     getrandom::getrandom(buf).map_err(|err| {
         error!("getrandom failure: {:?}", err);
         WasiError::EIO
@@ -147,7 +158,7 @@ pub(crate) fn random_get(
 }
 
 pub(crate) fn clock_res_get(
-    _wasi_ctx: &WasiCtx,
+    wasi_ctx: &WasiCtx,
     memory: &mut [u8],
     clock_id: wasi::__wasi_clockid_t,
     resolution_ptr: wasi32::uintptr_t,
@@ -158,7 +169,10 @@ pub(crate) fn clock_res_get(
         resolution_ptr,
     );
 
-    let resolution = hostcalls_impl::clock_res_get(clock_id)?;
+    let resolution = match wasi_ctx.clock(clock_id) {
+        Some(clock) => clock.resolution(),
+        None => hostcalls_impl::clock_res_get(clock_id)?,
+    };
 
     trace!("     | *resolution_ptr={:?}", resolution);
 
@@ -166,7 +180,7 @@ pub(crate) fn clock_res_get(
 }
 
 pub(crate) fn clock_time_get(
-    _wasi_ctx: &WasiCtx,
+    wasi_ctx: &WasiCtx,
     memory: &mut [u8],
     clock_id: wasi::__wasi_clockid_t,
     precision: wasi::__wasi_timestamp_t,
@@ -179,7 +193,10 @@ pub(crate) fn clock_time_get(
         time_ptr,
     );
 
-    let time = hostcalls_impl::clock_time_get(clock_id)?;
+    let time = match wasi_ctx.clock(clock_id) {
+        Some(clock) => clock.now(),
+        None => hostcalls_impl::clock_time_get(clock_id)?,
+    };
 
     trace!("     | *time_ptr={:?}", time);
 
@@ -220,11 +237,28 @@ pub(crate) fn poll_oneoff(
     let mut events = Vec::new();
 
     let mut timeout: Option<ClockEventData> = None;
+    let mut timers = Vec::new();
     let mut fd_events = Vec::new();
     for subscription in subscriptions {
         match subscription.u.tag {
             wasi::__WASI_EVENTTYPE_CLOCK => {
                 let clock = unsafe { subscription.u.u.clock };
+                // Clocks installed in the context don't follow real time, so they're checked
+                // while waiting rather than waited for.
+                if let Some(installed) = wasi_ctx.clock(clock.id) {
+                    let deadline =
+                        if clock.flags == wasi::__WASI_SUBCLOCKFLAGS_SUBSCRIPTION_CLOCK_ABSTIME {
+                            clock.timeout
+                        } else {
+                            installed.now().saturating_add(clock.timeout)
+                        };
+                    timers.push(TimerEventData {
+                        clock: Rc::clone(installed),
+                        deadline,
+                        userdata: Some(subscription.userdata),
+                    });
+                    continue;
+                }
                 let delay = wasi_clock_to_relative_ns_delay(clock)?;
 
                 tracing::debug!("poll_oneoff event.u.clock = {:?}", clock);
@@ -302,7 +336,28 @@ pub(crate) fn poll_oneoff(
     tracing::debug!("poll_oneoff timeout = {:?}", timeout);
     tracing::debug!("poll_oneoff fd_events = {:?}", fd_events);
 
-    hostcalls_impl::poll_oneoff(timeout, fd_events, &mut events)?;
+    // Virtual streams can't be polled by the host, and are always considered ready, so if any
+    // are subscribed to there's no need to wait for anything else.
+    let (virtual_events, fd_events): (Vec<_>, Vec<_>) = fd_events
+        .into_iter()
+        .partition(|event| event.descriptor.is_virtual());
+    if virtual_events.is_empty() && timers.is_empty() {
+        hostcalls_impl::poll_oneoff(timeout, fd_events, &mut events)?;
+    } else if virtual_events.is_empty() {
+        poll_oneoff_with_timers(timeout, &timers, &fd_events, &mut events)?;
+    } else {
+        for event in virtual_events {
+            events.push(wasi::__wasi_event_t {
+                userdata: event.userdata,
+                error: wasi::__WASI_ERRNO_SUCCESS,
+                r#type: event.r#type,
+                fd_readwrite: wasi::__wasi_event_fd_readwrite_t {
+                    nbytes: 0,
+                    flags: 0,
+                },
+            });
+        }
+    }
 
     let events_count = u32::try_from(events.len()).map_err(|_| WasiError::EOVERFLOW)?;
 
@@ -313,6 +368,73 @@ pub(crate) fn poll_oneoff(
     enc_int_byref(memory, nevents, events_count)
 }
 
+/// Wait until `timeout` expires, any of `timers` expires or any of `fd_events` is ready, checking
+/// the timers every `TIMER_POLL_INTERVAL` unless the clock of the first one to expire can move
+/// straight to its deadline.
+fn poll_oneoff_with_timers(
+    timeout: Option<ClockEventData>,
+    timers: &[TimerEventData],
+    fd_events: &[FdEventData],
+    events: &mut Vec<wasi::__wasi_event_t>,
+) -> WasiResult<()> {
+    let start = Instant::now();
+    let interval = TIMER_POLL_INTERVAL.as_nanos();
+    loop {
+        // Nothing blocks if there are events already, such as errors for subscriptions which
+        // couldn't be polled, or if a clock can move straight to the deadline of a timer.
+        let wait = if events.is_empty() && !clocks::skip_to_next_timer(timers) {
+            interval
+        } else {
+            0
+        };
+        let remaining = timeout.map(|timeout| ClockEventData {
+            delay: timeout.delay.saturating_sub(start.elapsed().as_nanos()),
+            userdata: timeout.userdata,
+        });
+        let (poll_timeout, expiring) = match remaining {
+            Some(remaining) if remaining.delay <= wait => (remaining, true),
+            _ => (
+                ClockEventData {
+                    delay: wait,
+                    userdata: 0,
+                },
+                false,
+            ),
+        };
+        let fd_events = fd_events
+            .iter()
+            .map(|event| FdEventData {
+                descriptor: event.descriptor,
+                r#type: event.r#type,
+                userdata: event.userdata,
+            })
+            .collect();
+        let mut ready = Vec::new();
+        hostcalls_impl::poll_oneoff(Some(poll_timeout), fd_events, &mut ready)?;
+        // A clock event is only the guest's if its timeout was what was waited for.
+        if !expiring {
+            ready.retain(|event| event.r#type != wasi::__WASI_EVENTTYPE_CLOCK);
+        }
+        events.extend(ready);
+        for timer in timers {
+            if let (true, Some(userdata)) = (timer.clock.now() >= timer.deadline, timer.userdata) {
+                events.push(wasi::__wasi_event_t {
+                    userdata,
+                    error: wasi::__WASI_ERRNO_SUCCESS,
+                    r#type: wasi::__WASI_EVENTTYPE_CLOCK,
+                    fd_readwrite: wasi::__wasi_event_fd_readwrite_t {
+                        nbytes: 0,
+                        flags: 0,
+                    },
+                });
+            }
+        }
+        if !events.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
 fn wasi_clock_to_relative_ns_delay(
     wasi_clock: wasi::__wasi_subscription_clock_t,
 ) -> WasiResult<u128> {
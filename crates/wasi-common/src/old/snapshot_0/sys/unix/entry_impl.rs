@@ -14,6 +14,9 @@ impl AsRawFd for Descriptor {
             Self::Stdin => io::stdin().as_raw_fd(),
             Self::Stdout => io::stdout().as_raw_fd(),
             Self::Stderr => io::stderr().as_raw_fd(),
+            // Virtual streams have no file descriptor, so anything which tries to use one
+            // fails with `EBADF`.
+            Self::VirtualReader(_) | Self::VirtualWriter(_) => -1,
         }
     }
 }
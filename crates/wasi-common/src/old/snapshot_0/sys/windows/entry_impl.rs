@@ -42,6 +42,9 @@ impl AsRawHandle for Descriptor {
             Self::Stdin => io::stdin().as_raw_handle(),
             Self::Stdout => io::stdout().as_raw_handle(),
             Self::Stderr => io::stderr().as_raw_handle(),
+            // Virtual streams have no handle, so this is `INVALID_HANDLE_VALUE`, and anything
+            // which tries to use it fails with `ERROR_INVALID_HANDLE`.
+            Self::VirtualReader(_) | Self::VirtualWriter(_) => usize::max_value() as RawHandle,
         }
     }
 }
@@ -216,6 +216,24 @@ pub(crate) const RIGHTS_TTY_BASE: __wasi_rights_t = __WASI_RIGHTS_FD_READ
 #[allow(unused)]
 pub(crate) const RIGHTS_TTY_INHERITING: __wasi_rights_t = 0;
 
+// Operations which modify files or directories.
+pub(crate) const RIGHTS_MUTATING: __wasi_rights_t = __WASI_RIGHTS_FD_DATASYNC
+    | __WASI_RIGHTS_FD_WRITE
+    | __WASI_RIGHTS_FD_ALLOCATE
+    | __WASI_RIGHTS_FD_FILESTAT_SET_SIZE
+    | __WASI_RIGHTS_FD_FILESTAT_SET_TIMES
+    | __WASI_RIGHTS_PATH_CREATE_DIRECTORY
+    | __WASI_RIGHTS_PATH_CREATE_FILE
+    | __WASI_RIGHTS_PATH_LINK_SOURCE
+    | __WASI_RIGHTS_PATH_LINK_TARGET
+    | __WASI_RIGHTS_PATH_RENAME_SOURCE
+    | __WASI_RIGHTS_PATH_RENAME_TARGET
+    | __WASI_RIGHTS_PATH_FILESTAT_SET_SIZE
+    | __WASI_RIGHTS_PATH_FILESTAT_SET_TIMES
+    | __WASI_RIGHTS_PATH_SYMLINK
+    | __WASI_RIGHTS_PATH_REMOVE_DIRECTORY
+    | __WASI_RIGHTS_PATH_UNLINK_FILE;
+
 pub fn whence_to_str(whence: __wasi_whence_t) -> &'static str {
     match whence {
         __WASI_WHENCE_CUR => "__WASI_WHENCE_CUR",
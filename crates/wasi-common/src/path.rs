@@ -1,3 +1,4 @@
+use crate::ctx::FollowSymlinks;
 use crate::entry::Entry;
 use crate::handle::{Fdflags, Filetype, Handle, HandleRights, Lookupflags, Oflags};
 use crate::{Error, Result};
@@ -95,6 +96,21 @@ pub(crate) fn get(
                             head.push('/');
                         }
 
+                        // A component is resolved if anything follows it, including a trailing
+                        // slash, or if the final component is to be followed. The host resolves
+                        // symlinks with a trailing slash even with `O_NOFOLLOW`, so check for
+                        // a symlink here, rather than relying on the checks below.
+                        if entry.follow_symlinks == FollowSymlinks::Never
+                            && (!path_stack.is_empty()
+                                || ends_with_slash
+                                || dirflags.contains(&Lookupflags::SYMLINK_FOLLOW))
+                        {
+                            let fd = dir_stack.last().ok_or(Error::Notcapable)?;
+                            if fd.readlinkat(head.trim_end_matches('/')).is_ok() {
+                                return Err(Error::Loop);
+                            }
+                        }
+
                         if !path_stack.is_empty() || (ends_with_slash && !needs_final_component) {
                             let fd = dir_stack.last().ok_or(Error::Notcapable)?;
                             match fd.openat(
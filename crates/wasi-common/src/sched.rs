@@ -1,8 +1,19 @@
+use crate::clocks;
+pub(crate) use crate::clocks::TimerEventData;
 use crate::entry::EntryHandle;
+use crate::sys::poll;
 pub use crate::wasi::types::{
-    Clockid, Errno, Event, EventFdReadwrite, Eventrwflags, Eventtype, Subclockflags,
+    Clockid, Errno, Event, EventFdReadwrite, Eventrwflags, Eventtype, Filesize, Subclockflags,
     SubscriptionClock, Timestamp, Userdata,
 };
+use crate::Result;
+use std::convert::TryInto;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often handles which report their own readiness are re-checked while waiting.
+const VIRTUAL_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 #[derive(Debug, Copy, Clone)]
 pub struct ClockEventData {
     pub delay: u128, // delay is expressed in nanoseconds
@@ -15,3 +26,115 @@ pub struct FdEventData {
     pub r#type: Eventtype,
     pub userdata: Userdata,
 }
+
+/// Readiness of a handle which `poll_oneoff` can't hand off to the host OS, as reported by
+/// `Handle::poll_readiness`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Readiness {
+    /// The handle can be read from or written to without blocking. `nbytes` is a hint of how
+    /// many bytes can be transferred, or 0 if that isn't known.
+    Ready { nbytes: Filesize },
+    /// The other end of the handle has been closed.
+    Hangup,
+    /// The handle would block.
+    NotReady,
+}
+
+/// Wait until `timeout` expires, any of `timers` expires or any of `fd_events` is ready, pushing
+/// the resulting events to `events`.
+///
+/// Handles backed by OS resources are left to the host's `poll`. When there are also handles
+/// which report their own readiness, or timers, everything is checked without blocking every
+/// `VIRTUAL_POLL_INTERVAL` instead, unless the clock of the first timer to expire can move
+/// straight to its deadline. Nothing blocks if `events` already holds events, such as errors for
+/// subscriptions which couldn't be polled.
+pub(crate) fn oneoff(
+    timeout: Option<ClockEventData>,
+    timers: Vec<TimerEventData>,
+    fd_events: Vec<FdEventData>,
+    events: &mut Vec<Event>,
+) -> Result<()> {
+    let (virtual_events, os_events): (Vec<_>, Vec<_>) = fd_events
+        .into_iter()
+        .partition(|event| event.handle.poll_readiness(event.r#type).is_some());
+    if virtual_events.is_empty() && timers.is_empty() && events.is_empty() {
+        return poll::oneoff(timeout, os_events, events);
+    }
+
+    let start = Instant::now();
+    loop {
+        events.extend(virtual_events.iter().filter_map(readiness_event));
+        if !os_events.is_empty() {
+            let now = ClockEventData {
+                delay: 0,
+                userdata: 0,
+            };
+            let os_events = os_events
+                .iter()
+                .map(|event| FdEventData {
+                    handle: event.handle.get(),
+                    r#type: event.r#type,
+                    userdata: event.userdata,
+                })
+                .collect();
+            let mut ready = Vec::new();
+            poll::oneoff(Some(now), os_events, &mut ready)?;
+            events.extend(ready.into_iter().filter(|e| e.type_ != Eventtype::Clock));
+        }
+        let mut expired = false;
+        for timer in timers.iter() {
+            if timer.clock.now() >= timer.deadline {
+                expired = true;
+                events.extend(timer.userdata.map(clock_event));
+            }
+        }
+        if expired || !events.is_empty() {
+            return Ok(());
+        }
+        if clocks::skip_to_next_timer(&timers) {
+            continue;
+        }
+
+        let mut interval = VIRTUAL_POLL_INTERVAL;
+        if let Some(timeout) = timeout {
+            let delay = Duration::from_nanos(timeout.delay.try_into().unwrap_or(u64::max_value()));
+            let remaining = delay.checked_sub(start.elapsed()).unwrap_or_default();
+            if remaining == Duration::from_secs(0) {
+                events.push(clock_event(timeout.userdata));
+                return Ok(());
+            }
+            interval = interval.min(remaining);
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn clock_event(userdata: Userdata) -> Event {
+    Event {
+        userdata,
+        error: Errno::Success,
+        type_: Eventtype::Clock,
+        fd_readwrite: EventFdReadwrite {
+            flags: Eventrwflags::empty(),
+            nbytes: 0,
+        },
+    }
+}
+
+fn readiness_event(event: &FdEventData) -> Option<Event> {
+    let (error, nbytes, flags) = match event.handle.poll_readiness(event.r#type)? {
+        Readiness::Ready { nbytes } => (Errno::Success, nbytes, Eventrwflags::empty()),
+        Readiness::NotReady => return None,
+        // A closed reader can still drain what was written, but nothing can be written to it.
+        Readiness::Hangup if event.r#type == Eventtype::FdWrite => {
+            (Errno::Pipe, 0, Eventrwflags::FD_READWRITE_HANGUP)
+        }
+        Readiness::Hangup => (Errno::Success, 0, Eventrwflags::FD_READWRITE_HANGUP),
+    };
+    Some(Event {
+        userdata: event.userdata,
+        error,
+        type_: event.r#type,
+        fd_readwrite: EventFdReadwrite { nbytes, flags },
+    })
+}
@@ -1,12 +1,14 @@
 use crate::entry::{Entry, EntryHandle};
-use crate::handle::{AsBytes, HandleRights};
-use crate::sys::{clock, poll};
+use crate::handle::{AsBytes, Dirent, Handle, HandleRights, DIRCOOKIE_START};
+use crate::sys::clock;
 use crate::wasi::types;
 use crate::wasi::wasi_snapshot_preview1::WasiSnapshotPreview1;
 use crate::{path, sched, Error, Result, WasiCtx};
 use std::convert::TryInto;
 use std::io::{self, SeekFrom};
-use std::ops::Deref;
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+use std::time::Instant;
 use tracing::{debug, trace};
 use wiggle::{GuestPtr, GuestSlice};
 
@@ -16,10 +18,16 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         argv: &GuestPtr<'b, GuestPtr<'b, u8>>,
         argv_buf: &GuestPtr<'b, u8>,
     ) -> Result<()> {
+        if self.args.cumulative_size > self.max_args_size {
+            return Err(Error::Overflow);
+        }
         self.args.write_to_guest(argv_buf, argv)
     }
 
     fn args_sizes_get(&self) -> Result<(types::Size, types::Size)> {
+        if self.args.cumulative_size > self.max_args_size {
+            return Err(Error::Overflow);
+        }
         Ok((self.args.number_elements, self.args.cumulative_size))
     }
 
@@ -36,7 +44,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn clock_res_get(&self, id: types::Clockid) -> Result<types::Timestamp> {
-        let resolution = clock::res_get(id)?;
+        let resolution = match self.clock(id) {
+            Some(clock) => clock.resolution(),
+            None => clock::res_get(id)?,
+        };
         Ok(resolution)
     }
 
@@ -45,7 +56,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         id: types::Clockid,
         _precision: types::Timestamp,
     ) -> Result<types::Timestamp> {
-        let time = clock::time_get(id)?;
+        let time = match self.clock(id) {
+            Some(clock) => clock.now(),
+            None => clock::time_get(id)?,
+        };
         Ok(time)
     }
 
@@ -117,13 +131,7 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         fs_rights_base: types::Rights,
         fs_rights_inheriting: types::Rights,
     ) -> Result<()> {
-        let rights = HandleRights::new(fs_rights_base, fs_rights_inheriting);
-        let entry = self.get_entry(fd)?;
-        if !entry.get_rights().contains(&rights) {
-            return Err(Error::Notcapable);
-        }
-        entry.set_rights(rights);
-        Ok(())
+        self.set_fd_rights(fd, fs_rights_base, fs_rights_inheriting)
     }
 
     fn fd_filestat_get(&self, fd: types::Fd) -> Result<types::Filestat> {
@@ -235,12 +243,7 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         ciovs: &types::CiovecArray<'_>,
         offset: types::Filesize,
     ) -> Result<types::Size> {
-        let mut guest_slices = Vec::new();
-        for ciov_ptr in ciovs.iter() {
-            let ciov_ptr = ciov_ptr?;
-            let ciov: types::Ciovec = ciov_ptr.read()?;
-            guest_slices.push(ciov.buf.as_array(ciov.buf_len).as_slice()?);
-        }
+        let bufs = CiovecBufs::borrow(ciovs)?;
 
         let required_rights =
             HandleRights::from_base(types::Rights::FD_WRITE | types::Rights::FD_SEEK);
@@ -250,14 +253,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             return Err(Error::Io);
         }
 
-        let host_nwritten = {
-            let buf: Vec<io::IoSlice> =
-                guest_slices.iter().map(|s| io::IoSlice::new(&*s)).collect();
-            entry
-                .as_handle(&required_rights)?
-                .pwritev(&buf, offset)?
-                .try_into()?
-        };
+        let host_nwritten = entry
+            .as_handle(&required_rights)?
+            .pwritev(&bufs.io_slices(), offset)?
+            .try_into()?;
         Ok(host_nwritten)
     }
 
@@ -295,9 +294,17 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         let required_rights = HandleRights::from_base(types::Rights::FD_READDIR);
         let entry = self.get_entry(fd)?;
 
+        let handle = entry.as_handle(&required_rights)?;
+        let dirents: Box<dyn Iterator<Item = Result<(Dirent, String)>> + '_> =
+            if self.sorted_readdir {
+                Box::new(sorted_readdir(&*handle, cookie)?.into_iter().map(Ok))
+            } else {
+                handle.readdir(cookie)?
+            };
+
         let mut bufused = 0;
         let mut buf = buf.clone();
-        for pair in entry.as_handle(&required_rights)?.readdir(cookie)? {
+        for pair in dirents {
             let (dirent, name) = pair?;
             let dirent_raw = dirent.as_bytes()?;
             let dirent_len: types::Size = dirent_raw.len().try_into()?;
@@ -319,23 +326,21 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn fd_renumber(&self, from: types::Fd, to: types::Fd) -> Result<()> {
-        if !self.contains_entry(from) {
-            return Err(Error::Badf);
-        }
-
-        // Don't allow renumbering over a pre-opened resource.
+        // Don't allow renumbering over a pre-opened resource, even onto itself.
         // TODO: Eventually, we do want to permit this, once libpreopen in
         // userspace is capable of removing entries from its tables as well.
-        if let Ok(from_fe) = self.get_entry(from) {
-            if from_fe.preopen_path.is_some() {
-                return Err(Error::Notsup);
-            }
+        let from_fe = self.get_entry(from)?;
+        if from_fe.preopen_path.is_some() {
+            return Err(Error::Notsup);
         }
         if let Ok(to_fe) = self.get_entry(to) {
             if to_fe.preopen_path.is_some() {
                 return Err(Error::Notsup);
             }
         }
+        if from == to {
+            return Ok(());
+        }
         let fe = self.remove_entry(from)?;
         self.insert_entry_at(to, fe);
         Ok(())
@@ -379,22 +384,13 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
     }
 
     fn fd_write(&self, fd: types::Fd, ciovs: &types::CiovecArray<'_>) -> Result<types::Size> {
-        let mut guest_slices = Vec::new();
-        for ciov_ptr in ciovs.iter() {
-            let ciov_ptr = ciov_ptr?;
-            let ciov: types::Ciovec = ciov_ptr.read()?;
-            guest_slices.push(ciov.buf.as_array(ciov.buf_len).as_slice()?);
-        }
+        let bufs = CiovecBufs::borrow(ciovs)?;
         let required_rights = HandleRights::from_base(types::Rights::FD_WRITE);
         let entry = self.get_entry(fd)?;
-        let host_nwritten = {
-            let slices: Vec<io::IoSlice> =
-                guest_slices.iter().map(|s| io::IoSlice::new(&*s)).collect();
-            entry
-                .as_handle(&required_rights)?
-                .write_vectored(&slices)?
-                .try_into()?
-        };
+        let host_nwritten = entry
+            .as_handle(&required_rights)?
+            .write_vectored(&bufs.io_slices())?
+            .try_into()?;
         Ok(host_nwritten)
     }
 
@@ -536,7 +532,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             write
         );
         let fd = dirfd.openat(&path, read, write, oflags, fdflags)?;
-        let entry = Entry::new(EntryHandle::from(fd));
+        let follow_symlinks = entry.follow_symlinks;
+        let mut entry = Entry::new(EntryHandle::from(fd));
+        // Directories opened from a preopen are subject to its symlink policy too.
+        entry.follow_symlinks = follow_symlinks;
         // We need to manually deny the rights which are not explicitly requested
         // because Entry::from will assign maximal consistent rights.
         let mut rights = entry.get_rights();
@@ -682,6 +681,7 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
 
         let mut events = Vec::new();
         let mut timeout: Option<sched::ClockEventData> = None;
+        let mut timers = Vec::new();
         let mut fd_events = Vec::new();
 
         // As mandated by the WASI spec:
@@ -691,8 +691,24 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         }
 
         for subscription in subscriptions {
-            match subscription.u {
+            let (fd, r#type, rights) = match subscription.u {
                 types::SubscriptionU::Clock(clock) => {
+                    // Clocks installed in the context don't follow real time, so they're
+                    // checked while waiting rather than waited for.
+                    if let Some(installed) = self.clock(clock.id) {
+                        let deadline =
+                            if clock.flags == types::Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME {
+                                clock.timeout
+                            } else {
+                                installed.now().saturating_add(clock.timeout)
+                            };
+                        timers.push(sched::TimerEventData {
+                            clock: Rc::clone(installed),
+                            deadline,
+                            userdata: Some(subscription.userdata),
+                        });
+                        continue;
+                    }
                     let delay = clock::to_relative_ns_delay(&clock)?;
                     debug!(
                         clock = tracing::field::debug(&clock),
@@ -707,59 +723,41 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
                     if current.delay < timeout.delay {
                         *timeout = current;
                     }
+                    continue;
                 }
-                types::SubscriptionU::FdRead(fd_read) => {
-                    let fd = fd_read.file_descriptor;
-                    let required_rights = HandleRights::from_base(
-                        types::Rights::FD_READ | types::Rights::POLL_FD_READWRITE,
-                    );
-                    let entry = match self.get_entry(fd) {
-                        Ok(entry) => entry,
-                        Err(error) => {
-                            events.push(types::Event {
-                                userdata: subscription.userdata,
-                                error: error.into(),
-                                type_: types::Eventtype::FdRead,
-                                fd_readwrite: types::EventFdReadwrite {
-                                    nbytes: 0,
-                                    flags: types::Eventrwflags::empty(),
-                                },
-                            });
-                            continue;
-                        }
-                    };
-                    fd_events.push(sched::FdEventData {
-                        handle: entry.as_handle(&required_rights)?,
-                        r#type: types::Eventtype::FdRead,
-                        userdata: subscription.userdata,
-                    });
-                }
-                types::SubscriptionU::FdWrite(fd_write) => {
-                    let fd = fd_write.file_descriptor;
-                    let required_rights = HandleRights::from_base(
-                        types::Rights::FD_WRITE | types::Rights::POLL_FD_READWRITE,
-                    );
-                    let entry = match self.get_entry(fd) {
-                        Ok(entry) => entry,
-                        Err(error) => {
-                            events.push(types::Event {
-                                userdata: subscription.userdata,
-                                error: error.into(),
-                                type_: types::Eventtype::FdWrite,
-                                fd_readwrite: types::EventFdReadwrite {
-                                    nbytes: 0,
-                                    flags: types::Eventrwflags::empty(),
-                                },
-                            });
-                            continue;
-                        }
-                    };
-                    fd_events.push(sched::FdEventData {
-                        handle: entry.as_handle(&required_rights)?,
-                        r#type: types::Eventtype::FdWrite,
-                        userdata: subscription.userdata,
-                    });
-                }
+                types::SubscriptionU::FdRead(fd_read) => (
+                    fd_read.file_descriptor,
+                    types::Eventtype::FdRead,
+                    types::Rights::FD_READ,
+                ),
+                types::SubscriptionU::FdWrite(fd_write) => (
+                    fd_write.file_descriptor,
+                    types::Eventtype::FdWrite,
+                    types::Rights::FD_WRITE,
+                ),
+            };
+            // A subscription which can't be polled is reported as an event of its own
+            // rather than failing the whole call.
+            let required_rights =
+                HandleRights::from_base(rights | types::Rights::POLL_FD_READWRITE);
+            match self
+                .get_entry(fd)
+                .and_then(|entry| entry.as_handle(&required_rights))
+            {
+                Ok(handle) => fd_events.push(sched::FdEventData {
+                    handle,
+                    r#type,
+                    userdata: subscription.userdata,
+                }),
+                Err(error) => events.push(types::Event {
+                    userdata: subscription.userdata,
+                    error: error.into(),
+                    type_: r#type,
+                    fd_readwrite: types::EventFdReadwrite {
+                        nbytes: 0,
+                        flags: types::Eventrwflags::empty(),
+                    },
+                }),
             }
         }
         debug!(
@@ -767,10 +765,47 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
             timeout = tracing::field::debug(timeout),
             "poll_oneoff"
         );
+        // Wait no longer than the host's deadline, if it comes first. Reaching it isn't an
+        // event for the guest: the host is told, so that it can stop the guest, and the call
+        // fails instead.
+        let mut has_deadline = false;
+        let mut host_deadline = None;
+        if let Some(deadline) = self.deadline() {
+            has_deadline = true;
+            let delay = deadline
+                .saturating_duration_since(Instant::now())
+                .as_nanos();
+            // With an installed clock, the deadline is also a timer on it, so that it's measured
+            // the same way as the guest's own timers.
+            if let Some(installed) = self.clock(types::Clockid::Monotonic) {
+                timers.push(sched::TimerEventData {
+                    clock: Rc::clone(installed),
+                    deadline: installed
+                        .now()
+                        .saturating_add(delay.try_into().unwrap_or(u64::max_value())),
+                    userdata: None,
+                });
+            }
+            if timeout.map_or(true, |timeout| delay < timeout.delay) {
+                timeout = Some(sched::ClockEventData { delay, userdata: 0 });
+                host_deadline = Some(deadline);
+            }
+        }
         // The underlying implementation should successfully and immediately return
         // if no events have been passed. Such situation may occur if all provided
         // events have been filtered out as errors in the code above.
-        poll::oneoff(timeout, fd_events, &mut events)?;
+        sched::oneoff(timeout, timers, fd_events, &mut events)?;
+        if has_deadline {
+            // A clock event stands in for a deadline on the host's clock, and before the
+            // deadline clock events can only come from installed clocks.
+            if host_deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                events.retain(|event| event.type_ != types::Eventtype::Clock);
+            }
+            if events.is_empty() {
+                self.deadline_expired();
+                return Err(Error::Intr);
+            }
+        }
         let nevents = events.len().try_into()?;
 
         let out_events = out.as_array(nevents);
@@ -801,7 +836,10 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
 
     fn random_get(&self, buf: &GuestPtr<u8>, buf_len: types::Size) -> Result<()> {
         let mut slice = buf.as_array(buf_len).as_slice()?;
-        getrandom::getrandom(&mut *slice)?;
+        match self.random() {
+            Some(mut random) => random.fill(&mut *slice),
+            None => getrandom::getrandom(&mut *slice)?,
+        }
         Ok(())
     }
 
@@ -827,3 +865,88 @@ impl<'a> WasiSnapshotPreview1 for WasiCtx {
         unimplemented!("sock_shutdown")
     }
 }
+
+/// Lists all of the entries of the directory `handle` sorted bytewise by name, except for `.` and
+/// `..` which come first in that order, and skips the first `cookie` of them.
+///
+/// Cookies are positions in the sorted listing, so they stay meaningful across calls as long as
+/// the directory doesn't change, whatever order the host lists entries in.
+fn sorted_readdir(handle: &dyn Handle, cookie: types::Dircookie) -> Result<Vec<(Dirent, String)>> {
+    let mut dirents = handle
+        .readdir(DIRCOOKIE_START)?
+        .collect::<Result<Vec<_>>>()?;
+    dirents.sort_by(|(_, a), (_, b)| {
+        let rank = |name: &str| match name {
+            "." => 0,
+            ".." => 1,
+            _ => 2,
+        };
+        (rank(a), a.as_bytes()).cmp(&(rank(b), b.as_bytes()))
+    });
+    for (i, (dirent, _)) in dirents.iter_mut().enumerate() {
+        dirent.d_next = i as types::Dircookie + 1;
+    }
+    let skip = cookie.try_into().unwrap_or(usize::max_value());
+    Ok(dirents.into_iter().skip(skip).collect())
+}
+
+/// The buffers of a guest's ciovecs, borrowed straight from its memory.
+///
+/// Buffers which are read into must not overlap, since they're borrowed mutably, but buffers
+/// written from may, for instance when the same buffer is written twice. So the regions spanned
+/// by overlapping ciovecs are each borrowed once, and shared by the ciovecs within them.
+struct CiovecBufs<'a> {
+    regions: Vec<GuestSlice<'a, u8>>,
+    // For each non-empty ciovec, its region and where it is within that region.
+    bufs: Vec<(usize, Range<usize>)>,
+}
+
+impl<'a> CiovecBufs<'a> {
+    /// Borrows the buffers of `ciovs`, failing if any of them is out of bounds.
+    fn borrow(ciovs: &types::CiovecArray<'a>) -> Result<Self> {
+        let mut spans = Vec::new();
+        for ciov_ptr in ciovs.iter() {
+            let ciov: types::Ciovec = ciov_ptr?.read()?;
+            let start = ciov.buf.offset();
+            let end = start.checked_add(ciov.buf_len).ok_or(Error::Overflow)?;
+            ciovs.mem().validate_size_align(start, 1, ciov.buf_len)?;
+            spans.push((start, end));
+        }
+
+        let mut merged: Vec<(u32, u32)> = spans.iter().copied().filter(|(s, e)| s < e).collect();
+        merged.sort();
+        merged.dedup_by(|next, prev| {
+            let overlaps = next.0 < prev.1;
+            if overlaps {
+                prev.1 = prev.1.max(next.1);
+            }
+            overlaps
+        });
+
+        let mut regions = Vec::with_capacity(merged.len());
+        for &(start, end) in &merged {
+            let ptr = GuestPtr::<u8>::new(ciovs.mem(), start);
+            regions.push(ptr.as_array(end - start).as_slice()?);
+        }
+        let bufs = spans
+            .into_iter()
+            .filter(|(start, end)| start < end)
+            .map(|(start, end)| {
+                let region = match merged.binary_search_by_key(&start, |&(s, _)| s) {
+                    Ok(i) => i,
+                    Err(i) => i - 1,
+                };
+                let offset = (start - merged[region].0) as usize;
+                (region, offset..offset + (end - start) as usize)
+            })
+            .collect();
+        Ok(Self { regions, bufs })
+    }
+
+    fn io_slices(&self) -> Vec<io::IoSlice<'_>> {
+        self.bufs
+            .iter()
+            .map(|(region, range)| io::IoSlice::new(&self.regions[*region][range.clone()]))
+            .collect()
+    }
+}
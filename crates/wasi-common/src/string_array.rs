@@ -23,19 +23,22 @@ impl From<OsString> for PendingString {
 }
 
 impl PendingString {
-    pub fn into_string(self) -> Result<String, StringArrayError> {
+    /// WASI strings are byte strings, so bytes are passed through unchanged,
+    /// even if they aren't valid UTF-8. Only host strings which can't be
+    /// represented as bytes, i.e. invalid UTF-16 on Windows, are rejected.
+    pub fn into_bytes(self) -> Result<Vec<u8>, StringArrayError> {
         let res = match self {
-            Self::Bytes(v) => String::from_utf8(v)?,
+            Self::Bytes(v) => v,
             #[cfg(unix)]
             Self::OsString(s) => {
                 use std::os::unix::ffi::OsStringExt;
-                String::from_utf8(s.into_vec())?
+                s.into_vec()
             }
             #[cfg(windows)]
             Self::OsString(s) => {
                 use std::os::windows::ffi::OsStrExt;
                 let bytes: Vec<u16> = s.encode_wide().collect();
-                String::from_utf16(&bytes)?
+                String::from_utf16(&bytes)?.into_bytes()
             }
         };
         Ok(res)
@@ -75,26 +78,29 @@ impl StringArray {
     pub fn from_pending_vec(elems: Vec<PendingString>) -> Result<Self, StringArrayError> {
         let elems = elems
             .into_iter()
-            .map(|arg| arg.into_string())
-            .collect::<Result<Vec<String>, StringArrayError>>()?;
-        Self::from_strings(elems)
+            .map(|arg| arg.into_bytes())
+            .collect::<Result<Vec<Vec<u8>>, StringArrayError>>()?;
+        Self::from_bytes(elems)
     }
     pub fn from_pending_map(
         elems: HashMap<PendingString, PendingString>,
     ) -> Result<Self, StringArrayError> {
         let mut pairs = Vec::new();
         for (k, v) in elems.into_iter() {
-            let mut pair = k.into_string()?;
-            pair.push('=');
-            pair.push_str(&v.into_string()?);
+            let mut pair = k.into_bytes()?;
+            pair.push(b'=');
+            pair.extend(v.into_bytes()?);
             pairs.push(pair);
         }
-        Self::from_strings(pairs)
+        Self::from_bytes(pairs)
     }
     pub fn from_strings(elems: Vec<String>) -> Result<Self, StringArrayError> {
+        Self::from_bytes(elems.into_iter().map(String::into_bytes).collect())
+    }
+    pub fn from_bytes(elems: Vec<Vec<u8>>) -> Result<Self, StringArrayError> {
         let elems = elems
             .into_iter()
-            .map(|s| CString::new(s))
+            .map(CString::new)
             .collect::<Result<Vec<CString>, _>>()?;
         let number_elements = elems
             .len()
@@ -1,6 +1,5 @@
 use crate::sched::{Subclockflags, SubscriptionClock};
-use crate::{Error, Result};
-use std::time::SystemTime;
+use crate::Result;
 
 pub(crate) use super::sys_impl::clock::*;
 
@@ -8,10 +7,8 @@ pub(crate) fn to_relative_ns_delay(clock: &SubscriptionClock) -> Result<u128> {
     if clock.flags != Subclockflags::SUBSCRIPTION_CLOCK_ABSTIME {
         return Ok(u128::from(clock.timeout));
     }
-    let now: u128 = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .map_err(|_| Error::Notcapable)?
-        .as_nanos();
+    // Absolute timeouts are measured against the clock they're for, as read by `clock_time_get`.
+    let now = u128::from(time_get(clock.id)?);
     let deadline = u128::from(clock.timeout);
     Ok(deadline.saturating_sub(now))
 }
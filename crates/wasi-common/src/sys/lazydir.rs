@@ -0,0 +1,220 @@
+use super::osdir::OsDir;
+use crate::handle::{
+    Advice, Dircookie, Dirent, Fdflags, Filesize, Filestat, Filetype, Fstflags, Handle,
+    HandleRights, Oflags, Rights, RightsExt,
+};
+use crate::sched::Timestamp;
+use crate::Result;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::io::{self, SeekFrom};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// A preopened directory which isn't opened until the guest first uses it.
+///
+/// Until then it only knows that it's a directory and which rights it has, which is all that
+/// `fd_prestat_get` and `fd_fdstat_get` need, so guests can enumerate their preopens without opening
+/// any of them. Failing to open the directory, for instance because it doesn't exist, is
+/// reported as the error of whichever operation first needed it.
+pub(crate) struct LazyDir {
+    path: PathBuf,
+    rights: Cell<HandleRights>,
+    dir: RefCell<Option<Rc<dyn Handle>>>,
+}
+
+impl LazyDir {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        let rights = HandleRights::new(Rights::directory_base(), Rights::directory_inheriting());
+        Self {
+            path,
+            rights: Cell::new(rights),
+            dir: RefCell::new(None),
+        }
+    }
+
+    /// Opens the directory if that hasn't happened yet, returning the opened directory.
+    pub(crate) fn open(&self) -> Result<Rc<dyn Handle>> {
+        if let Some(dir) = &*self.dir.borrow() {
+            return Ok(Rc::clone(dir));
+        }
+        tracing::debug!(
+            path = tracing::field::debug(&self.path),
+            "opening lazy preopen"
+        );
+        let file = super::preopen_dir(&self.path).map_err(|e| {
+            tracing::warn!(
+                path = tracing::field::debug(&self.path),
+                error = tracing::field::display(&e),
+                "failed to open lazy preopen"
+            );
+            e
+        })?;
+        let dir = OsDir::try_from(file)?;
+        // The guest may already have dropped some rights, and can't gain any the directory
+        // doesn't have.
+        let (lazy, opened) = (self.rights.get(), dir.get_rights());
+        let rights = HandleRights::new(
+            lazy.base() & opened.base(),
+            lazy.inheriting() & opened.inheriting(),
+        );
+        self.rights.set(rights);
+        dir.set_rights(rights);
+        let dir: Rc<dyn Handle> = Rc::new(dir);
+        *self.dir.borrow_mut() = Some(Rc::clone(&dir));
+        Ok(dir)
+    }
+
+    /// Opens `handle` if it's a `LazyDir`, so that it can be downcast to the directory it stands
+    /// for, and otherwise returns it as it is.
+    pub(crate) fn resolve(handle: Box<dyn Handle>) -> Result<Box<dyn Handle>> {
+        if let Some(lazy) = handle.as_any().downcast_ref::<Self>() {
+            return Ok(lazy.open()?.try_clone()?);
+        }
+        Ok(handle)
+    }
+}
+
+impl Handle for LazyDir {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn try_clone(&self) -> io::Result<Box<dyn Handle>> {
+        match &*self.dir.borrow() {
+            Some(dir) => dir.try_clone(),
+            None => {
+                let clone = Self::new(self.path.clone());
+                clone.rights.set(self.rights.get());
+                Ok(Box::new(clone))
+            }
+        }
+    }
+    fn get_file_type(&self) -> Filetype {
+        Filetype::Directory
+    }
+    fn get_rights(&self) -> HandleRights {
+        self.rights.get()
+    }
+    fn set_rights(&self, rights: HandleRights) {
+        self.rights.set(rights);
+        if let Some(dir) = &*self.dir.borrow() {
+            dir.set_rights(rights);
+        }
+    }
+    // FdOps
+    fn advise(&self, advice: Advice, offset: Filesize, len: Filesize) -> Result<()> {
+        self.open()?.advise(advice, offset, len)
+    }
+    fn allocate(&self, offset: Filesize, len: Filesize) -> Result<()> {
+        self.open()?.allocate(offset, len)
+    }
+    fn datasync(&self) -> Result<()> {
+        self.open()?.datasync()
+    }
+    fn fdstat_get(&self) -> Result<Fdflags> {
+        // Flags can only have been set on a directory that's been opened.
+        match &*self.dir.borrow() {
+            Some(dir) => dir.fdstat_get(),
+            None => Ok(Fdflags::empty()),
+        }
+    }
+    fn fdstat_set_flags(&self, fdflags: Fdflags) -> Result<()> {
+        self.open()?.fdstat_set_flags(fdflags)
+    }
+    fn filestat_get(&self) -> Result<Filestat> {
+        self.open()?.filestat_get()
+    }
+    fn filestat_set_size(&self, st_size: Filesize) -> Result<()> {
+        self.open()?.filestat_set_size(st_size)
+    }
+    fn filestat_set_times(
+        &self,
+        atim: Timestamp,
+        mtim: Timestamp,
+        fst_flags: Fstflags,
+    ) -> Result<()> {
+        self.open()?.filestat_set_times(atim, mtim, fst_flags)
+    }
+    fn preadv(&self, buf: &mut [io::IoSliceMut], offset: u64) -> Result<usize> {
+        self.open()?.preadv(buf, offset)
+    }
+    fn pwritev(&self, buf: &[io::IoSlice], offset: u64) -> Result<usize> {
+        self.open()?.pwritev(buf, offset)
+    }
+    fn read_vectored(&self, iovs: &mut [io::IoSliceMut]) -> Result<usize> {
+        self.open()?.read_vectored(iovs)
+    }
+    fn readdir<'a>(
+        &'a self,
+        cookie: Dircookie,
+    ) -> Result<Box<dyn Iterator<Item = Result<(Dirent, String)>> + 'a>> {
+        // The entries can't borrow from the opened directory, which is shared.
+        let entries = self.open()?.readdir(cookie)?.collect::<Vec<_>>();
+        Ok(Box::new(entries.into_iter()))
+    }
+    fn seek(&self, offset: SeekFrom) -> Result<u64> {
+        self.open()?.seek(offset)
+    }
+    fn sync(&self) -> Result<()> {
+        self.open()?.sync()
+    }
+    fn write_vectored(&self, iovs: &[io::IoSlice]) -> Result<usize> {
+        self.open()?.write_vectored(iovs)
+    }
+    // PathOps
+    fn create_directory(&self, path: &str) -> Result<()> {
+        self.open()?.create_directory(path)
+    }
+    fn filestat_get_at(&self, path: &str, follow: bool) -> Result<Filestat> {
+        self.open()?.filestat_get_at(path, follow)
+    }
+    fn filestat_set_times_at(
+        &self,
+        path: &str,
+        atim: Timestamp,
+        mtim: Timestamp,
+        fst_flags: Fstflags,
+        follow: bool,
+    ) -> Result<()> {
+        self.open()?
+            .filestat_set_times_at(path, atim, mtim, fst_flags, follow)
+    }
+    fn openat(
+        &self,
+        path: &str,
+        read: bool,
+        write: bool,
+        oflags: Oflags,
+        fd_flags: Fdflags,
+    ) -> Result<Box<dyn Handle>> {
+        self.open()?.openat(path, read, write, oflags, fd_flags)
+    }
+    fn link(
+        &self,
+        old_path: &str,
+        new_handle: Box<dyn Handle>,
+        new_path: &str,
+        follow: bool,
+    ) -> Result<()> {
+        self.open()?.link(old_path, new_handle, new_path, follow)
+    }
+    fn readlink(&self, path: &str, buf: &mut [u8]) -> Result<usize> {
+        self.open()?.readlink(path, buf)
+    }
+    fn readlinkat(&self, path: &str) -> Result<String> {
+        self.open()?.readlinkat(path)
+    }
+    fn remove_directory(&self, path: &str) -> Result<()> {
+        self.open()?.remove_directory(path)
+    }
+    fn rename(&self, old_path: &str, new_handle: Box<dyn Handle>, new_path: &str) -> Result<()> {
+        self.open()?.rename(old_path, new_handle, new_path)
+    }
+    fn symlink(&self, old_path: &str, new_path: &str) -> Result<()> {
+        self.open()?.symlink(old_path, new_path)
+    }
+    fn unlink_file(&self, path: &str) -> Result<()> {
+        self.open()?.unlink_file(path)
+    }
+}
@@ -1,5 +1,6 @@
 pub(crate) mod clock;
 pub(crate) mod fd;
+pub(crate) mod lazydir;
 pub(crate) mod osdir;
 pub(crate) mod osfile;
 pub(crate) mod osother;
@@ -1,3 +1,4 @@
+use super::lazydir::LazyDir;
 use super::sys_impl::oshandle::RawOsHandle;
 use super::{fd, path, AsFile};
 use crate::handle::{
@@ -102,6 +103,7 @@ impl Handle for OsDir {
         new_path: &str,
         follow: bool,
     ) -> Result<()> {
+        let new_handle = LazyDir::resolve(new_handle)?;
         let new_handle = match new_handle.as_any().downcast_ref::<Self>() {
             None => {
                 error!("Tried to link with handle that's not an OsDir");
@@ -121,6 +123,7 @@ impl Handle for OsDir {
         path::readlinkat(self, path)
     }
     fn rename(&self, old_path: &str, new_handle: Box<dyn Handle>, new_path: &str) -> Result<()> {
+        let new_handle = LazyDir::resolve(new_handle)?;
         let new_handle = match new_handle.as_any().downcast_ref::<Self>() {
             None => {
                 error!("Tried to rename with handle that's not an OsDir");
@@ -19,28 +19,40 @@ pub(crate) fn oneoff(
         return Ok(());
     }
 
-    let poll_fds: Result<Vec<_>> = fd_events
-        .iter()
-        .map(|event| {
-            let mut flags = PollFlags::empty();
-            match event.r#type {
-                Eventtype::FdRead => flags.insert(PollFlags::POLLIN),
-                Eventtype::FdWrite => flags.insert(PollFlags::POLLOUT),
-                // An event on a file descriptor can currently only be of type FD_READ or FD_WRITE
-                // Nothing else has been defined in the specification, and these are also the only two
-                // events we filtered before. If we get something else here, the code has a serious bug.
-                _ => unreachable!(),
-            };
-            let file = event.handle.as_file()?;
-            unsafe { Ok(PollFd::new(file.as_raw_fd(), flags)) }
-        })
-        .collect();
-    let mut poll_fds = poll_fds?;
+    let mut polled_events = Vec::new();
+    let mut poll_fds = Vec::new();
+    for event in fd_events {
+        let mut flags = PollFlags::empty();
+        match event.r#type {
+            Eventtype::FdRead => flags.insert(PollFlags::POLLIN),
+            Eventtype::FdWrite => flags.insert(PollFlags::POLLOUT),
+            // An event on a file descriptor can currently only be of type FD_READ or FD_WRITE
+            // Nothing else has been defined in the specification, and these are also the only two
+            // events we filtered before. If we get something else here, the code has a serious bug.
+            _ => unreachable!(),
+        };
+        match event.handle.as_file() {
+            Ok(file) => {
+                poll_fds.push(unsafe { PollFd::new(file.as_raw_fd(), flags) });
+                polled_events.push(event);
+            }
+            Err(e) => events.push(error_event(
+                &event,
+                Error::from(e).into(),
+                Eventrwflags::empty(),
+            )),
+        }
+    }
 
-    let poll_timeout = timeout.map_or(-1, |timeout| {
-        let delay = timeout.delay / 1_000_000; // poll syscall requires delay to expressed in milliseconds
-        delay.try_into().unwrap_or(libc::c_int::max_value())
-    });
+    // Don't wait if some subscriptions have already failed.
+    let poll_timeout = if events.is_empty() {
+        timeout.map_or(-1, |timeout| {
+            let delay = timeout.delay / 1_000_000; // poll syscall requires delay to expressed in milliseconds
+            delay.try_into().unwrap_or(libc::c_int::max_value())
+        })
+    } else {
+        0
+    };
     tracing::debug!(
         poll_timeout = tracing::field::debug(poll_timeout),
         "poll_oneoff"
@@ -59,12 +71,16 @@ pub(crate) fn oneoff(
         }
     };
 
-    Ok(if ready == 0 {
-        handle_timeout_event(timeout.expect("timeout should not be None"), events)
+    if ready == 0 {
+        if events.is_empty() {
+            handle_timeout_event(timeout.expect("timeout should not be None"), events);
+        }
     } else {
-        let ready_events = fd_events.into_iter().zip(poll_fds.into_iter()).take(ready);
-        handle_fd_event(ready_events, events)?
-    })
+        // The `ready` descriptors aren't necessarily the first ones, so look at all of them.
+        let ready_events = polled_events.into_iter().zip(poll_fds.into_iter());
+        handle_fd_event(ready_events, events);
+    }
+    Ok(())
 }
 
 fn handle_timeout_event(timeout: ClockEventData, events: &mut Vec<Event>) {
@@ -79,11 +95,20 @@ fn handle_timeout_event(timeout: ClockEventData, events: &mut Vec<Event>) {
     });
 }
 
+fn error_event(fd_event: &FdEventData, error: Errno, flags: Eventrwflags) -> Event {
+    Event {
+        userdata: fd_event.userdata,
+        error,
+        type_: fd_event.r#type,
+        fd_readwrite: EventFdReadwrite { nbytes: 0, flags },
+    }
+}
+
 fn handle_fd_event(
     ready_events: impl Iterator<Item = (FdEventData, yanix::poll::PollFd)>,
     events: &mut Vec<Event>,
-) -> Result<()> {
-    fn query_nbytes(handle: EntryHandle) -> Result<u64> {
+) {
+    fn query_nbytes(handle: &EntryHandle) -> Result<u64> {
         let file = handle.as_file()?;
         if handle.get_file_type() == Filetype::RegularFile {
             // fionread may overflow for large files, so use another way for regular files.
@@ -96,6 +121,29 @@ fn handle_fd_event(
         Ok(unsafe { fionread(file.as_raw_fd())?.into() })
     }
 
+    // The free space of a pipe is the only hint we can get for writes; everything else
+    // reports 0, as allowed by the spec.
+    #[cfg(target_os = "linux")]
+    fn query_write_nbytes(handle: &EntryHandle) -> u64 {
+        let file = match handle.as_file() {
+            Ok(file) => file,
+            Err(_) => return 0,
+        };
+        let capacity = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETPIPE_SZ) };
+        if capacity < 0 {
+            return 0;
+        }
+        match unsafe { fionread(file.as_raw_fd()) } {
+            Ok(queued) => (capacity as u64).saturating_sub(queued.into()),
+            Err(_) => 0,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn query_write_nbytes(_handle: &EntryHandle) -> u64 {
+        0
+    }
+
     for (fd_event, poll_fd) in ready_events {
         tracing::debug!(
             poll_fd = tracing::field::debug(poll_fd),
@@ -104,55 +152,67 @@ fn handle_fd_event(
         );
 
         let revents = match poll_fd.revents() {
-            Some(revents) => revents,
-            None => continue,
-        };
-
-        let nbytes = if fd_event.r#type == Eventtype::FdRead {
-            query_nbytes(fd_event.handle)?
-        } else {
-            0
+            Some(revents) if !revents.is_empty() => revents,
+            _ => continue,
         };
 
         let output_event = if revents.contains(PollFlags::POLLNVAL) {
-            Event {
-                userdata: fd_event.userdata,
-                error: Error::Badf.into(),
-                type_: fd_event.r#type,
-                fd_readwrite: EventFdReadwrite {
-                    nbytes: 0,
-                    flags: Eventrwflags::FD_READWRITE_HANGUP,
-                },
-            }
+            error_event(
+                &fd_event,
+                Error::Badf.into(),
+                Eventrwflags::FD_READWRITE_HANGUP,
+            )
         } else if revents.contains(PollFlags::POLLERR) {
-            Event {
-                userdata: fd_event.userdata,
-                error: Error::Io.into(),
-                type_: fd_event.r#type,
-                fd_readwrite: EventFdReadwrite {
-                    nbytes: 0,
-                    flags: Eventrwflags::FD_READWRITE_HANGUP,
-                },
+            // For the write end of a pipe this means the read end has been closed.
+            if fd_event.r#type == Eventtype::FdWrite {
+                error_event(
+                    &fd_event,
+                    Error::Pipe.into(),
+                    Eventrwflags::FD_READWRITE_HANGUP,
+                )
+            } else {
+                error_event(
+                    &fd_event,
+                    Error::Io.into(),
+                    Eventrwflags::FD_READWRITE_HANGUP,
+                )
             }
         } else if revents.contains(PollFlags::POLLHUP) {
-            Event {
-                userdata: fd_event.userdata,
-                error: Errno::Success,
-                type_: fd_event.r#type,
-                fd_readwrite: EventFdReadwrite {
-                    nbytes: 0,
-                    flags: Eventrwflags::FD_READWRITE_HANGUP,
-                },
+            // A reader may still drain whatever was written before the hangup.
+            if fd_event.r#type == Eventtype::FdWrite {
+                error_event(
+                    &fd_event,
+                    Error::Pipe.into(),
+                    Eventrwflags::FD_READWRITE_HANGUP,
+                )
+            } else {
+                Event {
+                    userdata: fd_event.userdata,
+                    error: Errno::Success,
+                    type_: fd_event.r#type,
+                    fd_readwrite: EventFdReadwrite {
+                        nbytes: query_nbytes(&fd_event.handle).unwrap_or(0),
+                        flags: Eventrwflags::FD_READWRITE_HANGUP,
+                    },
+                }
             }
         } else if revents.contains(PollFlags::POLLIN) | revents.contains(PollFlags::POLLOUT) {
-            Event {
-                userdata: fd_event.userdata,
-                error: Errno::Success,
-                type_: fd_event.r#type,
-                fd_readwrite: EventFdReadwrite {
-                    nbytes: nbytes.try_into()?,
-                    flags: Eventrwflags::empty(),
+            let nbytes = if fd_event.r#type == Eventtype::FdRead {
+                query_nbytes(&fd_event.handle)
+            } else {
+                Ok(query_write_nbytes(&fd_event.handle))
+            };
+            match nbytes {
+                Ok(nbytes) => Event {
+                    userdata: fd_event.userdata,
+                    error: Errno::Success,
+                    type_: fd_event.r#type,
+                    fd_readwrite: EventFdReadwrite {
+                        nbytes,
+                        flags: Eventrwflags::empty(),
+                    },
                 },
+                Err(e) => error_event(&fd_event, e.into(), Eventrwflags::empty()),
             }
         } else {
             continue;
@@ -160,6 +220,4 @@ fn handle_fd_event(
 
         events.push(output_event);
     }
-
-    Ok(())
 }
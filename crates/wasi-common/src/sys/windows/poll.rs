@@ -240,7 +240,7 @@ pub(crate) fn oneoff(
             }
         } else {
             tracing::error!("can poll FdEvent for OS resources only");
-            return Err(Error::Badf);
+            handle_error_event(event, Errno::Badf, events);
         }
     }
 
@@ -2,16 +2,16 @@ use crate::handle::{
     Advice, Dircookie, Dirent, Fdflags, Filesize, Filestat, Filetype, Fstflags, Handle,
     HandleRights, Oflags, Rights, RightsExt, Size, DIRCOOKIE_START,
 };
-use crate::sched::Timestamp;
+use crate::sched::{Eventtype, Readiness, Timestamp};
 use crate::{Error, Result};
 use std::any::Any;
 use std::cell::{Cell, RefCell};
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::io;
 use std::io::SeekFrom;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use tracing::trace;
 
@@ -85,6 +85,10 @@ impl FileContents for VecFileContents {
             let skip: u64 = read_total.try_into().map_err(|_| Error::Inval)?;
             let read = self.pread(iov, offset + skip)?;
             read_total = read_total.checked_add(read).expect("FileContents::preadv must not be called when reads could total to more bytes than the return value can hold");
+            // A short read means there's nothing left to read into the following buffers.
+            if read < iov.len() {
+                break;
+            }
         }
         Ok(read_total)
     }
@@ -95,6 +99,10 @@ impl FileContents for VecFileContents {
             let skip: u64 = write_total.try_into().map_err(|_| Error::Inval)?;
             let written = self.pwrite(iov, offset + skip)?;
             write_total = write_total.checked_add(written).expect("FileContents::pwritev must not be called when writes could total to more bytes than the return value can hold");
+            // Stop at a short write, so that no data is written past a gap.
+            if written < iov.len() {
+                break;
+            }
         }
         Ok(write_total)
     }
@@ -159,6 +167,16 @@ impl InMemoryFile {
         Self::new(Box::new(VecFileContents::new()))
     }
 
+    /// A file which can't be written to or resized through any of its handles.
+    pub fn read_only(contents: Box<dyn FileContents>) -> Self {
+        let file = Self::new(contents);
+        file.rights.set(HandleRights::new(
+            Rights::regular_file_base() & !Rights::mutating(),
+            Rights::regular_file_inheriting(),
+        ));
+        file
+    }
+
     pub fn new(contents: Box<dyn FileContents>) -> Self {
         let rights = HandleRights::new(
             Rights::regular_file_base(),
@@ -203,6 +221,14 @@ impl Handle for InMemoryFile {
     fn set_rights(&self, rights: HandleRights) {
         self.rights.set(rights)
     }
+    fn poll_readiness(&self, event: Eventtype) -> Option<Readiness> {
+        // In-memory files never block, just like regular files on the host.
+        let nbytes = match event {
+            Eventtype::FdRead => self.data.borrow().size().saturating_sub(self.cursor.get()),
+            _ => 0,
+        };
+        Some(Readiness::Ready { nbytes })
+    }
     // FdOps
     fn advise(&self, _advice: Advice, _offset: Filesize, _len: Filesize) -> Result<()> {
         // we'll just ignore advice for now, unless it's totally invalid
@@ -408,7 +434,8 @@ pub struct VirtualDir {
     // All copies of this `VirtualDir` must share `parent`, and changes in one copy's `parent`
     // must be reflected in all handles, so they share `Rc` of an underlying `parent`.
     parent: Rc<RefCell<Option<Box<dyn Handle>>>>,
-    entries: Rc<RefCell<HashMap<PathBuf, Box<dyn Handle>>>>,
+    // Entries are kept sorted by name, so that `readdir` lists them in a deterministic order.
+    entries: Rc<RefCell<BTreeMap<PathBuf, Box<dyn Handle>>>>,
 }
 
 impl VirtualDir {
@@ -419,7 +446,7 @@ impl VirtualDir {
             rights,
             writable,
             parent: Rc::new(RefCell::new(None)),
-            entries: Rc::new(RefCell::new(HashMap::new())),
+            entries: Rc::new(RefCell::new(BTreeMap::new())),
         }
     }
 
@@ -446,7 +473,40 @@ impl VirtualDir {
 
     #[allow(dead_code)]
     pub fn add_file<P: AsRef<Path>>(&mut self, content: Box<dyn FileContents>, path: P) {
-        let entry = Box::new(InMemoryFile::new(content));
+        self.add_entry(InMemoryFile::new(content), path);
+    }
+
+    /// Add a file which can't be written to or resized at `path`, relative to this directory.
+    ///
+    /// The directories leading up to the file must already exist, otherwise this returns `false`
+    /// and adds nothing.
+    pub fn add_read_only_file<P: AsRef<Path>>(
+        &self,
+        content: Box<dyn FileContents>,
+        path: P,
+    ) -> bool {
+        let mut components = path.as_ref().components();
+        match components.next() {
+            Some(Component::CurDir) => self.add_read_only_file(content, components.as_path()),
+            Some(Component::Normal(name)) if components.as_path() == Path::new("") => {
+                self.add_entry(InMemoryFile::read_only(content), name);
+                true
+            }
+            Some(Component::Normal(name)) => match self
+                .entries
+                .borrow()
+                .get(Path::new(name))
+                .and_then(|entry| entry.as_any().downcast_ref::<Self>())
+            {
+                Some(dir) => dir.add_read_only_file(content, components.as_path()),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn add_entry<P: AsRef<Path>>(&self, file: InMemoryFile, path: P) {
+        let entry = Box::new(file);
         entry.set_parent(Some(self.try_clone().expect("can clone self")));
         self.entries
             .borrow_mut()
@@ -488,6 +548,9 @@ impl Handle for VirtualDir {
     fn set_rights(&self, rights: HandleRights) {
         self.rights.set(rights)
     }
+    fn poll_readiness(&self, _event: Eventtype) -> Option<Readiness> {
+        Some(Readiness::Ready { nbytes: 0 })
+    }
     // FdOps
     fn filestat_get(&self) -> Result<Filestat> {
         let stat = Filestat {
@@ -508,7 +571,7 @@ impl Handle for VirtualDir {
     ) -> Result<Box<dyn Iterator<Item = Result<(Dirent, String)>>>> {
         struct VirtualDirIter {
             start: u32,
-            entries: Rc<RefCell<HashMap<PathBuf, Box<dyn Handle>>>>,
+            entries: Rc<RefCell<BTreeMap<PathBuf, Box<dyn Handle>>>>,
         }
         impl Iterator for VirtualDirIter {
             type Item = Result<(Dirent, String)>;
@@ -540,7 +603,7 @@ impl Handle for VirtualDir {
 
                 let entries = self.entries.borrow();
 
-                // Adjust `start` to be an appropriate number of HashMap entries.
+                // Adjust `start` to be an appropriate number of map entries.
                 let start = self.start - RESERVED_ENTRY_COUNT;
                 if start as usize >= entries.len() {
                     return None;
@@ -662,6 +725,23 @@ impl Handle for VirtualDir {
                     return Err(Error::Notdir);
                 }
 
+                if oflags.contains(&Oflags::TRUNC) {
+                    let file = e.get();
+                    if file.get_file_type() == Filetype::Directory {
+                        return Err(Error::Isdir);
+                    }
+                    // Files added with `add_read_only_file` can't be truncated either.
+                    if !self.writable
+                        || !file
+                            .get_rights()
+                            .base
+                            .contains(&Rights::FD_FILESTAT_SET_SIZE)
+                    {
+                        return Err(Error::Acces);
+                    }
+                    file.filestat_set_size(0)?;
+                }
+
                 e.get().try_clone().map_err(Into::into)
             }
             Entry::Vacant(v) => {
@@ -7,15 +7,18 @@
 //! Some convenience constructors are included for common backing types like `Vec<u8>` and `String`,
 //! but the virtual pipes can be instantiated with any `Read` or `Write` type.
 //!
-//! Note that `poll_oneoff` is not supported for these types, so they do not match the behavior of
-//! real pipes exactly.
+//! `ReadPipe` and `WritePipe` delegate to arbitrary readers and writers and so are always reported
+//! as ready by `poll_oneoff`. `BoundedPipe` has a fixed capacity, never blocks, and reports its
+//! readiness accurately, so it matches the behavior of a non-blocking Unix pipe more closely.
 use crate::handle::{
     Advice, Fdflags, Filesize, Filestat, Filetype, Handle, HandleRights, Oflags, Rights,
 };
+use crate::sched::{Eventtype, Readiness};
 use crate::{Error, Result};
 use std::any::Any;
+use std::collections::VecDeque;
 use std::io::{self, Read, Write};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// A virtual pipe read end.
 ///
@@ -127,6 +130,10 @@ impl<R: Read + Any> Handle for ReadPipe<R> {
         *self.rights.write().unwrap() = rights;
     }
 
+    fn poll_readiness(&self, _event: Eventtype) -> Option<Readiness> {
+        Some(Readiness::Ready { nbytes: 0 })
+    }
+
     fn advise(&self, _advice: Advice, _offset: Filesize, _len: Filesize) -> Result<()> {
         Err(Error::Spipe)
     }
@@ -306,6 +313,10 @@ impl<W: Write + Any> Handle for WritePipe<W> {
         *self.rights.write().unwrap() = rights;
     }
 
+    fn poll_readiness(&self, _event: Eventtype) -> Option<Readiness> {
+        Some(Readiness::Ready { nbytes: 0 })
+    }
+
     fn advise(&self, _advice: Advice, _offset: Filesize, _len: Filesize) -> Result<()> {
         Err(Error::Spipe)
     }
@@ -401,3 +412,255 @@ impl<W: Write + Any> Handle for WritePipe<W> {
         Err(Error::Notdir)
     }
 }
+
+/// A bounded, non-blocking virtual pipe.
+///
+/// All clones of a `BoundedPipe` share a single buffer, so one clone can be handed to a
+/// `WasiCtxBuilder` while the host keeps another to feed or drain the pipe:
+///
+/// ```
+/// # use wasi_common::WasiCtxBuilder;
+/// # use wasi_common::virtfs::pipe::BoundedPipe;
+/// let pipe = BoundedPipe::new(4096);
+/// let mut ctx = WasiCtxBuilder::new();
+/// ctx.stdout(pipe.clone());
+/// // ... run the guest, then collect its output with `pipe.drain()`.
+/// ```
+///
+/// Writes fail with `Errno::Again` once `capacity` bytes are buffered, and reads fail with
+/// `Errno::Again` while the pipe is empty. After `close` is called reads drain what's left and
+/// then report end of file, while writes fail with `Errno::Pipe`.
+#[derive(Debug)]
+pub struct BoundedPipe {
+    rights: RwLock<HandleRights>,
+    state: Arc<Mutex<PipeState>>,
+}
+
+#[derive(Debug)]
+struct PipeState {
+    buffer: VecDeque<u8>,
+    capacity: usize,
+    closed: bool,
+}
+
+impl Clone for BoundedPipe {
+    fn clone(&self) -> Self {
+        Self {
+            rights: RwLock::new(*self.rights.read().unwrap()),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl BoundedPipe {
+    /// Create a new empty pipe which buffers at most `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            rights: RwLock::new(HandleRights::from_base(
+                Rights::FD_DATASYNC
+                    | Rights::FD_FDSTAT_SET_FLAGS
+                    | Rights::FD_READ
+                    | Rights::FD_SYNC
+                    | Rights::FD_WRITE
+                    | Rights::FD_FILESTAT_GET
+                    | Rights::POLL_FD_READWRITE,
+            )),
+            state: Arc::new(Mutex::new(PipeState {
+                buffer: VecDeque::with_capacity(capacity),
+                capacity,
+                closed: false,
+            })),
+        }
+    }
+
+    /// The maximum number of bytes this pipe buffers.
+    pub fn capacity(&self) -> usize {
+        self.state.lock().unwrap().capacity
+    }
+
+    /// The number of bytes currently buffered.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().buffer.len()
+    }
+
+    /// Whether no bytes are currently buffered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append as much of `data` as fits to the pipe, returning the number of bytes appended.
+    pub fn fill(&self, data: &[u8]) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let n = data.len().min(state.capacity - state.buffer.len());
+        state.buffer.extend(&data[..n]);
+        n
+    }
+
+    /// Remove and return all buffered bytes.
+    pub fn drain(&self) -> Vec<u8> {
+        self.state.lock().unwrap().buffer.drain(..).collect()
+    }
+
+    /// Close the pipe for all of its clones.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+    }
+}
+
+impl Handle for BoundedPipe {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn try_clone(&self) -> io::Result<Box<dyn Handle>> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn get_file_type(&self) -> Filetype {
+        Filetype::Unknown
+    }
+
+    fn get_rights(&self) -> HandleRights {
+        *self.rights.read().unwrap()
+    }
+
+    fn set_rights(&self, rights: HandleRights) {
+        *self.rights.write().unwrap() = rights;
+    }
+
+    fn poll_readiness(&self, event: Eventtype) -> Option<Readiness> {
+        let state = self.state.lock().unwrap();
+        let available = match event {
+            Eventtype::FdRead => state.buffer.len(),
+            _ if state.closed => return Some(Readiness::Hangup),
+            _ => state.capacity - state.buffer.len(),
+        };
+        Some(if available > 0 {
+            Readiness::Ready {
+                nbytes: available as Filesize,
+            }
+        } else if state.closed {
+            Readiness::Hangup
+        } else {
+            Readiness::NotReady
+        })
+    }
+
+    fn advise(&self, _advice: Advice, _offset: Filesize, _len: Filesize) -> Result<()> {
+        Err(Error::Spipe)
+    }
+
+    fn allocate(&self, _offset: Filesize, _len: Filesize) -> Result<()> {
+        Err(Error::Spipe)
+    }
+
+    fn fdstat_set_flags(&self, _fdflags: Fdflags) -> Result<()> {
+        // do nothing for now
+        Ok(())
+    }
+
+    fn filestat_get(&self) -> Result<Filestat> {
+        let stat = Filestat {
+            dev: 0,
+            ino: 0,
+            nlink: 0,
+            size: 0,
+            atim: 0,
+            ctim: 0,
+            mtim: 0,
+            filetype: self.get_file_type(),
+        };
+        Ok(stat)
+    }
+
+    fn filestat_set_size(&self, _st_size: Filesize) -> Result<()> {
+        Err(Error::Spipe)
+    }
+
+    fn seek(&self, _offset: io::SeekFrom) -> Result<Filesize> {
+        Err(Error::Spipe)
+    }
+
+    fn read_vectored(&self, iovs: &mut [io::IoSliceMut]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.buffer.is_empty() && !state.closed {
+            return Err(Error::Again);
+        }
+        let mut nread = 0;
+        for iov in iovs {
+            let n = iov.len().min(state.buffer.len());
+            for (dst, src) in iov.iter_mut().zip(state.buffer.drain(..n)) {
+                *dst = src;
+            }
+            nread += n;
+        }
+        Ok(nread)
+    }
+
+    fn write_vectored(&self, iovs: &[io::IoSlice]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err(Error::Pipe);
+        }
+        let free = state.capacity - state.buffer.len();
+        if free == 0 && iovs.iter().any(|iov| !iov.is_empty()) {
+            return Err(Error::Again);
+        }
+        let mut nwritten = 0;
+        for iov in iovs {
+            let n = iov.len().min(free - nwritten);
+            state.buffer.extend(&iov[..n]);
+            nwritten += n;
+        }
+        Ok(nwritten)
+    }
+
+    fn create_directory(&self, _path: &str) -> Result<()> {
+        Err(Error::Notdir)
+    }
+
+    fn openat(
+        &self,
+        _path: &str,
+        _read: bool,
+        _write: bool,
+        _oflags: Oflags,
+        _fd_flags: Fdflags,
+    ) -> Result<Box<dyn Handle>> {
+        Err(Error::Notdir)
+    }
+
+    fn link(
+        &self,
+        _old_path: &str,
+        _new_handle: Box<dyn Handle>,
+        _new_path: &str,
+        _follow: bool,
+    ) -> Result<()> {
+        Err(Error::Notdir)
+    }
+
+    fn readlink(&self, _path: &str, _buf: &mut [u8]) -> Result<usize> {
+        Err(Error::Notdir)
+    }
+
+    fn readlinkat(&self, _path: &str) -> Result<String> {
+        Err(Error::Notdir)
+    }
+
+    fn rename(&self, _old_path: &str, _new_handle: Box<dyn Handle>, _new_path: &str) -> Result<()> {
+        Err(Error::Notdir)
+    }
+
+    fn remove_directory(&self, _path: &str) -> Result<()> {
+        Err(Error::Notdir)
+    }
+
+    fn symlink(&self, _old_path: &str, _new_path: &str) -> Result<()> {
+        Err(Error::Notdir)
+    }
+
+    fn unlink_file(&self, _path: &str) -> Result<()> {
+        Err(Error::Notdir)
+    }
+}
@@ -39,12 +39,14 @@ impl From<Error> for Errno {
             Error::GetRandom(_) => Errno::Io,
             Error::TooBig => Errno::TooBig,
             Error::Acces => Errno::Acces,
+            Error::Again => Errno::Again,
             Error::Badf => Errno::Badf,
             Error::Busy => Errno::Busy,
             Error::Exist => Errno::Exist,
             Error::Fault => Errno::Fault,
             Error::Fbig => Errno::Fbig,
             Error::Ilseq => Errno::Ilseq,
+            Error::Intr => Errno::Intr,
             Error::Inval => Errno::Inval,
             Error::Io => Errno::Io,
             Error::Isdir => Errno::Isdir,
@@ -1,3 +1,46 @@
+//! Integration of WASI with the `wasmtime` API.
+//!
+//! # Multiple tenants
+//!
+//! Any number of WASI programs, each with its own [`WasiCtx`] and so its own
+//! arguments, environment, preopened directories, stdio and file descriptor
+//! table, can run side by side, in one [`Store`](wasmtime::Store) or across
+//! several. WASI functions find the memory to operate on through the
+//! instance calling them rather than through anything stored ahead of time,
+//! so tenants never observe one another's memory or file descriptors, no
+//! matter how calls into them are interleaved.
+//!
+//! An [`Engine`](wasmtime::Engine) and the [`Module`](wasmtime::Module)s
+//! compiled with it are freely shared between tenants. What has to be
+//! created per tenant is its `WasiCtx` and the [`Wasi`] built from it, along
+//! with something to resolve the tenant's imports against it, typically a
+//! [`Linker`](wasmtime::Linker) of its own. A store can be shared by tenants,
+//! but note that instances are only freed along with their store, and that a
+//! store can only be used from one thread at a time.
+//!
+//! ```
+//! # use wasmtime::*;
+//! # use wasmtime_wasi::{Wasi, WasiCtxBuilder};
+//! # fn main() -> anyhow::Result<()> {
+//! let store = Store::default();
+//! let module = Module::new(store.engine(), r#"
+//!     (module
+//!         (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+//!         (memory (export "memory") 1)
+//!         (func (export "_start")))
+//! "#)?;
+//!
+//! let mut tenants = Vec::new();
+//! for name in &["a", "b"] {
+//!     let ctx = WasiCtxBuilder::new().arg(name).build()?;
+//!     let mut linker = Linker::new(&store);
+//!     Wasi::new(&store, ctx).add_to_linker(&mut linker)?;
+//!     tenants.push(linker.instantiate(&module)?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
 use wasmtime::Trap;
 
 pub mod old;
@@ -0,0 +1,35 @@
+use std::path::Path;
+use std::process::Command;
+use std::str;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Record the commit being built, if any, for `Engine::diagnostics`. It's
+    // only used for reporting, never to decide whether compiled artifacts are
+    // compatible.
+    let git = |args: &[&str]| -> Option<String> {
+        let output = Command::new("git").args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(str::from_utf8(&output.stdout).ok()?.trim().to_string())
+    };
+    if let Some(git_rev) = git(&["rev-parse", "HEAD"]) {
+        println!("cargo:rustc-env=GIT_REV={}", git_rev);
+    }
+
+    // Rebuild when the checked out commit changes: either `HEAD` is pointed
+    // elsewhere, or the branch it names moves. Files which don't exist are
+    // considered changed on every build, so only those which do are listed.
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        let mut files = vec![git_dir.join("HEAD"), git_dir.join("packed-refs")];
+        if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            files.push(git_dir.join(head_ref));
+        }
+        for file in files.iter().filter(|file| file.exists()) {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+use wasmtime_environ::entity::EntityRef;
+use wasmtime_environ::wasm::{FuncIndex, GlobalInit};
+
+/// Which functions of a [`Module`](crate::Module) may call which others, as
+/// decoded from their bodies.
+///
+/// Functions are identified by their index in the module's function index
+/// space, which includes imported functions. A function may call another if
+/// its body contains a `call`, `return_call` or `ref.func` of it. A body with
+/// a `call_indirect` or `return_call_indirect` is conservatively taken to call
+/// every function which may be placed in a table by the module itself: those
+/// in element segments and in `ref.func` initializers of globals. Functions
+/// which the host places in tables aren't accounted for.
+///
+/// Returned by [`Module::call_graph`](crate::Module::call_graph).
+#[derive(Clone, Debug)]
+pub struct CallGraph {
+    /// The functions each function refers to directly. Imported functions
+    /// have no body and so refer to nothing.
+    direct: Vec<Vec<u32>>,
+    /// Whether each function contains an indirect call.
+    indirect: Vec<bool>,
+    /// The functions an indirect call may reach.
+    table_funcs: Vec<u32>,
+}
+
+impl CallGraph {
+    /// Returns `None` if the functions `module`'s functions refer to weren't
+    /// recorded.
+    pub(crate) fn new(module: &wasmtime_environ::Module) -> Option<CallGraph> {
+        let num_funcs = module.functions.len();
+        if module.callees.len() != num_funcs - module.num_imported_funcs {
+            return None;
+        }
+        let mut direct = vec![Vec::new(); num_funcs];
+        let mut indirect = vec![false; num_funcs];
+        for (defined, callees) in module.callees.iter() {
+            let func = module.func_index(defined).index();
+            direct[func] = callees.direct.iter().map(|f| f.as_u32()).collect();
+            indirect[func] = callees.indirect;
+        }
+
+        let segments = module
+            .table_elements
+            .iter()
+            .map(|segment| &segment.elements)
+            .chain(module.passive_elements.values());
+        let globals = module.globals.values().filter_map(|g| match g.initializer {
+            GlobalInit::RefFunc(func) => Some(func),
+            _ => None,
+        });
+        let mut table_funcs = segments
+            .flat_map(|elements| elements.iter().copied())
+            .chain(globals)
+            // Null element segment entries are represented with the reserved
+            // index, which is out of bounds.
+            .filter(|func| func.index() < num_funcs)
+            .map(FuncIndex::as_u32)
+            .collect::<Vec<_>>();
+        table_funcs.sort_unstable();
+        table_funcs.dedup();
+
+        Some(CallGraph {
+            direct,
+            indirect,
+            table_funcs,
+        })
+    }
+
+    /// Returns the functions which may be executed as a result of calling
+    /// `func_index`: the function itself and everything it may transitively
+    /// call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `func_index` isn't the index of a function of the module.
+    pub fn reachable_from(&self, func_index: u32) -> HashSet<u32> {
+        assert!(
+            (func_index as usize) < self.direct.len(),
+            "function index {} is out of bounds",
+            func_index
+        );
+        let mut reachable = HashSet::new();
+        let mut worklist = vec![func_index];
+        reachable.insert(func_index);
+        while let Some(func) = worklist.pop() {
+            let func = func as usize;
+            let indirect: &[u32] = if self.indirect[func] {
+                &self.table_funcs[..]
+            } else {
+                &[]
+            };
+            for callee in self.direct[func].iter().chain(indirect) {
+                if reachable.insert(*callee) {
+                    worklist.push(*callee);
+                }
+            }
+        }
+        reachable
+    }
+}
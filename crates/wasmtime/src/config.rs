@@ -4,8 +4,10 @@ use anyhow::{bail, Result};
 use std::cmp;
 use std::convert::TryFrom;
 use std::fmt;
+use std::ops;
 #[cfg(feature = "cache")]
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use wasmparser::WasmFeatures;
 #[cfg(feature = "cache")]
@@ -13,7 +15,10 @@ use wasmtime_cache::CacheConfig;
 use wasmtime_environ::settings::{self, Configurable, SetError};
 use wasmtime_environ::{isa, isa::TargetIsa, Tunables};
 use wasmtime_jit::{native, CompilationStrategy, Compiler};
+
+pub use wasmtime_jit::CompilationAllocator;
 use wasmtime_profiling::{JitDumpAgent, NullProfilerAgent, ProfilingAgent, VTuneAgent};
+use wasmtime_runtime::{InstancePool, PoolingLimits};
 
 /// Global configuration options used to create an [`Engine`](crate::Engine)
 /// and customize its behavior.
@@ -30,8 +35,16 @@ pub struct Config {
     pub(crate) cache_config: CacheConfig,
     pub(crate) profiler: Arc<dyn ProfilingAgent>,
     pub(crate) memory_creator: Option<MemoryCreatorProxy>,
+    pub(crate) instance_pool: Option<InstancePool>,
+    pub(crate) secure_teardown: bool,
     pub(crate) max_wasm_stack: usize,
     pub(crate) features: WasmFeatures,
+    pub(crate) debug_ir_dump: Option<PathBuf>,
+    pub(crate) max_compiled_code_size: Option<usize>,
+    pub(crate) compilation_memory_limit: Option<usize>,
+    pub(crate) sampling_profiler: Option<u32>,
+    pub(crate) forbidden_features: FeatureMask,
+    pub(crate) parallel_compilation: bool,
 }
 
 impl Config {
@@ -40,12 +53,6 @@ impl Config {
     pub fn new() -> Config {
         let mut flags = settings::builder();
 
-        // There are two possible traps for division, and this way
-        // we get the proper one if code traps.
-        flags
-            .enable("avoid_div_traps")
-            .expect("should be valid flag");
-
         // Invert cranelift's default-on verification to instead default off.
         flags
             .set("enable_verifier", "false")
@@ -61,7 +68,7 @@ impl Config {
             .set("enable_probestack", "false")
             .expect("should be valid flag");
 
-        Config {
+        let mut config = Config {
             tunables: Tunables::default(),
             flags,
             isa_flags: native::builder(),
@@ -70,6 +77,8 @@ impl Config {
             cache_config: CacheConfig::new_cache_disabled(),
             profiler: Arc::new(NullProfilerAgent),
             memory_creator: None,
+            instance_pool: None,
+            secure_teardown: false,
             max_wasm_stack: 1 << 20,
             features: WasmFeatures {
                 reference_types: true,
@@ -77,7 +86,15 @@ impl Config {
                 multi_value: true,
                 ..WasmFeatures::default()
             },
-        }
+            debug_ir_dump: None,
+            max_compiled_code_size: None,
+            compilation_memory_limit: None,
+            sampling_profiler: None,
+            forbidden_features: FeatureMask::empty(),
+            parallel_compilation: true,
+        };
+        config.div_by_zero_behavior(DivBehavior::Trap);
+        config
     }
 
     /// Configures whether DWARF debug information will be emitted during
@@ -101,6 +118,35 @@ impl Config {
         self
     }
 
+    /// Configures whether [`Module::unused_functions`](crate::Module::unused_functions)
+    /// will be computed while compiling modules.
+    ///
+    /// When enabled each function body is additionally scanned for the
+    /// functions it refers to during compilation, to determine which defined
+    /// functions can never be reached from an export, the start function, or
+    /// an element segment. This is purely informational and doesn't change
+    /// what code is generated.
+    ///
+    /// By default this option is `false`.
+    pub fn report_unused_functions(&mut self, enable: bool) -> &mut Self {
+        self.tunables.report_unused_functions = enable;
+        self
+    }
+
+    /// Configures whether [`Module::call_graph`](crate::Module::call_graph)
+    /// will be available for modules compiled with this configuration.
+    ///
+    /// When enabled each function body is additionally scanned for the
+    /// functions it may call during compilation, and the result is kept with
+    /// the module, including when it's serialized. This is purely
+    /// informational and doesn't change what code is generated.
+    ///
+    /// By default this option is `false`.
+    pub fn record_call_graph(&mut self, enable: bool) -> &mut Self {
+        self.tunables.record_call_graph = enable;
+        self
+    }
+
     /// Configures the maximum amount of native stack space available to
     /// executing WebAssembly code.
     ///
@@ -132,6 +178,11 @@ impl Config {
     /// instructions. Note that enabling the threads feature will
     /// also enable the bulk memory feature.
     ///
+    /// Atomic accesses always check that their effective address is
+    /// naturally aligned, as the proposal requires, and trap with
+    /// [`TrapCode::HeapMisaligned`] if it isn't. There's no option to turn
+    /// the check off, nor a need to turn it on.
+    ///
     /// This is `false` by default.
     ///
     /// > **Note**: Wasmtime does not implement everything for the wasm threads
@@ -140,6 +191,7 @@ impl Config {
     /// > now.
     ///
     /// [threads]: https://github.com/webassembly/threads
+    /// [`TrapCode::HeapMisaligned`]: crate::TrapCode::HeapMisaligned
     pub fn wasm_threads(&mut self, enable: bool) -> &mut Self {
         self.features.threads = enable;
         // The threads proposal depends on the bulk memory proposal
@@ -212,11 +264,20 @@ impl Config {
     /// This feature gates items such as the `memory.copy` instruction, passive
     /// data/table segments, etc, being in a module.
     ///
+    /// Note that disabling the bulk memory feature will also disable the
+    /// reference types and threads features, which depend on it.
+    ///
     /// This is `true` by default.
     ///
     /// [proposal]: https://github.com/webassembly/bulk-memory-operations
     pub fn wasm_bulk_memory(&mut self, enable: bool) -> &mut Self {
         self.features.bulk_memory = enable;
+        // The reference types and threads proposals depend on the bulk memory
+        // proposal.
+        if !enable {
+            self.wasm_reference_types(false);
+            self.wasm_threads(false);
+        }
         self
     }
 
@@ -262,6 +323,86 @@ impl Config {
         self
     }
 
+    /// Configures whether the WebAssembly memory64 [proposal] will be enabled
+    /// for compilation.
+    ///
+    /// This feature gates modules declaring 64-bit linear memories, such as
+    /// `(memory i64 1)`, which are indexed with `i64` addresses. Loads and
+    /// stores on them are always bounds-checked explicitly, so they're
+    /// somewhat slower than on 32-bit memories. Bulk memory operations on
+    /// 64-bit memories aren't supported yet and fail compilation.
+    ///
+    /// 64-bit memories are only supported on 64-bit hosts; on 32-bit hosts
+    /// modules using them fail to compile. Sizes are still counted in 32-bit
+    /// numbers of pages, which allows up to 256 TiB. 64-bit memories without
+    /// a maximum, or with one beyond [`Config::static_memory_maximum_size`],
+    /// are allocated dynamically, only reserving address space for their
+    /// current size, and instantiation fails if the host can't reserve a
+    /// memory's minimum size. On the host side, the offsets taken by
+    /// [`Memory`](crate::Memory)'s accessors are `usize`, which covers the
+    /// whole of any memory that could be allocated.
+    ///
+    /// This is `false` by default.
+    ///
+    /// [proposal]: https://github.com/webassembly/memory64
+    pub fn wasm_memory64(&mut self, enable: bool) -> &mut Self {
+        self.features.memory64 = enable;
+        self
+    }
+
+    /// Resets this configuration to strictly follow the WebAssembly core
+    /// specification, disabling everything that isn't required by it.
+    ///
+    /// This is a preset intended for conformance testing and for reproducing
+    /// bugs against a canonical configuration. It sets the following
+    /// individual knobs:
+    ///
+    /// * All WebAssembly proposals are disabled: threads, reference types,
+    ///   SIMD, bulk memory, multi-value, multi-memory, module linking, and
+    ///   memory64.
+    /// * NaN canonicalization is enabled, see
+    ///   [`Config::cranelift_nan_canonicalization`], so floating point results
+    ///   are deterministic.
+    /// * Integer division traps with precise trap codes, see
+    ///   [`Config::div_by_zero_behavior`].
+    /// * The compilation cache is disabled, so every module is freshly
+    ///   compiled.
+    /// * Functions are compiled one at a time on the calling thread, see
+    ///   [`Config::parallel_compilation`], so that compiling a module always
+    ///   goes the same way, for instance when stepping through it.
+    /// * Every module the specification considers valid is accepted: no
+    ///   instructions are forbidden, see [`Config::forbid_features`], and
+    ///   there's no limit on the size of the compiled code or the memory
+    ///   compiling it takes, see [`Config::max_compiled_code_size`] and
+    ///   [`Config::compilation_memory_limit`].
+    ///
+    /// Options which don't affect the semantics of executing wasm, such as
+    /// optimization levels, memory reservations, or profiling, are left as-is.
+    ///
+    /// Individual knobs may still be changed after calling this method, for
+    /// example to enable just the one proposal a test needs.
+    pub fn strict_spec_mode(&mut self) -> &mut Self {
+        self.wasm_threads(false)
+            .wasm_reference_types(false)
+            .wasm_simd(false)
+            .wasm_bulk_memory(false)
+            .wasm_multi_value(false)
+            .wasm_multi_memory(false)
+            .wasm_module_linking(false)
+            .wasm_memory64(false)
+            .cranelift_nan_canonicalization(true)
+            .div_by_zero_behavior(DivBehavior::Trap)
+            .parallel_compilation(false);
+        self.forbidden_features = FeatureMask::empty();
+        self.max_compiled_code_size = None;
+        self.compilation_memory_limit = None;
+        #[cfg(feature = "cache")]
+        {
+            self.cache_config = CacheConfig::new_cache_disabled();
+        }
+        self
+    }
+
     /// Configures which compilation strategy will be used for wasm modules.
     ///
     /// This method can be used to configure which compiler is used for wasm
@@ -302,6 +443,55 @@ impl Config {
         Ok(self)
     }
 
+    /// Enables a sampling profiler which records where wasm code spends its
+    /// time, taking `frequency_hz` samples a second.
+    ///
+    /// While a [`Store`](crate::Store) created with this configuration is
+    /// executing wasm, a background thread belonging to the store
+    /// periodically interrupts it with `SIGPROF` and records the wasm call
+    /// stack at that moment. The samples are collected into a
+    /// [`SamplingProfile`](crate::SamplingProfile), returned by
+    /// [`Store::profile`](crate::Store::profile), which can be rendered as
+    /// folded stacks for a flamegraph.
+    ///
+    /// The signal handler records the interrupted program counter and walks
+    /// the frame pointers of the wasm frames below it, up to 32 of them and
+    /// stopping at the first frame of host code. So when wasm calls into the
+    /// host which calls back into wasm, only the innermost wasm frames are
+    /// recorded. Once the call into wasm returns, the frames are mapped back
+    /// to wasm functions through the frame information of the store's
+    /// modules. Function names come from the `name` section, so modules
+    /// compiled without one show up with function indices instead. Samples
+    /// which land in host code aren't recorded, so time spent in host
+    /// functions isn't accounted for.
+    ///
+    /// Samples are kept in a buffer of 4096 samples allocated with the store
+    /// until the call into wasm they were taken in returns, and samples taken
+    /// while it's full are dropped, so at 100 Hz a single call into wasm
+    /// longer than about 40 seconds is only partially profiled.
+    ///
+    /// Taking a sample doesn't change the state of the interrupted code,
+    /// which resumes as if it hadn't been interrupted. The overhead is that of
+    /// walking the wasm stack per sample plus resolving the samples to
+    /// functions once each call into wasm returns, so lower frequencies, like
+    /// the default of tools such as `perf` of around 100 Hz, are cheaper.
+    ///
+    /// This is only supported on Unix platforms, and returns an error
+    /// elsewhere or if `frequency_hz` is zero. A process which handles
+    /// `SIGPROF` itself will also receive the profiler's signals.
+    ///
+    /// By default the sampling profiler is disabled.
+    pub fn sampling_profiler(&mut self, frequency_hz: u32) -> Result<&mut Self> {
+        if !cfg!(unix) {
+            bail!("the sampling profiler is only supported on Unix platforms");
+        }
+        if frequency_hz == 0 {
+            bail!("the sampling profiler's frequency must be nonzero");
+        }
+        self.sampling_profiler = Some(frequency_hz);
+        Ok(self)
+    }
+
     /// Configures whether the debug verifier of Cranelift is enabled or not.
     ///
     /// When Cranelift is used as a code generation backend this will configure
@@ -318,13 +508,132 @@ impl Config {
         self
     }
 
+    /// Configures a directory into which functions that the code generator
+    /// fails to compile are dumped, for inclusion in bug reports.
+    ///
+    /// Compiling a valid module can still fail, or the code generator can
+    /// panic, on pathological input. Either way module creation fails with an
+    /// error naming the function, and when this is set the function's body,
+    /// exactly as it appears in the code section, is written to
+    /// `wasm-function-<index>.bin` in `dir`, along with its Cranelift IR in
+    /// `wasm-function-<index>.clif` if that's available. The directory is
+    /// created if it doesn't exist.
+    ///
+    /// By default nothing is dumped.
+    pub fn debug_ir_dump(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.debug_ir_dump = Some(dir.into());
+        self
+    }
+
+    /// Configures the maximum number of bytes of machine code which may be
+    /// generated for a single module.
+    ///
+    /// Small wasm modules can compile to very large amounts of machine code,
+    /// so services compiling untrusted modules may want to cap it. The size
+    /// is tallied as each function is compiled, and [`Module::new`] fails as
+    /// soon as the total exceeds `size`, without compiling the rest of the
+    /// module.
+    ///
+    /// Modules which are loaded from the cache or with
+    /// [`Module::deserialize`] aren't compiled and so aren't checked.
+    ///
+    /// By default there's no limit.
+    ///
+    /// [`Module::new`]: crate::Module::new
+    /// [`Module::deserialize`]: crate::Module::deserialize
+    pub fn max_compiled_code_size(&mut self, size: usize) -> &mut Self {
+        self.max_compiled_code_size = Some(size);
+        self
+    }
+
+    /// Configures the maximum number of bytes of memory which compiling a
+    /// single module may take.
+    ///
+    /// [`Config::max_compiled_code_size`] only bounds the output of the code
+    /// generator, but a small module can make it take far more memory than
+    /// that while it works, for instance with a function with thousands of
+    /// locals and thousands of branches. With this set, [`Module::new`] fails
+    /// with an error instead of compiling such modules.
+    ///
+    /// Memory is measured by [`CompilationAllocator`], which must be the
+    /// program's global allocator, otherwise compiling any module fails:
+    ///
+    /// ```
+    /// use std::alloc::System;
+    /// use wasmtime::CompilationAllocator;
+    ///
+    /// #[global_allocator]
+    /// static ALLOCATOR: CompilationAllocator = CompilationAllocator::new(System);
+    /// # fn main() {}
+    /// ```
+    ///
+    /// What's counted is the bytes allocated, less those freed, by each
+    /// thread while it compiles one of the module's functions. The limit is
+    /// checked before and after each function, since an allocation can't
+    /// fail gracefully, so compilation stops at the end of the first function
+    /// during which the total went over `bytes`. The peak memory use can
+    /// therefore exceed the limit by up to what the functions being compiled
+    /// at that moment take.
+    ///
+    /// Modules which are loaded from the cache or with
+    /// [`Module::deserialize`] aren't compiled and so aren't checked.
+    ///
+    /// By default there's no limit.
+    ///
+    /// [`Module::new`]: crate::Module::new
+    /// [`Module::deserialize`]: crate::Module::deserialize
+    pub fn compilation_memory_limit(&mut self, bytes: usize) -> &mut Self {
+        self.compilation_memory_limit = Some(bytes);
+        self
+    }
+
+    /// Configures whether the functions of a module are compiled in parallel,
+    /// on rayon's global thread pool.
+    ///
+    /// Each function is compiled independently, and the results are put
+    /// together in the same order either way, so this makes no difference to
+    /// the generated code, only to how long compiling a module with many
+    /// functions takes. Disabling it can help when debugging the compiler or
+    /// on a machine with a single core.
+    ///
+    /// This is `true` by default, but has no effect unless the
+    /// `parallel-compilation` feature of this crate is enabled, which it is
+    /// by default.
+    pub fn parallel_compilation(&mut self, enable: bool) -> &mut Self {
+        self.parallel_compilation = enable;
+        self
+    }
+
+    /// Configures classes of instructions which modules may not use, for
+    /// instance to keep floating point out of modules whose results must be
+    /// reproducible bit for bit.
+    ///
+    /// Unlike the `wasm_*` methods, which turn whole proposals on and off,
+    /// this restricts instructions which are part of the MVP. The function
+    /// bodies of a module are scanned by [`Module::validate`] and before
+    /// compilation by [`Module::new`], which fail with an error naming the
+    /// first forbidden instruction found, the function it's in and its offset
+    /// in the binary. Modules loaded with [`Module::deserialize`] aren't
+    /// scanned.
+    ///
+    /// Each call replaces the classes forbidden by the previous one. By
+    /// default nothing is forbidden.
+    ///
+    /// [`Module::validate`]: crate::Module::validate
+    /// [`Module::new`]: crate::Module::new
+    /// [`Module::deserialize`]: crate::Module::deserialize
+    pub fn forbid_features(&mut self, mask: FeatureMask) -> &mut Self {
+        self.forbidden_features = mask;
+        self
+    }
+
     /// Configures the Cranelift code generator optimization level.
     ///
     /// When the Cranelift code generator is used you can configure the
     /// optimization level used for generated code in a few various ways. For
     /// more information see the documentation of [`OptLevel`].
     ///
-    /// The default value for this is `OptLevel::None`.
+    /// The default value for this is `OptLevel::Speed`.
     pub fn cranelift_opt_level(&mut self, level: OptLevel) -> &mut Self {
         let val = match level {
             OptLevel::None => "none",
@@ -337,6 +646,26 @@ impl Config {
         self
     }
 
+    /// Configures how integer division and remainder instructions detect the
+    /// cases in which they trap.
+    ///
+    /// Either way, dividing by zero and `i32.div_s` or `i64.div_s` of the
+    /// minimum value by -1 trap, as the WebAssembly specification requires.
+    /// This only decides whether the trap codes tell the two apart. For more
+    /// information see the documentation of [`DivBehavior`].
+    ///
+    /// The default value for this is `DivBehavior::Trap`.
+    pub fn div_by_zero_behavior(&mut self, behavior: DivBehavior) -> &mut Self {
+        let val = match behavior {
+            DivBehavior::Trap => "true",
+            DivBehavior::Native => "false",
+        };
+        self.flags
+            .set("avoid_div_traps", val)
+            .expect("should be valid flag");
+        self
+    }
+
     /// Configures whether Cranelift should perform a NaN-canonicalization pass.
     ///
     /// When Cranelift is used as a code generation backend this will configure
@@ -436,6 +765,78 @@ impl Config {
         self
     }
 
+    /// Configures instances of modules to be allocated from a pool of
+    /// preallocated slots.
+    ///
+    /// The pool reserves address space for the linear memory, and allocates
+    /// the storage for the table, of [`PoolingConfig::new`]'s
+    /// `instance_count` instances up front, and every
+    /// [`Instance`](crate::Instance) created with an [`Engine`](crate::Engine)
+    /// using this configuration occupies one slot. A slot, and with it the
+    /// instance's memory and table, is recycled once the [`Store`](crate::Store) owning
+    /// the instance has been dropped. Memories are zeroed before reuse. This is
+    /// intended for embeddings which create and tear down many short-lived
+    /// instances, where mapping and unmapping memory for each one is costly.
+    ///
+    /// Modules instantiated from the pool may define at most one memory and
+    /// one table, and their memories and tables must fit within the
+    /// configured limits or instantiation fails. Host memories and tables
+    /// created with [`Memory::new`](crate::Memory::new) and
+    /// [`Table::new`](crate::Table::new) aren't drawn from the pool. Module
+    /// memories are, regardless of [`Config::with_host_memory`].
+    ///
+    /// The size of each memory reservation is derived from the current memory
+    /// tunables, so this should be called after methods such as
+    /// [`Config::static_memory_maximum_size`] and
+    /// [`Config::static_memory_guard_size`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool's address space can't be reserved or if
+    /// `memory_pages` exceeds the maximum size of a wasm memory.
+    pub fn pooling_allocation(&mut self, config: PoolingConfig) -> Result<&mut Self> {
+        let limits = PoolingLimits {
+            instance_count: config.instance_count,
+            memory_pages: config.memory_pages,
+            table_elements: config.table_elements,
+            block_when_exhausted: match config.on_exhausted {
+                PoolExhaustion::Error => false,
+                PoolExhaustion::Block => true,
+            },
+        };
+        self.instance_pool =
+            Some(InstancePool::new(limits, &self.tunables).map_err(anyhow::Error::msg)?);
+        Ok(self)
+    }
+
+    /// Configures whether state left behind by torn down instances and modules
+    /// is scrubbed before the memory holding it is released.
+    ///
+    /// Regardless of this setting, linear memories allocated by Wasmtime are
+    /// unmapped when they're deallocated, so the OS hands out zeroed pages
+    /// for any later mapping, and slots of the
+    /// [pooling allocator](Config::pooling_allocation) are decommitted before
+    /// they're reused. Memories from a custom [`MemoryCreator`] are the
+    /// embedder's responsibility. When this option is enabled additionally:
+    ///
+    /// * The `VMContext` of each instance, which holds the values of its
+    ///   globals among other things, is zeroed before it's returned to the
+    ///   process's allocator when the [`Store`](crate::Store) is dropped.
+    /// * JIT code of a [`Module`](crate::Module), and of host functions
+    ///   created with [`Func::new`](crate::Func::new), is made non-executable
+    ///   and zeroed before it's unmapped.
+    ///
+    /// The cost is a write over each instance's `VMContext` when its store is
+    /// dropped, which is small, plus a write over all of a module's code when
+    /// the last reference to the module goes away, which is proportional to
+    /// the size of the compiled code. Instantiation itself isn't affected.
+    ///
+    /// By default this option is `false`.
+    pub fn secure_teardown(&mut self, enable: bool) -> &mut Self {
+        self.secure_teardown = enable;
+        self
+    }
+
     /// Configures the maximum size, in bytes, where a linear memory is
     /// considered static, above which it'll be considered dynamic.
     ///
@@ -619,7 +1020,12 @@ impl Config {
 
     pub(crate) fn build_compiler(&self) -> Compiler {
         let isa = self.target_isa();
-        Compiler::new(isa, self.strategy, self.tunables.clone(), self.features)
+        let mut compiler = Compiler::new(isa, self.strategy, self.tunables.clone(), self.features);
+        compiler.set_ir_dump_dir(self.debug_ir_dump.clone());
+        compiler.set_max_code_size(self.max_compiled_code_size);
+        compiler.set_compilation_memory_limit(self.compilation_memory_limit);
+        compiler.set_parallel_compilation(self.parallel_compilation);
+        compiler
     }
 }
 
@@ -648,6 +1054,8 @@ impl fmt::Debug for Config {
             .field("wasm_simd", &self.features.simd)
             .field("wasm_multi_value", &self.features.multi_value)
             .field("wasm_module_linking", &self.features.module_linking)
+            .field("wasm_memory64", &self.features.memory64)
+            .field("parallel_compilation", &self.parallel_compilation)
             .field(
                 "flags",
                 &settings::Flags::new(self.flags.clone()).to_string(),
@@ -699,6 +1107,78 @@ pub enum OptLevel {
     SpeedAndSize,
 }
 
+/// How integer division detects the cases in which it traps, as configured
+/// with [`Config::div_by_zero_behavior`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DivBehavior {
+    /// Division is preceded by explicit checks of its operands, so dividing by
+    /// zero traps with [`TrapCode::IntegerDivisionByZero`] and signed division
+    /// of the minimum value by -1 with [`TrapCode::IntegerOverflow`].
+    ///
+    /// [`TrapCode::IntegerDivisionByZero`]: crate::TrapCode::IntegerDivisionByZero
+    /// [`TrapCode::IntegerOverflow`]: crate::TrapCode::IntegerOverflow
+    Trap,
+    /// Division relies on the checks of the native division instruction where
+    /// there are any, such as the faults raised by x86's. This saves the
+    /// explicit checks, but as the hardware raises the same fault in both
+    /// cases, overflow may be reported as division by zero.
+    Native,
+}
+
+/// A set of classes of instructions, which can be forbidden with
+/// [`Config::forbid_features`].
+///
+/// Sets are combined with `|`:
+///
+/// ```
+/// # use wasmtime::*;
+/// let mut config = Config::new();
+/// config.forbid_features(FeatureMask::FLOAT | FeatureMask::INDIRECT_CALLS);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FeatureMask(u32);
+
+impl FeatureMask {
+    /// Instructions which operate on `f32` or `f64` values, including their
+    /// loads, stores and constants, conversions to and from integers, and
+    /// the floating point lanes of SIMD.
+    pub const FLOAT: FeatureMask = FeatureMask(1 << 0);
+    /// `call_indirect` and `return_call_indirect`.
+    pub const INDIRECT_CALLS: FeatureMask = FeatureMask(1 << 1);
+    /// `memory.grow`.
+    pub const MEMORY_GROW: FeatureMask = FeatureMask(1 << 2);
+
+    /// Returns the empty set.
+    pub const fn empty() -> FeatureMask {
+        FeatureMask(0)
+    }
+
+    /// Returns whether this set is empty.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns whether every class in `other` is also in this set.
+    pub fn contains(self, other: FeatureMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for FeatureMask {
+    type Output = FeatureMask;
+
+    fn bitor(self, other: FeatureMask) -> FeatureMask {
+        FeatureMask(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for FeatureMask {
+    fn bitor_assign(&mut self, other: FeatureMask) {
+        self.0 |= other.0;
+    }
+}
+
 /// Select which profiling technique to support.
 #[derive(Debug, Clone, Copy)]
 pub enum ProfilingStrategy {
@@ -707,8 +1187,199 @@ pub enum ProfilingStrategy {
 
     /// Collect profiling info for "jitdump" file format, used with `perf` on
     /// Linux.
+    ///
+    /// A code load record is written to `jit-<pid>.dump`, in the current
+    /// directory, for each function and trampoline as its module is compiled.
+    /// Functions are named from the module's name section, demangled, or
+    /// otherwise `wasm[<module>]::function[<index>]`. Record them with
+    /// `perf record -k mono` and then run `perf inject --jit` on the result
+    /// to have samples in wasm code attributed to those functions.
     JitDump,
 
     /// Collect profiling info using the "ittapi", used with `VTune` on Linux.
     VTune,
 }
+
+/// Limits of the instance pool configured with [`Config::pooling_allocation`].
+#[derive(Debug, Clone)]
+pub struct PoolingConfig {
+    instance_count: u32,
+    memory_pages: u32,
+    table_elements: u32,
+    on_exhausted: PoolExhaustion,
+}
+
+impl PoolingConfig {
+    /// Creates a pool configuration allowing `instance_count` live instances.
+    ///
+    /// By default each memory may grow to 160 wasm pages (10 MiB), each table
+    /// may grow to 10,000 elements and instantiating with an exhausted pool
+    /// returns an error.
+    pub fn new(instance_count: u32) -> PoolingConfig {
+        PoolingConfig {
+            instance_count,
+            memory_pages: 160,
+            table_elements: 10_000,
+            on_exhausted: PoolExhaustion::Error,
+        }
+    }
+
+    /// Configures the maximum number of wasm pages a pooled memory may grow
+    /// to. Modules whose memories require more than this initially fail to
+    /// instantiate, and growth beyond it fails as if the memory's declared
+    /// maximum were reached.
+    pub fn memory_pages(&mut self, pages: u32) -> &mut Self {
+        self.memory_pages = pages;
+        self
+    }
+
+    /// Configures the maximum number of elements tables defined by pooled
+    /// instances may grow to. Modules whose tables require more than this
+    /// initially fail to instantiate, and growth beyond it fails as if the
+    /// table's declared maximum were reached.
+    pub fn table_elements(&mut self, elements: u32) -> &mut Self {
+        self.table_elements = elements;
+        self
+    }
+
+    /// Configures what instantiation does when every slot of the pool is in
+    /// use.
+    pub fn on_exhausted(&mut self, behavior: PoolExhaustion) -> &mut Self {
+        self.on_exhausted = behavior;
+        self
+    }
+}
+
+/// What to do when instantiating with an exhausted instance pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolExhaustion {
+    /// Instantiation fails with a "pool exhausted" error.
+    Error,
+
+    /// Instantiation blocks the current thread until another thread drops a
+    /// [`Store`](crate::Store) holding pooled instances.
+    ///
+    /// Note that blocking can never complete if all the slots are held by
+    /// stores on the current thread.
+    Block,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_flags_compose_with_raw_flags() -> Result<()> {
+        let flags = |config: &Config| settings::Flags::new(config.flags.clone());
+        let mut config = Config::new();
+        assert!(flags(&config).avoid_div_traps());
+
+        // Whichever of a typed setter and a raw flag comes last wins.
+        unsafe {
+            config.cranelift_other_flag("opt_level", "none")?;
+        }
+        assert_eq!(flags(&config).opt_level(), settings::OptLevel::None);
+        config.cranelift_opt_level(OptLevel::SpeedAndSize);
+        assert_eq!(flags(&config).opt_level(), settings::OptLevel::SpeedAndSize);
+
+        // Bad raw flags are rejected when they're set, rather than when compiling.
+        assert!(unsafe { config.cranelift_other_flag("opt_level", "fastest") }.is_err());
+        assert!(unsafe { config.cranelift_other_flag("no_such_flag", "true") }.is_err());
+        assert_eq!(flags(&config).opt_level(), settings::OptLevel::SpeedAndSize);
+        Ok(())
+    }
+
+    #[test]
+    fn proposal_dependencies() {
+        let mut config = Config::new();
+        config.wasm_reference_types(true).wasm_threads(true);
+        assert!(config.features.bulk_memory);
+
+        // Turning off a proposal turns off the ones which depend on it too,
+        // so that no combination of setters leaves them inconsistent.
+        config.wasm_bulk_memory(false);
+        assert!(!config.features.reference_types);
+        assert!(!config.features.threads);
+        assert!(!settings::Flags::new(config.flags.clone()).enable_safepoints());
+    }
+
+    // This test destructures `Config` exhaustively so that adding a new field
+    // fails to compile here until its setting in spec mode has been decided.
+    #[test]
+    fn strict_spec_mode_knobs() -> Result<()> {
+        let mut config = Config::new();
+        // Set directly, since the setter fails on platforms without support.
+        config.sampling_profiler = Some(100);
+        config
+            .wasm_threads(true)
+            .wasm_reference_types(true)
+            .wasm_simd(true)
+            .wasm_bulk_memory(true)
+            .wasm_multi_value(true)
+            .wasm_multi_memory(true)
+            .wasm_module_linking(true)
+            .wasm_memory64(true)
+            .cranelift_nan_canonicalization(false)
+            .div_by_zero_behavior(DivBehavior::Native)
+            .debug_info(true)
+            .max_wasm_stack(1 << 16)
+            .max_compiled_code_size(1 << 20)
+            .compilation_memory_limit(1 << 30)
+            .forbid_features(FeatureMask::FLOAT)
+            .parallel_compilation(true)
+            .strict_spec_mode();
+
+        let Config {
+            flags,
+            isa_flags: _,
+            tunables,
+            strategy: _,
+            #[cfg(feature = "cache")]
+            cache_config,
+            profiler: _,
+            memory_creator,
+            instance_pool,
+            secure_teardown,
+            max_wasm_stack,
+            features,
+            debug_ir_dump,
+            max_compiled_code_size,
+            compilation_memory_limit,
+            sampling_profiler,
+            forbidden_features,
+            parallel_compilation,
+        } = &config;
+
+        assert!(!features.threads);
+        assert!(!features.reference_types);
+        assert!(!features.simd);
+        assert!(!features.bulk_memory);
+        assert!(!features.multi_value);
+        assert!(!features.multi_memory);
+        assert!(!features.module_linking);
+        assert!(!features.memory64);
+
+        let flags = settings::Flags::new(flags.clone());
+        assert!(flags.enable_nan_canonicalization());
+        assert!(!flags.enable_simd());
+        assert!(!flags.enable_safepoints());
+        assert!(flags.avoid_div_traps());
+
+        #[cfg(feature = "cache")]
+        assert!(!cache_config.enabled());
+        assert!(!parallel_compilation);
+        assert!(forbidden_features.is_empty());
+        assert!(max_compiled_code_size.is_none());
+        assert!(compilation_memory_limit.is_none());
+
+        // Settings which don't affect semantics are left alone.
+        assert!(tunables.debug_info);
+        assert_eq!(*max_wasm_stack, 1 << 16);
+        assert!(memory_creator.is_none());
+        assert!(instance_pool.is_none());
+        assert!(!secure_teardown);
+        assert!(debug_ir_dump.is_none());
+        assert_eq!(*sampling_profiler, Some(100));
+        Ok(())
+    }
+}
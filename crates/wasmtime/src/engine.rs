@@ -1,7 +1,12 @@
+use crate::watchdog::Watchdog;
 use crate::Config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::sync::Arc;
 #[cfg(feature = "cache")]
 use wasmtime_cache::CacheConfig;
+use wasmtime_environ::WASM_PAGE_SIZE;
 use wasmtime_jit::Compiler;
 use wasmtime_runtime::debug_builtins;
 
@@ -35,6 +40,7 @@ pub struct Engine {
 struct EngineInner {
     config: Config,
     compiler: Compiler,
+    watchdog: Arc<Watchdog>,
 }
 
 impl Engine {
@@ -46,6 +52,7 @@ impl Engine {
             inner: Arc::new(EngineInner {
                 config: config.clone(),
                 compiler: config.build_compiler(),
+                watchdog: Default::default(),
             }),
         }
     }
@@ -59,6 +66,10 @@ impl Engine {
         &self.inner.compiler
     }
 
+    pub(crate) fn watchdog(&self) -> &Arc<Watchdog> {
+        &self.inner.watchdog
+    }
+
     #[cfg(feature = "cache")]
     pub(crate) fn cache_config(&self) -> &CacheConfig {
         &self.config().cache_config
@@ -68,6 +79,249 @@ impl Engine {
     pub fn same(a: &Engine, b: &Engine) -> bool {
         Arc::ptr_eq(&a.inner, &b.inner)
     }
+
+    /// Returns a report of what this engine compiles code for and how, which
+    /// is useful to include in bug reports.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// let engine = Engine::default();
+    /// let diagnostics = engine.diagnostics();
+    /// assert_eq!(diagnostics.version, env!("CARGO_PKG_VERSION"));
+    /// println!("{}", diagnostics);
+    /// ```
+    pub fn diagnostics(&self) -> EngineDiagnostics {
+        let config = self.config();
+        let isa = self.compiler().isa();
+        let tunables = &config.tunables;
+        let features = &config.features;
+
+        let mut cranelift_flags = BTreeMap::new();
+        parse_flags(&isa.flags().to_string(), &mut cranelift_flags);
+        parse_flags(&isa.to_string(), &mut cranelift_flags);
+
+        let wasm_features = [
+            ("threads", features.threads),
+            ("reference_types", features.reference_types),
+            ("simd", features.simd),
+            ("bulk_memory", features.bulk_memory),
+            ("multi_value", features.multi_value),
+            ("multi_memory", features.multi_memory),
+            ("module_linking", features.module_linking),
+            ("memory64", features.memory64),
+        ]
+        .iter()
+        .map(|(name, enabled)| (name.to_string(), *enabled))
+        .collect();
+
+        let mut summary = BTreeMap::new();
+        let mut add = |name: &str, value: String| summary.insert(name.to_string(), value);
+        add("strategy", format!("{:?}", config.strategy));
+        add(
+            "static_memory_maximum_size",
+            (u64::from(tunables.static_memory_bound) * u64::from(WASM_PAGE_SIZE)).to_string(),
+        );
+        add(
+            "static_memory_guard_size",
+            tunables.static_memory_offset_guard_size.to_string(),
+        );
+        add(
+            "dynamic_memory_guard_size",
+            tunables.dynamic_memory_offset_guard_size.to_string(),
+        );
+        add("debug_info", tunables.debug_info.to_string());
+        add("interruptable", tunables.interruptable.to_string());
+        add(
+            "report_unused_functions",
+            tunables.report_unused_functions.to_string(),
+        );
+        add("record_call_graph", tunables.record_call_graph.to_string());
+        add("max_wasm_stack", config.max_wasm_stack.to_string());
+        add("secure_teardown", config.secure_teardown.to_string());
+        add(
+            "pooling_allocation",
+            config.instance_pool.is_some().to_string(),
+        );
+        add(
+            "custom_memory_creator",
+            config.memory_creator.is_some().to_string(),
+        );
+        #[cfg(feature = "cache")]
+        add("cache", config.cache_config.enabled().to_string());
+
+        EngineDiagnostics {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            commit: option_env!("GIT_REV").map(|rev| rev.to_string()),
+            target: isa.triple().to_string(),
+            cranelift_flags,
+            wasm_features,
+            config: summary,
+            page_size: Some(region::page::size()),
+        }
+    }
+}
+
+/// The entries of `EngineDiagnostics::config` which affect compiled code.
+const COMPILATION_SETTINGS: &[&str] = &[
+    "strategy",
+    "static_memory_maximum_size",
+    "static_memory_guard_size",
+    "dynamic_memory_guard_size",
+    "debug_info",
+    "interruptable",
+    "report_unused_functions",
+    "record_call_graph",
+];
+
+/// Collects the `name = value` lines of Cranelift's textual settings format,
+/// which groups settings under `[group]` headers, as `group.name` entries.
+fn parse_flags(text: &str, flags: &mut BTreeMap<String, String>) {
+    let mut group = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            group = Some(&line[1..line.len() - 1]);
+        } else if let (Some(group), Some(eq)) = (group, line.find(" = ")) {
+            let value = line[eq + 3..].trim_matches('"');
+            flags.insert(format!("{}.{}", group, &line[..eq]), value.to_string());
+        }
+    }
+}
+
+/// A report of the environment an [`Engine`] compiles code for, returned by
+/// [`Engine::diagnostics`].
+///
+/// This can be serialized with `serde`, and its `Display` implementation lists
+/// one `name: value` line per setting. Serialized modules record the
+/// diagnostics of the engine which compiled them, and
+/// [`Module::deserialize`](crate::Module::deserialize) lists the
+/// [differences](EngineDiagnostics::differences) when it rejects a module
+/// compiled with incompatible settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineDiagnostics {
+    /// The version of the `wasmtime` crate.
+    pub version: String,
+    /// The git commit that `wasmtime` was built from, if it was built from a
+    /// git checkout.
+    ///
+    /// This isn't recorded in serialized modules and is `None` in the
+    /// diagnostics read from them.
+    pub commit: Option<String>,
+    /// The target triple that code is compiled for.
+    pub target: String,
+    /// Cranelift's settings, keyed by `group.name`. This includes the ISA
+    /// features that were enabled, either detected from the host CPU or set
+    /// with [`Config::cranelift_other_flag`], such as `x86.has_avx`.
+    pub cranelift_flags: BTreeMap<String, String>,
+    /// Which WebAssembly proposals are enabled.
+    pub wasm_features: BTreeMap<String, bool>,
+    /// A summary of the rest of the engine's [`Config`]. Serialized modules
+    /// only record the settings which affect compiled code.
+    pub config: BTreeMap<String, String>,
+    /// The host's page size in bytes.
+    ///
+    /// This doesn't affect compiled code, so it isn't recorded in serialized
+    /// modules and is `None` in the diagnostics read from them.
+    pub page_size: Option<usize>,
+}
+
+impl EngineDiagnostics {
+    /// Returns every setting which differs between `self` and `other`.
+    ///
+    /// Settings are named like in the `Display` output, for example
+    /// `cranelift_flags.x86.has_avx` or `wasm_features.simd`. The page size
+    /// is only compared if both reports include it.
+    pub fn differences(&self, other: &EngineDiagnostics) -> Vec<DiagnosticsDifference> {
+        let (mut ours, mut theirs) = (self.entries(), other.entries());
+        if self.page_size.is_none() || other.page_size.is_none() {
+            ours.remove("page_size");
+            theirs.remove("page_size");
+        }
+        let mut names = ours.keys().chain(theirs.keys()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .filter(|name| ours.get(*name) != theirs.get(*name))
+            .map(|name| DiagnosticsDifference {
+                name: name.clone(),
+                left: ours.get(name).cloned(),
+                right: theirs.get(name).cloned(),
+            })
+            .collect()
+    }
+
+    /// Leaves out everything which doesn't affect compiled code, so that
+    /// what's recorded in serialized modules stays reproducible. In
+    /// particular the commit is left out, so that compatibility is decided by
+    /// the crate version and the compilation settings alone.
+    pub(crate) fn without_host_details(mut self) -> EngineDiagnostics {
+        self.commit = None;
+        self.page_size = None;
+        self.config
+            .retain(|name, _| COMPILATION_SETTINGS.contains(&name.as_str()));
+        self
+    }
+
+    fn entries(&self) -> BTreeMap<String, String> {
+        let mut entries = BTreeMap::new();
+        entries.insert("version".to_string(), self.version.clone());
+        if let Some(commit) = &self.commit {
+            entries.insert("commit".to_string(), commit.clone());
+        }
+        entries.insert("target".to_string(), self.target.clone());
+        for (name, value) in &self.cranelift_flags {
+            entries.insert(format!("cranelift_flags.{}", name), value.clone());
+        }
+        for (name, enabled) in &self.wasm_features {
+            entries.insert(format!("wasm_features.{}", name), enabled.to_string());
+        }
+        for (name, value) in &self.config {
+            entries.insert(format!("config.{}", name), value.clone());
+        }
+        if let Some(page_size) = self.page_size {
+            entries.insert("page_size".to_string(), page_size.to_string());
+        }
+        entries
+    }
+}
+
+impl fmt::Display for EngineDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in self.entries() {
+            writeln!(f, "{}: {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A setting which differs between two [`EngineDiagnostics`], as returned by
+/// [`EngineDiagnostics::differences`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticsDifference {
+    /// The name of the setting, such as `wasm_features.simd`.
+    pub name: String,
+    /// The setting's value in the report `differences` was called on, if it
+    /// has one.
+    pub left: Option<String>,
+    /// The setting's value in the report passed to `differences`, if it has
+    /// one.
+    pub right: Option<String>,
+}
+
+impl fmt::Display for DiagnosticsDifference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = |value: &Option<String>| value.clone().unwrap_or_else(|| "<none>".to_string());
+        write!(
+            f,
+            "{}: {} != {}",
+            self.name,
+            value(&self.left),
+            value(&self.right)
+        )
+    }
 }
 
 impl Default for Engine {
@@ -82,6 +336,41 @@ mod tests {
     use anyhow::Result;
     use tempfile::TempDir;
 
+    #[test]
+    fn diagnostics_reflect_config() {
+        let mut config = Config::new();
+        config
+            .wasm_simd(true)
+            .cranelift_opt_level(OptLevel::None)
+            .max_wasm_stack(1 << 16);
+        let ours = Engine::new(&config).diagnostics();
+        assert_eq!(ours.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(ours.target, target_lexicon::Triple::host().to_string());
+        assert!(ours.wasm_features["simd"]);
+        assert!(!ours.wasm_features["threads"]);
+        assert_eq!(ours.cranelift_flags["shared.opt_level"], "none");
+        assert_eq!(ours.config["max_wasm_stack"], "65536");
+        assert_eq!(ours.page_size, Some(region::page::size()));
+        assert!(ours.to_string().contains("wasm_features.simd: true\n"));
+
+        let theirs = Engine::default().diagnostics();
+        let names = ours
+            .differences(&theirs)
+            .into_iter()
+            .map(|d| d.name)
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"wasm_features.simd".to_string()));
+        assert!(names.contains(&"cranelift_flags.shared.opt_level".to_string()));
+        assert!(names.contains(&"config.max_wasm_stack".to_string()));
+        assert!(!names.contains(&"page_size".to_string()));
+        assert!(ours.differences(&ours).is_empty());
+
+        let recorded = ours.clone().without_host_details();
+        assert!(recorded.commit.is_none());
+        assert!(recorded.page_size.is_none());
+        assert!(!recorded.config.contains_key("max_wasm_stack"));
+    }
+
     #[test]
     fn cache_accounts_for_opt_level() -> Result<()> {
         let td = TempDir::new()?;
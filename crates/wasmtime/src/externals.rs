@@ -6,7 +6,7 @@ use crate::{
     ExternRef, ExternType, Func, GlobalType, MemoryType, Mutability, Store, TableType, Trap,
     ValType,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Error, Result};
 use std::mem;
 use std::ptr;
 use std::slice;
@@ -462,10 +462,9 @@ impl Table {
             }
             _ => unreachable!("only `funcref` and `externref` tables are supported"),
         };
-        if let Some(size) = orig_size {
-            Ok(size)
-        } else {
-            bail!("failed to grow table by `{}`", delta)
+        match orig_size.map_err(Error::msg)? {
+            Some(size) => Ok(size),
+            None => bail!("failed to grow table by `{}`", delta),
         }
     }
 
@@ -729,6 +728,11 @@ impl Table {
 /// * Long-lived pointers are only valid if `Memory` isn't used in an unsafe way
 ///   while the pointer is valid. This includes both aliasing and growth.
 ///
+/// Where possible, prefer the scoped [`Memory::with_slice`] and
+/// [`Memory::with_slice_mut`], which enforce these rules for the duration of a
+/// callback. [`Memory::data`] and [`Memory::data_mut`] bounds-check a range
+/// like they do but leave the rest of these rules to the caller.
+///
 /// At this point it's worth reiterating again that working with `Memory` is
 /// pretty tricky and that's not great! Proposals such as [interface types] are
 /// intended to prevent wasm modules from even needing to import/export memory
@@ -942,14 +946,33 @@ impl Memory {
     /// # }
     /// ```
     pub fn grow(&self, delta: u32) -> Result<u32> {
+        let store = self.store();
+        if store.outstanding_memory_borrows().get() != 0
+            && store.memory_borrows().borrow().contains_key(&self.id())
+        {
+            bail!("cannot grow a memory while it is borrowed by `Memory::with_slice`");
+        }
         let index = self
             .instance
             .memory_index(unsafe { &*self.wasmtime_export.definition });
         self.instance
             .memory_grow(index, delta)
+            .map_err(Error::msg)?
             .ok_or_else(|| anyhow!("failed to grow memory"))
     }
 
+    pub(crate) fn store(&self) -> &Store {
+        &self.instance.store
+    }
+
+    /// Returns an identifier for the underlying linear memory.
+    ///
+    /// This is the id which is passed to callbacks registered with
+    /// [`Store::on_memory_drop`] when this memory is deallocated.
+    pub fn id(&self) -> MemoryId {
+        MemoryId(self.wasmtime_export.definition as usize)
+    }
+
     pub(crate) fn from_wasmtime_memory(
         wasmtime_export: wasmtime_runtime::ExportMemory,
         instance: StoreInstanceHandle,
@@ -964,6 +987,7 @@ impl Memory {
         let expected = &ty.memory;
         let actual = &self.wasmtime_export.memory.memory;
         expected.shared == actual.shared
+            && expected.memory64 == actual.memory64
             && expected.minimum <= actual.minimum
             && match expected.maximum {
                 Some(expected) => match actual.maximum {
@@ -982,6 +1006,14 @@ impl Memory {
     }
 }
 
+/// An opaque identifier for a linear memory, as returned by [`Memory::id`].
+///
+/// All [`Memory`] handles referring to the same linear memory have the same
+/// id, including handles obtained through an instance which imported it. Ids
+/// are only unique among memories which haven't been deallocated yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryId(pub(crate) usize);
+
 /// A linear memory. This trait provides an interface for raw memory buffers which are used
 /// by wasmtime, e.g. inside ['Memory']. Such buffers are in principle not thread safe.
 /// By implementing this trait together with MemoryCreator,
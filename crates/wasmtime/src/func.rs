@@ -1,6 +1,6 @@
 use crate::store::StoreInner;
 use crate::trampoline::StoreInstanceHandle;
-use crate::{Extern, ExternRef, FuncType, Memory, Store, Trap, Val, ValType};
+use crate::{Extern, ExternRef, FuncType, Memory, Store, Trap, TrapCode, Val, ValType};
 use anyhow::{bail, ensure, Context as _, Result};
 use smallvec::{smallvec, SmallVec};
 use std::cmp::max;
@@ -9,6 +9,7 @@ use std::mem;
 use std::panic::{self, AssertUnwindSafe};
 use std::ptr::{self, NonNull};
 use std::rc::Weak;
+use std::time::Duration;
 use wasmtime_runtime::{
     raise_user_trap, Export, InstanceHandle, VMContext, VMFunctionBody, VMSharedSignatureIndex,
     VMTrampoline,
@@ -147,6 +148,28 @@ pub struct Func {
     export: wasmtime_runtime::ExportFunction,
 }
 
+/// Renders a function signature like `(i32, i64) -> f32`, for type mismatch
+/// errors.
+fn signature_string(
+    params: impl IntoIterator<Item = ValType>,
+    results: impl IntoIterator<Item = ValType>,
+) -> String {
+    let params = params
+        .into_iter()
+        .map(|ty| ty.to_string())
+        .collect::<Vec<_>>();
+    let mut results = results
+        .into_iter()
+        .map(|ty| ty.to_string())
+        .collect::<Vec<_>>();
+    let results = if results.len() == 1 {
+        results.remove(0)
+    } else {
+        format!("({})", results.join(", "))
+    };
+    format!("({}) -> {}", params.join(", "), results)
+}
+
 macro_rules! getters {
     ($(
         $(#[$doc:meta])*
@@ -160,23 +183,34 @@ macro_rules! getters {
             $($args: WasmTy,)*
             R: WasmTy,
         {
-            // Verify all the paramers match the expected parameters, and that
-            // there are no extra parameters...
             let ty = self.ty();
-            let mut params = ty.params();
-            let n = 0;
-            $(
-                let n = n + 1;
-                $args::matches(&mut params)
-                    .with_context(|| format!("Type mismatch in argument {}", n))?;
-            )*
-            ensure!(params.next().is_none(), "Type mismatch: too many arguments (expected {})", n);
-
-            // ... then do the same for the results...
-            let mut results = ty.results();
-            R::matches(&mut results)
-                .context("Type mismatch in return type")?;
-            ensure!(results.next().is_none(), "Type mismatch: too many return values (expected 1)");
+            let typecheck = || -> anyhow::Result<()> {
+                // Verify all the paramers match the expected parameters, and that
+                // there are no extra parameters...
+                let mut params = ty.params();
+                let n = 0;
+                $(
+                    let n = n + 1;
+                    $args::matches(&mut params)
+                        .with_context(|| format!("Type mismatch in argument {}", n))?;
+                )*
+                ensure!(params.next().is_none(), "Type mismatch: too many arguments (expected {})", n);
+
+                // ... then do the same for the results...
+                let mut results = ty.results();
+                R::matches(&mut results)
+                    .context("Type mismatch in return type")?;
+                ensure!(results.next().is_none(), "Type mismatch: too many return values (expected 1)");
+                Ok(())
+            };
+            typecheck().with_context(|| {
+                let params: &[Option<ValType>] = &[$($args::valtype(),)*];
+                format!(
+                    "Type mismatch: expected a function of type `{}`, found `{}`",
+                    signature_string(params.iter().filter_map(|ty| ty.clone()), R::valtype()),
+                    signature_string(ty.params(), ty.results()),
+                )
+            })?;
 
             // Pass the instance into the closure so that we keep it live for
             // the lifetime of the closure. Pass the `anyfunc` in so that we can
@@ -265,7 +299,7 @@ impl Func {
         // Create our actual trampoline function which translates from a bunch
         // of bit patterns on the stack to actual instances of `Val` being
         // passed to the given function.
-        let func = Box::new(move |caller_vmctx, values_vec: *mut u128| {
+        let func = Box::new(move |callee_vmctx, caller_vmctx, values_vec: *mut u128| {
             // We have a dynamic guarantee that `values_vec` has the right
             // number of arguments and the right types of arguments. As a result
             // we should be able to safely run through them all and read them.
@@ -280,6 +314,13 @@ impl Func {
                     args.push(val);
                 }
             }
+            if let Some(hook) = store.host_call_hook() {
+                let name = store.host_func_name(callee_vmctx);
+                hook(&HostCallInfo {
+                    name: name.as_deref(),
+                    args: &args,
+                });
+            }
 
             let mut returns: SmallVec<[Val; STACK_RETURNS]> =
                 smallvec![Val::null(); ty_clone.results().len()];
@@ -545,15 +586,13 @@ impl Func {
     pub fn ty(&self) -> FuncType {
         // Signatures should always be registered in the store's registry of
         // shared signatures, so we should be able to unwrap safely here.
-        let signatures = self.instance.store.signatures().borrow();
-        let (wft, _) = signatures
-            .lookup_shared(self.sig_index())
-            .expect("signature should be registered");
-
-        // This is only called with `Export::Function`, and since it's coming
-        // from wasmtime_runtime itself we should support all the types coming
-        // out of it, so assert such here.
-        FuncType::from_wasm_func_type(&wft)
+        self.instance
+            .store
+            .signatures()
+            .borrow()
+            .lookup_type(self.sig_index())
+            .expect("signature should be registered")
+            .clone()
     }
 
     /// Returns the number of parameters that this function takes.
@@ -574,6 +613,19 @@ impl Func {
         sig.returns.len()
     }
 
+    /// Returns the type of the parameter at `index`, or `None` if this
+    /// function takes `index` parameters or fewer.
+    ///
+    /// Unlike looking the parameter up through [`Func::ty`] this doesn't need
+    /// to produce a [`FuncType`] at all.
+    pub fn param(&self, index: usize) -> Option<ValType> {
+        let signatures = self.instance.store.signatures().borrow();
+        let (sig, _) = signatures
+            .lookup_shared(self.sig_index())
+            .expect("signature should be registered");
+        sig.params.get(index).map(ValType::from_wasm_type)
+    }
+
     /// Invokes this function with the `params` given, returning the results and
     /// any trap, if one occurs.
     ///
@@ -583,6 +635,18 @@ impl Func {
     ///
     /// This function should not panic unless the underlying function itself
     /// initiates a panic.
+    ///
+    /// Traps are returned as an `Err` containing a [`Trap`] no matter how many
+    /// wasm and host frames are between this call and where the trap occurred.
+    /// They don't unwind, and don't involve Rust panics, so this also holds
+    /// when panics are configured to abort: control jumps straight back to
+    /// this call, skipping the frames in between. A host function that panics
+    /// has its panic carried across the wasm frames the same way, and resumed
+    /// from this call.
+    ///
+    /// The returned slice holds exactly one value per result of the
+    /// function's type, in the order the results are declared, so with
+    /// multi-value the first result of the wasm function is at index 0.
     pub fn call(&self, params: &[Val]) -> Result<Box<[Val]>> {
         // We need to perform a dynamic check that the arguments given to us
         // match the signature of this function and are appropriate to pass to
@@ -639,10 +703,43 @@ impl Func {
                 results.push(Val::read_value_from(&self.instance.store, ptr, ty));
             }
         }
+        debug_assert_eq!(results.len(), my_ty.results().len());
 
         Ok(results.into())
     }
 
+    /// Invokes this function like [`Func::call`], but interrupts it if it's
+    /// still executing once `timeout` has elapsed.
+    ///
+    /// If the call is interrupted by the deadline then the returned error has
+    /// a message saying so, and it can be downcast to the interrupt's
+    /// [`Trap`], whose [`Trap::trace`] records where the wasm was
+    /// interrupted. A host function which is executing once the deadline
+    /// passes runs to completion before the wasm which called it traps, so
+    /// this only returns in roughly `timeout` if host functions don't block.
+    ///
+    /// This requires [`Config::interruptable`](crate::Config::interruptable)
+    /// to be enabled, and is built on
+    /// [`InterruptHandle::interrupt_after`](crate::InterruptHandle::interrupt_after).
+    pub fn call_with_deadline(&self, params: &[Val], timeout: Duration) -> Result<Box<[Val]>> {
+        let timer = self
+            .instance
+            .store
+            .interrupt_handle()?
+            .interrupt_after(timeout);
+        let result = self.call(params);
+        let fired = timer.fired();
+        drop(timer);
+        result.map_err(|e| match e.downcast::<Trap>() {
+            Ok(trap) if fired && trap.trap_code() == Some(TrapCode::Interrupt) => {
+                anyhow::Error::new(trap)
+                    .context(format!("wasm call exceeded its deadline of {:?}", timeout))
+            }
+            Ok(trap) => trap.into(),
+            Err(e) => e,
+        })
+    }
+
     pub(crate) fn caller_checked_anyfunc(
         &self,
     ) -> NonNull<wasmtime_runtime::VMCallerCheckedAnyfunc> {
@@ -798,6 +895,14 @@ impl Func {
         &self.instance.store
     }
 
+    /// Returns whether this function is defined by the host, with `Func::new`
+    /// or `Func::wrap`, rather than exported by an instance of a wasm module.
+    pub(crate) fn is_host(&self) -> bool {
+        // Instances of wasm modules are the only ones without host state, see
+        // `Instance::new`.
+        !self.instance.host_state().is::<()>()
+    }
+
     pub(crate) fn matches_expected(&self, expected: VMSharedSignatureIndex) -> bool {
         self.sig_index() == expected
     }
@@ -824,14 +929,25 @@ pub(crate) fn invoke_wasm_and_catch_traps(
     store: &Store,
     closure: impl FnMut(),
 ) -> Result<(), Trap> {
+    // Wasm could otherwise alias, grow or free a slice the host is looking at.
+    if store.outstanding_memory_borrows().get() != 0 {
+        return Err(Trap::new(
+            "cannot call into wasm while a memory is borrowed by `Memory::with_slice`",
+        ));
+    }
     unsafe {
         let canary = 0;
         let _auto_reset_canary = store
             .externref_activations_table()
             .set_stack_canary(&canary);
 
-        wasmtime_runtime::catch_traps(vmctx, store, closure)
-            .map_err(|e| Trap::from_runtime(store, e))
+        #[cfg(unix)]
+        let sampling = store.sampler().map(|sampler| sampler.enter());
+        let result = wasmtime_runtime::catch_traps(vmctx, store, closure);
+        #[cfg(unix)]
+        drop(sampling);
+        store.flush_samples();
+        result.map_err(|e| Trap::from_runtime(store, e))
     }
 }
 
@@ -1433,6 +1549,27 @@ pub trait IntoFunc<Params, Results> {
     fn into_func(self, store: &Store) -> Func;
 }
 
+/// Describes a call from wasm into a host function, as passed to the hook
+/// registered with [`Store::on_host_call`].
+#[derive(Debug)]
+pub struct HostCallInfo<'a> {
+    pub(crate) name: Option<&'a str>,
+    pub(crate) args: &'a [Val],
+}
+
+impl<'a> HostCallInfo<'a> {
+    /// Returns the name of the function being called, like `env::foo`, if
+    /// it was defined in a [`Linker`](crate::Linker).
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
+
+    /// Returns the arguments the function is being called with.
+    pub fn args(&self) -> &'a [Val] {
+        self.args
+    }
+}
+
 /// A structure representing the *caller's* context when creating a function
 /// via [`Func::wrap`].
 ///
@@ -1603,6 +1740,28 @@ macro_rules! impl_into_func {
 
                     let ret = {
                         panic::catch_unwind(AssertUnwindSafe(|| {
+                            // The store is running this call, so it's alive, and
+                            // it's only upgraded when there's a hook to run.
+                            let hooked_store = if Store::weak_has_host_call_hook(store) {
+                                Store::upgrade(store)
+                            } else {
+                                None
+                            };
+                            if let Some(store) = hooked_store {
+                                if let Some(hook) = store.host_call_hook() {
+                                    #[allow(unused_mut)]
+                                    let mut args = Vec::new();
+                                    $(
+                                        if let Some(ty) = $args::valtype() {
+                                            let mut slot = 0;
+                                            $args::store_to_args($args, &mut slot);
+                                            args.push(Val::read_value_from(&store, &slot, ty));
+                                        }
+                                    )*
+                                    let name = store.host_func_name(vmctx);
+                                    hook(&HostCallInfo { name: name.as_deref(), args: &args });
+                                }
+                            }
                             func(
                                 Caller { store, caller_vmctx },
                                 $( $args::from_abi($args, weak_store), )*
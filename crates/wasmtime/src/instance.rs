@@ -6,8 +6,8 @@ use std::mem;
 use wasmtime_environ::wasm::EntityIndex;
 use wasmtime_jit::CompiledModule;
 use wasmtime_runtime::{
-    Imports, InstantiationError, StackMapRegistry, VMContext, VMExternRefActivationsTable,
-    VMFunctionBody,
+    Imports, InstantiationError, RuntimeMemoryCreator, StackMapRegistry, VMContext,
+    VMExternRefActivationsTable, VMFunctionBody,
 };
 
 fn instantiate(
@@ -15,22 +15,45 @@ fn instantiate(
     compiled_module: &CompiledModule,
     imports: Imports<'_>,
     host: Box<dyn Any>,
+    init: impl FnOnce(&StoreInstanceHandle) -> Result<()>,
 ) -> Result<StoreInstanceHandle, Error> {
+    store.reserve_module_instance()?;
+
     // Register the module just before instantiation to ensure we have a
     // trampoline registered for every signature and to preserve the module's
     // compiled JIT code within the `Store`.
     store.register_module(compiled_module);
 
     let config = store.engine().config();
+
+    // With pooling enabled the instance's slot is claimed up front, and its
+    // memory and table are carved out of the pool's reservation for that
+    // slot.
+    let (slot, mem_creator) = match &config.instance_pool {
+        Some(pool) => (
+            Some(pool.acquire(compiled_module.module()).map_err(Error::msg)?),
+            Some(pool as &dyn RuntimeMemoryCreator),
+        ),
+        None => (
+            None,
+            config
+                .memory_creator
+                .as_ref()
+                .map(|a| a as &dyn RuntimeMemoryCreator),
+        ),
+    };
+
     let instance = unsafe {
         let instance = compiled_module.instantiate(
             imports,
             &store.lookup_shared_signature(compiled_module.module()),
-            config.memory_creator.as_ref().map(|a| a as _),
+            mem_creator,
+            config.instance_pool.as_ref(),
             store.interrupts(),
             host,
             store.externref_activations_table() as *const VMExternRefActivationsTable as *mut _,
             store.stack_map_registry() as *const StackMapRegistry as *mut _,
+            store.limiter(),
         )?;
 
         // After we've created the `InstanceHandle` we still need to run
@@ -41,6 +64,9 @@ fn instantiate(
         // tables. This means that from this point on, regardless of whether
         // initialization is successful, we need to keep the instance alive.
         let instance = store.add_instance(instance);
+        if let Some(slot) = slot {
+            store.add_instance_slot(slot);
+        }
         instance
             .initialize(
                 config.features.bulk_memory,
@@ -56,6 +82,8 @@ fn instantiate(
         instance
     };
 
+    init(&instance)?;
+
     let start_func = instance.handle.module().start_func;
 
     // If a start function is present, invoke it. Make sure we use all the
@@ -161,12 +189,78 @@ impl Instance {
     /// [issue]: https://github.com/bytecodealliance/wasmtime/issues/727
     /// [`ExternType`]: crate::ExternType
     pub fn new(store: &Store, module: &Module, imports: &[Extern]) -> Result<Instance, Error> {
+        Instance::new_with_table_init(store, module, imports, |_| Ok(()))
+    }
+
+    /// Same as [`Instance::new`], except that `init` gets to modify the
+    /// instance's tables before its `start` function runs.
+    ///
+    /// The `init` closure is passed all of the tables of the instance, imported
+    /// ones included, in the order of their indices in the module. It runs
+    /// after the tables and memories have been initialized with the module's
+    /// element and data segments, so entries it sets with [`Table::set`] take
+    /// precedence over those of the segments, and before the `start` function,
+    /// so that `call_indirect` in the `start` function can reach whatever
+    /// `init` puts in the tables. This is useful to install host functions
+    /// into a module's table when it has no imports for them.
+    ///
+    /// If `init` returns an error then instantiation fails with that error,
+    /// and the `start` function isn't run. As with a trapping `start`
+    /// function, any changes already made to imported tables are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(store.engine(), r#"
+    ///     (module
+    ///         (type $t (func))
+    ///         (table 1 funcref)
+    ///         (func $start (call_indirect (type $t) (i32.const 0)))
+    ///         (start $start))
+    /// "#)?;
+    /// let hello = Func::wrap(&store, || println!("hello from the table!"));
+    /// Instance::new_with_table_init(&store, &module, &[], |tables| {
+    ///     tables[0].set(0, hello.into())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new_with_table_init(
+        store: &Store,
+        module: &Module,
+        imports: &[Extern],
+        init: impl FnOnce(&[Table]) -> Result<()>,
+    ) -> Result<Instance, Error> {
         if !Engine::same(store.engine(), module.engine()) {
             bail!("cross-`Engine` instantiation is not currently supported");
         }
 
         let handle = with_imports(store, module.compiled_module(), imports, |imports| {
-            instantiate(store, module.compiled_module(), imports, Box::new(()))
+            instantiate(
+                store,
+                module.compiled_module(),
+                imports,
+                Box::new(()),
+                |instance| {
+                    let tables = instance
+                        .module()
+                        .table_plans
+                        .keys()
+                        .map(|index| {
+                            match instance.lookup_by_declaration(&EntityIndex::Table(index)) {
+                                wasmtime_runtime::Export::Table(t) => {
+                                    Table::from_wasmtime_table(t, instance.clone())
+                                }
+                                _ => unreachable!(),
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    init(&tables)
+                },
+            )
         })?;
 
         Ok(Instance {
@@ -225,6 +319,9 @@ impl Instance {
     ///
     /// Returns `None` if there was no export named `name`, or if there was but
     /// it wasn't a memory.
+    ///
+    /// The [`Memory`] refers to the memory rather than to its current buffer,
+    /// so it remains valid, and sees the new pages, after the memory grows.
     pub fn get_memory(&self, name: &str) -> Option<Memory> {
         self.get_export(name)?.into_memory()
     }
@@ -234,6 +234,7 @@
 #![doc(test(attr(deny(warnings))))]
 #![doc(test(attr(allow(dead_code, unused_variables, unused_mut))))]
 
+mod call_graph;
 mod config;
 mod engine;
 mod externals;
@@ -241,15 +242,20 @@ mod frame_info;
 mod func;
 mod instance;
 mod linker;
+mod memory;
 mod module;
 mod r#ref;
+mod resource_table;
+mod sampling;
 mod sig_registry;
 mod store;
 mod trampoline;
 mod trap;
 mod types;
 mod values;
+mod watchdog;
 
+pub use crate::call_graph::CallGraph;
 pub use crate::config::*;
 pub use crate::engine::*;
 pub use crate::externals::*;
@@ -257,8 +263,11 @@ pub use crate::frame_info::FrameInfo;
 pub use crate::func::*;
 pub use crate::instance::Instance;
 pub use crate::linker::*;
-pub use crate::module::Module;
+pub use crate::memory::*;
+pub use crate::module::{AbiChange, AbiDiff, Module, ValidationError};
 pub use crate::r#ref::ExternRef;
+pub use crate::resource_table::ResourceTable;
+pub use crate::sampling::SamplingProfile;
 pub use crate::store::*;
 pub use crate::trap::*;
 pub use crate::types::*;
@@ -279,4 +288,5 @@ fn _assert_send_sync() {
     _assert::<Engine>();
     _assert::<Config>();
     _assert::<InterruptHandle>();
+    _assert::<InterruptTimer>();
 }
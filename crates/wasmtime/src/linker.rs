@@ -1,10 +1,12 @@
 use crate::{
-    Extern, ExternType, Func, FuncType, GlobalType, ImportType, Instance, IntoFunc, Module, Store,
-    Trap,
+    Extern, ExternType, Func, FuncType, Global, GlobalType, ImportType, Instance, IntoFunc, Memory,
+    Module, Store, Table, Trap, Val, ValType,
 };
-use anyhow::{anyhow, bail, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use log::warn;
 use std::collections::hash_map::{Entry, HashMap};
+use std::fmt;
+use std::hash::Hash;
 use std::rc::Rc;
 
 /// Structure used to link wasm modules/instances together.
@@ -41,6 +43,13 @@ pub struct Linker {
     store: Store,
     string2idx: HashMap<Rc<str>, usize>,
     strings: Vec<Rc<str>>,
+    // Function and global types are interned too, so that looking up an
+    // import hashes and compares them once rather than for every probe of
+    // `map`.
+    func_type2idx: HashMap<FuncType, usize>,
+    func_types: Vec<FuncType>,
+    global_type2idx: HashMap<GlobalType, usize>,
+    global_types: Vec<GlobalType>,
     map: HashMap<ImportKey, Extern>,
     allow_shadowing: bool,
 }
@@ -52,10 +61,10 @@ struct ImportKey {
     kind: ImportKind,
 }
 
-#[derive(Hash, PartialEq, Eq, Debug)]
+#[derive(Hash, PartialEq, Eq, Clone, Copy)]
 enum ImportKind {
-    Func(FuncType),
-    Global(GlobalType),
+    Func(usize),
+    Global(usize),
     Memory,
     Table,
 }
@@ -83,6 +92,10 @@ impl Linker {
             map: HashMap::new(),
             string2idx: HashMap::new(),
             strings: Vec::new(),
+            func_type2idx: HashMap::new(),
+            func_types: Vec::new(),
+            global_type2idx: HashMap::new(),
+            global_types: Vec::new(),
             allow_shadowing: false,
         }
     }
@@ -483,14 +496,150 @@ impl Linker {
         Ok(())
     }
 
+    /// Defines a stub for every function import of `module` which isn't
+    /// already defined in this linker, where calling a stubbed function
+    /// traps.
+    ///
+    /// This is useful when porting large applications, whose modules often
+    /// import functions which are never called on the code paths being
+    /// exercised. The trap raised by a stubbed function names the import it
+    /// stands in for, with a message like "unknown import called: env::foo".
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without defining anything, if an undefined import of
+    /// `module` isn't a function, since an imported global, memory or table
+    /// can't be faked without changing the behavior of code which uses it.
+    /// [`Linker::define_unknown_imports_as_default_values`] stubs those too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let store = Store::default();
+    /// let wat = r#"
+    ///     (module
+    ///         (import "env" "foo" (func $foo))
+    ///         (func (export "run") call $foo)
+    ///     )
+    /// "#;
+    /// let module = Module::new(store.engine(), wat)?;
+    /// let mut linker = Linker::new(&store);
+    /// linker.define_unknown_imports_as_traps(&module)?;
+    /// let instance = linker.instantiate(&module)?;
+    /// let trap = instance.get_func("run").unwrap().call(&[]).unwrap_err();
+    /// assert!(trap.to_string().contains("unknown import called: env::foo"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn define_unknown_imports_as_traps(&mut self, module: &Module) -> Result<&mut Self> {
+        for import in module.imports() {
+            if self.get(&import).is_some() {
+                continue;
+            }
+            let kind = match import.ty() {
+                ExternType::Func(_) => continue,
+                ExternType::Global(_) => "global",
+                ExternType::Memory(_) => "memory",
+                ExternType::Table(_) => "table",
+                ExternType::Module(_) => "module",
+                ExternType::Instance(_) => "instance",
+            };
+            bail!(
+                "unknown import: `{}::{}` is a {}, which can't be stubbed with a trap",
+                import.module(),
+                import.name(),
+                kind
+            );
+        }
+        self.define_unknown_imports(module, true)
+    }
+
+    /// Defines a stub for every import of `module` which isn't already
+    /// defined in this linker, where calling a stubbed function returns zero
+    /// or null for each of its results.
+    ///
+    /// This is useful to instantiate and poke at a module without providing
+    /// all of its imports by hand. Imported globals, memories and tables are
+    /// stubbed with fresh items of the imported type, with globals and table
+    /// elements set to zero or null.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `module` imports modules or instances, which can't
+    /// be stubbed.
+    pub fn define_unknown_imports_as_default_values(
+        &mut self,
+        module: &Module,
+    ) -> Result<&mut Self> {
+        self.define_unknown_imports(module, false)
+    }
+
+    fn define_unknown_imports(&mut self, module: &Module, trap: bool) -> Result<&mut Self> {
+        for import in module.imports() {
+            if self.get(&import).is_some() {
+                continue;
+            }
+            let item = match import.ty() {
+                ExternType::Func(ty) => {
+                    let message = format!(
+                        "unknown import called: {}::{}",
+                        import.module(),
+                        import.name()
+                    );
+                    let results = ty.results().collect::<Vec<_>>();
+                    Func::new(&self.store, ty, move |_, _, out| {
+                        if trap {
+                            return Err(Trap::new(message.clone()));
+                        }
+                        for (slot, ty) in out.iter_mut().zip(&results) {
+                            *slot = default_value(ty);
+                        }
+                        Ok(())
+                    })
+                    .into()
+                }
+                ExternType::Global(ty) => {
+                    let val = default_value(ty.content());
+                    Global::new(&self.store, ty, val)?.into()
+                }
+                ExternType::Memory(ty) => Memory::new(&self.store, ty).into(),
+                ExternType::Table(ty) => {
+                    let init = default_value(ty.element());
+                    Table::new(&self.store, ty, init)?.into()
+                }
+                ExternType::Module(_) | ExternType::Instance(_) => bail!(
+                    "cannot stub import `{}::{}`: only functions, globals, memories \
+                     and tables can be stubbed",
+                    import.module(),
+                    import.name()
+                ),
+            };
+            self.insert(import.module(), import.name(), item)?;
+        }
+        Ok(self)
+    }
+
     fn insert(&mut self, module: &str, name: &str, item: Extern) -> Result<()> {
         let key = self.import_key(module, name, item.ty());
+        match &item {
+            // Only host functions are ever looked up by name, see
+            // `Store::on_host_call`. Functions exported by wasm instances
+            // share their instance's `VMContext`, so mustn't be named.
+            Extern::Func(f) if f.is_host() => {
+                let vmctx = unsafe { f.caller_checked_anyfunc().as_ref().vmctx };
+                self.store
+                    .name_host_func(vmctx, || format!("{}::{}", module, name));
+            }
+            _ => {}
+        }
         match self.map.entry(key) {
             Entry::Occupied(o) if !self.allow_shadowing => bail!(
-                "import of `{}::{}` with kind {:?} defined twice",
+                "import of `{}::{}` with kind {} defined twice",
                 module,
                 name,
-                o.key().kind,
+                self.describe_kind(o.key().kind),
             ),
             Entry::Occupied(mut o) => {
                 o.insert(item);
@@ -506,20 +655,48 @@ impl Linker {
         ImportKey {
             module: self.intern_str(module),
             name: self.intern_str(name),
-            kind: self.import_kind(ty),
+            kind: self.intern_kind(ty),
         }
     }
 
-    fn import_kind(&self, ty: ExternType) -> ImportKind {
+    fn intern_kind(&mut self, ty: ExternType) -> ImportKind {
         match ty {
-            ExternType::Func(f) => ImportKind::Func(f),
-            ExternType::Global(f) => ImportKind::Global(f),
+            ExternType::Func(f) => {
+                ImportKind::Func(intern(&mut self.func_type2idx, &mut self.func_types, f))
+            }
+            ExternType::Global(g) => {
+                ImportKind::Global(intern(&mut self.global_type2idx, &mut self.global_types, g))
+            }
+            ExternType::Memory(_) => ImportKind::Memory,
+            ExternType::Table(_) => ImportKind::Table,
+
+            // FIXME(#2094)
+            ExternType::Module(_) => unimplemented!(),
+            ExternType::Instance(_) => unimplemented!(),
+        }
+    }
+
+    /// Returns the kind of definitions which can satisfy an import of type
+    /// `ty`, or `None` if nothing defined in this linker has its type.
+    fn import_kind(&self, ty: ExternType) -> Option<ImportKind> {
+        Some(match ty {
+            ExternType::Func(f) => ImportKind::Func(*self.func_type2idx.get(&f)?),
+            ExternType::Global(g) => ImportKind::Global(*self.global_type2idx.get(&g)?),
             ExternType::Memory(_) => ImportKind::Memory,
             ExternType::Table(_) => ImportKind::Table,
 
             // FIXME(#2094)
             ExternType::Module(_) => unimplemented!(),
             ExternType::Instance(_) => unimplemented!(),
+        })
+    }
+
+    fn describe_kind(&self, kind: ImportKind) -> String {
+        match kind {
+            ImportKind::Func(idx) => format!("Func({:?})", self.func_types[idx]),
+            ImportKind::Global(idx) => format!("Global({:?})", self.global_types[idx]),
+            ImportKind::Memory => "Memory".to_string(),
+            ImportKind::Table => "Table".to_string(),
         }
     }
 
@@ -582,41 +759,43 @@ impl Linker {
     }
 
     fn compute_imports(&self, module: &Module) -> Result<Vec<Extern>> {
-        module
-            .imports()
-            .map(|import| self.get(&import).ok_or_else(|| self.link_error(&import)))
-            .collect()
-    }
-
-    fn link_error(&self, import: &ImportType) -> Error {
-        let mut options = Vec::new();
-        for i in self.map.keys() {
-            if &*self.strings[i.module] != import.module()
-                || &*self.strings[i.name] != import.name()
-            {
-                continue;
+        // Every import is resolved, even once one has failed to, so that all
+        // of the problems can be reported at once.
+        let mut imports = Vec::new();
+        let mut problems = Vec::new();
+        for import in module.imports() {
+            match self.get(&import) {
+                Some(item) => imports.push(item),
+                None => problems.push(self.import_problem(&import)),
             }
-            options.push(format!("  * {:?}\n", i.kind));
         }
-        if options.is_empty() {
-            return anyhow!(
-                "unknown import: `{}::{}` has not been defined",
-                import.module(),
-                import.name()
-            );
+        if !problems.is_empty() {
+            return Err(MissingImports { problems }.into());
         }
+        Ok(imports)
+    }
 
-        options.sort();
-
-        anyhow!(
-            "incompatible import type for `{}::{}` specified\n\
-                 desired signature was: {:?}\n\
-                 signatures available:\n\n{}",
-            import.module(),
-            import.name(),
-            import.ty(),
-            options.concat(),
-        )
+    fn import_problem(&self, import: &ImportType) -> ImportProblem {
+        let module = import.module().to_string();
+        let name = import.name().to_string();
+        let mut available = self
+            .get_by_name(import.module(), import.name())
+            .map(|item| item.ty())
+            .collect::<Vec<_>>();
+        if available.is_empty() {
+            return ImportProblem::Undefined {
+                module,
+                name,
+                ty: import.ty(),
+            };
+        }
+        available.sort_by_cached_key(|ty| format!("{:?}", ty));
+        ImportProblem::IncompatibleType {
+            module,
+            name,
+            expected: import.ty(),
+            available,
+        }
     }
 
     /// Returns the [`Store`] that this linker is connected to.
@@ -650,7 +829,7 @@ impl Linker {
         let key = ImportKey {
             module: *self.string2idx.get(import.module())?,
             name: *self.string2idx.get(import.name())?,
-            kind: self.import_kind(import.ty()),
+            kind: self.import_kind(import.ty())?,
         };
         self.map.get(&key).cloned()
     }
@@ -726,6 +905,34 @@ impl Linker {
     }
 }
 
+/// The zero or null value of `ty`, used for stubbed imports.
+fn default_value(ty: &ValType) -> Val {
+    match ty {
+        ValType::I32 => Val::I32(0),
+        ValType::I64 => Val::I64(0),
+        ValType::F32 => Val::F32(0),
+        ValType::F64 => Val::F64(0),
+        ValType::V128 => Val::V128(0),
+        ValType::ExternRef => Val::ExternRef(None),
+        ValType::FuncRef => Val::FuncRef(None),
+    }
+}
+
+/// Returns the index of `item` in `items`, adding it first if it isn't there.
+fn intern<T: Hash + Eq + Clone>(
+    item2idx: &mut HashMap<T, usize>,
+    items: &mut Vec<T>,
+    item: T,
+) -> usize {
+    if let Some(idx) = item2idx.get(&item) {
+        return *idx;
+    }
+    let idx = items.len();
+    items.push(item.clone());
+    item2idx.insert(item, idx);
+    idx
+}
+
 /// Modules can be interpreted either as Commands or Reactors.
 enum ModuleKind {
     /// The instance is a Command, meaning an instance is created for each
@@ -769,3 +976,122 @@ impl ModuleKind {
         }
     }
 }
+
+/// The imports of a module which a [`Linker`] couldn't resolve, returned as
+/// the error of [`Linker::instantiate`] and [`Linker::module`].
+///
+/// All of a module's imports are resolved before this is reported, so it lists
+/// every problem at once.
+///
+/// # Examples
+///
+/// ```
+/// # use wasmtime::*;
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let mut linker = Linker::new(&store);
+/// linker.func("env", "f", || 1)?;
+/// let module = Module::new(store.engine(), r#"
+///     (module
+///         (import "env" "f" (func))
+///         (import "env" "g" (func)))
+/// "#)?;
+/// let err = linker.instantiate(&module).unwrap_err();
+/// let missing = err.downcast_ref::<MissingImports>().unwrap();
+/// assert_eq!(missing.problems().len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MissingImports {
+    problems: Vec<ImportProblem>,
+}
+
+impl MissingImports {
+    /// Returns the problem with each import that couldn't be resolved, in the
+    /// order of the module's imports.
+    ///
+    /// This is never empty.
+    pub fn problems(&self) -> &[ImportProblem] {
+        &self.problems
+    }
+}
+
+impl fmt::Display for MissingImports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [problem] = self.problems.as_slice() {
+            return write!(f, "{}", problem);
+        }
+        write!(f, "{} imports couldn't be resolved:", self.problems.len())?;
+        for problem in self.problems.iter() {
+            write!(f, "\n  * {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingImports {}
+
+/// Why a [`Linker`] couldn't resolve an import, as listed by
+/// [`MissingImports`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportProblem {
+    /// Nothing is defined under the import's module and name.
+    Undefined {
+        /// The module name of the import.
+        module: String,
+        /// The field name of the import.
+        name: String,
+        /// The type of the import.
+        ty: ExternType,
+    },
+    /// Items are defined under the import's module and name, but none of
+    /// them has the type the import expects.
+    IncompatibleType {
+        /// The module name of the import.
+        module: String,
+        /// The field name of the import.
+        name: String,
+        /// The type of the import.
+        expected: ExternType,
+        /// The types of the items which are defined under its name.
+        available: Vec<ExternType>,
+    },
+}
+
+impl fmt::Display for ImportProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportProblem::Undefined { module, name, .. } => write!(
+                f,
+                "unknown import: `{}::{}` has not been defined",
+                module, name
+            ),
+            ImportProblem::IncompatibleType {
+                module,
+                name,
+                expected,
+                available,
+            } => {
+                write!(
+                    f,
+                    "incompatible import type for `{}::{}` specified\n\
+                     desired signature was: {:?}\n\
+                     signatures available:\n\n",
+                    module, name, expected,
+                )?;
+                for ty in available {
+                    match ty {
+                        ExternType::Func(ty) => writeln!(f, "  * Func({:?})", ty)?,
+                        ExternType::Global(ty) => writeln!(f, "  * Global({:?})", ty)?,
+                        ExternType::Memory(_) => writeln!(f, "  * Memory")?,
+                        ExternType::Table(_) => writeln!(f, "  * Table")?,
+                        ty => writeln!(f, "  * {:?}", ty)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,624 @@
+//! Safe, bounds-checked helpers for the host to read and write the contents
+//! of a [`Memory`].
+
+use crate::Memory;
+use std::convert::TryInto;
+use std::fmt;
+use std::marker;
+use std::mem;
+use std::ops::Range;
+use std::slice;
+use std::str;
+
+/// An error returned when the host fails to access the contents of a
+/// [`Memory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemoryAccessError {
+    /// Computing the end of the accessed range, `offset + len`, overflowed.
+    Overflow {
+        /// The offset the access started at.
+        offset: usize,
+        /// The length, in bytes, of the access.
+        len: usize,
+    },
+    /// The accessed range isn't entirely contained within the memory.
+    OutOfBounds {
+        /// The offset the access started at.
+        offset: usize,
+        /// The length, in bytes, of the access.
+        len: usize,
+        /// The size, in bytes, of the memory at the time of the access.
+        memory_size: usize,
+    },
+    /// A typed access was performed at an offset which isn't a multiple of the
+    /// type's alignment.
+    Unaligned {
+        /// The offset the access started at.
+        offset: usize,
+        /// The required alignment, in bytes.
+        align: usize,
+    },
+    /// The bytes read as a string weren't valid UTF-8.
+    InvalidUtf8(str::Utf8Error),
+    /// No NUL terminator was found within the maximum number of bytes allowed
+    /// to be scanned.
+    MissingNul {
+        /// The offset the string started at.
+        offset: usize,
+        /// The maximum number of bytes that were scanned.
+        max_len: usize,
+    },
+    /// The memory is already borrowed by [`Memory::with_slice_mut`], or by
+    /// [`Memory::with_slice`] for a mutable access.
+    AlreadyBorrowed,
+}
+
+impl fmt::Display for MemoryAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MemoryAccessError::Overflow { offset, len } => write!(
+                f,
+                "memory access of {} bytes at offset {:#x} overflows",
+                len, offset
+            ),
+            MemoryAccessError::OutOfBounds {
+                offset,
+                len,
+                memory_size,
+            } => write!(
+                f,
+                "out of bounds memory access of {} bytes at offset {:#x} (memory size is {:#x} bytes)",
+                len, offset, memory_size
+            ),
+            MemoryAccessError::Unaligned { offset, align } => write!(
+                f,
+                "unaligned memory access at offset {:#x}, expected alignment of {}",
+                offset, align
+            ),
+            MemoryAccessError::InvalidUtf8(_) => write!(f, "string is not valid utf-8"),
+            MemoryAccessError::MissingNul { offset, max_len } => write!(
+                f,
+                "string at offset {:#x} is not NUL-terminated within {} bytes",
+                offset, max_len
+            ),
+            MemoryAccessError::AlreadyBorrowed => write!(f, "memory is already borrowed"),
+        }
+    }
+}
+
+impl std::error::Error for MemoryAccessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MemoryAccessError::InvalidUtf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Types which can be read from and written to wasm linear memory as plain
+/// old data.
+///
+/// Values are always stored in memory in little-endian byte order, as
+/// WebAssembly itself does, regardless of the host's endianness.
+///
+/// # Safety
+///
+/// Implementors must be valid for any bit pattern of `size_of::<Self>()`
+/// bytes and contain no padding. This trait is implemented for all of the
+/// primitive integer and floating point types and shouldn't typically need to
+/// be implemented elsewhere.
+pub unsafe trait Pod: Copy + Send + Sync + 'static {
+    /// Decodes a value from `bytes` in little-endian byte order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes.len()` isn't `size_of::<Self>()`.
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+
+    /// Encodes this value into `bytes` in little-endian byte order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes.len()` isn't `size_of::<Self>()`.
+    fn write_le_bytes(self, bytes: &mut [u8]);
+}
+
+macro_rules! pod {
+    ($($t:ident)*) => ($(
+        unsafe impl Pod for $t {
+            fn from_le_bytes(bytes: &[u8]) -> $t {
+                <$t>::from_le_bytes(bytes.try_into().unwrap())
+            }
+
+            fn write_le_bytes(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*)
+}
+
+pod!(u8 i8 u16 i16 u32 i32 u64 i64 f32 f64);
+
+/// A typed pointer into wasm linear memory.
+///
+/// A `WasmPtr<T>` is just a 32-bit offset into a [`Memory`], the same as a
+/// pointer in a guest's address space, paired with the type it points to.
+/// Reads and writes through it are bounds-checked and alignment-checked
+/// against the memory they're performed on, and values are encoded in
+/// little-endian byte order.
+///
+/// # Examples
+///
+/// ```
+/// # use wasmtime::*;
+/// # fn main() -> anyhow::Result<()> {
+/// let store = Store::default();
+/// let memory = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+///
+/// let ptr = WasmPtr::<u32>::new(8);
+/// ptr.write(&memory, 0xdead_beef)?;
+/// assert_eq!(ptr.read(&memory)?, 0xdead_beef);
+///
+/// let next = ptr.add(1)?;
+/// assert_eq!(next.offset(), 12);
+/// assert_eq!(next.read(&memory)?, 0);
+/// # Ok(())
+/// # }
+/// ```
+pub struct WasmPtr<T> {
+    offset: u32,
+    _marker: marker::PhantomData<fn() -> T>,
+}
+
+impl<T: Pod> WasmPtr<T> {
+    /// Creates a new pointer to a `T` located at `offset` in linear memory.
+    pub fn new(offset: u32) -> WasmPtr<T> {
+        WasmPtr {
+            offset,
+            _marker: marker::PhantomData,
+        }
+    }
+
+    /// Returns the offset in linear memory that this pointer points to.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns a pointer to the `count`-th `T` after this one, as if this
+    /// pointer pointed to an array of `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the resulting offset doesn't fit in 32 bits.
+    pub fn add(&self, count: u32) -> Result<WasmPtr<T>, MemoryAccessError> {
+        let overflow = || MemoryAccessError::Overflow {
+            offset: self.offset as usize,
+            len: (count as usize).saturating_mul(mem::size_of::<T>()),
+        };
+        let len = count
+            .checked_mul(mem::size_of::<T>() as u32)
+            .ok_or_else(overflow)?;
+        let offset = self.offset.checked_add(len).ok_or_else(overflow)?;
+        Ok(WasmPtr::new(offset))
+    }
+
+    /// Reads the `T` this pointer points to out of `memory`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pointer isn't suitably aligned for `T` or if
+    /// any byte of the value lies outside of `memory`.
+    pub fn read(&self, memory: &Memory) -> Result<T, MemoryAccessError> {
+        let range = self.range(memory)?;
+        memory.with_slice(range.start, range.len(), T::from_le_bytes)
+    }
+
+    /// Writes `val` into `memory` at the location this pointer points to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pointer isn't suitably aligned for `T` or if
+    /// any byte of the value lies outside of `memory`. Nothing is written if
+    /// an error is returned.
+    pub fn write(&self, memory: &Memory, val: T) -> Result<(), MemoryAccessError> {
+        let range = self.range(memory)?;
+        memory.with_slice_mut(range.start, range.len(), |bytes| val.write_le_bytes(bytes))
+    }
+
+    fn range(&self, memory: &Memory) -> Result<Range<usize>, MemoryAccessError> {
+        let offset = self.offset as usize;
+        let align = mem::align_of::<T>();
+        if offset % align != 0 {
+            return Err(MemoryAccessError::Unaligned { offset, align });
+        }
+        memory.checked_range(offset, mem::size_of::<T>())
+    }
+}
+
+impl<T> Clone for WasmPtr<T> {
+    fn clone(&self) -> WasmPtr<T> {
+        *self
+    }
+}
+
+impl<T> Copy for WasmPtr<T> {}
+
+impl<T> PartialEq for WasmPtr<T> {
+    fn eq(&self, other: &WasmPtr<T>) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T> Eq for WasmPtr<T> {}
+
+impl<T> fmt::Debug for WasmPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WasmPtr<{}>({:#x})",
+            std::any::type_name::<T>(),
+            self.offset
+        )
+    }
+}
+
+impl Memory {
+    /// Calls `f` with a view of the `len` bytes of this memory starting at
+    /// `offset`, without copying them.
+    ///
+    /// The range is bounds-checked once, before `f` is called. While `f` runs
+    /// this memory can't be resized or freed, and no wasm can run in its
+    /// store: [`Memory::grow`] on this memory fails, as does calling any wasm
+    /// function. Nested `with_slice` calls are fine, but accessing this
+    /// memory mutably from within `f` fails with
+    /// [`MemoryAccessError::AlreadyBorrowed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without calling `f`, if the range doesn't lie
+    /// entirely within this memory or if this memory is mutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let memory = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    /// memory.write_slice(8, b"hello")?;
+    /// let sum = memory.with_slice(8, 5, |bytes| {
+    ///     // Neither this memory nor wasm can be touched in here.
+    ///     assert!(memory.grow(1).is_err());
+    ///     bytes.iter().map(|b| *b as u32).sum::<u32>()
+    /// })?;
+    /// assert_eq!(sum, 532);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_slice<R>(
+        &self,
+        offset: usize,
+        len: usize,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> Result<R, MemoryAccessError> {
+        let range = self.checked_range(offset, len)?;
+        let _borrow = MemoryBorrow::new(self, false)?;
+        Ok(f(unsafe { &self.data_unchecked()[range] }))
+    }
+
+    /// Calls `f` with a mutable view of the `len` bytes of this memory
+    /// starting at `offset`, without copying them.
+    ///
+    /// This behaves like [`Memory::with_slice`], except that any other access
+    /// to this memory from within `f`, including through `with_slice`, fails
+    /// with [`MemoryAccessError::AlreadyBorrowed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without calling `f`, if the range doesn't lie
+    /// entirely within this memory or if this memory is already borrowed.
+    pub fn with_slice_mut<R>(
+        &self,
+        offset: usize,
+        len: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R, MemoryAccessError> {
+        let range = self.checked_range(offset, len)?;
+        let _borrow = MemoryBorrow::new(self, true)?;
+        Ok(f(unsafe { &mut self.data_unchecked_mut()[range] }))
+    }
+
+    /// Returns a view of the `len` bytes of this memory starting at `offset`,
+    /// without copying them.
+    ///
+    /// This is the unchecked counterpart of [`Memory::with_slice`], for
+    /// embedders who can't structure their code as a callback. The range is
+    /// bounds-checked, and this fails if the memory is mutably borrowed by
+    /// [`Memory::with_slice_mut`], but nothing is borrowed for the lifetime of
+    /// the returned slice.
+    ///
+    /// # Safety
+    ///
+    /// For as long as the returned slice is alive, the caller must ensure
+    /// that:
+    ///
+    /// * this memory isn't grown, by [`Memory::grow`] or by wasm, since
+    ///   growing may move it;
+    /// * no wasm which can write to this memory runs, in this store or, for
+    ///   a shared memory, on any other thread;
+    /// * the bytes aren't written through any other view of this memory, for
+    ///   instance one from [`Memory::data_mut`],
+    ///   [`Memory::data_unchecked_mut`] or [`Memory::with_slice_mut`].
+    ///
+    /// See the documentation of [`Memory`] for more about these hazards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range doesn't lie entirely within this memory
+    /// or if this memory is mutably borrowed.
+    pub unsafe fn data(&self, offset: usize, len: usize) -> Result<&[u8], MemoryAccessError> {
+        let range = self.checked_range(offset, len)?;
+        // Only checks for conflicting borrows, the borrow ends right away.
+        MemoryBorrow::new(self, false)?;
+        Ok(&self.data_unchecked()[range])
+    }
+
+    /// Returns a mutable view of the `len` bytes of this memory starting at
+    /// `offset`, without copying them.
+    ///
+    /// This is the unchecked counterpart of [`Memory::with_slice_mut`], and
+    /// behaves like [`Memory::data`] except that it also fails if the memory
+    /// is borrowed by [`Memory::with_slice`].
+    ///
+    /// # Safety
+    ///
+    /// The requirements of [`Memory::data`] apply, and additionally, for as
+    /// long as the returned slice is alive, the caller must ensure that the
+    /// bytes aren't accessed at all through any other view of this memory,
+    /// including by wasm and by other calls to this method. Nothing stops
+    /// this method from being called twice for overlapping ranges.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range doesn't lie entirely within this memory
+    /// or if this memory is already borrowed.
+    pub unsafe fn data_mut(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<&mut [u8], MemoryAccessError> {
+        let range = self.checked_range(offset, len)?;
+        // Only checks for conflicting borrows, the borrow ends right away.
+        MemoryBorrow::new(self, true)?;
+        Ok(&mut self.data_unchecked_mut()[range])
+    }
+
+    /// Calls `f` with a view of the `count` values of type `T` in this memory
+    /// starting at `offset`, without copying them where possible.
+    ///
+    /// This is the typed counterpart of [`Memory::with_slice`], with the same
+    /// restrictions on what can happen while `f` runs. Values are read in
+    /// little-endian byte order; on big-endian hosts, or if the memory's
+    /// storage isn't aligned for `T`, they're copied out first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, without calling `f`, if `offset` isn't a multiple of
+    /// the alignment of `T`, if the values don't lie entirely within this
+    /// memory, or if this memory is mutably borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let memory = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    /// memory.write_slice(8, &[1, 0, 0, 0, 2, 0, 0, 0])?;
+    /// let sum = memory.with_typed_slice(8, 2, |values: &[u32]| values.iter().sum::<u32>())?;
+    /// assert_eq!(sum, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_typed_slice<T: Pod, R>(
+        &self,
+        offset: usize,
+        count: usize,
+        f: impl FnOnce(&[T]) -> R,
+    ) -> Result<R, MemoryAccessError> {
+        let align = mem::align_of::<T>();
+        if offset % align != 0 {
+            return Err(MemoryAccessError::Unaligned { offset, align });
+        }
+        let len = count.saturating_mul(mem::size_of::<T>());
+        self.with_slice(offset, len, |bytes| {
+            if cfg!(target_endian = "little") && bytes.as_ptr() as usize % align == 0 {
+                // `Pod` types are valid for any bit pattern and are stored in
+                // the host's byte order here, and the pointer was just checked
+                // to be aligned.
+                f(unsafe { slice::from_raw_parts(bytes.as_ptr() as *const T, count) })
+            } else {
+                let values = bytes
+                    .chunks_exact(mem::size_of::<T>())
+                    .map(T::from_le_bytes)
+                    .collect::<Vec<_>>();
+                f(&values)
+            }
+        })
+    }
+
+    /// Copies the `count` values of type `T` in this memory starting at
+    /// `offset` out into a `Vec`, for example an array of numbers returned by
+    /// a guest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `offset` isn't a multiple of the alignment of `T`
+    /// or if the values don't lie entirely within this memory.
+    pub fn read_slice<T: Pod>(
+        &self,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<T>, MemoryAccessError> {
+        self.with_typed_slice(offset, count, |values: &[T]| values.to_vec())
+    }
+
+    /// Copies `buffer.len()` bytes out of this memory, starting at `offset`,
+    /// into `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range `offset..offset + buffer.len()` doesn't
+    /// lie entirely within this memory, in which case `buffer` is left
+    /// untouched.
+    pub fn read(&self, offset: usize, buffer: &mut [u8]) -> Result<(), MemoryAccessError> {
+        self.with_slice(offset, buffer.len(), |bytes| buffer.copy_from_slice(bytes))
+    }
+
+    /// Copies all of `data` into this memory, starting at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range `offset..offset + data.len()` doesn't lie
+    /// entirely within this memory, in which case the memory is left
+    /// untouched.
+    pub fn write_slice(&self, offset: usize, data: &[u8]) -> Result<(), MemoryAccessError> {
+        self.with_slice_mut(offset, data.len(), |bytes| bytes.copy_from_slice(data))
+    }
+
+    /// Reads the `len` bytes of this memory starting at `offset` as a UTF-8
+    /// string, for example a `(ptr, len)` pair passed from a guest.
+    ///
+    /// The string is copied out of the memory, so it remains valid regardless
+    /// of what happens to the memory afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the range is out of bounds or if the bytes aren't
+    /// valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let module = Module::new(
+    ///     store.engine(),
+    ///     r#"(module (memory (export "memory") 1) (data (i32.const 16) "hello"))"#,
+    /// )?;
+    /// let instance = Instance::new(&store, &module, &[])?;
+    /// let memory = instance.get_memory("memory").unwrap();
+    /// assert_eq!(memory.read_string(16, 5)?, "hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_string(&self, offset: usize, len: usize) -> Result<String, MemoryAccessError> {
+        self.with_slice(offset, len, |bytes| match str::from_utf8(bytes) {
+            Ok(s) => Ok(s.to_string()),
+            Err(e) => Err(MemoryAccessError::InvalidUtf8(e)),
+        })?
+    }
+
+    /// Reads a NUL-terminated UTF-8 string, such as a C string passed from a
+    /// guest, starting at `offset`.
+    ///
+    /// At most `max_len` bytes, not including the terminator, are scanned for
+    /// the NUL terminator. The returned string doesn't include the terminator.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `offset` is out of bounds, if the end of the memory
+    /// is reached before a terminator is found, if no terminator is found
+    /// within `max_len` bytes, or if the string isn't valid UTF-8.
+    pub fn read_nul_terminated(
+        &self,
+        offset: usize,
+        max_len: usize,
+    ) -> Result<String, MemoryAccessError> {
+        let size = self.data_size();
+        if offset >= size {
+            return Err(MemoryAccessError::OutOfBounds {
+                offset,
+                len: 1,
+                memory_size: size,
+            });
+        }
+        // Scan one byte beyond `max_len` so a terminator immediately after
+        // `max_len` bytes of string is accepted.
+        let scan_len = max_len.saturating_add(1).min(size - offset);
+        self.with_slice(offset, scan_len, |bytes| {
+            let len = match bytes.iter().position(|b| *b == 0) {
+                Some(len) => len,
+                None if scan_len <= max_len => {
+                    return Err(MemoryAccessError::OutOfBounds {
+                        offset,
+                        len: scan_len + 1,
+                        memory_size: size,
+                    })
+                }
+                None => return Err(MemoryAccessError::MissingNul { offset, max_len }),
+            };
+            match str::from_utf8(&bytes[..len]) {
+                Ok(s) => Ok(s.to_string()),
+                Err(e) => Err(MemoryAccessError::InvalidUtf8(e)),
+            }
+        })?
+    }
+
+    pub(crate) fn checked_range(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<Range<usize>, MemoryAccessError> {
+        let end = offset
+            .checked_add(len)
+            .ok_or(MemoryAccessError::Overflow { offset, len })?;
+        let memory_size = self.data_size();
+        if end > memory_size {
+            return Err(MemoryAccessError::OutOfBounds {
+                offset,
+                len,
+                memory_size,
+            });
+        }
+        Ok(offset..end)
+    }
+}
+
+/// Records a borrow of a memory in its store for the duration of a
+/// `Memory::with_slice{,_mut}` callback.
+struct MemoryBorrow<'a> {
+    memory: &'a Memory,
+}
+
+impl<'a> MemoryBorrow<'a> {
+    fn new(memory: &'a Memory, mutable: bool) -> Result<Self, MemoryAccessError> {
+        let mut borrows = memory.store().memory_borrows().borrow_mut();
+        let id = memory.id();
+        let state = borrows.get(&id).copied().unwrap_or(0);
+        if state < 0 || (mutable && state > 0) {
+            return Err(MemoryAccessError::AlreadyBorrowed);
+        }
+        borrows.insert(id, if mutable { -1 } else { state + 1 });
+        let outstanding = memory.store().outstanding_memory_borrows();
+        outstanding.set(outstanding.get() + 1);
+        Ok(MemoryBorrow { memory })
+    }
+}
+
+impl Drop for MemoryBorrow<'_> {
+    fn drop(&mut self) {
+        let mut borrows = self.memory.store().memory_borrows().borrow_mut();
+        let id = self.memory.id();
+        match borrows.get_mut(&id) {
+            Some(state) if *state > 1 => *state -= 1,
+            _ => {
+                borrows.remove(&id);
+            }
+        }
+        let outstanding = self.memory.store().outstanding_memory_borrows();
+        outstanding.set(outstanding.get() - 1);
+    }
+}
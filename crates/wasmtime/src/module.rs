@@ -1,13 +1,20 @@
 use crate::types::{EntityType, ExportType, ExternType, ImportType};
-use crate::Engine;
+use crate::{CallGraph, Engine, EngineDiagnostics, FeatureMask};
 use anyhow::{bail, Context, Result};
 use bincode::Options;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
 use std::hash::Hash;
 use std::path::Path;
 use std::sync::Arc;
-use wasmparser::Validator;
+use wasmparser::{
+    BinaryReaderError, ImportSectionEntryType, Operator, Parser, Payload, ValidPayload, Validator,
+    WasmFeatures,
+};
 #[cfg(feature = "cache")]
 use wasmtime_cache::ModuleCacheEntry;
+use wasmtime_environ::wasm::EntityIndex;
 use wasmtime_jit::{CompilationArtifacts, CompiledModule};
 
 /// A compiled WebAssembly module, ready to be instantiated.
@@ -239,18 +246,30 @@ impl Module {
     /// # }
     /// ```
     pub fn from_binary(engine: &Engine, binary: &[u8]) -> Result<Module> {
+        check_forbidden_features(binary, engine.config().forbidden_features)?;
+
         #[cfg(feature = "cache")]
         let artifacts = ModuleCacheEntry::new("wasmtime", engine.cache_config())
             .get_data((engine.compiler(), binary), |(compiler, binary)| {
                 CompilationArtifacts::build(compiler, binary)
-            })?;
+            });
         #[cfg(not(feature = "cache"))]
-        let artifacts = CompilationArtifacts::build(engine.compiler(), binary)?;
+        let artifacts = CompilationArtifacts::build(engine.compiler(), binary);
+        let artifacts = match artifacts {
+            Ok(artifacts) => artifacts,
+            // Compilation only says what went wrong, so if that's because
+            // the module is invalid validate it again to find out where.
+            Err(e) => {
+                validate_binary(binary, engine.config().features)?;
+                return Err(e.into());
+            }
+        };
 
         let compiled = CompiledModule::from_artifacts_list(
             artifacts,
             engine.compiler().isa(),
             &*engine.config().profiler,
+            engine.config().secure_teardown,
         )?;
 
         Ok(Module {
@@ -270,26 +289,60 @@ impl Module {
     /// configuration for WebAssembly features, for example, which are used to
     /// indicate what should be valid and what shouldn't be.
     ///
-    /// Validation automatically happens as part of [`Module::new`].
+    /// Validation automatically happens as part of [`Module::new`], but no
+    /// code is compiled here, so this is a cheap way to check a module ahead
+    /// of time.
     ///
     /// # Errors
     ///
     /// If validation fails for any reason (type check error, usage of a feature
-    /// that wasn't enabled, etc) then an error with a description of the
-    /// validation issue will be returned.
+    /// that wasn't enabled, an instruction forbidden with
+    /// [`Config::forbid_features`](crate::Config::forbid_features), etc) then
+    /// an error will be returned. It can be downcast to a [`ValidationError`]
+    /// describing the issue and where in `binary` it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let engine = Engine::default();
+    /// let wasm = wat::parse_str("(module (func (result i32) local.get 3))")?;
+    /// let err = Module::validate(&engine, &wasm).unwrap_err();
+    /// let err = err.downcast_ref::<ValidationError>().unwrap();
+    /// assert_eq!(err.func_index(), Some(0));
+    /// assert!(err.to_string().starts_with("func 0: "));
+    /// # Ok(())
+    /// # }
+    /// ```
     ///
     /// [binary]: https://webassembly.github.io/spec/core/binary/index.html
     pub fn validate(engine: &Engine, binary: &[u8]) -> Result<()> {
-        let mut validator = Validator::new();
-        validator.wasm_features(engine.config().features);
-        validator.validate_all(binary)?;
+        validate_binary(binary, engine.config().features)?;
+        check_forbidden_features(binary, engine.config().forbidden_features)?;
         Ok(())
     }
 
     /// Serialize compilation artifacts to the buffer. See also `deseriaize`.
+    ///
+    /// The output is reproducible: the same wasm compiled with the same
+    /// wasmtime version and the same [`Config`](crate::Config) serializes to
+    /// the same bytes, across processes and machines, no matter whether
+    /// compilation happened in parallel. Nothing like timestamps or paths is
+    /// embedded. Everything that determines the generated code has to be the
+    /// same for this to hold though, and one thing which is easy to miss is
+    /// that by default Cranelift targets the CPU features detected on the
+    /// host. Pin those with [`Config::cranelift_other_flag`] (e.g. `has_avx`)
+    /// when comparing artifacts built on different machines. DWARF passed
+    /// through with [`Config::debug_info`] is taken from the wasm as-is, so
+    /// any paths the producer recorded there end up in the artifact too.
+    ///
+    /// [`Config::cranelift_other_flag`]: crate::Config::cranelift_other_flag
+    /// [`Config::debug_info`]: crate::Config::debug_info
     pub fn serialize(&self) -> Result<Vec<u8>> {
         let artifacts = (
             compiler_fingerprint(&self.engine),
+            recorded_diagnostics(&self.engine),
             self.compiled
                 .iter()
                 .map(|i| i.compilation_artifacts())
@@ -301,29 +354,56 @@ impl Module {
         Ok(buffer)
     }
 
+    /// Returns the SHA-256 hash of this module's [serialized](Module::serialize)
+    /// form.
+    ///
+    /// Since serialization is reproducible this can be used to check that a
+    /// distributed artifact matches one compiled from source.
+    pub fn artifact_hash(&self) -> [u8; 32] {
+        let buffer = self
+            .serialize()
+            .expect("serializing compilation artifacts to memory cannot fail");
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&Sha256::digest(&buffer));
+        hash
+    }
+
     /// Deserializes and creates a module from the compilation artifacts.
     /// The `serialize` saves the compilation artifacts along with the host
     /// fingerprint, which consists of target, compiler flags, and wasmtime
     /// package version.
     ///
     /// The method will fail if fingerprints of current host and serialized
-    /// one are different. The method does not verify the serialized artifacts
-    /// for modifications or curruptions. All responsibily of signing and its
-    /// verification falls on the embedder.
+    /// one are different, with an error listing the settings which differ
+    /// between the [diagnostics](Engine::diagnostics) recorded in the
+    /// artifact and `engine`'s. The method does not verify the serialized
+    /// artifacts for modifications or curruptions. All responsibily of signing
+    /// and its verification falls on the embedder.
     pub fn deserialize(engine: &Engine, serialized: &[u8]) -> Result<Module> {
         let expected_fingerprint = compiler_fingerprint(engine);
 
-        let (fingerprint, artifacts, index) = bincode_options()
-            .deserialize::<(u64, _, _)>(serialized)
+        let (fingerprint, diagnostics, artifacts, index) = bincode_options()
+            .deserialize::<(u64, EngineDiagnostics, _, _)>(serialized)
             .context("Deserialize compilation artifacts")?;
         if fingerprint != expected_fingerprint {
-            bail!("Incompatible compilation artifact");
+            let mut message = String::from("Incompatible compilation artifact");
+            let differences = diagnostics.differences(&recorded_diagnostics(engine));
+            if differences.is_empty() {
+                message.push_str(", though no recorded setting differs");
+            } else {
+                message.push_str(", settings differ (artifact != host):");
+                for difference in differences {
+                    message.push_str(&format!("\n  {}", difference));
+                }
+            }
+            bail!(message);
         }
 
         let compiled = CompiledModule::from_artifacts_list(
             artifacts,
             engine.compiler().isa(),
             &*engine.config().profiler,
+            engine.config().secure_teardown,
         )?;
 
         Ok(Module {
@@ -427,6 +507,47 @@ impl Module {
             })
     }
 
+    /// Returns the WASI functions this [`Module`] imports, as `(module, name)`
+    /// pairs in the order they're first imported.
+    ///
+    /// These are the function imports from the `wasi_snapshot_preview1` and
+    /// `wasi_unstable` modules, the WASI snapshots that `wasmtime-wasi`
+    /// implements. This is the exact set of WASI functions an instance of the
+    /// module could call, so it can be used to audit a module's capabilities
+    /// before deciding whether to instantiate it, or to set up WASI at all.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let engine = Engine::default();
+    /// let wat = r#"
+    ///     (module
+    ///         (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+    ///         (import "env" "foo" (func))
+    ///     )
+    /// "#;
+    /// let module = Module::new(&engine, wat)?;
+    /// assert_eq!(module.wasi_imports(), [("wasi_snapshot_preview1", "proc_exit")]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wasi_imports(&self) -> Vec<(&str, &str)> {
+        let mut imports = Vec::new();
+        for (module, name, index) in self.compiled_module().module().imports.iter() {
+            let pair = (module.as_str(), name.as_str());
+            if let EntityIndex::Function(_) = index {
+                if (pair.0 == "wasi_snapshot_preview1" || pair.0 == "wasi_unstable")
+                    && !imports.contains(&pair)
+                {
+                    imports.push(pair);
+                }
+            }
+        }
+        imports
+    }
+
     /// Returns the list of exports that this [`Module`] has and will be
     /// available after instantiation.
     ///
@@ -540,12 +661,374 @@ impl Module {
         Some(EntityType::new(entity_index, module).extern_type())
     }
 
+    /// Returns the indices of defined functions in this [`Module`] which can
+    /// never be executed.
+    ///
+    /// A function is considered reachable if it's exported, is the start
+    /// function, appears in an element segment (and thus may be the target of
+    /// a `call_indirect`), or is referenced via `call` or `ref.func` from
+    /// another reachable function. Indices are in the module's function index
+    /// space, which includes imported functions, and are sorted in ascending
+    /// order.
+    ///
+    /// This list is only computed when
+    /// [`Config::report_unused_functions`](crate::Config::report_unused_functions)
+    /// is enabled, otherwise it's always empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut config = Config::new();
+    /// config.report_unused_functions(true);
+    /// let engine = Engine::new(&config);
+    /// let wat = r#"
+    ///     (module
+    ///         (func (export "foo") call 1)
+    ///         (func)
+    ///         (func)
+    ///     )
+    /// "#;
+    /// let module = Module::new(&engine, wat)?;
+    /// assert_eq!(module.unused_functions(), &[2]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unused_functions(&self) -> &[u32] {
+        &self.compiled_module().module().unused_functions
+    }
+
+    /// Returns which functions of this [`Module`] may call which others,
+    /// which can tell which functions an export needs.
+    ///
+    /// See [`CallGraph`] for how indirect calls are accounted for.
+    ///
+    /// This returns `None` unless
+    /// [`Config::record_call_graph`](crate::Config::record_call_graph) was
+    /// enabled when the module was compiled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut config = Config::new();
+    /// config.record_call_graph(true);
+    /// let engine = Engine::new(&config);
+    /// let wat = r#"
+    ///     (module
+    ///         (func (export "foo") call 1)
+    ///         (func call 2)
+    ///         (func)
+    ///         (func (export "bar"))
+    ///     )
+    /// "#;
+    /// let module = Module::new(&engine, wat)?;
+    /// let graph = module.call_graph().unwrap();
+    /// assert_eq!(graph.reachable_from(0), [0, 1, 2].iter().copied().collect());
+    /// assert_eq!(graph.reachable_from(3), [3].iter().copied().collect());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_graph(&self) -> Option<CallGraph> {
+        CallGraph::new(self.compiled_module().module())
+    }
+
+    /// Checks whether this [`Module`] can stand in for `other` without
+    /// changing how it's linked or used.
+    ///
+    /// The modules are compatible if both export exactly the same names with
+    /// the same [`ExternType`]s, and if every import of this module is also
+    /// imported by `other` with the same type. That is, this module may import
+    /// less than `other` but never anything more, so whatever satisfied
+    /// `other`'s imports will satisfy this module's as well.
+    ///
+    /// This is useful, for example, to validate a new version of a module
+    /// before replacing an older version of it in a running system.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AbiDiff`] listing each incompatibility found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// # let engine = Engine::default();
+    /// let v1 = Module::new(&engine, r#"(module (func (export "run") (param i32)))"#)?;
+    /// let v2 = Module::new(&engine, r#"(module (func (export "run") (param i64)))"#)?;
+    /// assert!(v1.abi_compatible_with(&v1).is_ok());
+    ///
+    /// let diff = v2.abi_compatible_with(&v1).unwrap_err();
+    /// match &diff.changes()[0] {
+    ///     AbiChange::ExportType { name, .. } => assert_eq!(name, "run"),
+    ///     other => panic!("unexpected change {:?}", other),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn abi_compatible_with(&self, other: &Module) -> Result<(), AbiDiff> {
+        let mut changes = Vec::new();
+
+        let mut other_exports = other
+            .exports()
+            .map(|e| (e.name(), e.ty()))
+            .collect::<HashMap<_, _>>();
+        for export in self.exports() {
+            let found = export.ty();
+            match other_exports.remove(export.name()) {
+                Some(expected) if expected == found => {}
+                Some(expected) => changes.push(AbiChange::ExportType {
+                    name: export.name().to_string(),
+                    expected,
+                    found,
+                }),
+                None => changes.push(AbiChange::ExtraExport {
+                    name: export.name().to_string(),
+                    ty: found,
+                }),
+            }
+        }
+        // Report removed exports in `other`'s order to keep the diff stable.
+        for export in other.exports() {
+            if let Some(ty) = other_exports.remove(export.name()) {
+                changes.push(AbiChange::MissingExport {
+                    name: export.name().to_string(),
+                    ty,
+                });
+            }
+        }
+
+        let other_imports = other
+            .imports()
+            .map(|i| ((i.module(), i.name()), i.ty()))
+            .collect::<HashMap<_, _>>();
+        for import in self.imports() {
+            let found = import.ty();
+            match other_imports.get(&(import.module(), import.name())) {
+                Some(expected) if *expected == found => {}
+                Some(expected) => changes.push(AbiChange::ImportType {
+                    module: import.module().to_string(),
+                    name: import.name().to_string(),
+                    expected: expected.clone(),
+                    found,
+                }),
+                None => changes.push(AbiChange::ExtraImport {
+                    module: import.module().to_string(),
+                    name: import.name().to_string(),
+                    ty: found,
+                }),
+            }
+        }
+
+        if changes.is_empty() {
+            Ok(())
+        } else {
+            Err(AbiDiff { changes })
+        }
+    }
+
     /// Returns the [`Engine`] that this [`Module`] was compiled by.
     pub fn engine(&self) -> &Engine {
         &self.engine
     }
 }
 
+/// The incompatibilities between two modules, as reported by
+/// [`Module::abi_compatible_with`].
+#[derive(Debug, Clone)]
+pub struct AbiDiff {
+    changes: Vec<AbiChange>,
+}
+
+impl AbiDiff {
+    /// Returns every incompatibility that was found.
+    ///
+    /// Exports are listed before imports, and this is never empty.
+    pub fn changes(&self) -> &[AbiChange] {
+        &self.changes
+    }
+}
+
+impl fmt::Display for AbiDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "modules are not ABI compatible:")?;
+        for change in self.changes.iter() {
+            write!(f, "\n  {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AbiDiff {}
+
+/// A single incompatibility in an [`AbiDiff`].
+///
+/// Here "expected" refers to the module passed to
+/// [`Module::abi_compatible_with`] and "found" to the module it was called on.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiChange {
+    /// An export of the expected module is missing.
+    MissingExport {
+        /// The name of the export.
+        name: String,
+        /// The type of the export in the expected module.
+        ty: ExternType,
+    },
+    /// An export isn't present in the expected module.
+    ExtraExport {
+        /// The name of the export.
+        name: String,
+        /// The type of the export.
+        ty: ExternType,
+    },
+    /// An export is present in both modules but with different types.
+    ExportType {
+        /// The name of the export.
+        name: String,
+        /// The type of the export in the expected module.
+        expected: ExternType,
+        /// The type of the export in the module being checked.
+        found: ExternType,
+    },
+    /// An import isn't imported by the expected module.
+    ExtraImport {
+        /// The module name of the import.
+        module: String,
+        /// The field name of the import.
+        name: String,
+        /// The type of the import.
+        ty: ExternType,
+    },
+    /// An import is present in both modules but with different types.
+    ImportType {
+        /// The module name of the import.
+        module: String,
+        /// The field name of the import.
+        name: String,
+        /// The type of the import in the expected module.
+        expected: ExternType,
+        /// The type of the import in the module being checked.
+        found: ExternType,
+    },
+}
+
+impl fmt::Display for AbiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiChange::MissingExport { name, .. } => write!(f, "missing export `{}`", name),
+            AbiChange::ExtraExport { name, .. } => write!(f, "unexpected export `{}`", name),
+            AbiChange::ExportType {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "export `{}` has type {:?}, expected {:?}",
+                name, found, expected
+            ),
+            AbiChange::ExtraImport { module, name, .. } => {
+                write!(f, "unexpected import `{}::{}`", module, name)
+            }
+            AbiChange::ImportType {
+                module,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "import `{}::{}` has type {:?}, expected {:?}",
+                module, name, found, expected
+            ),
+        }
+    }
+}
+
+/// Why, and where, a WebAssembly binary failed validation, as reported by
+/// [`Module::validate`] and [`Module::new`].
+#[derive(Debug, Clone)]
+pub struct ValidationError {
+    message: String,
+    offset: usize,
+    section: Option<&'static str>,
+    func: Option<(u32, usize)>,
+}
+
+impl ValidationError {
+    fn new(error: BinaryReaderError, section: Option<&'static str>) -> ValidationError {
+        ValidationError {
+            message: error.message().to_string(),
+            offset: error.offset(),
+            section,
+            func: None,
+        }
+    }
+
+    fn in_func(
+        message: String,
+        offset: usize,
+        func_index: u32,
+        body_offset: usize,
+    ) -> ValidationError {
+        ValidationError {
+            message,
+            offset,
+            section: Some("code"),
+            func: Some((func_index, offset.saturating_sub(body_offset))),
+        }
+    }
+
+    /// Returns a description of what's wrong, without its location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the byte offset into the binary at which the error was found.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the name of the section which was being parsed, such as
+    /// `"import"` or `"code"`, if the error was found in one.
+    pub fn section(&self) -> Option<&str> {
+        self.section
+    }
+
+    /// Returns the index, in the function index space, of the function whose
+    /// body the error was found in, if any.
+    pub fn func_index(&self) -> Option<u32> {
+        self.func.map(|(index, _)| index)
+    }
+
+    /// Returns the byte offset of the error from the start of the body of the
+    /// function it was found in, if any.
+    pub fn func_offset(&self) -> Option<usize> {
+        self.func.map(|(_, offset)| offset)
+    }
+}
+
+impl From<BinaryReaderError> for ValidationError {
+    fn from(error: BinaryReaderError) -> ValidationError {
+        ValidationError::new(error, None)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.func, self.section) {
+            (Some((index, _)), _) => write!(f, "func {}: ", index)?,
+            (None, Some(section)) => write!(f, "{} section: ", section)?,
+            (None, None) => {}
+        }
+        write!(f, "{} at offset {:#x}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
 fn bincode_options() -> impl Options {
     // Use a variable-length integer encoding instead of fixed length. The
     // module shown on #2318 gets compressed from ~160MB to ~110MB simply using
@@ -557,6 +1040,10 @@ fn bincode_options() -> impl Options {
     bincode::DefaultOptions::new().with_varint_encoding()
 }
 
+fn recorded_diagnostics(engine: &Engine) -> EngineDiagnostics {
+    engine.diagnostics().without_host_details()
+}
+
 fn compiler_fingerprint(engine: &Engine) -> u64 {
     use std::hash::Hasher;
     let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -564,6 +1051,297 @@ fn compiler_fingerprint(engine: &Engine) -> u64 {
     hasher.finish()
 }
 
+/// Validates `binary` with `features` enabled, locating the first error.
+fn validate_binary(binary: &[u8], features: WasmFeatures) -> Result<(), ValidationError> {
+    let mut validator = Validator::new();
+    validator.wasm_features(features);
+
+    let mut funcs = FuncIndices::default();
+    let mut section = None;
+    for payload in Parser::new(0).parse_all(binary) {
+        let payload = payload.map_err(|e| ValidationError::new(e, section))?;
+        section = section_name(&payload).or(section);
+        let valid = validator
+            .payload(&payload)
+            .map_err(|e| ValidationError::new(e, section))?;
+        if let ValidPayload::Func(mut func, body) = valid {
+            let func_index = funcs.next_defined();
+            let body_offset = body.get_binary_reader().original_position();
+            func.validate(&body).map_err(|e| {
+                ValidationError::in_func(
+                    e.message().to_string(),
+                    e.offset(),
+                    func_index,
+                    body_offset,
+                )
+            })?;
+        }
+        funcs.update(payload)?;
+    }
+    Ok(())
+}
+
+/// Works out the index of each function body while a module is parsed.
+///
+/// Function indices count imported functions first. Nested modules have their
+/// own index spaces, so the counts of enclosing modules are stashed until
+/// their nested modules end.
+#[derive(Default)]
+struct FuncIndices {
+    imported: u32,
+    defined: u32,
+    stack: Vec<(u32, u32)>,
+}
+
+impl FuncIndices {
+    /// Returns the index of the function whose body is being parsed, and
+    /// moves on to the next one.
+    fn next_defined(&mut self) -> u32 {
+        self.defined += 1;
+        self.imported + self.defined - 1
+    }
+
+    /// Accounts for `payload`, which is to be called with every payload of
+    /// the module in order.
+    fn update(&mut self, payload: Payload) -> Result<(), BinaryReaderError> {
+        match payload {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if let ImportSectionEntryType::Function(_) = import?.ty {
+                        self.imported += 1;
+                    }
+                }
+            }
+            Payload::ModuleCodeSectionEntry { .. } => {
+                self.stack.push((self.imported, self.defined));
+                self.imported = 0;
+                self.defined = 0;
+            }
+            Payload::End => {
+                if let Some((imported, defined)) = self.stack.pop() {
+                    self.imported = imported;
+                    self.defined = defined;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of the section `payload` belongs to, if it's part of one.
+fn section_name(payload: &Payload) -> Option<&'static str> {
+    Some(match payload {
+        Payload::TypeSection(_) => "type",
+        Payload::ImportSection(_) => "import",
+        Payload::AliasSection(_) => "alias",
+        Payload::InstanceSection(_) => "instance",
+        Payload::ModuleSection(_) => "module",
+        Payload::FunctionSection(_) => "function",
+        Payload::TableSection(_) => "table",
+        Payload::MemorySection(_) => "memory",
+        Payload::GlobalSection(_) => "global",
+        Payload::ExportSection(_) => "export",
+        Payload::StartSection { .. } => "start",
+        Payload::ElementSection(_) => "element",
+        Payload::DataCountSection { .. } => "data count",
+        Payload::DataSection(_) => "data",
+        Payload::CodeSectionStart { .. } | Payload::CodeSectionEntry(_) => "code",
+        Payload::ModuleCodeSectionStart { .. } | Payload::ModuleCodeSectionEntry { .. } => {
+            "module code"
+        }
+        Payload::CustomSection { .. } => "custom",
+        Payload::UnknownSection { .. } => "unknown",
+        Payload::Version { .. } | Payload::End => return None,
+    })
+}
+
+/// Fails with an error naming the first instruction in `binary` which belongs
+/// to a class in `forbidden`.
+fn check_forbidden_features(binary: &[u8], forbidden: FeatureMask) -> Result<(), ValidationError> {
+    if forbidden.is_empty() {
+        return Ok(());
+    }
+
+    let mut funcs = FuncIndices::default();
+    for payload in Parser::new(0).parse_all(binary) {
+        let payload = payload?;
+        if let Payload::CodeSectionEntry(body) = &payload {
+            let func_index = funcs.next_defined();
+            let body_offset = body.get_binary_reader().original_position();
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                let offset = reader.original_position();
+                let op = reader.read()?;
+                let (class, description) = match instruction_class(&op) {
+                    Some(class) => class,
+                    None => continue,
+                };
+                if forbidden.contains(class) {
+                    // Operators are named after their variants.
+                    let name = format!("{:?}", op);
+                    let name = name.split(|c| c == ' ' || c == '{').next().unwrap();
+                    let message = format!(
+                        "instruction `{}` is forbidden: {} is disallowed by \
+                         `Config::forbid_features`",
+                        name, description,
+                    );
+                    return Err(ValidationError::in_func(
+                        message,
+                        offset,
+                        func_index,
+                        body_offset,
+                    ));
+                }
+            }
+        }
+        funcs.update(payload)?;
+    }
+    Ok(())
+}
+
+/// Returns the class `op` belongs to along with a description of that class,
+/// if it belongs to one.
+fn instruction_class(op: &Operator) -> Option<(FeatureMask, &'static str)> {
+    match op {
+        Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => {
+            Some((FeatureMask::INDIRECT_CALLS, "indirect calling"))
+        }
+        Operator::MemoryGrow { .. } => Some((FeatureMask::MEMORY_GROW, "growing memory")),
+        // Every instruction which computes with, converts to or from, loads,
+        // stores or materializes a float.
+        Operator::F32Abs
+        | Operator::F32Add
+        | Operator::F32Ceil
+        | Operator::F32Const { .. }
+        | Operator::F32ConvertI32S
+        | Operator::F32ConvertI32U
+        | Operator::F32ConvertI64S
+        | Operator::F32ConvertI64U
+        | Operator::F32Copysign
+        | Operator::F32DemoteF64
+        | Operator::F32Div
+        | Operator::F32Eq
+        | Operator::F32Floor
+        | Operator::F32Ge
+        | Operator::F32Gt
+        | Operator::F32Le
+        | Operator::F32Load { .. }
+        | Operator::F32Lt
+        | Operator::F32Max
+        | Operator::F32Min
+        | Operator::F32Mul
+        | Operator::F32Ne
+        | Operator::F32Nearest
+        | Operator::F32Neg
+        | Operator::F32ReinterpretI32
+        | Operator::F32Sqrt
+        | Operator::F32Store { .. }
+        | Operator::F32Sub
+        | Operator::F32Trunc
+        | Operator::F32x4Abs
+        | Operator::F32x4Add
+        | Operator::F32x4Ceil
+        | Operator::F32x4ConvertI32x4S
+        | Operator::F32x4ConvertI32x4U
+        | Operator::F32x4Div
+        | Operator::F32x4Eq
+        | Operator::F32x4ExtractLane { .. }
+        | Operator::F32x4Floor
+        | Operator::F32x4Ge
+        | Operator::F32x4Gt
+        | Operator::F32x4Le
+        | Operator::F32x4Lt
+        | Operator::F32x4Max
+        | Operator::F32x4Min
+        | Operator::F32x4Mul
+        | Operator::F32x4Ne
+        | Operator::F32x4Nearest
+        | Operator::F32x4Neg
+        | Operator::F32x4PMax
+        | Operator::F32x4PMin
+        | Operator::F32x4ReplaceLane { .. }
+        | Operator::F32x4Splat
+        | Operator::F32x4Sqrt
+        | Operator::F32x4Sub
+        | Operator::F32x4Trunc
+        | Operator::F64Abs
+        | Operator::F64Add
+        | Operator::F64Ceil
+        | Operator::F64Const { .. }
+        | Operator::F64ConvertI32S
+        | Operator::F64ConvertI32U
+        | Operator::F64ConvertI64S
+        | Operator::F64ConvertI64U
+        | Operator::F64Copysign
+        | Operator::F64Div
+        | Operator::F64Eq
+        | Operator::F64Floor
+        | Operator::F64Ge
+        | Operator::F64Gt
+        | Operator::F64Le
+        | Operator::F64Load { .. }
+        | Operator::F64Lt
+        | Operator::F64Max
+        | Operator::F64Min
+        | Operator::F64Mul
+        | Operator::F64Ne
+        | Operator::F64Nearest
+        | Operator::F64Neg
+        | Operator::F64PromoteF32
+        | Operator::F64ReinterpretI64
+        | Operator::F64Sqrt
+        | Operator::F64Store { .. }
+        | Operator::F64Sub
+        | Operator::F64Trunc
+        | Operator::F64x2Abs
+        | Operator::F64x2Add
+        | Operator::F64x2Ceil
+        | Operator::F64x2Div
+        | Operator::F64x2Eq
+        | Operator::F64x2ExtractLane { .. }
+        | Operator::F64x2Floor
+        | Operator::F64x2Ge
+        | Operator::F64x2Gt
+        | Operator::F64x2Le
+        | Operator::F64x2Lt
+        | Operator::F64x2Max
+        | Operator::F64x2Min
+        | Operator::F64x2Mul
+        | Operator::F64x2Ne
+        | Operator::F64x2Nearest
+        | Operator::F64x2Neg
+        | Operator::F64x2PMax
+        | Operator::F64x2PMin
+        | Operator::F64x2ReplaceLane { .. }
+        | Operator::F64x2Splat
+        | Operator::F64x2Sqrt
+        | Operator::F64x2Sub
+        | Operator::F64x2Trunc
+        | Operator::I32ReinterpretF32
+        | Operator::I32TruncF32S
+        | Operator::I32TruncF32U
+        | Operator::I32TruncF64S
+        | Operator::I32TruncF64U
+        | Operator::I32TruncSatF32S
+        | Operator::I32TruncSatF32U
+        | Operator::I32TruncSatF64S
+        | Operator::I32TruncSatF64U
+        | Operator::I32x4TruncSatF32x4S
+        | Operator::I32x4TruncSatF32x4U
+        | Operator::I64ReinterpretF64
+        | Operator::I64TruncF32S
+        | Operator::I64TruncF32U
+        | Operator::I64TruncF64S
+        | Operator::I64TruncF64U
+        | Operator::I64TruncSatF32S
+        | Operator::I64TruncSatF32U
+        | Operator::I64TruncSatF64S
+        | Operator::I64TruncSatF64U => Some((FeatureMask::FLOAT, "floating point")),
+        _ => None,
+    }
+}
+
 fn _assert_send_sync() {
     fn _assert<T: Send + Sync>() {}
     _assert::<Module>();
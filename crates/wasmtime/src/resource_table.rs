@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+/// A table of host objects which wasm refers to by integer handles.
+///
+/// Host functions often need to give wasm something it can't hold itself, like
+/// a file or a connection, and resolve it again when wasm passes it back. A
+/// `ResourceTable` keeps such objects and mints a `u32` handle for each one,
+/// which wasm can pass around as an `i32`.
+///
+/// Handles aren't reused once their resource is removed, so a stale handle
+/// resolves to `None` rather than to some other resource, and host functions
+/// can trap on it. Handles are never zero, so wasm can use zero as a null
+/// handle.
+///
+/// Each [`Store`](crate::Store) has a table per type of resource, returned by
+/// [`Store::resource_table`](crate::Store::resource_table), but tables can
+/// also be created and kept anywhere else.
+#[derive(Debug)]
+pub struct ResourceTable<T> {
+    resources: HashMap<u32, T>,
+    next: u32,
+}
+
+impl<T> ResourceTable<T> {
+    /// Creates a new, empty, table.
+    pub fn new() -> ResourceTable<T> {
+        ResourceTable {
+            resources: HashMap::new(),
+            next: 1,
+        }
+    }
+
+    /// Adds `resource` to this table, returning its handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table already holds `u32::max_value() - 1` resources.
+    pub fn insert(&mut self, resource: T) -> u32 {
+        // Keeping at least one non-zero handle free guarantees that the
+        // search below terminates.
+        assert!(
+            self.resources.len() < u32::max_value() as usize - 1,
+            "resource table is full"
+        );
+        // Handles only wrap around once 4 billion have been minted, at which
+        // point those still in use are skipped.
+        let mut handle = self.next;
+        while handle == 0 || self.resources.contains_key(&handle) {
+            handle = handle.wrapping_add(1);
+        }
+        self.next = handle.wrapping_add(1);
+        self.resources.insert(handle, resource);
+        handle
+    }
+
+    /// Returns the resource for `handle`, or `None` if there isn't one, for
+    /// instance because it's been removed.
+    pub fn get(&self, handle: u32) -> Option<&T> {
+        self.resources.get(&handle)
+    }
+
+    /// Returns the resource for `handle` mutably, or `None` if there isn't
+    /// one.
+    pub fn get_mut(&mut self, handle: u32) -> Option<&mut T> {
+        self.resources.get_mut(&handle)
+    }
+
+    /// Removes the resource for `handle` from this table and returns it, or
+    /// `None` if there isn't one.
+    pub fn remove(&mut self, handle: u32) -> Option<T> {
+        self.resources.remove(&handle)
+    }
+
+    /// Returns the number of resources in this table.
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    /// Returns whether this table has no resources.
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+}
+
+impl<T> Default for ResourceTable<T> {
+    fn default() -> ResourceTable<T> {
+        ResourceTable::new()
+    }
+}
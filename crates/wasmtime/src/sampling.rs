@@ -0,0 +1,160 @@
+use crate::FrameInfo;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Maximum number of samples buffered during a call into wasm before they're
+/// resolved, beyond which samples are dropped.
+const MAX_SAMPLES: usize = 4096;
+
+/// Maximum number of frames recorded per sample.
+const MAX_FRAMES: usize = 32;
+
+/// Where wasm code spent its time, as collected by the sampling profiler
+/// enabled with [`Config::sampling_profiler`](crate::Config::sampling_profiler).
+///
+/// Each sample is the wasm call stack at the moment it was taken. Functions are
+/// named `module!function`, the same way as in the backtrace of a
+/// [`Trap`](crate::Trap), with `<unknown>` standing in for a module without a
+/// name and `<wasm function N>` for a function without one.
+///
+/// Returned by [`Store::profile`](crate::Store::profile).
+#[derive(Clone, Debug, Default)]
+pub struct SamplingProfile {
+    /// Number of samples of each distinct call stack, outermost frame first.
+    stacks: BTreeMap<Vec<String>, u64>,
+}
+
+impl SamplingProfile {
+    pub(crate) fn record(&mut self, stack: Vec<String>) {
+        *self.stacks.entry(stack).or_insert(0) += 1;
+    }
+
+    /// Returns the total number of samples taken.
+    pub fn samples(&self) -> u64 {
+        self.stacks.values().sum()
+    }
+
+    /// Returns the number of samples taken while `func` itself was executing,
+    /// that is with `func` at the top of the stack.
+    pub fn self_samples(&self, func: &str) -> u64 {
+        self.stacks
+            .iter()
+            .filter(|(stack, _)| stack.last().map(|s| s.as_str()) == Some(func))
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Returns the number of samples taken while `func` was anywhere on the
+    /// stack, that is while it or anything it called was executing.
+    pub fn total_samples(&self, func: &str) -> u64 {
+        self.stacks
+            .iter()
+            .filter(|(stack, _)| stack.iter().any(|s| s == func))
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Returns each distinct call stack that was sampled, outermost frame
+    /// first, along with the number of times it was sampled.
+    pub fn stacks(&self) -> impl Iterator<Item = (&[String], u64)> + '_ {
+        self.stacks
+            .iter()
+            .map(|(stack, count)| (stack.as_slice(), *count))
+    }
+
+    /// Renders this profile in the "folded stacks" format read by
+    /// [`flamegraph.pl`](https://github.com/brendangregg/FlameGraph) and
+    /// [inferno](https://github.com/jonhoo/inferno).
+    ///
+    /// Each line is one call stack, with its frames from the outermost one
+    /// inwards separated by `;`, followed by a space and the number of samples
+    /// of that stack.
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for (stack, count) in self.stacks.iter() {
+            writeln!(out, "{} {}", stack.join(";"), count).unwrap();
+        }
+        out
+    }
+}
+
+/// The name `frame`'s function goes by in a `SamplingProfile`.
+pub(crate) fn frame_name(frame: &FrameInfo) -> String {
+    let module = frame.module_name().unwrap_or("<unknown>");
+    let name = match frame.func_name() {
+        Some(name) => format!("{}!{}", module, rustc_demangle::demangle(name)),
+        None => format!("{}!<wasm function {}>", module, frame.func_index()),
+    };
+    // `;` separates frames in the folded format.
+    name.replace(';', ":")
+}
+
+/// A fixed-size ring buffer of samples, written by the sampler's signal
+/// handler and read once the call into wasm they were taken in returns.
+///
+/// Each sample is a list of program counters, innermost first. The buffer is
+/// allocated up front so that pushing a sample is async-signal-safe. There's
+/// one writer, the signal handler, and one reader, on the same thread, so
+/// reads may be interrupted by writes but not the other way around.
+pub(crate) struct SampleBuffer {
+    /// `MAX_FRAMES` program counters per sample.
+    pcs: Box<[AtomicUsize]>,
+    /// The number of program counters of each sample.
+    lens: Box<[AtomicUsize]>,
+    /// The number of samples ever pushed.
+    head: AtomicUsize,
+    /// The number of samples ever taken out.
+    tail: AtomicUsize,
+}
+
+impl SampleBuffer {
+    pub(crate) fn new() -> SampleBuffer {
+        SampleBuffer {
+            pcs: (0..MAX_SAMPLES * MAX_FRAMES)
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+            lens: (0..MAX_SAMPLES).map(|_| AtomicUsize::new(0)).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records a sample with the first `MAX_FRAMES` program counters of
+    /// `pcs`, unless the buffer is full. Neither allocates nor locks.
+    pub(crate) fn push(&self, pcs: impl Iterator<Item = usize>) {
+        let head = self.head.load(Ordering::Relaxed);
+        if head - self.tail.load(Ordering::Acquire) == MAX_SAMPLES {
+            return;
+        }
+        let slot = head % MAX_SAMPLES;
+        let frames = &self.pcs[slot * MAX_FRAMES..][..MAX_FRAMES];
+        let mut len = 0;
+        for (frame, pc) in frames.iter().zip(pcs) {
+            frame.store(pc, Ordering::Relaxed);
+            len += 1;
+        }
+        self.lens[slot].store(len, Ordering::Relaxed);
+        self.head.store(head + 1, Ordering::Release);
+    }
+
+    /// Takes all the samples out of the buffer.
+    pub(crate) fn drain(&self) -> Vec<Vec<usize>> {
+        let mut samples = Vec::new();
+        loop {
+            let tail = self.tail.load(Ordering::Relaxed);
+            if tail == self.head.load(Ordering::Acquire) {
+                return samples;
+            }
+            let slot = tail % MAX_SAMPLES;
+            let len = self.lens[slot].load(Ordering::Relaxed);
+            samples.push(
+                self.pcs[slot * MAX_FRAMES..][..len]
+                    .iter()
+                    .map(|pc| pc.load(Ordering::Relaxed))
+                    .collect(),
+            );
+            self.tail.store(tail + 1, Ordering::Release);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Implement a registry of function signatures, for fast indirect call
 //! signature checking.
 
+use crate::FuncType;
 use std::collections::{hash_map, HashMap};
 use std::convert::TryFrom;
 use wasmtime_environ::wasm::WasmFuncType;
@@ -23,8 +24,9 @@ pub struct SignatureRegistry {
 
 #[derive(Debug)]
 struct Entry {
-    // The WebAssembly type signature, using wasm types.
-    wasm: WasmFuncType,
+    // The WebAssembly type signature. This is kept as a `FuncType` so handing
+    // it out to `Func::ty` is only a reference count increment.
+    ty: FuncType,
     // The native trampoline used to invoke this type signature from `Func`.
     // Note that the code memory for this trampoline is not owned by this
     // type, but instead it's expected to be owned by the store that this
@@ -53,7 +55,7 @@ impl SignatureRegistry {
                 debug_assert_eq!(len, self.index_map.len());
                 let index = VMSharedSignatureIndex::new(u32::try_from(len).unwrap());
                 self.index_map.push(Entry {
-                    wasm: wasm.clone(),
+                    ty: FuncType::from_wasm_func_type(wasm),
                     trampoline,
                 });
                 entry.insert(index);
@@ -77,6 +79,13 @@ impl SignatureRegistry {
     ) -> Option<(&WasmFuncType, VMTrampoline)> {
         self.index_map
             .get(idx.bits() as usize)
-            .map(|e| (&e.wasm, e.trampoline))
+            .map(|e| (e.ty.as_wasm_func_type(), e.trampoline))
+    }
+
+    /// Looks up the type of a shared signature index.
+    ///
+    /// Like `lookup_shared`, `idx` must have come from `register`.
+    pub fn lookup_type(&self, idx: VMSharedSignatureIndex) -> Option<&FuncType> {
+        self.index_map.get(idx.bits() as usize).map(|e| &e.ty)
     }
 }
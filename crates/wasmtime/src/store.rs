@@ -1,20 +1,26 @@
 use crate::frame_info::StoreFrameInfo;
+use crate::sampling::{self, SampleBuffer, SamplingProfile};
 use crate::sig_registry::SignatureRegistry;
 use crate::trampoline::StoreInstanceHandle;
-use crate::Engine;
+use crate::watchdog::{ScheduledInterrupt, Watchdog};
+use crate::{Engine, HostCallInfo, MemoryId, ResourceTable};
 use anyhow::{bail, Result};
-use std::any::Any;
-use std::cell::RefCell;
-use std::collections::HashSet;
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::rc::{Rc, Weak};
-use std::sync::Arc;
-use wasmtime_environ::wasm;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wasmtime_environ::wasm::{self, EntityIndex};
 use wasmtime_jit::{CompiledModule, ModuleCode};
+#[cfg(unix)]
+use wasmtime_runtime::Sampler;
 use wasmtime_runtime::{
-    InstanceHandle, RuntimeMemoryCreator, SignalHandler, StackMapRegistry, TrapInfo, VMExternRef,
-    VMExternRefActivationsTable, VMInterrupts, VMSharedSignatureIndex,
+    Export, InstanceHandle, InstanceSlot, ResourceLimiter, RuntimeMemoryCreator, SignalHandler,
+    StackMapRegistry, TrapInfo, VMContext, VMExternRef, VMExternRefActivationsTable, VMInterrupts,
+    VMSharedSignatureIndex,
 };
 
 /// A `Store` is a collection of WebAssembly instances and host-defined items.
@@ -58,6 +64,10 @@ pub(crate) struct StoreInner {
     interrupts: Arc<VMInterrupts>,
     signatures: RefCell<SignatureRegistry>,
     instances: RefCell<Vec<InstanceHandle>>,
+    /// Slots of the engine's instance pool held by `instances`. These are
+    /// only released once the instances have been deallocated, which happens
+    /// in `Drop for StoreInner` before any fields are dropped.
+    instance_slots: RefCell<Vec<InstanceSlot>>,
     signal_handler: RefCell<Option<Box<SignalHandler<'static>>>>,
     externref_activations_table: VMExternRefActivationsTable,
     stack_map_registry: StackMapRegistry,
@@ -67,8 +77,47 @@ pub(crate) struct StoreInner {
     /// Set of all compiled modules that we're holding a strong reference to
     /// the module's code for. This includes JIT functions, trampolines, etc.
     modules: RefCell<HashSet<ArcModuleCode>>,
+    /// Callbacks registered with `Store::on_memory_drop`.
+    memory_drop_callbacks: RefCell<Vec<Box<dyn Fn(MemoryId) + Send>>>,
+    /// Memories currently borrowed by `Memory::with_slice{,_mut}`: the number
+    /// of shared borrows, or -1 for a mutable one.
+    memory_borrows: RefCell<HashMap<MemoryId, isize>>,
+    /// The number of live `Memory::with_slice{,_mut}` borrows, so that calls
+    /// into wasm only need to look at `memory_borrows` when there are any.
+    outstanding_memory_borrows: Cell<usize>,
+    /// Limits installed with `Store::set_limits`.
+    limits: RefCell<Option<Rc<StoreLimits>>>,
+    /// Number of modules instantiated in this store so far.
+    module_instances: Cell<usize>,
+    /// The hook registered with `Store::on_host_call`.
+    host_call_hook: RefCell<Option<Rc<HostCallHook>>>,
+    /// Whether `host_call_hook` is set, which is checked on every host call.
+    has_host_call_hook: Cell<bool>,
+    /// Names of host functions, keyed by the address of their `VMContext`,
+    /// as they were first defined in a `Linker`.
+    host_func_names: RefCell<HashMap<usize, Rc<str>>>,
+    /// The timer driving the sampling profiler, if it's enabled.
+    #[cfg(unix)]
+    sampler: Option<Sampler>,
+    /// Samples taken during the current call into wasm, if the sampling
+    /// profiler is enabled: the program counter each was taken at followed by
+    /// the return addresses of the wasm frames on the stack at the time.
+    samples: Option<SampleBuffer>,
+    /// Samples resolved to wasm call stacks once the call they were taken in
+    /// has returned.
+    profile: RefCell<SamplingProfile>,
+    /// The tables returned by `Store::resource_table`, keyed by the type of
+    /// their resources.
+    resource_tables: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+    /// The deadline set with `Store::set_deadline`, shared with this store's
+    /// `InterruptHandle`s.
+    deadline: Arc<Mutex<Option<Instant>>>,
+    /// The timer which interrupts wasm at `deadline`.
+    deadline_timer: RefCell<Option<InterruptTimer>>,
 }
 
+type HostCallHook = dyn Fn(&HostCallInfo<'_>) + Send;
+
 struct HostInfoKey(VMExternRef);
 
 impl PartialEq for HostInfoKey {
@@ -104,11 +153,30 @@ impl Store {
                 interrupts: Arc::new(Default::default()),
                 signatures: RefCell::new(Default::default()),
                 instances: RefCell::new(Vec::new()),
+                instance_slots: RefCell::new(Vec::new()),
                 signal_handler: RefCell::new(None),
                 externref_activations_table: VMExternRefActivationsTable::new(),
                 stack_map_registry: StackMapRegistry::default(),
                 frame_info: Default::default(),
                 modules: Default::default(),
+                memory_drop_callbacks: Default::default(),
+                memory_borrows: Default::default(),
+                outstanding_memory_borrows: Cell::new(0),
+                limits: RefCell::new(None),
+                module_instances: Cell::new(0),
+                host_call_hook: RefCell::new(None),
+                has_host_call_hook: Cell::new(false),
+                host_func_names: Default::default(),
+                #[cfg(unix)]
+                sampler: engine.config().sampling_profiler.map(Sampler::new),
+                samples: engine
+                    .config()
+                    .sampling_profiler
+                    .map(|_| SampleBuffer::new()),
+                profile: Default::default(),
+                resource_tables: Default::default(),
+                deadline: Default::default(),
+                deadline_timer: RefCell::new(None),
             }),
         }
     }
@@ -222,6 +290,23 @@ impl Store {
         }
     }
 
+    /// Accounts for a module about to be instantiated in this store, failing
+    /// if that would exceed the store's instance limit.
+    pub(crate) fn reserve_module_instance(&self) -> Result<()> {
+        let count = self.inner.module_instances.get();
+        if let Some(max) = self.limits().and_then(|l| l.instances) {
+            if count >= max {
+                bail!("instantiation exceeds the store limit of {} instances", max);
+            }
+        }
+        self.inner.module_instances.set(count + 1);
+        Ok(())
+    }
+
+    pub(crate) fn add_instance_slot(&self, slot: InstanceSlot) {
+        self.inner.instance_slots.borrow_mut().push(slot);
+    }
+
     pub(crate) fn existing_instance_handle(&self, handle: InstanceHandle) -> StoreInstanceHandle {
         debug_assert!(self
             .inner
@@ -348,12 +433,299 @@ impl Store {
         if self.engine().config().tunables.interruptable {
             Ok(InterruptHandle {
                 interrupts: self.inner.interrupts.clone(),
+                deadline: self.inner.deadline.clone(),
+                watchdog: self.engine().watchdog().clone(),
             })
         } else {
             bail!("interrupts aren't enabled for this `Store`")
         }
     }
 
+    /// Interrupts execution within this `Store` once `deadline` has passed,
+    /// replacing any deadline set previously.
+    ///
+    /// This works like [`InterruptHandle::interrupt_after`], except that the
+    /// timer belongs to the store, and that the deadline can be read back with
+    /// [`Store::deadline`] or [`InterruptHandle::deadline`], including from
+    /// host functions. Host functions which block, WASI's `poll_oneoff` in
+    /// particular, can then avoid waiting past it: a guest which sleeps past
+    /// the deadline would otherwise only be interrupted once it wakes up. For
+    /// WASI, pass `move || handle.deadline()` and `move || other.interrupt()`,
+    /// with `handle` and `other` interrupt handles of this store, to
+    /// `WasiCtxBuilder::deadline`, so that a guest whose `poll_oneoff` gives
+    /// up at the deadline is interrupted right away rather than whenever the
+    /// timer gets to it.
+    ///
+    /// The deadline is measured with [`Instant`]. Like any interrupt it's only
+    /// delivered once, to whichever wasm is executing or next executes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if [`Config::interruptable`](crate::Config::interruptable) isn't
+    /// enabled.
+    pub fn set_deadline(&self, deadline: Instant) -> Result<()> {
+        let handle = self.interrupt_handle()?;
+        // Drop any previous timer first, which withdraws its interrupt if it
+        // fired but wasn't delivered.
+        self.inner.deadline_timer.borrow_mut().take();
+        *self.inner.deadline.lock().unwrap() = Some(deadline);
+        let timer = handle.interrupt_after(deadline.saturating_duration_since(Instant::now()));
+        *self.inner.deadline_timer.borrow_mut() = Some(timer);
+        Ok(())
+    }
+
+    /// Removes the deadline set with [`Store::set_deadline`], if any.
+    pub fn clear_deadline(&self) {
+        self.inner.deadline_timer.borrow_mut().take();
+        *self.inner.deadline.lock().unwrap() = None;
+    }
+
+    /// Returns the deadline set with [`Store::set_deadline`], if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        *self.inner.deadline.lock().unwrap()
+    }
+
+    /// Registers a callback to be invoked with the [`MemoryId`] of each linear
+    /// memory in this store right before the memory is deallocated.
+    ///
+    /// This covers memories defined by instantiated modules as well as ones
+    /// created with [`Memory::new`](crate::Memory::new), and each memory is
+    /// reported exactly once no matter how many instances imported it. Note
+    /// that memories, like instances, are only deallocated once the `Store`
+    /// itself goes away, so callbacks run while the last reference to the
+    /// store is being dropped. They can't call back into the store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use wasmtime::*;
+    /// let store = Store::default();
+    /// let memory = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    /// let id = memory.id();
+    ///
+    /// let dropped = Arc::new(Mutex::new(Vec::new()));
+    /// let dropped2 = dropped.clone();
+    /// store.on_memory_drop(move |id| dropped2.lock().unwrap().push(id));
+    ///
+    /// drop(memory);
+    /// drop(store);
+    /// assert_eq!(*dropped.lock().unwrap(), [id]);
+    /// ```
+    pub fn on_memory_drop(&self, callback: impl Fn(MemoryId) + Send + 'static) {
+        self.inner
+            .memory_drop_callbacks
+            .borrow_mut()
+            .push(Box::new(callback));
+    }
+
+    /// Registers a hook to be invoked each time a host function, such as one
+    /// created with [`Func::wrap`](crate::Func::wrap) or
+    /// [`Func::new`](crate::Func::new), is called, replacing any previous
+    /// hook.
+    ///
+    /// The hook runs right before the host function does, and is passed the
+    /// call's arguments along with the name of the function if it's known.
+    /// That's the case for functions defined in a [`Linker`](crate::Linker),
+    /// which are named after the module and name they were first defined
+    /// with, like `env::foo`. This gives a uniform log of every interaction
+    /// of wasm with the host, WASI included. Host functions called directly
+    /// through [`Func::call`](crate::Func::call) are reported as well.
+    ///
+    /// Stores without a hook don't pay for it beyond a check on each host
+    /// call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let calls = Arc::new(Mutex::new(Vec::new()));
+    /// let calls2 = calls.clone();
+    /// store.on_host_call(move |info| {
+    ///     let name = info.name().unwrap_or("<unnamed>").to_string();
+    ///     calls2.lock().unwrap().push((name, info.args()[0].unwrap_i32()));
+    /// });
+    ///
+    /// let mut linker = Linker::new(&store);
+    /// linker.func("env", "log", |_: i32| {})?;
+    /// let module = Module::new(store.engine(), r#"
+    ///     (module
+    ///         (import "env" "log" (func $log (param i32)))
+    ///         (func (export "run") (call $log (i32.const 7))))
+    /// "#)?;
+    /// let instance = linker.instantiate(&module)?;
+    /// instance.get_func("run").unwrap().call(&[])?;
+    /// assert_eq!(*calls.lock().unwrap(), [("env::log".to_string(), 7)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_host_call(&self, hook: impl Fn(&HostCallInfo<'_>) + Send + 'static) {
+        *self.inner.host_call_hook.borrow_mut() = Some(Rc::new(hook));
+        self.inner.has_host_call_hook.set(true);
+    }
+
+    pub(crate) fn host_call_hook(&self) -> Option<Rc<HostCallHook>> {
+        if !self.inner.has_host_call_hook.get() {
+            return None;
+        }
+        self.inner.host_call_hook.borrow().clone()
+    }
+
+    /// Returns whether the store `weak` refers to has a hook registered with
+    /// `Store::on_host_call`, without upgrading `weak`.
+    ///
+    /// # Unsafety
+    ///
+    /// The store must still be alive, which it is while its wasm is running.
+    pub(crate) unsafe fn weak_has_host_call_hook(weak: &Weak<StoreInner>) -> bool {
+        (*weak.as_ptr()).has_host_call_hook.get()
+    }
+
+    /// Names the host function whose `VMContext` is `vmctx`, unless it's
+    /// already named.
+    pub(crate) fn name_host_func(&self, vmctx: *mut VMContext, name: impl FnOnce() -> String) {
+        self.inner
+            .host_func_names
+            .borrow_mut()
+            .entry(vmctx as usize)
+            .or_insert_with(|| name().into());
+    }
+
+    pub(crate) fn host_func_name(&self, vmctx: *mut VMContext) -> Option<Rc<str>> {
+        self.inner
+            .host_func_names
+            .borrow()
+            .get(&(vmctx as usize))
+            .cloned()
+    }
+
+    /// Returns the profile collected so far by the sampling profiler.
+    ///
+    /// The profile covers all of the wasm code run in this store since it was
+    /// created, and is empty unless the profiler was enabled with
+    /// [`Config::sampling_profiler`](crate::Config::sampling_profiler).
+    pub fn profile(&self) -> SamplingProfile {
+        self.inner.profile.borrow().clone()
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn sampler(&self) -> Option<&Sampler> {
+        self.inner.sampler.as_ref()
+    }
+
+    /// Adds the samples taken during a call into wasm, which has now returned,
+    /// to the profile.
+    pub(crate) fn flush_samples(&self) {
+        let samples = match &self.inner.samples {
+            Some(samples) => samples.drain(),
+            None => return,
+        };
+        if samples.is_empty() {
+            return;
+        }
+        let frame_info = self.frame_info().borrow();
+        let mut profile = self.inner.profile.borrow_mut();
+        for pcs in samples {
+            // The same as for the backtrace of a trap, the sampled pc is
+            // looked up precisely and return addresses by their call
+            // instruction.
+            let mut stack = pcs
+                .iter()
+                .enumerate()
+                .filter_map(|(i, pc)| {
+                    frame_info.lookup_frame_info(if i == 0 { *pc } else { pc - 1 })
+                })
+                .map(|frame| sampling::frame_name(&frame))
+                .collect::<Vec<_>>();
+            stack.reverse();
+            profile.record(stack);
+        }
+    }
+
+    /// Limits the resources that can be consumed by this store.
+    ///
+    /// The limits apply to memories, tables and instances created after this
+    /// call; existing memories and tables are only checked when they next
+    /// grow. Memories and tables which exceed them fail to be created, which
+    /// makes instantiation fail, and growing them beyond the limits fails as
+    /// if their declared maximum was reached, unless
+    /// [`StoreLimits::trap_on_grow_failure`] is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let mut limits = StoreLimits::new();
+    /// limits.memory_size(1 << 20);
+    /// store.set_limits(limits);
+    ///
+    /// let memory = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    /// assert!(memory.grow(15).is_ok());
+    /// assert!(memory.grow(1).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_limits(&self, limits: StoreLimits) {
+        *self.inner.limits.borrow_mut() = Some(Rc::new(limits));
+    }
+
+    /// Returns this store's table of resources of type `T`, which is created
+    /// empty the first time it's asked for.
+    ///
+    /// This lets host functions mint handles for host objects which wasm
+    /// passes around as integers, and resolve them again, through
+    /// [`Caller::store`](crate::Caller::store).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wasmtime::*;
+    /// # fn main() -> anyhow::Result<()> {
+    /// let store = Store::default();
+    /// let open = Func::wrap(&store, |caller: Caller<'_>| {
+    ///     let table = caller.store().resource_table::<String>();
+    ///     let handle = table.borrow_mut().insert("hello".to_string());
+    ///     handle as i32
+    /// });
+    /// let len = Func::wrap(&store, |caller: Caller<'_>, handle: i32| {
+    ///     let table = caller.store().resource_table::<String>();
+    ///     let table = table.borrow();
+    ///     match table.get(handle as u32) {
+    ///         Some(s) => Ok(s.len() as i32),
+    ///         None => Err(Trap::new("bad handle")),
+    ///     }
+    /// });
+    ///
+    /// let handle = open.get0::<i32>()?()?;
+    /// assert_eq!(len.get1::<i32, i32>()?(handle)?, 5);
+    /// assert!(len.get1::<i32, i32>()?(handle + 1).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resource_table<T: 'static>(&self) -> Rc<RefCell<ResourceTable<T>>> {
+        self.inner
+            .resource_tables
+            .borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(RefCell::new(ResourceTable::<T>::new())) as Rc<dyn Any>)
+            .clone()
+            .downcast()
+            .unwrap()
+    }
+
+    pub(crate) fn limits(&self) -> Option<Rc<StoreLimits>> {
+        self.inner.limits.borrow().clone()
+    }
+
+    pub(crate) fn limiter(&self) -> Option<Rc<dyn ResourceLimiter>> {
+        self.limits().map(|l| l as Rc<dyn ResourceLimiter>)
+    }
+
     pub(crate) fn externref_activations_table(&self) -> &VMExternRefActivationsTable {
         &self.inner.externref_activations_table
     }
@@ -366,6 +738,14 @@ impl Store {
         &self.inner.frame_info
     }
 
+    pub(crate) fn memory_borrows(&self) -> &RefCell<HashMap<MemoryId, isize>> {
+        &self.inner.memory_borrows
+    }
+
+    pub(crate) fn outstanding_memory_borrows(&self) -> &Cell<usize> {
+        &self.inner.outstanding_memory_borrows
+    }
+
     /// Perform garbage collection of `ExternRef`s.
     pub fn gc(&self) {
         // For this crate's API, we ensure that `set_stack_canary` invariants
@@ -399,6 +779,102 @@ unsafe impl TrapInfo for Store {
     fn max_wasm_stack(&self) -> usize {
         self.engine().config().max_wasm_stack
     }
+
+    #[cfg(unix)]
+    fn record_sample(&self, pc: usize, fp: usize) {
+        // This interrupted whatever this thread was doing, which could have
+        // been updating the frame info. If it's not available then `pc` isn't
+        // in wasm code.
+        let frame_info = match self.frame_info().try_borrow() {
+            Ok(frame_info) => frame_info,
+            Err(_) => return,
+        };
+        if !frame_info.contains_pc(pc) {
+            return;
+        }
+        if let Some(samples) = &self.inner.samples {
+            let in_wasm = |pc: usize| frame_info.contains_pc(pc);
+            let frames = unsafe { wasmtime_runtime::wasm_return_addresses(fp, in_wasm) };
+            samples.push(std::iter::once(pc).chain(frames));
+        }
+    }
+}
+
+/// Limits on the resources consumed by a [`Store`], installed with
+/// [`Store::set_limits`].
+///
+/// By default nothing is limited.
+#[derive(Debug, Clone, Default)]
+pub struct StoreLimits {
+    memory_size: Option<usize>,
+    table_elements: Option<u32>,
+    instances: Option<usize>,
+    trap_on_grow_failure: bool,
+}
+
+impl StoreLimits {
+    /// Creates a new set of limits which doesn't limit anything.
+    pub fn new() -> StoreLimits {
+        StoreLimits::default()
+    }
+
+    /// Configures the maximum size, in bytes, of each linear memory.
+    pub fn memory_size(&mut self, bytes: usize) -> &mut Self {
+        self.memory_size = Some(bytes);
+        self
+    }
+
+    /// Configures the maximum number of elements of each table.
+    pub fn table_elements(&mut self, elements: u32) -> &mut Self {
+        self.table_elements = Some(elements);
+        self
+    }
+
+    /// Configures the maximum number of modules which may be instantiated in
+    /// the store, over its whole lifetime.
+    pub fn instances(&mut self, instances: usize) -> &mut Self {
+        self.instances = Some(instances);
+        self
+    }
+
+    /// Configures whether `memory.grow` and `table.grow` instructions which
+    /// exceed these limits trap, with an error naming the limit, instead of
+    /// returning -1.
+    ///
+    /// Returning -1 is what the wasm specification prescribes, but programs
+    /// rarely handle it gracefully, so trapping makes it easier to tell why a
+    /// program stopped. Defaults to `false`.
+    pub fn trap_on_grow_failure(&mut self, enable: bool) -> &mut Self {
+        self.trap_on_grow_failure = enable;
+        self
+    }
+}
+
+impl ResourceLimiter for StoreLimits {
+    fn memory_growing(&self, _current: u32, desired: u32) -> Result<(), String> {
+        let bytes = u64::from(desired) * u64::from(wasmtime_environ::WASM_PAGE_SIZE);
+        match self.memory_size {
+            Some(max) if bytes > max as u64 => Err(format!(
+                "memory of {} bytes exceeds the store limit of {} bytes",
+                bytes, max
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn table_growing(&self, _current: u32, desired: u32) -> Result<(), String> {
+        match self.table_elements {
+            Some(max) if desired > max => Err(format!(
+                "table of {} elements exceeds the store limit of {} elements",
+                desired, max
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn trap_on_grow_failure(&self) -> bool {
+        self.trap_on_grow_failure
+    }
 }
 
 impl Default for Store {
@@ -416,9 +892,31 @@ impl fmt::Debug for Store {
 
 impl Drop for StoreInner {
     fn drop(&mut self) {
+        let secure_teardown = self.engine.config().secure_teardown;
+        let callbacks = self.memory_drop_callbacks.get_mut();
         for instance in self.instances.get_mut().iter() {
+            if !callbacks.is_empty() {
+                let module = instance.module();
+                for index in module
+                    .memory_plans
+                    .keys()
+                    .skip(module.num_imported_memories)
+                {
+                    let id = match instance.lookup_by_declaration(&EntityIndex::Memory(index)) {
+                        Export::Memory(m) => MemoryId(m.definition as usize),
+                        _ => unreachable!(),
+                    };
+                    for callback in callbacks.iter() {
+                        callback(id);
+                    }
+                }
+            }
             unsafe {
-                instance.dealloc();
+                if secure_teardown {
+                    instance.dealloc_zeroed();
+                } else {
+                    instance.dealloc();
+                }
             }
         }
     }
@@ -430,6 +928,8 @@ impl Drop for StoreInner {
 /// This structure is created by the [`Store::interrupt_handle`] method.
 pub struct InterruptHandle {
     interrupts: Arc<VMInterrupts>,
+    deadline: Arc<Mutex<Option<Instant>>>,
+    watchdog: Arc<Watchdog>,
 }
 
 impl InterruptHandle {
@@ -443,6 +943,70 @@ impl InterruptHandle {
     pub fn interrupt(&self) {
         self.interrupts.interrupt()
     }
+
+    /// Returns the deadline set with
+    /// [`Store::set_deadline`](crate::Store::set_deadline) on this handle's
+    /// original [`Store`], if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        *self.deadline.lock().unwrap()
+    }
+
+    /// Flags that execution within this handle's original [`Store`] should be
+    /// interrupted once `timeout` has elapsed, unless the returned
+    /// [`InterruptTimer`] is dropped first.
+    ///
+    /// This is useful for bounding the time taken by a section of code, such
+    /// as instantiating a module and running its exports, without having to
+    /// manage a timer thread. Keep the timer alive for the duration of the
+    /// section and drop it afterwards, on the same thread. All timers of an
+    /// [`Engine`] are served by a single thread, which only runs while any of
+    /// them are pending, so they're cheap to create.
+    ///
+    /// Like [`InterruptHandle::interrupt`] the interrupt is only delivered to
+    /// wasm code, so a host function which is executing once the timer fires
+    /// runs to completion, after which the wasm which called it traps.
+    /// [`Func::call_with_deadline`](crate::Func::call_with_deadline) wraps
+    /// this up for a single call.
+    pub fn interrupt_after(&self, timeout: Duration) -> InterruptTimer {
+        let withdraw_on_drop = self.interrupts.is_idle();
+        let scheduled = self
+            .watchdog
+            .schedule(Instant::now() + timeout, self.interrupts.clone());
+        InterruptTimer {
+            scheduled,
+            interrupts: self.interrupts.clone(),
+            withdraw_on_drop,
+        }
+    }
+}
+
+/// A timer which interrupts execution within a [`Store`] once it expires.
+///
+/// This structure is created by the [`InterruptHandle::interrupt_after`]
+/// method, and dropping it cancels the timer.
+pub struct InterruptTimer {
+    scheduled: ScheduledInterrupt,
+    interrupts: Arc<VMInterrupts>,
+    // Whether no wasm was executing when the timer was created, in which case
+    // an interrupt which fired but was never delivered can be withdrawn when
+    // the timer is dropped, so it doesn't interrupt unrelated code later on.
+    withdraw_on_drop: bool,
+}
+
+impl InterruptTimer {
+    /// Returns whether the timer has expired and flagged an interrupt.
+    pub fn fired(&self) -> bool {
+        self.scheduled.fired()
+    }
+}
+
+impl Drop for InterruptTimer {
+    fn drop(&mut self) {
+        self.scheduled.cancel();
+        if self.scheduled.fired() && self.withdraw_on_drop {
+            self.interrupts.cancel_interrupt();
+        }
+    }
 }
 
 // Wrapper struct to implement hash/equality based on the pointer value of the
@@ -31,11 +31,13 @@ pub(crate) fn create_handle(
             &finished_functions,
             imports,
             store.memory_creator(),
+            None,
             &store.lookup_shared_signature(&module2),
             state,
             store.interrupts(),
             store.externref_activations_table() as *const VMExternRefActivationsTable as *mut _,
             store.stack_map_registry() as *const StackMapRegistry as *mut _,
+            store.limiter(),
         )?;
         Ok(store.add_instance(handle))
     }
@@ -21,7 +21,7 @@ use wasmtime_jit::CodeMemory;
 use wasmtime_runtime::{InstanceHandle, VMContext, VMFunctionBody, VMTrampoline};
 
 struct TrampolineState {
-    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap>>,
+    func: Box<dyn Fn(*mut VMContext, *mut VMContext, *mut u128) -> Result<(), Trap>>,
     #[allow(dead_code)]
     code_memory: CodeMemory,
 }
@@ -74,7 +74,7 @@ unsafe extern "C" fn stub_fn(
             .host_state()
             .downcast_ref::<TrampolineState>()
             .expect("state");
-        (state.func)(caller_vmctx, values_vec)
+        (state.func)(vmctx, caller_vmctx, values_vec)
     }
 }
 
@@ -204,7 +204,7 @@ fn make_trampoline(
 
 pub fn create_handle_with_function(
     ft: &FuncType,
-    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap>>,
+    func: Box<dyn Fn(*mut VMContext, *mut VMContext, *mut u128) -> Result<(), Trap>>,
     store: &Store,
 ) -> Result<(StoreInstanceHandle, VMTrampoline)> {
     // Note that we specifically enable reference types here in our ISA because
@@ -220,6 +220,7 @@ pub fn create_handle_with_function(
     let mut module = Module::new();
     let mut finished_functions = PrimaryMap::new();
     let mut code_memory = CodeMemory::new();
+    code_memory.set_wipe_on_drop(store.engine().config().secure_teardown);
 
     // First up we manufacture a trampoline which has the ABI specified by `ft`
     // and calls into `stub_fn`...
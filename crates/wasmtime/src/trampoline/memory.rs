@@ -1,8 +1,8 @@
 use super::create_handle::create_handle;
 use crate::externals::{LinearMemory, MemoryCreator};
 use crate::trampoline::StoreInstanceHandle;
+use crate::MemoryType;
 use crate::Store;
-use crate::{Limits, MemoryType};
 use anyhow::Result;
 use wasmtime_environ::entity::PrimaryMap;
 use wasmtime_environ::{wasm, MemoryPlan, MemoryStyle, Module, WASM_PAGE_SIZE};
@@ -20,6 +20,7 @@ pub fn create_handle_with_memory(
         minimum: memory.limits().min(),
         maximum: memory.limits().max(),
         shared: false, // TODO
+        memory64: memory.is_64(),
     };
 
     let memory_plan =
@@ -60,7 +61,7 @@ pub(crate) struct MemoryCreatorProxy {
 
 impl RuntimeMemoryCreator for MemoryCreatorProxy {
     fn new_memory(&self, plan: &MemoryPlan) -> Result<Box<dyn RuntimeLinearMemory>, String> {
-        let ty = MemoryType::new(Limits::new(plan.memory.minimum, plan.memory.maximum));
+        let ty = MemoryType::from_wasmtime_memory(&plan.memory);
         let reserved_size_in_bytes = match plan.style {
             MemoryStyle::Static { bound } => Some(bound as u64 * WASM_PAGE_SIZE as u64),
             MemoryStyle::Dynamic => None,
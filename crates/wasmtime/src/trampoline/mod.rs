@@ -47,7 +47,7 @@ impl Deref for StoreInstanceHandle {
 
 pub fn generate_func_export(
     ft: &FuncType,
-    func: Box<dyn Fn(*mut VMContext, *mut u128) -> Result<(), Trap>>,
+    func: Box<dyn Fn(*mut VMContext, *mut VMContext, *mut u128) -> Result<(), Trap>>,
     store: &Store,
 ) -> Result<(
     StoreInstanceHandle,
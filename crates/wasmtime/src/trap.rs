@@ -51,6 +51,10 @@ pub enum TrapCode {
     MemoryOutOfBounds,
 
     /// A wasm atomic operation was presented with a not-naturally-aligned linear-memory address.
+    ///
+    /// This is only ever raised by atomic instructions of the threads
+    /// proposal, which always check the alignment of their effective address.
+    /// Non-atomic accesses may be unaligned.
     HeapMisaligned,
 
     /// An out-of-bounds access to a table.
@@ -254,6 +258,10 @@ impl Trap {
 
     /// If the trap was the result of an explicit program exit with a classic
     /// `i32` exit status value, return the value, otherwise return `None`.
+    ///
+    /// This is how a WASI program calling `proc_exit` is reported, by either
+    /// snapshot of WASI. Such a program exited rather than failed, so a status
+    /// of 0 means it ran successfully.
     pub fn i32_exit_status(&self) -> Option<i32> {
         match self.inner.reason {
             TrapReason::I32Exit(status) => Some(status),
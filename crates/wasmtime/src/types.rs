@@ -1,4 +1,5 @@
 use std::fmt;
+use std::sync::Arc;
 use wasmtime_environ::wasm::WasmFuncType;
 use wasmtime_environ::{ir, wasm};
 
@@ -144,7 +145,7 @@ impl ValType {
 ///
 /// This list can be found in [`ImportType`] or [`ExportType`], so these types
 /// can either be imported or exported.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExternType {
     /// This external type is the type of a WebAssembly function.
     Func(FuncType),
@@ -265,9 +266,12 @@ impl From<InstanceType> for ExternType {
 /// A descriptor for a function in a WebAssembly module.
 ///
 /// WebAssembly functions can have 0 or more parameters and results.
+///
+/// The parameter and result lists are reference counted, so cloning a
+/// `FuncType` doesn't allocate.
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct FuncType {
-    sig: WasmFuncType,
+    sig: Arc<WasmFuncType>,
 }
 
 impl FuncType {
@@ -280,10 +284,10 @@ impl FuncType {
         results: impl IntoIterator<Item = ValType>,
     ) -> FuncType {
         FuncType {
-            sig: WasmFuncType {
+            sig: Arc::new(WasmFuncType {
                 params: params.into_iter().map(|t| t.to_wasm_type()).collect(),
                 returns: results.into_iter().map(|t| t.to_wasm_type()).collect(),
-            },
+            }),
         }
     }
 
@@ -324,7 +328,9 @@ impl FuncType {
     }
 
     pub(crate) fn from_wasm_func_type(sig: &wasm::WasmFuncType) -> FuncType {
-        FuncType { sig: sig.clone() }
+        FuncType {
+            sig: Arc::new(sig.clone()),
+        }
     }
 }
 
@@ -427,13 +433,32 @@ impl TableType {
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct MemoryType {
     limits: Limits,
+    memory64: bool,
 }
 
 impl MemoryType {
     /// Creates a new descriptor for a WebAssembly memory given the specified
     /// limits of the memory.
     pub fn new(limits: Limits) -> MemoryType {
-        MemoryType { limits }
+        MemoryType {
+            limits,
+            memory64: false,
+        }
+    }
+
+    /// Creates a new descriptor for a 64-bit WebAssembly memory given the
+    /// specified limits of the memory.
+    ///
+    /// 64-bit memories are part of the WebAssembly memory64 [proposal] and
+    /// can only be used by modules when [`Config::wasm_memory64`] is enabled.
+    ///
+    /// [proposal]: https://github.com/webassembly/memory64
+    /// [`Config::wasm_memory64`]: crate::Config::wasm_memory64
+    pub fn new64(limits: Limits) -> MemoryType {
+        MemoryType {
+            limits,
+            memory64: true,
+        }
     }
 
     /// Returns the limits (in pages) that are configured for this memory.
@@ -441,8 +466,16 @@ impl MemoryType {
         &self.limits
     }
 
+    /// Returns whether this is a 64-bit memory, indexed with `i64` addresses.
+    pub fn is_64(&self) -> bool {
+        self.memory64
+    }
+
     pub(crate) fn from_wasmtime_memory(memory: &wasm::Memory) -> MemoryType {
-        MemoryType::new(Limits::new(memory.minimum, memory.maximum))
+        MemoryType {
+            limits: Limits::new(memory.minimum, memory.maximum),
+            memory64: memory.memory64,
+        }
     }
 }
 
@@ -453,7 +486,7 @@ impl MemoryType {
 /// This is a part of the [WebAssembly module-linking proposal][proposal].
 ///
 /// [proposal]: https://github.com/webassembly/module-linking
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ModuleType {
     imports: Vec<(String, Option<String>, ExternType)>,
     exports: Vec<(String, ExternType)>,
@@ -530,7 +563,7 @@ impl ModuleType {
 /// This is a part of the [WebAssembly module-linking proposal][proposal].
 ///
 /// [proposal]: https://github.com/webassembly/module-linking
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InstanceType {
     exports: Vec<(String, ExternType)>,
 }
@@ -252,6 +252,107 @@ impl From<Func> for Val {
     }
 }
 
+/// Converts the JSON value `json` into a [`Val`] of type `ty`.
+///
+/// This is meant for tools which take the arguments of wasm functions as
+/// JSON. JSON numbers can't represent every 64-bit integer, so integers may
+/// be given as strings such as `"42"` as well as numbers. Integers have to
+/// fit the type, either as a signed or as an unsigned number, so `-1` and
+/// `"18446744073709551615"` are the same `i64`. Floats are rounded to the
+/// nearest value of their type, and may also be given as the strings
+/// `"NaN"`, `"inf"` and `"-inf"`. References can only be `null`.
+///
+/// # Errors
+///
+/// Returns an error if `json` can't be converted to `ty`.
+#[cfg(feature = "json")]
+pub fn val_from_json(ty: ValType, json: &serde_json::Value) -> Result<Val> {
+    use serde_json::Value;
+
+    // Accepts both the signed and the unsigned range of a `bits`-wide integer.
+    fn int(json: &Value, bits: u32) -> Option<i128> {
+        let i = match json {
+            Value::Number(n) => n
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from))?,
+            Value::String(s) => s.parse().ok()?,
+            _ => return None,
+        };
+        if -(1 << (bits - 1)) <= i && i < (1 << bits) {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    fn v128(json: &Value) -> Option<u128> {
+        match json {
+            Value::Number(n) => n.as_u64().map(u128::from),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn float(json: &Value) -> Option<f64> {
+        match json {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => match s.as_str() {
+                "NaN" | "inf" | "-inf" => s.parse().ok(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    let val = match ty {
+        ValType::I32 => int(json, 32).map(|i| Val::I32(i as i32)),
+        ValType::I64 => int(json, 64).map(|i| Val::I64(i as i64)),
+        ValType::F32 => float(json).map(|f| Val::F32((f as f32).to_bits())),
+        ValType::F64 => float(json).map(|f| Val::F64(f.to_bits())),
+        ValType::V128 => v128(json).map(Val::V128),
+        ValType::ExternRef if json.is_null() => Some(Val::ExternRef(None)),
+        ValType::FuncRef if json.is_null() => Some(Val::FuncRef(None)),
+        ValType::ExternRef | ValType::FuncRef => None,
+    };
+    match val {
+        Some(val) => Ok(val),
+        None => bail!("cannot convert JSON value `{}` to {}", json, ty),
+    }
+}
+
+/// Converts `val` into JSON, the inverse of [`val_from_json`].
+///
+/// 64-bit and 128-bit integers are converted to strings, since JSON numbers
+/// can't represent all of them, while `i32`s are converted to numbers.
+/// Floats are converted to numbers, except for NaNs and infinities which are
+/// converted to the strings `"NaN"`, `"inf"` and `"-inf"`. Null references
+/// are converted to `null`, and other references to the strings
+/// `"<externref>"` and `"<funcref>"`.
+#[cfg(feature = "json")]
+pub fn val_to_json(val: &Val) -> serde_json::Value {
+    use serde_json::Value;
+
+    fn float(f: f64) -> Value {
+        if f.is_finite() {
+            Value::from(f)
+        } else {
+            Value::from(f.to_string())
+        }
+    }
+
+    match val {
+        Val::I32(i) => Value::from(*i),
+        Val::I64(i) => Value::from(i.to_string()),
+        Val::F32(bits) => float(f32::from_bits(*bits).into()),
+        Val::F64(bits) => float(f64::from_bits(*bits)),
+        Val::V128(i) => Value::from(i.to_string()),
+        Val::ExternRef(None) | Val::FuncRef(None) => Value::Null,
+        Val::ExternRef(Some(_)) => Value::from("<externref>"),
+        Val::FuncRef(Some(_)) => Value::from("<funcref>"),
+    }
+}
+
 pub(crate) fn into_checked_anyfunc(
     val: Val,
     store: &Store,
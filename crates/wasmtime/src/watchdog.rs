@@ -0,0 +1,118 @@
+//! The thread which delivers the interrupts scheduled with
+//! [`InterruptHandle::interrupt_after`](crate::InterruptHandle::interrupt_after).
+//!
+//! Each [`Engine`](crate::Engine) has one [`Watchdog`], shared by all of its
+//! stores, which keeps the pending interrupts ordered by deadline. Its thread
+//! is started when an interrupt is scheduled and exits once none are pending,
+//! so no thread is around while no timers are, and there's never more than one
+//! per engine no matter how many timers are.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+use wasmtime_runtime::VMInterrupts;
+
+#[derive(Default)]
+pub(crate) struct Watchdog {
+    state: Mutex<WatchdogState>,
+    wakeup: Condvar,
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    // Ordered by deadline, with ties broken by the order they were scheduled
+    // in.
+    pending: BTreeMap<(Instant, u64), Pending>,
+    next_id: u64,
+    running: bool,
+}
+
+struct Pending {
+    interrupts: Arc<VMInterrupts>,
+    fired: Arc<AtomicBool>,
+}
+
+/// An interrupt scheduled with [`Watchdog::schedule`].
+pub(crate) struct ScheduledInterrupt {
+    watchdog: Arc<Watchdog>,
+    key: (Instant, u64),
+    fired: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    /// Schedules `interrupts` to be interrupted at `deadline`, unless the
+    /// returned interrupt is cancelled first.
+    pub(crate) fn schedule(
+        self: &Arc<Self>,
+        deadline: Instant,
+        interrupts: Arc<VMInterrupts>,
+    ) -> ScheduledInterrupt {
+        let fired = Arc::new(AtomicBool::new(false));
+        let mut state = self.state.lock().unwrap();
+        let key = (deadline, state.next_id);
+        state.next_id += 1;
+        state.pending.insert(
+            key,
+            Pending {
+                interrupts,
+                fired: fired.clone(),
+            },
+        );
+        if state.running {
+            // The new deadline may be earlier than the one being waited for.
+            self.wakeup.notify_one();
+        } else {
+            let watchdog = self.clone();
+            thread::Builder::new()
+                .name("wasmtime-watchdog".to_string())
+                .spawn(move || watchdog.run())
+                .expect("failed to spawn the watchdog thread");
+            state.running = true;
+        }
+        ScheduledInterrupt {
+            watchdog: self.clone(),
+            key,
+            fired,
+        }
+    }
+
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            while let Some(&key) = state.pending.keys().next() {
+                if key.0 > now {
+                    break;
+                }
+                let pending = state.pending.remove(&key).unwrap();
+                pending.fired.store(true, SeqCst);
+                pending.interrupts.interrupt();
+            }
+            let next = match state.pending.keys().next() {
+                Some(&(deadline, _)) => deadline,
+                None => break,
+            };
+            state = self.wakeup.wait_timeout(state, next - now).unwrap().0;
+        }
+        state.running = false;
+    }
+}
+
+impl ScheduledInterrupt {
+    /// Returns whether the deadline has passed and the interrupt was flagged.
+    pub(crate) fn fired(&self) -> bool {
+        self.fired.load(SeqCst)
+    }
+
+    /// Makes sure the interrupt isn't flagged from now on, if it hasn't been
+    /// already.
+    pub(crate) fn cancel(&self) {
+        let mut state = self.watchdog.state.lock().unwrap();
+        if state.pending.remove(&self.key).is_some() {
+            // Lets the thread exit if this was the last pending interrupt.
+            self.watchdog.wakeup.notify_one();
+        }
+    }
+}
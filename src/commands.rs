@@ -1,8 +1,9 @@
 //! The module for the Wasmtime CLI commands.
 
 mod config;
+mod diagnose;
 mod run;
 mod wasm2obj;
 mod wast;
 
-pub use self::{config::*, run::*, wasm2obj::*, wast::*};
+pub use self::{config::*, diagnose::*, run::*, wasm2obj::*, wast::*};
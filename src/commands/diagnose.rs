@@ -0,0 +1,36 @@
+//! The module that implements the `wasmtime diagnose` command.
+
+use crate::CommonOptions;
+use anyhow::Result;
+use structopt::{clap::AppSettings, StructOpt};
+use wasmtime::Engine;
+
+/// Prints what Wasmtime compiles code for and how, for bug reports
+#[derive(StructOpt)]
+#[structopt(
+    name = "diagnose",
+    version = env!("CARGO_PKG_VERSION"),
+    setting = AppSettings::ColoredHelp,
+)]
+pub struct DiagnoseCommand {
+    #[structopt(flatten)]
+    common: CommonOptions,
+
+    /// Print the report as JSON
+    #[structopt(long)]
+    json: bool,
+}
+
+impl DiagnoseCommand {
+    /// Executes the command.
+    pub fn execute(&self) -> Result<()> {
+        let engine = Engine::new(&self.common.config()?);
+        let diagnostics = engine.diagnostics();
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+        } else {
+            print!("{}", diagnostics);
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,19 @@
 //! The module that implements the `wasmtime run` command.
 
 use crate::{init_file_per_thread_logger, CommonOptions};
-use anyhow::{bail, Context as _, Result};
-use std::thread;
+use anyhow::{anyhow, bail, Context as _, Result};
+use std::convert::TryFrom;
 use std::time::Duration;
 use std::{
     ffi::{OsStr, OsString},
-    fs::File,
-    path::{Component, PathBuf},
+    fs::{self, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
     process,
 };
 use structopt::{clap::AppSettings, StructOpt};
-use wasi_common::{preopen_dir, WasiCtxBuilder};
-use wasmtime::{Engine, Func, Linker, Module, Store, Trap, Val, ValType};
+use wasi_common::{preopen_dir, OsFile, OsOther, WasiCtxBuilder};
+use wasmtime::{Engine, Func, Linker, Module, Store, StoreLimits, Trap, Val, ValType};
 use wasmtime_wasi::Wasi;
 
 #[cfg(feature = "wasi-nn")]
@@ -21,9 +22,8 @@ use wasmtime_wasi_nn::{WasiNn, WasiNnCtx};
 fn parse_module(s: &OsStr) -> Result<PathBuf, OsString> {
     // Do not accept wasmtime subcommand names as the module name
     match s.to_str() {
-        Some("help") | Some("config") | Some("run") | Some("wasm2obj") | Some("wast") => {
-            Err("module name cannot be the same as a subcommand".into())
-        }
+        Some("help") | Some("config") | Some("diagnose") | Some("run") | Some("wasm2obj")
+        | Some("wast") => Err("module name cannot be the same as a subcommand".into()),
         _ => Ok(s.into()),
     }
 }
@@ -54,6 +54,30 @@ fn parse_dur(s: &str) -> Result<Duration> {
     Ok(dur)
 }
 
+fn parse_size(s: &str) -> Result<usize> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| s.len());
+    let (digits, unit) = s.split_at(split);
+    let scale: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KiB" => 1 << 10,
+        "M" | "MiB" => 1 << 20,
+        "G" | "GiB" => 1 << 30,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        other => bail!("unknown size unit `{}`", other),
+    };
+    let bytes = digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| anyhow!("invalid size `{}`", s))?;
+    Ok(bytes)
+}
+
 fn parse_preloads(s: &str) -> Result<(String, PathBuf)> {
     let parts: Vec<&str> = s.splitn(2, '=').collect();
     if parts.len() != 2 {
@@ -85,7 +109,7 @@ pub struct RunCommand {
     #[structopt(long = "mapdir", number_of_values = 1, value_name = "GUEST_DIR::HOST_DIR", parse(try_from_str = parse_map_dirs))]
     map_dirs: Vec<(String, String)>,
 
-    /// The path of the WebAssembly module to run
+    /// The path of the WebAssembly module to run, or `-` to read it from stdin
     #[structopt(
         index = 1,
         required = true,
@@ -111,6 +135,38 @@ pub struct RunCommand {
     )]
     wasm_timeout: Option<Duration>,
 
+    /// Maximum size of each linear memory (65536, 64KiB, 512MiB, etc); growing
+    /// beyond it traps
+    #[structopt(
+        long = "max-memory",
+        value_name = "SIZE",
+        parse(try_from_str = parse_size),
+    )]
+    max_memory: Option<usize>,
+
+    /// Maximum number of elements of each table; growing beyond it traps
+    #[structopt(long = "max-table-elements", value_name = "COUNT")]
+    max_table_elements: Option<u32>,
+
+    /// Maximum number of module instances, including preloads and command
+    /// instances created for each call into them
+    #[structopt(long = "max-instances", value_name = "COUNT")]
+    max_instances: Option<usize>,
+
+    /// Define function imports of the main module which aren't otherwise
+    /// provided as functions which trap when called
+    #[structopt(long = "trap-unknown-imports")]
+    trap_unknown_imports: bool,
+
+    /// Redirect the program's stdin from the given file
+    #[structopt(long = "stdin", value_name = "FILE", parse(from_os_str))]
+    stdin: Option<PathBuf>,
+
+    /// Allow the main and preloaded modules to be precompiled artifacts from
+    /// `Module::serialize`, whose native code is run without being verified
+    #[structopt(long = "allow-precompiled")]
+    allow_precompiled: bool,
+
     // NOTE: this must come last for trailing varargs
     /// The arguments to pass to the module
     #[structopt(value_name = "ARGS")]
@@ -133,18 +189,26 @@ impl RunCommand {
         }
         let engine = Engine::new(&config);
         let store = Store::new(&engine);
+        if let Some(limits) = self.compute_limits() {
+            store.set_limits(limits);
+        }
 
         // Make wasi available by default.
         let preopen_dirs = self.compute_preopen_dirs()?;
         let argv = self.compute_argv();
 
         let mut linker = Linker::new(&store);
-        populate_with_wasi(&mut linker, &preopen_dirs, &argv, &self.vars)?;
+        populate_with_wasi(
+            &mut linker,
+            &preopen_dirs,
+            &argv,
+            &self.vars,
+            self.stdin.as_deref(),
+        )?;
 
         // Load the preload wasm modules.
         for (name, path) in self.preloads.iter() {
-            // Read the wasm module binary either as `*.wat` or a raw binary
-            let module = Module::from_file(&engine, path)?;
+            let module = read_module(&engine, path, self.allow_precompiled)?;
 
             // Add the module's functions to the linker.
             linker.module(name, &module).context(format!(
@@ -195,6 +259,30 @@ impl RunCommand {
         Ok(())
     }
 
+    fn compute_limits(&self) -> Option<StoreLimits> {
+        if self.max_memory.is_none()
+            && self.max_table_elements.is_none()
+            && self.max_instances.is_none()
+        {
+            return None;
+        }
+
+        let mut limits = StoreLimits::new();
+        if let Some(bytes) = self.max_memory {
+            limits.memory_size(bytes);
+        }
+        if let Some(elements) = self.max_table_elements {
+            limits.table_elements(elements);
+        }
+        if let Some(instances) = self.max_instances {
+            limits.instances(instances);
+        }
+        // Report a program that hits a limit rather than relying on it to
+        // notice that growing failed.
+        limits.trap_on_grow_failure(true);
+        Some(limits)
+    }
+
     fn compute_preopen_dirs(&self) -> Result<Vec<(String, File)>> {
         let mut preopen_dirs = Vec::new();
 
@@ -240,17 +328,21 @@ impl RunCommand {
     }
 
     fn load_main_module(&self, linker: &mut Linker) -> Result<()> {
-        if let Some(timeout) = self.wasm_timeout {
-            let handle = linker.store().interrupt_handle()?;
-            thread::spawn(move || {
-                thread::sleep(timeout);
-                handle.interrupt();
-            });
-        }
+        // The timeout covers instantiation as well as the invoked function.
+        let _timer = match self.wasm_timeout {
+            Some(timeout) => Some(linker.store().interrupt_handle()?.interrupt_after(timeout)),
+            None => None,
+        };
 
-        // Read the wasm module binary either as `*.wat` or a raw binary.
         // Use "" as a default module name.
-        let module = Module::from_file(linker.store().engine(), &self.module)?;
+        let module = read_module(
+            linker.store().engine(),
+            &self.module,
+            self.allow_precompiled,
+        )?;
+        if self.trap_unknown_imports {
+            linker.define_unknown_imports_as_traps(&module)?;
+        }
         linker
             .module("", &module)
             .context(format!("failed to instantiate {:?}", self.module))?;
@@ -299,8 +391,8 @@ impl RunCommand {
                 // parses base-10 representations.
                 ValType::I32 => Val::I32(val.parse()?),
                 ValType::I64 => Val::I64(val.parse()?),
-                ValType::F32 => Val::F32(val.parse()?),
-                ValType::F64 => Val::F64(val.parse()?),
+                ValType::F32 => Val::from(val.parse::<f32>()?),
+                ValType::F64 => Val::from(val.parse::<f64>()?),
                 t => bail!("unsupported argument type {:?}", t),
             });
         }
@@ -325,10 +417,10 @@ impl RunCommand {
             match result {
                 Val::I32(i) => println!("{}", i),
                 Val::I64(i) => println!("{}", i),
-                Val::F32(f) => println!("{}", f),
-                Val::F64(f) => println!("{}", f),
+                Val::F32(f) => println!("{}", f32::from_bits(f)),
+                Val::F64(f) => println!("{}", f64::from_bits(f)),
                 Val::ExternRef(_) => println!("<externref>"),
-                Val::FuncRef(_) => println!("<externref>"),
+                Val::FuncRef(_) => println!("<funcref>"),
                 Val::V128(i) => println!("{}", i),
             }
         }
@@ -337,17 +429,69 @@ impl RunCommand {
     }
 }
 
+/// Reads a module from `path`, or from stdin if `path` is `-`.
+///
+/// Modules are always read in full rather than being opened by name so that
+/// pipes, including `/dev/fd/N` paths, work. As there may be no file name to go
+/// by, the contents decide whether this is wasm, in either format, or a
+/// precompiled artifact from `Module::serialize`. The latter contains native
+/// code which is trusted to be what wasmtime generated, so it's only loaded if
+/// `allow_precompiled` is set; otherwise anything which isn't a wasm binary is
+/// reported as invalid text.
+fn read_module(engine: &Engine, path: &Path, allow_precompiled: bool) -> Result<Module> {
+    let bytes = if path == Path::new("-") {
+        let mut bytes = Vec::new();
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .context("failed to read module from stdin")?;
+        bytes
+    } else {
+        fs::read(path).with_context(|| format!("failed to read `{}`", path.display()))?
+    };
+
+    if allow_precompiled && !bytes.starts_with(b"\0asm") && !looks_like_wat(&bytes) {
+        Module::deserialize(engine, &bytes)
+    } else {
+        Module::new(engine, &bytes)
+    }
+}
+
+/// Returns whether `bytes` start out like the wasm text format: with an
+/// s-expression or a comment, after any whitespace.
+fn looks_like_wat(bytes: &[u8]) -> bool {
+    let text = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .map_or(&[][..], |start| &bytes[start..]);
+    text.starts_with(b"(") || text.starts_with(b";;")
+}
+
+/// Opens `path` to be used as a program's stdin.
+fn open_stdin(path: &Path) -> Result<File> {
+    File::open(path).with_context(|| format!("failed to open stdin file `{}`", path.display()))
+}
+
 /// Populates the given `Linker` with WASI APIs.
 fn populate_with_wasi(
     linker: &mut Linker,
     preopen_dirs: &[(String, File)],
     argv: &[String],
     vars: &[(String, String)],
+    stdin: Option<&Path>,
 ) -> Result<()> {
     // Add the current snapshot to the linker.
     let mut cx = WasiCtxBuilder::new();
     cx.inherit_stdio().args(argv).envs(vars);
 
+    if let Some(path) = stdin {
+        let file = open_stdin(path)?;
+        if file.metadata()?.is_file() {
+            cx.stdin(OsFile::try_from(file)?);
+        } else {
+            cx.stdin(OsOther::try_from(file)?);
+        }
+    }
+
     for (name, file) in preopen_dirs {
         cx.preopened_dir(file.try_clone()?, name);
     }
@@ -366,6 +510,10 @@ fn populate_with_wasi(
     let mut cx = wasi_common::old::snapshot_0::WasiCtxBuilder::new();
     cx.inherit_stdio().args(argv).envs(vars);
 
+    if let Some(path) = stdin {
+        cx.stdin(open_stdin(path)?);
+    }
+
     for (name, file) in preopen_dirs {
         cx.preopened_dir(file.try_clone()?, name);
     }
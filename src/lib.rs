@@ -127,10 +127,31 @@ struct CommonOptions {
     #[structopt(long)]
     enable_multi_memory: bool,
 
+    /// Enable support for the memory64 proposal
+    #[structopt(long)]
+    enable_memory64: bool,
+
     /// Enable all experimental Wasm features
     #[structopt(long)]
     enable_all: bool,
 
+    /// Strictly follow the WebAssembly core specification: disable all
+    /// proposals, canonicalize NaNs, and disable the cache
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "enable-simd",
+            "enable-reference-types",
+            "enable-multi-value",
+            "enable-threads",
+            "enable-bulk-memory",
+            "enable-multi-memory",
+            "enable-memory64",
+            "enable-all",
+        ],
+    )]
+    spec_mode: bool,
+
     /// Use Lightbeam for all compilation
     #[structopt(long, conflicts_with = "cranelift")]
     lightbeam: bool,
@@ -198,6 +219,7 @@ impl CommonOptions {
             .wasm_multi_value(self.enable_multi_value.unwrap_or(true) || self.enable_all)
             .wasm_threads(self.enable_threads || self.enable_all)
             .wasm_multi_memory(self.enable_multi_memory || self.enable_all)
+            .wasm_memory64(self.enable_memory64 || self.enable_all)
             .cranelift_opt_level(self.opt_level())
             .strategy(pick_compilation_strategy(self.cranelift, self.lightbeam)?)?
             .profiler(pick_profiling_strategy(self.jitdump, self.vtune)?)?
@@ -207,7 +229,9 @@ impl CommonOptions {
                 config.cranelift_other_flag(name, value)?;
             }
         }
-        if !self.disable_cache {
+        if self.spec_mode {
+            config.strict_spec_mode();
+        } else if !self.disable_cache {
             match &self.config {
                 Some(path) => {
                     config.cache_config_load(path)?;
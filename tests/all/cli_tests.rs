@@ -1,11 +1,11 @@
 use anyhow::{bail, Result};
 use std::io::Write;
 use std::path::Path;
-use std::process::{Command, Output};
+use std::process::{Command, Output, Stdio};
 use tempfile::NamedTempFile;
 
-// Run the wasmtime CLI with the provided args and return the `Output`.
-fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
+// Build a `Command` which runs the wasmtime CLI with the provided args.
+fn wasmtime_command(args: &[&str]) -> Result<Command> {
     let runner = std::env::vars()
         .filter(|(k, _v)| k.starts_with("CARGO_TARGET") && k.ends_with("RUNNER"))
         .next();
@@ -28,7 +28,25 @@ fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
     } else {
         Command::new(&me)
     };
-    cmd.args(args).output().map_err(Into::into)
+    cmd.args(args);
+    Ok(cmd)
+}
+
+// Run the wasmtime CLI with the provided args and return the `Output`.
+fn run_wasmtime_for_output(args: &[&str]) -> Result<Output> {
+    wasmtime_command(args)?.output().map_err(Into::into)
+}
+
+// Run the wasmtime CLI with the provided args, writing `stdin` to it through a
+// pipe, and return the `Output`.
+fn run_wasmtime_with_stdin(args: &[&str], stdin: &[u8]) -> Result<Output> {
+    let mut child = wasmtime_command(args)?
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(stdin)?;
+    child.wait_with_output().map_err(Into::into)
 }
 
 // Run the wasmtime CLI with the provided args and, if it succeeds, return
@@ -85,6 +103,23 @@ fn run_wasmtime_simple_fail_no_args() -> Result<()> {
     Ok(())
 }
 
+// Float arguments and results are converted from and to their decimal forms.
+#[test]
+fn run_wasmtime_float_params() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/float-params.wat")?;
+    let stdout = run_wasmtime(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--invoke",
+        "scale",
+        "--disable-cache",
+        "1.5",
+        "3",
+    ])?;
+    assert_eq!(stdout, "4.5\n");
+    Ok(())
+}
+
 // Running simple wat
 #[test]
 fn run_wasmtime_simple_wat() -> Result<()> {
@@ -361,3 +396,231 @@ fn exit_with_saved_fprs() -> Result<()> {
     assert!(output.stdout.is_empty());
     Ok(())
 }
+
+// Read the main module from stdin, in both the binary and the text format.
+#[test]
+fn run_module_from_stdin() -> Result<()> {
+    let args = ["run", "--disable-cache", "--invoke", "simple", "-", "4"];
+
+    let wasm = wat::parse_file("tests/wasm/simple.wat")?;
+    let output = run_wasmtime_with_stdin(&args, &wasm)?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout)?, "4\n");
+
+    let wat = std::fs::read("tests/wasm/simple.wat")?;
+    let output = run_wasmtime_with_stdin(&args, &wat)?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout)?, "4\n");
+
+    // Anything else is only taken to be a precompiled module on request.
+    let output = run_wasmtime_with_stdin(&args, b"\x01\x02\x03\x04")?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(!stderr.contains("compilation artifacts"), "{}", stderr);
+
+    let output = run_wasmtime_with_stdin(
+        &[
+            "run",
+            "--disable-cache",
+            "--allow-precompiled",
+            "--invoke",
+            "simple",
+            "-",
+            "4",
+        ],
+        b"\x01\x02\x03\x04",
+    )?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("compilation artifacts"), "{}", stderr);
+    Ok(())
+}
+
+// Redirect the program's stdin from a file while the module comes from stdin.
+#[test]
+fn run_module_from_stdin_with_stdin_file() -> Result<()> {
+    let mut input = NamedTempFile::new()?;
+    input.write_all(b"hello from a file\n")?;
+    let wasm = wat::parse_file("tests/wasm/echo_stdin.wat")?;
+    let output = run_wasmtime_with_stdin(
+        &[
+            "run",
+            "--disable-cache",
+            "--stdin",
+            input.path().to_str().unwrap(),
+            "-",
+        ],
+        &wasm,
+    )?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout)?, "hello from a file\n");
+    Ok(())
+}
+
+// Read the main module through a `/dev/fd/N` path which refers to a pipe.
+#[cfg(unix)]
+#[test]
+fn run_module_from_dev_fd() -> Result<()> {
+    let wasm = wat::parse_file("tests/wasm/simple.wat")?;
+    let output = run_wasmtime_with_stdin(
+        &[
+            "run",
+            "--disable-cache",
+            "--invoke",
+            "simple",
+            "/dev/fd/0",
+            "4",
+        ],
+        &wasm,
+    )?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8(output.stdout)?, "4\n");
+    Ok(())
+}
+
+// A module which keeps growing its memory is stopped at `--max-memory`, with
+// a trap naming the limit, while a timeout is also in effect.
+#[test]
+fn max_memory_contains_memory_hog() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/memory_hog.wat")?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--max-memory",
+        "1MiB",
+        "--wasm-timeout",
+        "1min",
+    ])?;
+    assert_eq!(output.stdout, b"");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("memory of 1114112 bytes exceeds the store limit of 1048576 bytes"),
+        "bad stderr: {}",
+        stderr
+    );
+
+    let code = output
+        .status
+        .code()
+        .expect("wasmtime process should exit normally");
+    #[cfg(unix)]
+    assert_eq!(code, 128 + libc::SIGABRT);
+    #[cfg(windows)]
+    assert_eq!(code, 3);
+    Ok(())
+}
+
+// A module whose initial memory is already too large fails to instantiate.
+#[test]
+fn max_memory_rejects_large_initial_memory() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/greeter_reactor.wat")?;
+    let output = run_wasmtime_for_output(&[
+        "run",
+        wasm.path().to_str().unwrap(),
+        "--disable-cache",
+        "--max-memory",
+        "32KiB",
+    ])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("memory of 65536 bytes exceeds the store limit of 32768 bytes"),
+        "bad stderr: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// Instances created for calls into a preloaded command count towards
+// `--max-instances`.
+#[test]
+fn max_instances_counts_command_calls() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/greeter_command.wat")?;
+    let args = |max| {
+        [
+            "run",
+            wasm.path().to_str().unwrap(),
+            "--disable-cache",
+            "--preload",
+            "reactor=tests/wasm/greeter_callable_command.wat",
+            "--max-instances",
+            max,
+        ]
+    };
+
+    // The main command, plus an instance of the preloaded command for its
+    // call to `greet`.
+    let stdout = run_wasmtime(&args("2"))?;
+    assert_eq!(stdout, "Hello _start\nHello callable greet\nHello done\n");
+
+    let output = run_wasmtime_for_output(&args("1"))?;
+    assert!(!output.status.success());
+    assert_eq!(output.stdout, b"Hello _start\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("instantiation exceeds the store limit of 1 instances"),
+        "bad stderr: {}",
+        stderr
+    );
+    Ok(())
+}
+
+// With `--trap-unknown-imports` a module whose imports aren't all provided
+// runs until it calls one of the missing ones.
+#[test]
+fn trap_unknown_imports() -> Result<()> {
+    let wasm = build_wasm("tests/wasm/unknown_import.wat")?;
+    let args = |invoke| {
+        [
+            "run",
+            wasm.path().to_str().unwrap(),
+            "--disable-cache",
+            "--trap-unknown-imports",
+            "--invoke",
+            invoke,
+        ]
+    };
+
+    let output = run_wasmtime_for_output(&args("run")[..3])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown import: `env::missing` has not been defined"),
+        "bad stderr: {}",
+        stderr
+    );
+
+    assert_eq!(run_wasmtime(&args("run"))?, "42\n");
+
+    let output = run_wasmtime_for_output(&args("call_missing"))?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("unknown import called: env::missing"),
+        "bad stderr: {}",
+        stderr
+    );
+    Ok(())
+}
+
+#[test]
+fn diagnose_reports_config() -> Result<()> {
+    let stdout = run_wasmtime(&["diagnose", "--disable-cache", "--enable-simd"])?;
+    assert!(
+        stdout.contains(&format!("version: {}\n", env!("CARGO_PKG_VERSION"))),
+        "bad output: {}",
+        stdout
+    );
+    assert!(stdout.contains("wasm_features.simd: true\n"));
+    assert!(stdout.contains("cranelift_flags.shared.opt_level: "));
+
+    let stdout = run_wasmtime(&["diagnose", "--disable-cache", "--json"])?;
+    assert!(
+        stdout.trim_start().starts_with('{'),
+        "bad output: {}",
+        stdout
+    );
+    assert!(stdout.contains("\"wasm_features\""));
+    Ok(())
+}
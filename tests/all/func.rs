@@ -297,6 +297,21 @@ fn get_from_signature() {
     assert!(f.get1::<i32, f64>().is_ok());
 }
 
+#[test]
+fn get_mismatch_message() {
+    let store = Store::default();
+    let ty = FuncType::new(vec![ValType::I32, ValType::I64], Some(ValType::F64));
+    let f = Func::new(&store, ty, |_, _, _| panic!());
+    let err = f.get2::<i32, i32, f64>().err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Type mismatch: expected a function of type `(i32, i32) -> f64`, \
+         found `(i32, i64) -> f64`"
+    );
+    let err = f.get0::<()>().err().unwrap();
+    assert!(err.to_string().contains("`() -> ()`"), "{}", err);
+}
+
 #[test]
 fn get_from_module() -> anyhow::Result<()> {
     let store = Store::default();
@@ -549,3 +564,115 @@ fn trampolines_always_valid() -> anyhow::Result<()> {
     func.call(&[])?;
     Ok(())
 }
+
+#[test]
+fn on_host_call() -> anyhow::Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    let store = Store::default();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let calls2 = calls.clone();
+    store.on_host_call(move |info| {
+        let args = info.args().iter().map(|a| format!("{:?}", a)).collect();
+        let name = info.name().map(|s| s.to_string());
+        calls2.lock().unwrap().push((name, args));
+    });
+
+    let mut linker = Linker::new(&store);
+    linker.func("env", "wrapped", |a: i32, b: i64| a as i64 + b)?;
+    let dynamic = Func::new(
+        &store,
+        FuncType::new(Some(ValType::F32), None),
+        |_, _, _| Ok(()),
+    );
+    linker.define("env", "dynamic", dynamic)?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "env" "wrapped" (func $wrapped (param i32 i64) (result i64)))
+                (import "env" "dynamic" (func $dynamic (param f32)))
+                (func (export "run")
+                    (drop (call $wrapped (i32.const 1) (i64.const 2)))
+                    (call $dynamic (f32.const 1.5))))
+        "#,
+    )?;
+    let run = linker.instantiate(&module)?.get_func("run").unwrap();
+    run.call(&[])?;
+
+    // Functions which never went through a `Linker` have no name, and calls
+    // made by the host are reported too.
+    let unnamed = Func::wrap(&store, |_: Option<ExternRef>| {});
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $unnamed (param externref)))
+                (func (export "run") (call $unnamed (ref.null extern))))
+        "#,
+    )?;
+    let run = Instance::new(&store, &module, &[unnamed.clone().into()])?
+        .get_func("run")
+        .unwrap();
+    run.call(&[])?;
+    unnamed.call(&[Val::ExternRef(None)])?;
+
+    let calls = calls.lock().unwrap();
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        *calls,
+        [
+            (
+                Some("env::wrapped".to_string()),
+                args(&["I32(1)", "I64(2)"])
+            ),
+            (Some("env::dynamic".to_string()), args(&["F32(1069547520)"])),
+            (None, args(&["ExternRef(None)"])),
+            (None, args(&["ExternRef(None)"])),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn call_results_in_declaration_order() -> anyhow::Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $host (result i32 i64 f32)))
+                (func (export "wasm") (result i32 i64 f32)
+                    i32.const 1
+                    i64.const 2
+                    f32.const 3)
+                (func (export "through_host") (result i32 i64 f32)
+                    call $host))
+        "#,
+    )?;
+    let host = Func::new(
+        &store,
+        FuncType::new(None, vec![ValType::I32, ValType::I64, ValType::F32]),
+        |_, _, results| {
+            results[0] = Val::I32(4);
+            results[1] = Val::I64(5);
+            results[2] = Val::F32(6.0f32.to_bits());
+            Ok(())
+        },
+    );
+    let instance = Instance::new(&store, &module, &[host.clone().into()])?;
+
+    let check = |results: Box<[Val]>, expected: (i32, i64, f32)| {
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].unwrap_i32(), expected.0);
+        assert_eq!(results[1].unwrap_i64(), expected.1);
+        assert_eq!(results[2].unwrap_f32(), expected.2);
+    };
+    check(instance.get_func("wasm").unwrap().call(&[])?, (1, 2, 3.0));
+    check(
+        instance.get_func("through_host").unwrap().call(&[])?,
+        (4, 5, 6.0),
+    );
+    check(host.call(&[])?, (4, 5, 6.0));
+    Ok(())
+}
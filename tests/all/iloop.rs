@@ -1,4 +1,5 @@
 use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::time::{Duration, Instant};
 use wasmtime::*;
 
 fn interruptable_store() -> Store {
@@ -134,3 +135,196 @@ fn function_interrupt_from_afar() -> anyhow::Result<()> {
     );
     Ok(())
 }
+
+fn assert_deadline_exceeded(err: &anyhow::Error, func_name: &str) {
+    assert!(
+        err.to_string().contains("wasm call exceeded its deadline"),
+        "bad message: {:?}",
+        err
+    );
+    let trap = err
+        .downcast_ref::<Trap>()
+        .expect("the error should be a trap");
+    assert_eq!(trap.trap_code(), Some(TrapCode::Interrupt));
+    assert_eq!(trap.trace()[0].func_name(), Some(func_name));
+}
+
+#[test]
+fn call_with_deadline_spin() -> anyhow::Result<()> {
+    let store = interruptable_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (func $spin (export "spin") (loop br 0))
+                (func (export "quick") (result i32) i32.const 1))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let spin = instance.get_func("spin").unwrap();
+    let quick = instance.get_func("quick").unwrap();
+
+    let start = Instant::now();
+    let err = spin
+        .call_with_deadline(&[], Duration::from_millis(50))
+        .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_deadline_exceeded(&err, "spin");
+
+    let results = quick.call_with_deadline(&[], Duration::from_secs(60))?;
+    assert_eq!(results[0].unwrap_i32(), 1);
+    quick.call(&[])?;
+    Ok(())
+}
+
+#[test]
+fn call_with_deadline_requires_interrupts() -> anyhow::Result<()> {
+    let store = Store::default();
+    let func = Func::wrap(&store, || {});
+    assert!(func
+        .call_with_deadline(&[], Duration::from_secs(1))
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn call_with_deadline_blocked_in_host() -> anyhow::Result<()> {
+    static HOST_DONE: AtomicUsize = AtomicUsize::new(0);
+    let store = interruptable_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $block))
+                (func $run (export "run") (call $block) (loop br 0)))
+        "#,
+    )?;
+    let block = Func::wrap(&store, || {
+        std::thread::sleep(Duration::from_millis(200));
+        HOST_DONE.fetch_add(1, SeqCst);
+    });
+    let instance = Instance::new(&store, &module, &[block.into()])?;
+    let run = instance.get_func("run").unwrap();
+
+    // The host function finishes, and then the wasm which called it traps.
+    let start = Instant::now();
+    let err = run
+        .call_with_deadline(&[], Duration::from_millis(20))
+        .unwrap_err();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(HOST_DONE.load(SeqCst), 1);
+    assert_deadline_exceeded(&err, "run");
+    Ok(())
+}
+
+#[test]
+fn call_with_deadline_sleeping_in_poll_oneoff() -> anyhow::Result<()> {
+    let store = interruptable_store();
+    let mut linker = Linker::new(&store);
+    wasmtime_wasi::Wasi::new(&store, wasi_common::WasiCtxBuilder::new().build()?)
+        .add_to_linker(&mut linker)?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "wasi_snapshot_preview1" "poll_oneoff"
+                    (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+
+                ;; Sleeps for 200ms on the monotonic clock, and then spins.
+                (func $run (export "run")
+                    (i32.store (i32.const 16) (i32.const 1))
+                    (i64.store (i32.const 24) (i64.const 200000000))
+                    (drop (call $poll_oneoff
+                        (i32.const 0) (i32.const 64) (i32.const 1) (i32.const 128)))
+                    (loop br 0)))
+        "#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let run = instance.get_func("run").unwrap();
+
+    let start = Instant::now();
+    let err = run
+        .call_with_deadline(&[], Duration::from_millis(20))
+        .unwrap_err();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_deadline_exceeded(&err, "run");
+    Ok(())
+}
+
+#[test]
+fn undelivered_deadline_is_withdrawn() -> anyhow::Result<()> {
+    let store = interruptable_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "" (func $block))
+                (func (export "run") (call $block))
+                (func (export "spin_briefly") (param i32)
+                    (loop
+                        (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                        (br_if 0 (local.get 0)))))
+        "#,
+    )?;
+    let block = Func::wrap(&store, || {
+        std::thread::sleep(Duration::from_millis(100));
+    });
+    let instance = Instance::new(&store, &module, &[block.into()])?;
+
+    // There's nowhere for the interrupt to be delivered after the host
+    // function returns, so the call succeeds...
+    let run = instance.get_func("run").unwrap();
+    run.call_with_deadline(&[], Duration::from_millis(10))?;
+
+    // ... and the interrupt doesn't leak into later calls.
+    let spin_briefly = instance.get_func("spin_briefly").unwrap();
+    spin_briefly.call(&[Val::I32(1000)])?;
+    Ok(())
+}
+
+#[test]
+fn many_interrupt_timers() -> anyhow::Result<()> {
+    let store = interruptable_store();
+    let other = Store::new(store.engine());
+    let handle = store.interrupt_handle()?;
+    let other_handle = other.interrupt_handle()?;
+
+    // Timers of all of the engine's stores fire in deadline order, no matter
+    // the order they're created in, and cancelled ones never fire.
+    let late = handle.interrupt_after(Duration::from_secs(60));
+    let cancelled = (0..1000)
+        .map(|i| other_handle.interrupt_after(Duration::from_secs(30 + i % 50)))
+        .collect::<Vec<_>>();
+    drop(cancelled);
+    let early = other_handle.interrupt_after(Duration::from_millis(10));
+    let start = Instant::now();
+    while !early.fired() {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(!late.fired());
+    drop(late);
+    drop(early);
+
+    // The interrupt which fired was never delivered, so dropping its timer
+    // withdrew it.
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (func $spin (export "spin") (loop br 0))
+                (func (export "quick")))
+        "#,
+    )?;
+    let instance = Instance::new(&other, &module, &[])?;
+    instance.get_func("quick").unwrap().call(&[])?;
+
+    // New timers still work afterwards.
+    let spin = instance.get_func("spin").unwrap();
+    let err = spin
+        .call_with_deadline(&[], Duration::from_millis(20))
+        .unwrap_err();
+    assert_deadline_exceeded(&err, "spin");
+    Ok(())
+}
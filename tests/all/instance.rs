@@ -11,3 +11,108 @@ fn wrong_import_numbers() -> Result<()> {
     assert!(Instance::new(&store, &module, &[func.clone().into(), func.into()]).is_err());
     Ok(())
 }
+
+#[test]
+fn table_init_before_start() -> Result<()> {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "table" (table 1 funcref))
+                (type $t (func (param i32)))
+                (table $plugins 10 funcref)
+                (func $default (param i32) unreachable)
+                (elem (table $plugins) (i32.const 4) func $default $default)
+                (func $start
+                    (call_indirect $plugins (type $t) (i32.const 42) (i32.const 5)))
+                (start $start))
+        "#,
+    )?;
+    let imported = Table::new(
+        &store,
+        TableType::new(ValType::FuncRef, Limits::new(1, None)),
+        Val::FuncRef(None),
+    )?;
+
+    let called = Rc::new(Cell::new(None));
+    let called2 = called.clone();
+    let plugin = Func::wrap(&store, move |x: i32| called2.set(Some(x)));
+    Instance::new_with_table_init(&store, &module, &[imported.clone().into()], |tables| {
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].size(), 1);
+        assert_eq!(tables[1].size(), 10);
+        // The element segment has already been applied, and `init` overrides
+        // its entry at index 5.
+        assert!(tables[1].get(4).unwrap().unwrap_funcref().is_some());
+        assert!(tables[1].get(5).unwrap().unwrap_funcref().is_some());
+        tables[1].set(5, plugin.into())
+    })?;
+    assert_eq!(called.get(), Some(42));
+
+    // An error from `init` fails instantiation without running `start`.
+    called.set(None);
+    let err = Instance::new_with_table_init(&store, &module, &[imported.into()], |_| {
+        anyhow::bail!("no plugins today")
+    })
+    .err()
+    .unwrap();
+    assert_eq!(err.to_string(), "no plugins today");
+    assert_eq!(called.get(), None);
+    Ok(())
+}
+
+#[test]
+fn exports_observe_guest_state() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") 1)
+                (global (export "status") (mut i32) (i32.const 0))
+                (global (export "pages") i32 (i32.const 1))
+                (table (export "table") 2 funcref)
+                (elem (i32.const 1) $run)
+                (func $run (export "run")
+                    (drop (memory.grow (i32.const 1)))
+                    (i32.store (i32.const 65536) (i32.const 0x2a))
+                    (global.set 0 (i32.const 7))))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("memory").unwrap();
+    let status = instance.get_global("status").unwrap();
+    assert_eq!(memory.size(), 1);
+    assert!(memory.read(65536, &mut [0; 4]).is_err());
+
+    // Handles taken before the guest grew its memory see the new pages.
+    instance.get_func("run").unwrap().call(&[])?;
+    assert_eq!(memory.size(), 2);
+    let mut word = [0; 4];
+    memory.read(65536, &mut word)?;
+    assert_eq!(u32::from_le_bytes(word), 0x2a);
+    memory.write_slice(131068, &[1, 2, 3, 4])?;
+    assert!(memory.write_slice(131069, &[1, 2, 3, 4]).is_err());
+    assert_eq!(status.get().unwrap_i32(), 7);
+
+    // Globals can only be set to values of their type, if they're mutable.
+    status.set(Val::I32(0))?;
+    assert!(status.set(Val::I64(0)).is_err());
+    assert!(instance
+        .get_global("pages")
+        .unwrap()
+        .set(Val::I32(2))
+        .is_err());
+    assert_eq!(status.get().unwrap_i32(), 0);
+
+    let table = instance.get_table("table").unwrap();
+    assert_eq!(table.size(), 2);
+    assert!(table.get(0).unwrap().unwrap_funcref().is_none());
+    assert!(table.get(1).unwrap().unwrap_funcref().is_some());
+    assert!(table.get(2).is_none());
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use std::convert::TryInto;
+use std::fs;
+use wasmtime::*;
+
+const JIT_CODE_LOAD: u32 = 0;
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_ne_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[test]
+fn records_functions_and_trampolines() -> Result<()> {
+    let mut config = Config::new();
+    config.profiler(ProfilingStrategy::JitDump)?;
+    let engine = Engine::new(&config);
+    let path = format!("./jit-{}.dump", std::process::id());
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func $named (export "named") (param i32) (result i32)
+                    local.get 0)
+                (func (export "unnamed")))
+        "#,
+    );
+    let bytes = fs::read(&path);
+    fs::remove_file(&path)?;
+    module?;
+    let bytes = bytes?;
+
+    // The file header.
+    assert_eq!(u32_at(&bytes, 0), 0x4A695444);
+    assert_eq!(u32_at(&bytes, 4), 1);
+    let header_size = u32_at(&bytes, 8) as usize;
+    assert_eq!(header_size, 40);
+    assert_eq!(u32_at(&bytes, 20), std::process::id());
+
+    // Followed by a code load record for each function and trampoline.
+    let mut names = Vec::new();
+    let mut offset = header_size;
+    while offset < bytes.len() {
+        let id = u32_at(&bytes, offset);
+        let size = u32_at(&bytes, offset + 4) as usize;
+        assert!(offset + size <= bytes.len(), "truncated record");
+        if id == JIT_CODE_LOAD {
+            let code_size = u64_at(&bytes, offset + 40) as usize;
+            let name = &bytes[offset + 56..offset + size - code_size];
+            assert_eq!(name.last(), Some(&0));
+            names.push(String::from_utf8(name[..name.len() - 1].to_vec())?);
+        }
+        offset += size;
+    }
+    assert_eq!(offset, bytes.len());
+    assert!(names.contains(&"named".to_string()), "names: {:?}", names);
+    assert!(
+        names.contains(&"wasm::function[1]".to_string()),
+        "names: {:?}",
+        names
+    );
+    assert!(
+        names
+            .iter()
+            .any(|name| name.starts_with("wasm::trampoline[")),
+        "names: {:?}",
+        names
+    );
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use anyhow::Result;
+use wasi_common::virtfs::pipe::ReadPipe;
+use wasi_common::WasiCtxBuilder;
+use wasmtime::*;
+use wasmtime_wasi::Wasi;
+
+const MODULE: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (table (export "table") 1 funcref)
+        (func (export "grow") (param i32) (result i32)
+            (memory.grow (local.get 0)))
+    )
+"#;
+
+fn limited_store(limits: &StoreLimits) -> Store {
+    let store = Store::default();
+    store.set_limits(limits.clone());
+    store
+}
+
+#[test]
+fn memory_growth_is_limited() -> Result<()> {
+    let mut limits = StoreLimits::new();
+    limits.memory_size(2 * 0x10000);
+    let store = limited_store(&limits);
+    let module = Module::new(store.engine(), MODULE)?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+    let memory = instance.get_memory("memory").unwrap();
+
+    assert_eq!(grow(1)?, 1);
+    assert_eq!(grow(1)?, -1);
+    assert_eq!(memory.size(), 2);
+
+    let err = memory.grow(1).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("memory of 196608 bytes exceeds the store limit of 131072 bytes"),
+        "bad error: {}",
+        err
+    );
+    assert_eq!(memory.grow(0)?, 2);
+    Ok(())
+}
+
+#[test]
+fn trap_on_grow_failure() -> Result<()> {
+    let mut limits = StoreLimits::new();
+    limits.memory_size(2 * 0x10000).trap_on_grow_failure(true);
+    let store = limited_store(&limits);
+    let module = Module::new(store.engine(), MODULE)?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+
+    assert_eq!(grow(1)?, 1);
+    let trap = grow(1).unwrap_err();
+    assert!(
+        trap.to_string()
+            .contains("memory of 196608 bytes exceeds the store limit of 131072 bytes"),
+        "bad trap: {}",
+        trap
+    );
+    Ok(())
+}
+
+#[test]
+fn initial_sizes_are_limited() -> Result<()> {
+    let mut limits = StoreLimits::new();
+    limits.memory_size(0x10000).table_elements(10);
+    let store = limited_store(&limits);
+    let engine = store.engine();
+
+    let module = Module::new(engine, "(module (memory 2))")?;
+    let err = Instance::new(&store, &module, &[]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("memory of 131072 bytes exceeds the store limit of 65536 bytes"),
+        "bad error: {}",
+        err
+    );
+
+    let module = Module::new(engine, "(module (table 11 funcref))")?;
+    let err = Instance::new(&store, &module, &[]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("table of 11 elements exceeds the store limit of 10 elements"),
+        "bad error: {}",
+        err
+    );
+
+    // Host-defined items are limited as well.
+    let ty = TableType::new(ValType::FuncRef, Limits::new(11, None));
+    assert!(Table::new(&store, ty, Val::FuncRef(None)).is_err());
+    let ty = TableType::new(ValType::FuncRef, Limits::new(10, None));
+    let table = Table::new(&store, ty, Val::FuncRef(None))?;
+    assert!(table.grow(1, Val::FuncRef(None)).is_err());
+    Ok(())
+}
+
+#[test]
+fn instances_are_limited() -> Result<()> {
+    let mut limits = StoreLimits::new();
+    limits.instances(2);
+    let store = limited_store(&limits);
+    let module = Module::new(store.engine(), MODULE)?;
+
+    // Host items don't count as instances.
+    Func::wrap(&store, || {});
+    Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+
+    Instance::new(&store, &module, &[])?;
+    Instance::new(&store, &module, &[])?;
+    let err = Instance::new(&store, &module, &[]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("instantiation exceeds the store limit of 2 instances"),
+        "bad error: {}",
+        err
+    );
+
+    // The limit is per store.
+    let other = limited_store(&limits);
+    Instance::new(&other, &module, &[])?;
+    Ok(())
+}
+
+#[test]
+fn greedy_growth_observes_failure() -> Result<()> {
+    let mut limits = StoreLimits::new();
+    limits.memory_size(64 << 20);
+    let store = limited_store(&limits);
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") 1)
+                ;; Grows memory a page at a time until that fails, returning the
+                ;; number of pages it ended up with.
+                (func (export "grow_all") (result i32)
+                    (block $done
+                        (loop $grow
+                            (br_if $done (i32.eq (memory.grow (i32.const 1)) (i32.const -1)))
+                            (br $grow)))
+                    (memory.size))
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let grow_all = instance.get_func("grow_all").unwrap().get0::<i32>()?;
+    assert_eq!(grow_all()?, 1024);
+    assert_eq!(instance.get_memory("memory").unwrap().size(), 1024);
+
+    // The store is still usable afterwards.
+    assert_eq!(grow_all()?, 1024);
+    let instance = Instance::new(&store, &module, &[])?;
+    assert_eq!(instance.get_memory("memory").unwrap().size(), 1);
+    Ok(())
+}
+
+#[test]
+fn wasi_within_limits() -> Result<()> {
+    const ERRNO_SUCCESS: i32 = 0;
+    const ERRNO_FAULT: i32 = 21;
+
+    // WASI is made of host functions, so it doesn't count as an instance.
+    let mut limits = StoreLimits::new();
+    limits.memory_size(0x10000).instances(1);
+    let store = limited_store(&limits);
+    let ctx = WasiCtxBuilder::new()
+        .stdin(ReadPipe::from("hello"))
+        .build()?;
+    let mut linker = Linker::new(&store);
+    Wasi::new(&store, ctx).add_to_linker(&mut linker)?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "wasi_snapshot_preview1" "fd_read"
+                    (func $fd_read (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "grow") (param i32) (result i32)
+                    (memory.grow (local.get 0)))
+                ;; Reads stdin into `len` bytes at `buf`, returning the errno.
+                (func (export "read") (param $buf i32) (param $len i32) (result i32)
+                    (i32.store (i32.const 0) (local.get $buf))
+                    (i32.store (i32.const 4) (local.get $len))
+                    (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+            )
+        "#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let grow = instance.get_func("grow").unwrap().get1::<i32, i32>()?;
+    let read = instance.get_func("read").unwrap().get2::<i32, i32, i32>()?;
+    let memory = instance.get_memory("memory").unwrap();
+
+    // Memory can't grow to make room for a larger buffer, and WASI doesn't
+    // read past the end of memory.
+    assert_eq!(grow(1)?, -1);
+    assert_eq!(read(0xfff0, 0x100)?, ERRNO_FAULT);
+    assert_eq!(read(0xfff0, 0x10)?, ERRNO_SUCCESS);
+    assert_eq!(
+        unsafe { &memory.data_unchecked()[0xfff0..0xfff5] },
+        b"hello"
+    );
+    Ok(())
+}
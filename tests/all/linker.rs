@@ -19,6 +19,67 @@ fn link_undefined() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn link_reports_every_missing_import() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.func("env", "f", |_: i32| {})?;
+    linker.func("env", "ok", || {})?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "env" "missing" (func))
+                (import "env" "ok" (func))
+                (import "env" "f" (func (param i64)))
+                (import "other" "memory" (memory 1)))
+        "#,
+    )?;
+    let err = linker.instantiate(&module).unwrap_err();
+    let missing = err.downcast_ref::<MissingImports>().unwrap();
+    let problems = missing.problems();
+    assert_eq!(problems.len(), 3);
+    match &problems[0] {
+        ImportProblem::Undefined { module, name, ty } => {
+            assert_eq!((module.as_str(), name.as_str()), ("env", "missing"));
+            assert!(ty.func().is_some());
+        }
+        p => panic!("unexpected problem {:?}", p),
+    }
+    match &problems[1] {
+        ImportProblem::IncompatibleType {
+            module,
+            name,
+            expected,
+            available,
+        } => {
+            assert_eq!((module.as_str(), name.as_str()), ("env", "f"));
+            assert_eq!(expected.func().unwrap().params(), [ValType::I64]);
+            assert_eq!(available.len(), 1);
+            assert_eq!(available[0].func().unwrap().params(), [ValType::I32]);
+        }
+        p => panic!("unexpected problem {:?}", p),
+    }
+    match &problems[2] {
+        ImportProblem::Undefined { module, name, ty } => {
+            assert_eq!((module.as_str(), name.as_str()), ("other", "memory"));
+            assert!(ty.memory().is_some());
+        }
+        p => panic!("unexpected problem {:?}", p),
+    }
+
+    let message = err.to_string();
+    assert!(
+        message.starts_with("3 imports couldn't be resolved"),
+        "{}",
+        message
+    );
+    for name in ["env::missing", "env::f", "other::memory"].iter() {
+        assert!(message.contains(name), "{} not in {}", name, message);
+    }
+    Ok(())
+}
+
 #[test]
 fn link_twice_bad() -> Result<()> {
     let store = Store::default();
@@ -68,6 +129,61 @@ fn link_twice_bad() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn link_checks_types_shared_by_definitions() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.func("env", "f", |_: i32| {})?;
+    linker.func("env", "g", |_: i64| {})?;
+    let ty = GlobalType::new(ValType::I32, Mutability::Const);
+    linker.define("env", "c", Global::new(&store, ty, Val::I32(0))?)?;
+    let ty = GlobalType::new(ValType::I32, Mutability::Var);
+    linker.define("env", "v", Global::new(&store, ty, Val::I32(0))?)?;
+
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "env" "f" (func (param i32)))
+                (import "env" "g" (func (param i64)))
+                (import "env" "c" (global i32))
+                (import "env" "v" (global (mut i32))))
+        "#,
+    )?;
+    for _ in 0..3 {
+        linker.instantiate(&module)?;
+    }
+
+    // Each of these types is that of some definition, just not of the one
+    // with the import's name.
+    for wat in [
+        r#"(module (import "env" "f" (func (param i64))))"#,
+        r#"(module (import "env" "g" (func (param i32))))"#,
+        r#"(module (import "env" "c" (global (mut i32))))"#,
+        r#"(module (import "env" "v" (global i32)))"#,
+    ]
+    .iter()
+    {
+        let module = Module::new(store.engine(), wat)?;
+        let err = linker.instantiate(&module).unwrap_err();
+        assert!(
+            err.to_string().contains("incompatible import type"),
+            "bad error for {}: {}",
+            wat,
+            err
+        );
+    }
+
+    let err = linker.func("env", "g", |_: i64| {}).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("import of `env::g` with kind Func(FuncType"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
 #[test]
 fn function_interposition() -> Result<()> {
     let store = Store::default();
@@ -160,3 +276,188 @@ fn module_interposition() -> Result<()> {
     assert_eq!(func()?, 112);
     Ok(())
 }
+
+#[test]
+fn stub_unknown_imports() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"
+        (module
+            (import "env" "foo" (func $foo (result i32 f64)))
+            (import "env" "known" (func $known (result i32)))
+            (func (export "call_foo") (result i32 f64) call $foo)
+            (func (export "call_known") (result i32) call $known)
+        )
+    "#;
+    let module = Module::new(store.engine(), wat)?;
+
+    let mut linker = Linker::new(&store);
+    linker.func("env", "known", || 7)?;
+    linker.define_unknown_imports_as_traps(&module)?;
+    let instance = linker.instantiate(&module)?;
+
+    // Items which were already defined aren't replaced, and the module runs
+    // fine as long as it doesn't call a stub.
+    assert_eq!(
+        instance.get_func("call_known").unwrap().call(&[])?[0].unwrap_i32(),
+        7
+    );
+    let trap = instance
+        .get_func("call_foo")
+        .unwrap()
+        .call(&[])
+        .unwrap_err()
+        .downcast::<Trap>()?;
+    assert!(
+        trap.to_string().contains("unknown import called: env::foo"),
+        "bad trap: {}",
+        trap
+    );
+
+    let mut linker = Linker::new(&store);
+    linker.define_unknown_imports_as_default_values(&module)?;
+    let instance = linker.instantiate(&module)?;
+    let results = instance.get_func("call_foo").unwrap().call(&[])?;
+    assert_eq!(results[0].unwrap_i32(), 0);
+    assert_eq!(results[1].unwrap_f64(), 0.0);
+    assert_eq!(
+        instance.get_func("call_known").unwrap().call(&[])?[0].unwrap_i32(),
+        0
+    );
+    Ok(())
+}
+
+#[test]
+fn stub_unknown_non_function_imports() -> Result<()> {
+    let store = Store::default();
+    let wat = r#"
+        (module
+            (import "env" "foo" (func $foo))
+            (import "env" "g" (global i64))
+            (import "env" "m" (memory 1))
+            (import "env" "t" (table 2 funcref))
+            (func (export "g") (result i64) global.get 0)
+            (func (export "size") (result i32) memory.size)
+        )
+    "#;
+    let module = Module::new(store.engine(), wat)?;
+
+    // Only functions can be stubbed with traps, and nothing is defined when
+    // something else is missing.
+    let mut linker = Linker::new(&store);
+    let err = linker
+        .define_unknown_imports_as_traps(&module)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        err.contains("`env::g` is a global, which can't be stubbed with a trap"),
+        "bad error: {}",
+        err
+    );
+    assert_eq!(linker.iter().count(), 0);
+
+    let global = Global::new(
+        &store,
+        GlobalType::new(ValType::I64, Mutability::Const),
+        Val::I64(3),
+    )?;
+    let memory = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    let table = Table::new(
+        &store,
+        TableType::new(ValType::FuncRef, Limits::new(2, None)),
+        Val::FuncRef(None),
+    )?;
+    linker.define("env", "g", global)?;
+    linker.define("env", "m", memory)?;
+    linker.define("env", "t", table)?;
+    linker.define_unknown_imports_as_traps(&module)?;
+    let instance = linker.instantiate(&module)?;
+    assert_eq!(
+        instance.get_func("g").unwrap().call(&[])?[0].unwrap_i64(),
+        3
+    );
+
+    // Default values stub everything.
+    let mut linker = Linker::new(&store);
+    linker.define_unknown_imports_as_default_values(&module)?;
+    let instance = linker.instantiate(&module)?;
+    assert_eq!(
+        instance.get_func("g").unwrap().call(&[])?[0].unwrap_i64(),
+        0
+    );
+    assert_eq!(
+        instance.get_func("size").unwrap().call(&[])?[0].unwrap_i32(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn host_funcs_alongside_wasi() -> Result<()> {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    let ctx = wasi_common::WasiCtxBuilder::new().build()?;
+    wasmtime_wasi::Wasi::new(&store, ctx).add_to_linker(&mut linker)?;
+
+    // `env.log` decodes a string from the caller's memory, and then calls
+    // back into the caller to have it `double` the string's length.
+    let logged = Rc::new(RefCell::new(Vec::new()));
+    let log = logged.clone();
+    linker.func(
+        "env",
+        "log",
+        move |caller: Caller<'_>, ptr: u32, len: u32| -> Result<i32, Trap> {
+            let memory = match caller.get_export("memory") {
+                Some(Extern::Memory(memory)) => memory,
+                _ => return Err(Trap::new("missing memory export")),
+            };
+            let s = memory
+                .read_string(ptr as usize, len as usize)
+                .map_err(|e| Trap::new(e.to_string()))?;
+            log.borrow_mut().push(s);
+            let double = match caller.get_export("double") {
+                Some(Extern::Func(double)) => double.get1::<i32, i32>().unwrap(),
+                _ => return Err(Trap::new("missing double export")),
+            };
+            double(len as i32)
+        },
+    )?;
+
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (import "env" "log" (func $log (param i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "hello")
+                (func (export "double") (param i32) (result i32)
+                    (i32.mul (local.get 0) (i32.const 2)))
+                (func (export "run") (param i32) (result i32)
+                    (call $log (i32.const 16) (local.get 0)))
+                ;; Writes nothing to stdout, returning the errno.
+                (func (export "write") (result i32)
+                    (call $fd_write (i32.const 1) (i32.const 0) (i32.const 0) (i32.const 8))))
+        "#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let run = instance.get_func("run").unwrap().get1::<i32, i32>()?;
+    assert_eq!(run(5)?, 10);
+    assert_eq!(run(4)?, 8);
+    assert_eq!(*logged.borrow(), ["hello", "hell"]);
+    let write = instance.get_func("write").unwrap().get0::<i32>()?;
+    assert_eq!(write()?, 0);
+
+    // Errors from the host function trap the guest which called it.
+    let err = run(65536).unwrap_err();
+    assert!(
+        err.to_string().contains("out of bounds"),
+        "bad error: {}",
+        err
+    );
+    assert_eq!(logged.borrow().len(), 2);
+    Ok(())
+}
@@ -10,15 +10,30 @@ mod import_calling_export;
 mod import_indexes;
 mod instance;
 mod invoke_func_via_table;
+#[cfg(all(target_os = "linux", feature = "jitdump"))]
+mod jitdump;
+mod limits;
 mod linker;
+mod memory;
+mod memory64;
 mod memory_creator;
+mod module;
 mod module_linking;
 mod module_serialize;
 mod name;
+mod pooling;
+mod resource_table;
+mod sampling;
 mod stack_overflow;
 mod table;
 mod traps;
 mod use_after_drop;
+mod val_json;
+mod wasi_deadline;
+mod wasi_determinism;
+mod wasi_exit;
+mod wasi_tenants;
+mod wasm_features;
 mod wast;
 
 // TODO(#1886): Cranelift only supports reference types on x64.
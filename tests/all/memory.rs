@@ -0,0 +1,435 @@
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use wasmtime::*;
+
+fn one_page_memory(store: &Store) -> Memory {
+    Memory::new(store, MemoryType::new(Limits::new(1, None)))
+}
+
+#[test]
+fn read_write_slice() -> Result<()> {
+    let store = Store::default();
+    let memory = one_page_memory(&store);
+
+    memory.write_slice(10, b"hello")?;
+    let mut buf = [0; 5];
+    memory.read(10, &mut buf)?;
+    assert_eq!(&buf, b"hello");
+
+    // Accesses which end exactly at the end of memory are fine.
+    memory.write_slice(65531, b"world")?;
+    memory.read(65531, &mut buf)?;
+    assert_eq!(&buf, b"world");
+    memory.read(65536, &mut [])?;
+
+    // ... but not one byte past it.
+    assert_eq!(
+        memory.write_slice(65532, b"world"),
+        Err(MemoryAccessError::OutOfBounds {
+            offset: 65532,
+            len: 5,
+            memory_size: 65536,
+        })
+    );
+    assert!(memory.read(65532, &mut buf).is_err());
+    assert!(memory.read(65537, &mut []).is_err());
+    Ok(())
+}
+
+#[test]
+fn adversarial_offsets() -> Result<()> {
+    let store = Store::default();
+    let memory = one_page_memory(&store);
+    let mut buf = [0; 16];
+
+    for offset in [u32::max_value() as usize - 1, u32::max_value() as usize].iter() {
+        match memory.read(*offset, &mut buf) {
+            Err(MemoryAccessError::OutOfBounds { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match memory.write_slice(*offset, &buf) {
+            Err(MemoryAccessError::OutOfBounds { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(memory.read_string(*offset, 4).is_err());
+        assert!(memory.read_nul_terminated(*offset, 4).is_err());
+    }
+
+    // Lengths which wrap around the address space.
+    assert_eq!(
+        memory.read_string(16, usize::max_value()),
+        Err(MemoryAccessError::Overflow {
+            offset: 16,
+            len: usize::max_value(),
+        })
+    );
+    assert_eq!(
+        memory.read(usize::max_value(), &mut buf),
+        Err(MemoryAccessError::Overflow {
+            offset: usize::max_value(),
+            len: 16,
+        })
+    );
+    // A huge `max_len` is bounded by the size of memory instead.
+    memory.write_slice(100, b"abc\0")?;
+    assert_eq!(memory.read_nul_terminated(100, usize::max_value())?, "abc");
+    Ok(())
+}
+
+#[test]
+fn strings() -> Result<()> {
+    let store = Store::default();
+    let memory = one_page_memory(&store);
+
+    memory.write_slice(0, b"hello\0world")?;
+    assert_eq!(memory.read_string(0, 5)?, "hello");
+    assert_eq!(memory.read_string(6, 0)?, "");
+    assert_eq!(memory.read_nul_terminated(0, 100)?, "hello");
+    assert_eq!(memory.read_nul_terminated(0, 5)?, "hello");
+    assert_eq!(
+        memory.read_nul_terminated(0, 4),
+        Err(MemoryAccessError::MissingNul {
+            offset: 0,
+            max_len: 4,
+        })
+    );
+
+    memory.write_slice(200, &[0xff, 0xfe, 0])?;
+    match memory.read_string(200, 2) {
+        Err(MemoryAccessError::InvalidUtf8(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match memory.read_nul_terminated(200, 10) {
+        Err(MemoryAccessError::InvalidUtf8(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // A string running into the end of memory without a terminator.
+    memory.write_slice(65534, b"ab")?;
+    match memory.read_nul_terminated(65534, 10) {
+        Err(MemoryAccessError::OutOfBounds { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn typed_pointers() -> Result<()> {
+    let store = Store::default();
+    let memory = one_page_memory(&store);
+
+    let ptr = WasmPtr::<u32>::new(4);
+    ptr.write(&memory, 0x0102_0304)?;
+    let mut bytes = [0; 4];
+    memory.read(4, &mut bytes)?;
+    assert_eq!(bytes, [4, 3, 2, 1]);
+    assert_eq!(ptr.read(&memory)?, 0x0102_0304);
+
+    let ptr = WasmPtr::<f64>::new(16);
+    ptr.write(&memory, 1.5)?;
+    assert_eq!(ptr.read(&memory)?, 1.5);
+
+    assert_eq!(
+        WasmPtr::<u32>::new(6).read(&memory),
+        Err(MemoryAccessError::Unaligned {
+            offset: 6,
+            align: 4,
+        })
+    );
+    assert!(WasmPtr::<u64>::new(65528).read(&memory).is_ok());
+    match WasmPtr::<u64>::new(65536).read(&memory) {
+        Err(MemoryAccessError::OutOfBounds { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    match WasmPtr::<u32>::new(u32::max_value() - 3).write(&memory, 1) {
+        Err(MemoryAccessError::OutOfBounds { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert_eq!(WasmPtr::<u16>::new(2).add(3)?.offset(), 8);
+    match WasmPtr::<u32>::new(u32::max_value() - 3).add(1) {
+        Err(MemoryAccessError::Overflow { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(WasmPtr::<u64>::new(0).add(u32::max_value()).is_err());
+    Ok(())
+}
+
+#[test]
+fn from_caller() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "log" (func $log (param i32 i32)))
+                (import "" "log_c" (func $log_c (param i32)))
+                (import "" "set" (func $set (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "from wasm")
+                (data (i32.const 32) "c string\00")
+                (func (export "run") (result i32)
+                    (call $log (i32.const 8) (i32.const 9))
+                    (call $log_c (i32.const 32))
+                    (call $set (i32.const 64))
+                    (i32.load (i32.const 64)))
+            )
+        "#,
+    )?;
+
+    let logged = Rc::new(RefCell::new(Vec::new()));
+    let memory = |caller: &Caller<'_>| match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => Ok(mem),
+        _ => Err(Trap::new("failed to find memory")),
+    };
+    let log = {
+        let logged = logged.clone();
+        Func::wrap(&store, move |caller: Caller<'_>, ptr: u32, len: u32| {
+            let s = memory(&caller)?
+                .read_string(ptr as usize, len as usize)
+                .map_err(|e| Trap::new(e.to_string()))?;
+            logged.borrow_mut().push(s);
+            Ok(())
+        })
+    };
+    let log_c = {
+        let logged = logged.clone();
+        Func::wrap(&store, move |caller: Caller<'_>, ptr: u32| {
+            let s = memory(&caller)?
+                .read_nul_terminated(ptr as usize, 1024)
+                .map_err(|e| Trap::new(e.to_string()))?;
+            logged.borrow_mut().push(s);
+            Ok(())
+        })
+    };
+    let set = Func::wrap(&store, move |caller: Caller<'_>, ptr: u32| {
+        WasmPtr::<i32>::new(ptr)
+            .write(&memory(&caller)?, 42)
+            .map_err(|e| Trap::new(e.to_string()))
+    });
+
+    let instance = Instance::new(&store, &module, &[log.into(), log_c.into(), set.into()])?;
+    let run = instance.get_func("run").unwrap().get0::<i32>()?;
+    assert_eq!(run()?, 42);
+    assert_eq!(*logged.borrow(), ["from wasm", "c string"]);
+    Ok(())
+}
+
+#[test]
+fn on_memory_drop() -> Result<()> {
+    let dropped = Arc::new(Mutex::new(Vec::new()));
+    let (host_id, defined_id) = {
+        let store = Store::default();
+        let dropped = dropped.clone();
+        store.on_memory_drop(move |id| dropped.lock().unwrap().push(id));
+
+        // Without multi-memory a module has at most one memory, so one module
+        // imports the host's memory and another defines its own.
+        let host = one_page_memory(&store);
+        let importer = Module::new(store.engine(), r#"(module (import "" "" (memory 1)))"#)?;
+        Instance::new(&store, &importer, &[host.clone().into()])?;
+        let definer = Module::new(store.engine(), r#"(module (memory (export "defined") 1))"#)?;
+        let instance = Instance::new(&store, &definer, &[])?;
+        let defined = instance.get_memory("defined").unwrap();
+        assert_ne!(host.id(), defined.id());
+        (host.id(), defined.id())
+    };
+
+    // The imported memory is only reported by the instance which owns it.
+    let mut dropped = dropped.lock().unwrap().clone();
+    dropped.sort_by_key(|id| *id == defined_id);
+    assert_eq!(dropped, [host_id, defined_id]);
+    Ok(())
+}
+
+#[test]
+fn with_slice_bounds() -> Result<()> {
+    let store = Store::default();
+    let memory = one_page_memory(&store);
+    memory.write_slice(65530, b"abcdef")?;
+
+    assert_eq!(memory.with_slice(65535, 1, |b| b.to_vec())?, b"f");
+    assert_eq!(memory.with_slice(65536, 0, |b| b.len())?, 0);
+    memory.with_slice_mut(65530, 6, |b| b.copy_from_slice(b"ghijkl"))?;
+    assert_eq!(memory.read_string(65530, 6)?, "ghijkl");
+
+    let mut called = false;
+    assert_eq!(
+        memory.with_slice(65535, 2, |_| called = true),
+        Err(MemoryAccessError::OutOfBounds {
+            offset: 65535,
+            len: 2,
+            memory_size: 65536,
+        })
+    );
+    assert_eq!(
+        memory.with_slice_mut(usize::max_value(), 2, |_| called = true),
+        Err(MemoryAccessError::Overflow {
+            offset: usize::max_value(),
+            len: 2,
+        })
+    );
+    assert!(memory.with_slice(65537, 0, |_| called = true).is_err());
+    assert!(!called);
+    Ok(())
+}
+
+#[test]
+fn data_accessors() -> Result<()> {
+    let store = Store::default();
+    let memory = one_page_memory(&store);
+    memory.write_slice(65530, b"abcdef")?;
+
+    unsafe {
+        assert_eq!(memory.data(65535, 1)?, b"f");
+        assert_eq!(memory.data(65536, 0)?, b"");
+        memory.data_mut(65530, 6)?.copy_from_slice(b"ghijkl");
+        assert_eq!(memory.data(65530, 6)?, b"ghijkl");
+
+        assert_eq!(
+            memory.data(65535, 2),
+            Err(MemoryAccessError::OutOfBounds {
+                offset: 65535,
+                len: 2,
+                memory_size: 65536,
+            })
+        );
+        assert_eq!(
+            memory.data_mut(usize::max_value(), 2),
+            Err(MemoryAccessError::Overflow {
+                offset: usize::max_value(),
+                len: 2,
+            })
+        );
+
+        // Scoped borrows are respected.
+        memory.with_slice(0, 1, |_| {
+            assert!(memory.data(0, 1).is_ok());
+            assert_eq!(
+                memory.data_mut(0, 1),
+                Err(MemoryAccessError::AlreadyBorrowed)
+            );
+        })?;
+        memory.with_slice_mut(0, 1, |_| {
+            assert_eq!(memory.data(0, 1), Err(MemoryAccessError::AlreadyBorrowed));
+        })?;
+    }
+    Ok(())
+}
+
+#[test]
+fn with_slice_reentrancy() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "grow") (result i32)
+                    (memory.grow (i32.const 1)))
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("memory").unwrap();
+    let grow = instance.get_func("grow").unwrap();
+    let other = one_page_memory(&store);
+
+    memory.with_slice(0, 16, |_| -> Result<()> {
+        // Neither wasm nor the host may grow the memory while it's borrowed.
+        let err = grow.call(&[]).unwrap_err();
+        assert!(err.to_string().contains("borrowed"), "bad error: {}", err);
+        assert!(memory.grow(1).is_err());
+
+        // Other shared borrows are fine, mutable ones aren't.
+        assert_eq!(memory.with_slice(0, 1, |b| b[0])?, 0);
+        assert_eq!(
+            memory.write_slice(0, b"x"),
+            Err(MemoryAccessError::AlreadyBorrowed)
+        );
+
+        // Other memories are only restricted from running wasm.
+        other.write_slice(0, b"y")?;
+        other.grow(1)?;
+        Ok(())
+    })??;
+
+    memory.with_slice_mut(0, 16, |_| {
+        let mut buf = [0; 1];
+        assert_eq!(
+            memory.read(0, &mut buf),
+            Err(MemoryAccessError::AlreadyBorrowed)
+        );
+        assert_eq!(
+            memory.with_slice_mut(0, 1, |_| ()),
+            Err(MemoryAccessError::AlreadyBorrowed)
+        );
+    })?;
+
+    // A panicking callback releases its borrow.
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        memory.with_slice_mut(0, 16, |_| panic!("oops"))
+    }));
+    assert!(result.is_err());
+
+    // Everything works again once the callbacks have returned.
+    assert_eq!(grow.call(&[])?[0].unwrap_i32(), 1);
+    assert_eq!(memory.grow(1)?, 2);
+    memory.write_slice(0, b"x")?;
+    Ok(())
+}
+
+#[test]
+fn typed_slices() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") 1)
+                ;; Stores `i / 2` into the `i`th of 16 f32 lanes at `ptr`.
+                (func (export "fill") (param $ptr i32)
+                    (local $i i32)
+                    (loop $lanes
+                        (f32.store
+                            (i32.add (local.get $ptr) (i32.mul (local.get $i) (i32.const 4)))
+                            (f32.div (f32.convert_i32_s (local.get $i)) (f32.const 2)))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $lanes (i32.lt_u (local.get $i) (i32.const 16)))))
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("memory").unwrap();
+    let fill = instance.get_func("fill").unwrap().get1::<i32, ()>()?;
+    fill(64)?;
+
+    let expected = (0..16).map(|i| i as f32 / 2.0).collect::<Vec<_>>();
+    assert_eq!(memory.read_slice::<f32>(64, 16)?, expected);
+    memory.with_typed_slice(64, 16, |lanes: &[f32]| assert_eq!(lanes, &expected[..]))?;
+    assert_eq!(memory.read_slice::<u32>(64, 2)?, [0, 0x3f00_0000]);
+    assert_eq!(memory.read_slice::<f64>(0, 0)?, []);
+
+    assert_eq!(
+        memory.read_slice::<f32>(66, 1),
+        Err(MemoryAccessError::Unaligned {
+            offset: 66,
+            align: 4,
+        })
+    );
+    assert_eq!(
+        memory.read_slice::<f32>(65532, 2),
+        Err(MemoryAccessError::OutOfBounds {
+            offset: 65532,
+            len: 8,
+            memory_size: 65536,
+        })
+    );
+    match memory.read_slice::<u64>(8, usize::max_value()) {
+        Err(MemoryAccessError::Overflow { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn memory64_store() -> Store {
+    let mut config = Config::new();
+    config.wasm_memory64(true);
+    Store::new(&Engine::new(&config))
+}
+
+#[test]
+fn disabled_by_default() {
+    let engine = Engine::default();
+    assert!(Module::new(&engine, "(module (memory i64 1))").is_err());
+}
+
+#[test]
+fn load_store_grow() -> Result<()> {
+    let store = memory64_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") i64 1 3)
+                (func (export "load") (param i64) (result i32)
+                    (i32.load (local.get 0)))
+                (func (export "store") (param i64 i32)
+                    (i32.store (local.get 0) (local.get 1)))
+                (func (export "size") (result i64)
+                    (memory.size))
+                (func (export "grow") (param i64) (result i64)
+                    (memory.grow (local.get 0)))
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("memory").unwrap();
+    assert!(memory.ty().is_64());
+    let load = instance.get_func("load").unwrap().get1::<i64, i32>()?;
+    let store_fn = instance.get_func("store").unwrap().get2::<i64, i32, ()>()?;
+    let size = instance.get_func("size").unwrap().get0::<i64>()?;
+    let grow = instance.get_func("grow").unwrap().get1::<i64, i64>()?;
+
+    store_fn(100, 42)?;
+    assert_eq!(load(100)?, 42);
+    let mut buf = [0; 4];
+    memory.read(100, &mut buf)?;
+    assert_eq!(i32::from_le_bytes(buf), 42);
+
+    // Addresses are bounds-checked as 64-bit numbers, so the high bits of an
+    // address aren't ignored.
+    assert!(load(65533).is_err());
+    assert!(load(1 << 32).is_err());
+    assert!(load(-1).is_err());
+
+    assert_eq!(size()?, 1);
+    assert_eq!(grow(1)?, 1);
+    assert_eq!(size()?, 2);
+    assert_eq!(grow(2)?, -1);
+    assert_eq!(grow(1 << 32)?, -1);
+    assert_eq!(grow(-1)?, -1);
+    assert_eq!(size()?, 2);
+    Ok(())
+}
+
+#[test]
+fn data_segments() -> Result<()> {
+    let store = memory64_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") i64 1)
+                (data (i64.const 16) "hello"))
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("memory").unwrap();
+    assert_eq!(memory.read_string(16, 5)?, "hello");
+
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory i64 1)
+                (data (i64.const 0x1_0000_0000) "hello"))
+        "#,
+    )?;
+    assert!(Instance::new(&store, &module, &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn bulk_memory_is_unsupported() {
+    let mut config = Config::new();
+    config.wasm_memory64(true).wasm_bulk_memory(true);
+    let result = Module::new(
+        &Engine::new(&config),
+        r#"
+            (module
+                (memory i64 1)
+                (func (param i64 i32 i64)
+                    (memory.fill (local.get 0) (local.get 1) (local.get 2))))
+        "#,
+    );
+    let err = format!("{:?}", result.unwrap_err());
+    assert!(err.contains("64-bit memories"), "bad error: {}", err);
+}
+
+#[test]
+fn imports_match_index_type() -> Result<()> {
+    let store = memory64_store();
+    let memory64 = Memory::new(&store, MemoryType::new64(Limits::new(1, None)));
+    let memory32 = Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    assert!(memory64.ty().is_64());
+    assert!(!memory32.ty().is_64());
+
+    let module = Module::new(store.engine(), r#"(module (import "" "" (memory i64 1)))"#)?;
+    match module.imports().next().unwrap().ty() {
+        ExternType::Memory(ty) => assert!(ty.is_64()),
+        ty => panic!("unexpected import type {:?}", ty),
+    }
+    Instance::new(&store, &module, &[memory64.clone().into()])?;
+    assert!(Instance::new(&store, &module, &[memory32.clone().into()]).is_err());
+
+    let module = Module::new(store.engine(), r#"(module (import "" "" (memory 1)))"#)?;
+    Instance::new(&store, &module, &[memory32.into()])?;
+    assert!(Instance::new(&store, &module, &[memory64.into()]).is_err());
+    Ok(())
+}
+
+// Accessing more than 4 GiB needs a memory which is actually that large, so
+// this only runs where there's the address space for it.
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn offsets_above_4gib() -> Result<()> {
+    let store = memory64_store();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (memory (export "memory") i64 65537)
+                (func (export "load") (param i64) (result i64)
+                    (i64.load (local.get 0)))
+                (func (export "store") (param i64 i64)
+                    (i64.store (local.get 0) (local.get 1)))
+            )
+        "#,
+    )?;
+    let instance = match Instance::new(&store, &module, &[]) {
+        Ok(instance) => instance,
+        Err(e) => {
+            eprintln!("skipping, can't allocate a 4 GiB memory: {}", e);
+            return Ok(());
+        }
+    };
+    let memory = instance.get_memory("memory").unwrap();
+    let load = instance.get_func("load").unwrap().get1::<i64, i64>()?;
+    let store_fn = instance.get_func("store").unwrap().get2::<i64, i64, ()>()?;
+
+    let offset = 0x1_0000_0010;
+    store_fn(offset, 0x0123_4567_89ab_cdef)?;
+    assert_eq!(load(offset)?, 0x0123_4567_89ab_cdef);
+    assert_eq!(load(offset - (1 << 32))?, 0, "addresses don't wrap");
+
+    let mut buf = [0; 8];
+    memory.read(offset as usize, &mut buf)?;
+    assert_eq!(i64::from_le_bytes(buf), 0x0123_4567_89ab_cdef);
+    memory.write_slice(offset as usize + 8, &7i64.to_le_bytes())?;
+    assert_eq!(load(offset + 8)?, 7);
+
+    let end = 65537 * 65536;
+    assert!(load(end - 8).is_ok());
+    assert!(load(end - 7).is_err());
+    Ok(())
+}
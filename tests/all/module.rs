@@ -0,0 +1,494 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use wasmtime::*;
+
+fn unused_functions_engine() -> Engine {
+    let mut config = Config::new();
+    config.report_unused_functions(true);
+    Engine::new(&config)
+}
+
+#[test]
+fn unused_functions_disabled_by_default() -> Result<()> {
+    let module = Module::new(&Engine::default(), "(module (func) (func))")?;
+    assert!(module.unused_functions().is_empty());
+    Ok(())
+}
+
+#[test]
+fn unused_functions() -> Result<()> {
+    let engine = unused_functions_engine();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "" "" (func $import))
+                (func $exported (export "run") call $callee)
+                (func $callee call $import)
+                (func $dead)
+                (func $dead_caller call $callee)
+                (func $start)
+                (start $start)
+            )
+        "#,
+    )?;
+    assert_eq!(module.unused_functions(), &[3, 4]);
+    Ok(())
+}
+
+#[test]
+fn unused_functions_accounts_for_element_segments() -> Result<()> {
+    let engine = unused_functions_engine();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (table 2 funcref)
+                (func $in_active)
+                (func $in_passive)
+                (func $called_from_table call $indirect_callee)
+                (func $indirect_callee)
+                (func $dead)
+                (elem (i32.const 0) $in_active $called_from_table)
+                (elem func $in_passive)
+            )
+        "#,
+    )?;
+    assert_eq!(module.unused_functions(), &[4]);
+    Ok(())
+}
+
+// TODO(#1886): Cranelift only supports reference types on x64.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn unused_functions_accounts_for_ref_func() -> Result<()> {
+    let engine = unused_functions_engine();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (func (export "run") (result funcref) ref.func $referenced)
+                (func $referenced)
+                (func $dead)
+                (elem declare func $referenced)
+            )
+        "#,
+    )?;
+    assert_eq!(module.unused_functions(), &[2]);
+    Ok(())
+}
+
+#[test]
+fn call_graph() -> Result<()> {
+    let mut config = Config::new();
+    config.record_call_graph(true);
+    let module = Module::new(
+        &Engine::new(&config),
+        r#"
+            (module
+                (import "" "" (func $import))
+                (type $t (func))
+                (table 1 funcref)
+                (elem (i32.const 0) $in_table)
+                (func $a (export "a") call $b)
+                (func $b call $import)
+                (func $c)
+                (func $indirect (export "indirect")
+                    (call_indirect (type $t) (i32.const 0)))
+                (func $in_table call $c)
+                (func $cycle (export "cycle") call $cycle))
+        "#,
+    )?;
+    let graph = module.call_graph().unwrap();
+    let set = |funcs: &[u32]| funcs.iter().copied().collect::<HashSet<_>>();
+
+    // Function indices include the import, so `$a` is 1.
+    assert_eq!(graph.reachable_from(1), set(&[1, 2, 0]));
+    assert_eq!(graph.reachable_from(3), set(&[3]));
+    // Indirect calls may reach anything in a table, and from there onwards.
+    assert_eq!(graph.reachable_from(4), set(&[4, 5, 3]));
+    assert_eq!(graph.reachable_from(6), set(&[6]));
+    assert_eq!(graph.reachable_from(0), set(&[0]));
+
+    // Nothing is recorded unless asked for.
+    let module = Module::new(&Engine::default(), "(module (func))")?;
+    assert!(module.call_graph().is_none());
+    Ok(())
+}
+
+#[test]
+fn abi_compatible_with() -> Result<()> {
+    let engine = Engine::default();
+    let v1 = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "host" "log" (func (param i32)))
+                (import "host" "now" (func (result i64)))
+                (func (export "run") (param i32) (result i32) local.get 0)
+                (memory (export "memory") 1)
+            )
+        "#,
+    )?;
+
+    // Identical ABIs, with a different implementation and fewer imports.
+    let v2 = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "host" "log" (func (param i32)))
+                (func (export "run") (param i32) (result i32) i32.const 1)
+                (memory (export "memory") 1)
+            )
+        "#,
+    )?;
+    v2.abi_compatible_with(&v1)?;
+    v1.abi_compatible_with(&v1)?;
+
+    // ... but `v1` needs more imports than `v2`.
+    let diff = v1.abi_compatible_with(&v2).unwrap_err();
+    assert_eq!(
+        diff.changes(),
+        [AbiChange::ExtraImport {
+            module: "host".to_string(),
+            name: "now".to_string(),
+            ty: FuncType::new(None, Some(ValType::I64)).into(),
+        }]
+    );
+
+    let v3 = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "host" "log" (func (param i64)))
+                (func (export "run") (param i64) (result i32) i32.const 1)
+                (memory (export "memory2") 1)
+            )
+        "#,
+    )?;
+    let diff = v3.abi_compatible_with(&v1).unwrap_err();
+    assert_eq!(
+        diff.changes(),
+        [
+            AbiChange::ExportType {
+                name: "run".to_string(),
+                expected: FuncType::new(Some(ValType::I32), Some(ValType::I32)).into(),
+                found: FuncType::new(Some(ValType::I64), Some(ValType::I32)).into(),
+            },
+            AbiChange::ExtraExport {
+                name: "memory2".to_string(),
+                ty: MemoryType::new(Limits::new(1, None)).into(),
+            },
+            AbiChange::MissingExport {
+                name: "memory".to_string(),
+                ty: MemoryType::new(Limits::new(1, None)).into(),
+            },
+            AbiChange::ImportType {
+                module: "host".to_string(),
+                name: "log".to_string(),
+                expected: FuncType::new(Some(ValType::I32), None).into(),
+                found: FuncType::new(Some(ValType::I64), None).into(),
+            },
+        ]
+    );
+    assert!(diff.to_string().contains("export `run` has type"));
+    Ok(())
+}
+
+// Generated modules can have thousands of imports and exports, all of which
+// should link and be found by name.
+#[test]
+fn many_imports_and_exports() -> Result<()> {
+    use std::fmt::Write;
+
+    const N: usize = 5_000;
+    let mut wat = String::from("(module\n");
+    for i in 0..N {
+        writeln!(wat, "(import \"host\" \"f\" (func $import{}))", i)?;
+    }
+    wat.push_str("(func $f)\n");
+    for i in 0..N {
+        writeln!(wat, "(export \"e{}\" (func $f))", i)?;
+    }
+    wat.push_str(")");
+
+    let store = Store::default();
+    let module = Module::new(store.engine(), &wat)?;
+    assert_eq!(module.imports().len(), N);
+    assert_eq!(module.exports().len(), N);
+
+    let mut linker = Linker::new(&store);
+    linker.func("host", "f", || {})?;
+    let instance = linker.instantiate(&module)?;
+    for i in (0..N).rev() {
+        let name = format!("e{}", i);
+        assert!(module.get_export(&name).is_some());
+        assert!(instance.get_func(&name).is_some());
+    }
+    assert_eq!(instance.exports().count(), N);
+    Ok(())
+}
+
+// The code generator can't compile SIMD code for x86 CPUs without SSE4.1,
+// which is enough to exercise how its failures are reported.
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn codegen_failures_are_reported() -> Result<()> {
+    let dir = tempfile::TempDir::new()?;
+    let mut config = Config::new();
+    config.wasm_simd(true).debug_ir_dump(dir.path());
+    unsafe {
+        config.cranelift_other_flag("has_ssse3", "false")?;
+        config.cranelift_other_flag("has_sse41", "false")?;
+        config.cranelift_other_flag("has_sse42", "false")?;
+    }
+    let engine = Engine::new(&config);
+    let wasm = wat::parse_str(
+        r#"
+            (module
+                (func)
+                (func (param v128 v128) (result v128)
+                    (i32x4.min_s
+                        (i32x4.mul (local.get 0) (local.get 1))
+                        (local.get 1)))
+            )
+        "#,
+    )?;
+    let err = Module::new(&engine, &wasm).unwrap_err();
+    let message = format!("{:?}", err);
+    assert!(
+        message.contains("wasm function 1"),
+        "bad error: {}",
+        message
+    );
+
+    // Only the function which failed is dumped.
+    let body = std::fs::read(dir.path().join("wasm-function-1.bin"))?;
+    assert_eq!(body.last(), Some(&0x0b));
+    assert!(!dir.path().join("wasm-function-0.bin").exists());
+
+    // Compiling serially reports the same function.
+    config.parallel_compilation(false);
+    let err = Module::new(&Engine::new(&config), &wasm).unwrap_err();
+    let message = format!("{:?}", err);
+    assert!(
+        message.contains("wasm function 1"),
+        "bad error: {}",
+        message
+    );
+    Ok(())
+}
+
+#[test]
+fn max_compiled_code_size() -> Result<()> {
+    let mut config = Config::new();
+    config.max_compiled_code_size(1024);
+    let engine = Engine::new(&config);
+
+    Module::new(
+        &engine,
+        "(module (func (export \"run\") (result i32) i32.const 1))",
+    )?;
+
+    let mut wat = String::from("(module (memory 1)");
+    for i in 0..100 {
+        wat.push_str(&format!(
+            "(func (param i32) (result i32) (i32.store (local.get 0) (i32.const {})) (i32.load (local.get 0)))",
+            i
+        ));
+    }
+    wat.push(')');
+    let err = Module::new(&engine, &wat).unwrap_err();
+    let err = format!("{:?}", err);
+    assert!(
+        err.contains("Compiled code exceeds the limit of 1024 bytes"),
+        "bad error: {}",
+        err
+    );
+
+    Module::new(&Engine::default(), &wat)?;
+    Ok(())
+}
+
+#[test]
+fn max_compiled_code_size_with_cache() -> Result<()> {
+    let td = tempfile::TempDir::new()?;
+    let config_path = td.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        &format!(
+            "
+                [cache]
+                enabled = true
+                directory = '{}'
+            ",
+            td.path().join("cache").display()
+        ),
+    )?;
+    let mut wat = String::from("(module (memory 1)");
+    for i in 0..100 {
+        wat.push_str(&format!(
+            "(func (param i32) (result i32) (i32.store (local.get 0) (i32.const {})) (i32.load (local.get 0)))",
+            i
+        ));
+    }
+    wat.push(')');
+
+    // Without a limit the module compiles and is cached.
+    let mut config = Config::new();
+    config.cache_config_load(&config_path)?;
+    Module::new(&Engine::new(&config), &wat)?;
+
+    // The cached module isn't used once there's a limit it exceeds.
+    config.max_compiled_code_size(1024);
+    let err = Module::new(&Engine::new(&config), &wat).unwrap_err();
+    let err = format!("{:?}", err);
+    assert!(
+        err.contains("Compiled code exceeds the limit of 1024 bytes"),
+        "bad error: {}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn compilation_memory_limit_requires_allocator() -> Result<()> {
+    // The allocator isn't installed in this test binary, see
+    // `tests/compilation_memory_limit.rs` for the limit itself.
+    let mut config = Config::new();
+    config.compilation_memory_limit(1 << 30);
+    let engine = Engine::new(&config);
+    let err = Module::new(&engine, "(module (func))").unwrap_err();
+    let err = format!("{:?}", err);
+    assert!(
+        err.contains("without `CompilationAllocator` as the global allocator"),
+        "bad error: {}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn forbid_features() -> Result<()> {
+    let mut config = Config::new();
+    config.forbid_features(FeatureMask::FLOAT);
+    let engine = Engine::new(&config);
+
+    Module::new(
+        &engine,
+        r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    (i32.add (local.get 0) (local.get 1)))
+                (func (export "div") (param i64 i64) (result i64)
+                    (i64.div_s (local.get 0) (local.get 1))))
+        "#,
+    )?;
+
+    let wasm = wat::parse_str(
+        r#"
+            (module
+                (import "" "" (func))
+                (func (param i32) (result i32) local.get 0)
+                (func (param f32 f32) (result f32)
+                    (f32.add (local.get 0) (local.get 1))))
+        "#,
+    )?;
+    for err in vec![
+        Module::validate(&engine, &wasm).unwrap_err().to_string(),
+        Module::new(&engine, &wasm).unwrap_err().to_string(),
+    ] {
+        assert!(
+            err.starts_with("func 2: instruction `F32Add` is forbidden"),
+            "bad error: {}",
+            err
+        );
+        assert!(err.contains(" at offset 0x"), "bad error: {}", err);
+        assert!(err.contains("floating point"), "bad error: {}", err);
+    }
+
+    // Other classes are left alone.
+    let mut config = Config::new();
+    config.forbid_features(FeatureMask::INDIRECT_CALLS | FeatureMask::MEMORY_GROW);
+    Module::new(&Engine::new(&config), &wasm)?;
+    Ok(())
+}
+
+#[test]
+fn validation_errors_are_located() -> Result<()> {
+    let engine = Engine::default();
+    let wasm = wat::parse_str(
+        r#"
+            (module
+                (import "" "" (func))
+                (func)
+                (func (local i32)
+                    local.get 3
+                    drop))
+        "#,
+    )?;
+    let err = Module::validate(&engine, &wasm).unwrap_err();
+    let err = err.downcast_ref::<ValidationError>().unwrap();
+    assert_eq!(err.section(), Some("code"));
+    assert_eq!(err.func_index(), Some(2));
+    assert!(err.func_offset().unwrap() > 0);
+    assert!(err.func_offset().unwrap() < err.offset());
+    let expected = format!("func 2: {} at offset {:#x}", err.message(), err.offset());
+    assert_eq!(err.to_string(), expected);
+
+    // `Module::new` reports the same error, rather than just what went wrong.
+    let err = Module::new(&engine, &wasm).unwrap_err();
+    let err = err.downcast_ref::<ValidationError>().unwrap();
+    assert_eq!(err.to_string(), expected);
+    Ok(())
+}
+
+#[test]
+fn validation_respects_config() -> Result<()> {
+    let wasm = wat::parse_str("(module (func (result i32 i32) i32.const 0 i32.const 1))")?;
+    let mut config = Config::new();
+    config.wasm_multi_value(true);
+    Module::validate(&Engine::new(&config), &wasm)?;
+
+    config.wasm_multi_value(false);
+    let err = Module::validate(&Engine::new(&config), &wasm).unwrap_err();
+    let err = err.downcast_ref::<ValidationError>().unwrap();
+    assert_eq!(err.section(), Some("type"));
+    assert_eq!(err.func_index(), None);
+    assert!(
+        err.to_string().starts_with("type section: "),
+        "bad error: {}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn wasi_imports() -> Result<()> {
+    let engine = Engine::default();
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+                (import "env" "fd_write" (func))
+                (import "wasi_snapshot_preview1" "clock_time_get" (func (param i32 i64 i32) (result i32)))
+                (import "wasi_unstable" "proc_exit" (func (param i32)))
+                (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "memory" (memory 1))
+            )
+        "#,
+    )?;
+    assert_eq!(
+        module.wasi_imports(),
+        [
+            ("wasi_snapshot_preview1", "fd_write"),
+            ("wasi_snapshot_preview1", "clock_time_get"),
+            ("wasi_unstable", "proc_exit"),
+        ]
+    );
+
+    let module = Module::new(&engine, r#"(module (import "env" "foo" (func)))"#)?;
+    assert!(module.wasi_imports().is_empty());
+    Ok(())
+}
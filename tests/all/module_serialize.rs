@@ -30,6 +30,43 @@ fn test_module_serialize_simple() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_module_serialize_keeps_metadata() -> Result<()> {
+    let buffer = serialize(
+        &Engine::default(),
+        r#"
+            (module $m
+                (import "host" "log" (func (param i32)))
+                (func $boom (export "boom") unreachable)
+                (memory (export "memory") 1))
+        "#,
+    )?;
+
+    let store = Store::default();
+    let module = Module::deserialize(store.engine(), &buffer)?;
+    assert_eq!(module.name(), Some("m"));
+    let imports = module
+        .imports()
+        .map(|i| (i.module(), i.name()))
+        .collect::<Vec<_>>();
+    assert_eq!(imports, [("host", "log")]);
+    let exports = module.exports().map(|e| e.name()).collect::<Vec<_>>();
+    assert_eq!(exports, ["boom", "memory"]);
+
+    // Traps still map back to the function which trapped.
+    let log = Func::wrap(&store, |_: i32| {});
+    let instance = Instance::new(&store, &module, &[log.into()])?;
+    let trap = instance
+        .get_func("boom")
+        .unwrap()
+        .call(&[])
+        .unwrap_err()
+        .downcast::<Trap>()?;
+    assert_eq!(trap.trap_code(), Some(TrapCode::UnreachableCodeReached));
+    assert_eq!(trap.trace()[0].func_name(), Some("boom"));
+    Ok(())
+}
+
 #[test]
 fn test_module_serialize_fail() -> Result<()> {
     let buffer = serialize(
@@ -42,7 +79,111 @@ fn test_module_serialize_fail() -> Result<()> {
     let store = Store::new(&Engine::new(&config));
     match deserialize_and_instantiate(&store, &buffer) {
         Ok(_) => bail!("expected failure at deserialization"),
-        Err(_) => (),
+        Err(e) => {
+            // The error pinpoints the mismatched setting, and nothing else.
+            let message = e.to_string();
+            assert!(
+                message.contains("cranelift_flags.shared.opt_level: speed != none"),
+                "bad error: {}",
+                message
+            );
+            assert_eq!(message.lines().count(), 2, "bad error: {}", message);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_module_serialize_fail_lists_every_difference() -> Result<()> {
+    let mut config = Config::new();
+    config.wasm_simd(true).static_memory_guard_size(0x10000);
+    let buffer = serialize(&Engine::new(&config), "(module)")?;
+
+    // Settings which don't affect compiled code are neither recorded nor
+    // reported.
+    let mut config = Config::new();
+    config.wasm_simd(false).max_wasm_stack(1 << 16);
+    let err = Module::deserialize(&Engine::new(&config), &buffer).unwrap_err();
+    let message = err.to_string();
+    for name in &[
+        "wasm_features.simd: true != false",
+        "cranelift_flags.shared.enable_simd: true != false",
+        "config.static_memory_guard_size: 65536 != ",
+    ] {
+        assert!(message.contains(name), "bad error: {}", message);
+    }
+    assert!(
+        !message.contains("max_wasm_stack"),
+        "bad error: {}",
+        message
+    );
+    assert!(!message.contains("page_size"), "bad error: {}", message);
+    Ok(())
+}
+
+#[test]
+fn test_module_serialize_reproducible() -> Result<()> {
+    let wat = r#"
+        (module
+            (import "" "f" (func $f (param i32)))
+            (func $a (export "a") (call $f (i32.const 1)))
+            (func $b (export "b") (call $f (i32.const 2)))
+            (func $c (call $a) (call $b))
+            (table 3 funcref)
+            (elem (i32.const 0) $a $b $c)
+            (elem func $a $b)
+            (elem func $c)
+            (memory 1)
+            (data "passive")
+            (data "data")
+            (data (i32.const 0) "active")
+        )
+    "#;
+
+    let mut config = Config::new();
+    config.wasm_bulk_memory(true);
+    let first = Module::new(&Engine::new(&config), wat)?;
+    let second = Module::new(&Engine::new(&config), wat)?;
+    assert_eq!(first.serialize()?, second.serialize()?);
+    assert_eq!(first.artifact_hash(), second.artifact_hash());
+
+    // Round-tripping through `deserialize` doesn't change anything either.
+    let third = Module::deserialize(&Engine::new(&config), &first.serialize()?)?;
+    assert_eq!(first.artifact_hash(), third.artifact_hash());
+
+    let other = Module::new(&Engine::new(&config), "(module)")?;
+    assert_ne!(first.artifact_hash(), other.artifact_hash());
+    Ok(())
+}
+
+#[test]
+fn test_module_serialize_parallel_compilation() -> Result<()> {
+    // Enough functions, of different sizes, that they finish compiling out of
+    // order when compiled in parallel.
+    let mut wat = String::from("(module (memory 1)");
+    for i in 0..200 {
+        wat.push_str(&format!(
+            "(func (export \"f{}\") (param i32) (result i32)",
+            i
+        ));
+        wat.push_str("(local.get 0)");
+        for j in 0..(i % 17) * 10 {
+            wat.push_str(&format!(
+                "(i32.add (i32.load offset={} (i32.const 0)))",
+                j * 4
+            ));
+        }
+        wat.push_str(")");
     }
+    wat.push_str(")");
+
+    let serialize = |parallel| -> Result<Vec<u8>> {
+        let mut config = Config::new();
+        config.parallel_compilation(parallel);
+        Ok(Module::new(&Engine::new(&config), &wat)?.serialize()?)
+    };
+    let serial = serialize(false)?;
+    assert_eq!(serialize(true)?, serial);
+    assert_eq!(serialize(true)?, serial);
     Ok(())
 }
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use std::sync::mpsc;
+use std::thread;
+use wasmtime::*;
+
+const MODULE: &str = r#"
+    (module
+        (memory (export "memory") 1)
+        (table 10 funcref)
+        (func (export "load") (result i32)
+            (i32.load (i32.const 0)))
+        (func (export "store") (param i32)
+            (i32.store (i32.const 0) (local.get 0)))
+    )
+"#;
+
+fn pooled_engine(config: PoolingConfig) -> Result<Engine> {
+    let mut cfg = Config::new();
+    cfg.pooling_allocation(config)?;
+    Ok(Engine::new(&cfg))
+}
+
+#[test]
+fn slots_are_recycled() -> Result<()> {
+    let engine = pooled_engine(PoolingConfig::new(2))?;
+    let module = Module::new(&engine, MODULE)?;
+
+    for i in 0..10 {
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &module, &[])?;
+        let load = instance.get_func("load").unwrap().get0::<i32>()?;
+        let store_fn = instance.get_func("store").unwrap().get1::<i32, ()>()?;
+
+        // Memory handed back to the pool is reset before it's reused.
+        assert_eq!(load()?, 0);
+        store_fn(i + 1)?;
+        assert_eq!(load()?, i + 1);
+    }
+    Ok(())
+}
+
+#[test]
+fn exhausted_pool_errors() -> Result<()> {
+    let engine = pooled_engine(PoolingConfig::new(2))?;
+    let module = Module::new(&engine, MODULE)?;
+
+    let store = Store::new(&engine);
+    Instance::new(&store, &module, &[])?;
+    Instance::new(&store, &module, &[])?;
+    let err = Instance::new(&store, &module, &[]).unwrap_err();
+    assert!(
+        err.to_string().contains("pool exhausted"),
+        "bad error: {}",
+        err
+    );
+
+    // Another store draws from the same pool.
+    let other = Store::new(&engine);
+    assert!(Instance::new(&other, &module, &[]).is_err());
+
+    drop(store);
+    Instance::new(&other, &module, &[])?;
+    Instance::new(&other, &module, &[])?;
+    Ok(())
+}
+
+#[test]
+fn host_items_do_not_use_slots() -> Result<()> {
+    let engine = pooled_engine(PoolingConfig::new(1))?;
+    let module = Module::new(&engine, MODULE)?;
+
+    let store = Store::new(&engine);
+    Memory::new(&store, MemoryType::new(Limits::new(1, None)));
+    Func::wrap(&store, || {});
+    Instance::new(&store, &module, &[])?;
+    Ok(())
+}
+
+#[test]
+fn limits_are_enforced() -> Result<()> {
+    let mut pooling = PoolingConfig::new(1);
+    pooling.memory_pages(2).table_elements(5);
+    let engine = pooled_engine(pooling)?;
+    let store = Store::new(&engine);
+
+    let module = Module::new(&engine, "(module (memory 3))")?;
+    assert!(Instance::new(&store, &module, &[]).is_err());
+    let module = Module::new(&engine, "(module (table 6 funcref))")?;
+    assert!(Instance::new(&store, &module, &[]).is_err());
+
+    // Rejected modules never claimed the pool's only slot.
+    let module = Module::new(&engine, "(module (memory (export \"m\") 1))")?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("m").unwrap();
+    assert_eq!(memory.grow(1)?, 1);
+    assert!(memory.grow(1).is_err());
+    assert_eq!(memory.size(), 2);
+    Ok(())
+}
+
+#[test]
+fn tables_are_pooled() -> Result<()> {
+    let mut pooling = PoolingConfig::new(1);
+    pooling.table_elements(5);
+    let engine = pooled_engine(pooling)?;
+
+    let module = Module::new(&engine, "(module (table 1 funcref) (table 1 funcref))")?;
+    let err = Instance::new(&Store::new(&engine), &module, &[]).unwrap_err();
+    assert!(
+        err.to_string().contains("at most one"),
+        "bad error: {}",
+        err
+    );
+
+    let module = Module::new(&engine, "(module (table (export \"t\") 2 funcref))")?;
+    for _ in 0..3 {
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &module, &[])?;
+        let table = instance.get_table("t").unwrap();
+        assert_eq!(table.size(), 2);
+        assert!(table.get(1).unwrap().funcref().unwrap().is_none());
+        table.set(1, Val::FuncRef(Some(Func::wrap(&store, || {}))))?;
+        assert_eq!(table.grow(3, Val::FuncRef(None))?, 2);
+        assert!(table.grow(1, Val::FuncRef(None)).is_err());
+        assert_eq!(table.size(), 5);
+    }
+    Ok(())
+}
+
+#[test]
+fn memory_grows_to_the_wasm_maximum() -> Result<()> {
+    let mut pooling = PoolingConfig::new(1);
+    pooling.memory_pages(65536);
+    let engine = pooled_engine(pooling)?;
+    let module = Module::new(&engine, "(module (memory (export \"m\") 65535))")?;
+    let store = Store::new(&engine);
+    let instance = Instance::new(&store, &module, &[])?;
+    let memory = instance.get_memory("m").unwrap();
+    assert_eq!(memory.grow(1)?, 65535);
+    assert!(memory.grow(1).is_err());
+    Ok(())
+}
+
+#[test]
+fn dynamic_memories() -> Result<()> {
+    let mut config = Config::new();
+    config
+        .static_memory_maximum_size(0)
+        .pooling_allocation(PoolingConfig::new(1))?;
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, MODULE)?;
+
+    for _ in 0..3 {
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &module, &[])?;
+        let store_fn = instance.get_func("store").unwrap().get1::<i32, ()>()?;
+        store_fn(1)?;
+        let memory = instance.get_memory("memory").unwrap();
+        memory.grow(3)?;
+        assert_eq!(memory.size(), 4);
+    }
+    Ok(())
+}
+
+#[test]
+fn exhausted_pool_blocks() -> Result<()> {
+    let mut pooling = PoolingConfig::new(1);
+    pooling.on_exhausted(PoolExhaustion::Block);
+    let engine = pooled_engine(pooling)?;
+    let module = Module::new(&engine, MODULE)?;
+
+    let store = Store::new(&engine);
+    Instance::new(&store, &module, &[])?;
+
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::spawn(move || -> Result<()> {
+        tx.send(()).unwrap();
+        let store = Store::new(&engine);
+        Instance::new(&store, &module, &[])?;
+        Ok(())
+    });
+
+    // The spawned thread can only finish once our slot has been released.
+    rx.recv().unwrap();
+    drop(store);
+    handle.join().unwrap()?;
+    Ok(())
+}
+
+#[test]
+fn secure_teardown_never_leaks_between_tenants() -> Result<()> {
+    let mut config = Config::new();
+    config
+        .secure_teardown(true)
+        .pooling_allocation(PoolingConfig::new(1))?;
+    let engine = Engine::new(&config);
+    let wat = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "fill") (param i32)
+                (memory.fill (i32.const 0) (local.get 0) (i32.const 0x20000)))
+        )
+    "#;
+    const SENTINEL: u8 = 0xa5;
+
+    for tenant in 0..4 {
+        let store = Store::new(&engine);
+        let module = Module::new(&engine, wat)?;
+        let instance = Instance::new(&store, &module, &[])?;
+        let memory = instance.get_memory("memory").unwrap();
+        memory.grow(1)?;
+
+        // Nothing written by a previous tenant, in either the initial or the
+        // grown pages, is visible.
+        let data = unsafe { memory.data_unchecked() };
+        assert!(
+            data.iter().all(|b| *b == 0),
+            "tenant {} saw old data",
+            tenant
+        );
+        let fill = instance.get_func("fill").unwrap().get1::<i32, ()>()?;
+        fill(SENTINEL.into())?;
+        assert_eq!(unsafe { memory.data_unchecked()[0x1ffff] }, SENTINEL);
+    }
+    Ok(())
+}
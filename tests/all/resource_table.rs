@@ -0,0 +1,117 @@
+use anyhow::Result;
+use wasmtime::*;
+
+struct Counter {
+    name: &'static str,
+    reads: i32,
+}
+
+#[test]
+fn handles_resolve_to_their_resource() -> Result<()> {
+    let store = Store::default();
+    let mut linker = Linker::new(&store);
+    linker.func("host", "open", |caller: Caller<'_>, which: i32| {
+        let name = if which == 0 { "zero" } else { "other" };
+        let table = caller.store().resource_table::<Counter>();
+        let handle = table.borrow_mut().insert(Counter { name, reads: 0 });
+        handle as i32
+    })?;
+    linker.func("host", "read", |caller: Caller<'_>, handle: i32| {
+        let table = caller.store().resource_table::<Counter>();
+        let mut table = table.borrow_mut();
+        match table.get_mut(handle as u32) {
+            Some(counter) => {
+                counter.reads += 1;
+                Ok(counter.reads * 100 + counter.name.len() as i32)
+            }
+            None => Err(Trap::new("read of a closed handle")),
+        }
+    })?;
+    linker.func("host", "close", |caller: Caller<'_>, handle: i32| {
+        let table = caller.store().resource_table::<Counter>();
+        let removed = table.borrow_mut().remove(handle as u32);
+        match removed {
+            Some(_) => Ok(()),
+            None => Err(Trap::new("close of a closed handle")),
+        }
+    })?;
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "host" "open" (func $open (param i32) (result i32)))
+                (import "host" "read" (func $read (param i32) (result i32)))
+                (import "host" "close" (func $close (param i32)))
+                (global $a (mut i32) (i32.const 0))
+                (global $b (mut i32) (i32.const 0))
+                (func (export "open")
+                    (global.set $a (call $open (i32.const 0)))
+                    (global.set $b (call $open (i32.const 1))))
+                (func (export "read_b") (result i32)
+                    (call $read (global.get $b)))
+                (func (export "read_a") (result i32)
+                    (call $read (global.get $a)))
+                (func (export "close_a")
+                    (call $close (global.get $a))))
+        "#,
+    )?;
+    let instance = linker.instantiate(&module)?;
+    let open = instance.get_func("open").unwrap().get0::<()>()?;
+    let read_a = instance.get_func("read_a").unwrap().get0::<i32>()?;
+    let read_b = instance.get_func("read_b").unwrap().get0::<i32>()?;
+    let close_a = instance.get_func("close_a").unwrap().get0::<()>()?;
+
+    open()?;
+    assert_eq!(read_b()?, 105);
+    assert_eq!(read_b()?, 205);
+    assert_eq!(read_a()?, 104);
+    assert_eq!(store.resource_table::<Counter>().borrow().len(), 2);
+
+    // A removed handle no longer resolves, even once more resources are added.
+    close_a()?;
+    let err = read_a().unwrap_err();
+    assert!(
+        err.to_string().contains("closed handle"),
+        "bad error: {}",
+        err
+    );
+    open()?;
+    assert!(read_a().is_err());
+    assert_eq!(store.resource_table::<Counter>().borrow().len(), 3);
+    Ok(())
+}
+
+#[test]
+fn tables_are_per_type_and_store() {
+    let store = Store::default();
+    let strings = store.resource_table::<String>();
+    let handle = strings.borrow_mut().insert("hello".to_string());
+    assert_ne!(handle, 0);
+    assert!(store.resource_table::<u64>().borrow().get(handle).is_none());
+    assert_eq!(
+        store
+            .resource_table::<String>()
+            .borrow()
+            .get(handle)
+            .unwrap(),
+        "hello"
+    );
+
+    let other = Store::default();
+    assert!(other.resource_table::<String>().borrow().is_empty());
+}
+
+#[test]
+fn handles_are_not_reused() {
+    let mut table = ResourceTable::new();
+    let a = table.insert('a');
+    let b = table.insert('b');
+    assert_ne!(a, b);
+    assert_eq!(table.remove(a), Some('a'));
+    assert_eq!(table.remove(a), None);
+    let c = table.insert('c');
+    assert_ne!(c, a);
+    assert_eq!(table.get(a), None);
+    assert_eq!(table.get(b), Some(&'b'));
+    assert_eq!(table.get(c), Some(&'c'));
+}
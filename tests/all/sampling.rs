@@ -0,0 +1,99 @@
+#[cfg(unix)]
+mod tests {
+    use anyhow::Result;
+    use wasmtime::*;
+
+    const WAT: &str = r#"
+        (module $prof
+            (func $spin (param i32)
+                (loop
+                    (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                    (br_if 0 (local.get 0))))
+            (func $hot (param i32)
+                (call $spin (local.get 0)))
+            (func $cold (param i32)
+                (call $spin (i32.div_u (local.get 0) (i32.const 20))))
+            (func $run (export "run") (param i32)
+                (call $cold (local.get 0))
+                (call $hot (local.get 0))))
+    "#;
+
+    #[test]
+    fn disabled_by_default() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(store.engine(), WAT)?;
+        let instance = Instance::new(&store, &module, &[])?;
+        let run = instance.get_func("run").unwrap().get1::<i32, ()>()?;
+        run(1_000_000)?;
+        assert_eq!(store.profile().samples(), 0);
+        assert_eq!(store.profile().folded(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn hot_function_dominates() -> Result<()> {
+        let mut config = Config::new();
+        config.sampling_profiler(1000)?;
+        let store = Store::new(&Engine::new(&config));
+        let module = Module::new(store.engine(), WAT)?;
+        let instance = Instance::new(&store, &module, &[])?;
+        let run = instance.get_func("run").unwrap().get1::<i32, ()>()?;
+        for _ in 0..100 {
+            if store.profile().samples() >= 200 {
+                break;
+            }
+            run(100_000_000)?;
+        }
+
+        let profile = store.profile();
+        assert!(
+            profile.samples() >= 200,
+            "too few samples: {}",
+            profile.samples()
+        );
+        let hot = profile.total_samples("prof!hot");
+        let cold = profile.total_samples("prof!cold");
+        assert!(hot > 5 * cold, "hot: {}, cold: {}", hot, cold);
+        assert!(hot > profile.samples() / 2);
+        assert_eq!(profile.self_samples("prof!hot"), 0);
+        assert!(profile.self_samples("prof!spin") > profile.samples() / 2);
+        assert_eq!(profile.total_samples("prof!run"), profile.samples());
+
+        // Stacks are listed from the outermost frame in.
+        let folded = profile.folded();
+        let line = folded
+            .lines()
+            .find(|line| line.starts_with("prof!run;prof!hot;prof!spin "))
+            .unwrap_or_else(|| panic!("no stack through `hot` in:\n{}", folded));
+        let count = line.rsplit(' ').next().unwrap().parse::<u64>()?;
+        assert!(count > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn execution_is_unaffected() -> Result<()> {
+        let mut config = Config::new();
+        config.sampling_profiler(10_000)?;
+        let store = Store::new(&Engine::new(&config));
+        let module = Module::new(
+            store.engine(),
+            r#"
+                (module
+                    (func (export "sum") (param i32) (result i64)
+                        (local i64)
+                        (loop
+                            (local.set 1 (i64.add (local.get 1) (i64.extend_i32_u (local.get 0))))
+                            (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                            (br_if 0 (local.get 0)))
+                        (local.get 1)))
+            "#,
+        )?;
+        let instance = Instance::new(&store, &module, &[])?;
+        let sum = instance.get_func("sum").unwrap().get1::<i32, i64>()?;
+        let n = 50_000_000i64;
+        for _ in 0..3 {
+            assert_eq!(sum(n as i32)?, n * (n + 1) / 2);
+        }
+        Ok(())
+    }
+}
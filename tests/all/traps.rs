@@ -64,6 +64,7 @@ fn test_trap_trace() -> Result<()> {
     assert_eq!(trace[1].func_name(), None);
     assert_eq!(trace[1].func_offset(), 1);
     assert_eq!(trace[1].module_offset(), 0x21);
+    assert_eq!(e.trap_code(), Some(TrapCode::UnreachableCodeReached));
     assert!(
         e.to_string().contains("unreachable"),
         "wrong message: {}",
@@ -138,6 +139,7 @@ fn test_trap_stack_overflow() -> Result<()> {
         assert_eq!(trace[i].func_index(), 0);
         assert_eq!(trace[i].func_name(), Some("run"));
     }
+    assert_eq!(e.trap_code(), Some(TrapCode::StackOverflow));
     assert!(e.to_string().contains("call stack exhausted"));
 
     Ok(())
@@ -489,3 +491,167 @@ fn heap_out_of_bounds_trap() {
         TrapCode::MemoryOutOfBounds,
     );
 }
+
+#[test]
+// Atomic instructions are only implemented by the new backends.
+#[cfg_attr(
+    not(any(feature = "experimental_x64", target_arch = "aarch64")),
+    ignore
+)]
+fn unaligned_atomic() -> Result<()> {
+    let mut config = Config::new();
+    config.wasm_threads(true);
+    let engine = Engine::new(&config);
+    let store = Store::new(&engine);
+    let module = Module::new(
+        &engine,
+        r#"
+            (module
+                (memory 1 1 shared)
+                (func (export "load") (param i32) (result i32)
+                    local.get 0
+                    i32.atomic.load)
+                (func (export "load8") (param i32) (result i32)
+                    local.get 0
+                    i32.atomic.load8_u)
+                (func (export "store") (param i32)
+                    local.get 0
+                    i64.const 1
+                    i64.atomic.store offset=1)
+                (func (export "add") (param i32) (result i32)
+                    local.get 0
+                    i32.const 1
+                    i32.atomic.rmw16.add_u)
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let load = instance.get_func("load").unwrap().get1::<i32, i32>()?;
+    let load8 = instance.get_func("load8").unwrap().get1::<i32, i32>()?;
+    let store_fn = instance.get_func("store").unwrap().get1::<i32, ()>()?;
+    let add = instance.get_func("add").unwrap().get1::<i32, i32>()?;
+
+    let assert_unaligned = |trap: Trap| {
+        assert_eq!(trap.trap_code(), Some(TrapCode::HeapMisaligned));
+        assert!(
+            trap.to_string().contains("misaligned memory access"),
+            "{}",
+            trap
+        );
+    };
+
+    // Naturally aligned accesses, taking the static offset into account, work.
+    assert_eq!(load(4)?, 0);
+    assert_eq!(load8(3)?, 0);
+    store_fn(7)?;
+    assert_eq!(add(2)?, 0);
+
+    assert_unaligned(load(1).unwrap_err());
+    assert_unaligned(load(6).unwrap_err());
+    assert_unaligned(store_fn(8).unwrap_err());
+    assert_unaligned(add(3).unwrap_err());
+    Ok(())
+}
+
+#[test]
+fn trap_through_nested_host_calls() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (import "" "reenter" (func $reenter (param i32)))
+                (import "" "fail" (func $fail))
+                (func (export "run") (param i32) (call $a (local.get 0)))
+                (func $a (param i32) (call $reenter (local.get 0)))
+                (func (export "inner") (param i32) (call $b (local.get 0)))
+                (func $b (param i32) (call $c (local.get 0)))
+                (func $c (param i32)
+                    (if (local.get 0)
+                        (then (call $fail))
+                        (else unreachable)))
+                (func (export "ok") (result i32) (i32.const 42))
+            )
+        "#,
+    )?;
+
+    // Calls back into wasm from the host, so traps have to cross frames of
+    // both kinds.
+    let reenter = Func::wrap(&store, |caller: Caller<'_>, x: i32| {
+        let inner = caller
+            .get_export("inner")
+            .and_then(|e| e.into_func())
+            .ok_or_else(|| Trap::new("missing `inner`"))?
+            .get1::<i32, ()>()
+            .map_err(|e| Trap::new(e.to_string()))?;
+        inner(x)
+    });
+    let fail = Func::wrap(&store, || -> Result<(), Trap> {
+        Err(Trap::new("host error"))
+    });
+    let instance = Instance::new(&store, &module, &[reenter.into(), fail.into()])?;
+    let run = instance.get_func("run").unwrap();
+
+    let trap = run.call(&[0.into()]).unwrap_err().downcast::<Trap>()?;
+    assert_eq!(trap.trap_code(), Some(TrapCode::UnreachableCodeReached));
+    let trap = run.call(&[1.into()]).unwrap_err().downcast::<Trap>()?;
+    assert!(
+        trap.to_string().contains("host error"),
+        "bad trap: {}",
+        trap
+    );
+
+    // The store is still perfectly usable afterwards.
+    let ok = instance.get_func("ok").unwrap().get0::<i32>()?;
+    assert_eq!(ok()?, 42);
+    Ok(())
+}
+
+#[test]
+fn division_trap_codes() -> Result<()> {
+    let wat = r#"
+        (module
+            (func (export "div_s") (param i32 i32) (result i32)
+                (i32.div_s (local.get 0) (local.get 1)))
+            (func (export "rem_u") (param i64 i64) (result i64)
+                (i64.rem_u (local.get 0) (local.get 1))))
+    "#;
+    let mut explicit = Config::new();
+    explicit.div_by_zero_behavior(DivBehavior::Trap);
+    for config in [Config::new(), explicit].iter() {
+        let store = Store::new(&Engine::new(config));
+        let module = Module::new(store.engine(), wat)?;
+        let instance = Instance::new(&store, &module, &[])?;
+        let div_s = instance
+            .get_func("div_s")
+            .unwrap()
+            .get2::<i32, i32, i32>()?;
+        let rem_u = instance
+            .get_func("rem_u")
+            .unwrap()
+            .get2::<i64, i64, i64>()?;
+
+        assert_eq!(div_s(7, -2)?, -3);
+        let trap = div_s(1, 0).unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::IntegerDivisionByZero));
+        let trap = div_s(i32::min_value(), -1).unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::IntegerOverflow));
+        let trap = rem_u(1, 0).unwrap_err();
+        assert_eq!(trap.trap_code(), Some(TrapCode::IntegerDivisionByZero));
+    }
+
+    // Either way, division traps where the specification says it does.
+    let mut native = Config::new();
+    native.div_by_zero_behavior(DivBehavior::Native);
+    let store = Store::new(&Engine::new(&native));
+    let module = Module::new(store.engine(), wat)?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let div_s = instance
+        .get_func("div_s")
+        .unwrap()
+        .get2::<i32, i32, i32>()?;
+    assert_eq!(div_s(7, -2)?, -3);
+    assert!(div_s(1, 0).is_err());
+    assert!(div_s(i32::min_value(), -1).is_err());
+    Ok(())
+}
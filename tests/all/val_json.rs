@@ -0,0 +1,89 @@
+use anyhow::Result;
+use serde_json::json;
+use wasmtime::*;
+
+#[test]
+fn round_trip_signature() -> Result<()> {
+    let args = json!(["42", 3.5]);
+    let vals = [ValType::I64, ValType::F32]
+        .iter()
+        .zip(args.as_array().unwrap())
+        .map(|(ty, json)| val_from_json(ty.clone(), json))
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(vals[0].unwrap_i64(), 42);
+    assert_eq!(vals[1].unwrap_f32(), 3.5);
+    let back = vals.iter().map(val_to_json).collect::<Vec<_>>();
+    assert_eq!(serde_json::Value::from(back), args);
+    Ok(())
+}
+
+#[test]
+fn integers() -> Result<()> {
+    assert_eq!(val_from_json(ValType::I32, &json!(-1))?.unwrap_i32(), -1);
+    assert_eq!(
+        val_from_json(ValType::I32, &json!(4294967295u32))?.unwrap_i32(),
+        -1
+    );
+    assert_eq!(val_from_json(ValType::I32, &json!("7"))?.unwrap_i32(), 7);
+    assert!(val_from_json(ValType::I32, &json!(4294967296u64)).is_err());
+    assert!(val_from_json(ValType::I32, &json!(1.5)).is_err());
+
+    let max = "18446744073709551615";
+    assert_eq!(val_from_json(ValType::I64, &json!(max))?.unwrap_i64(), -1);
+    assert_eq!(
+        val_from_json(ValType::I64, &json!("-9223372036854775808"))?.unwrap_i64(),
+        i64::min_value()
+    );
+    assert!(val_from_json(ValType::I64, &json!("18446744073709551616")).is_err());
+    assert!(val_from_json(ValType::I64, &json!("forty-two")).is_err());
+    assert_eq!(
+        val_to_json(&Val::I64(i64::max_value())),
+        json!("9223372036854775807")
+    );
+    assert_eq!(val_to_json(&Val::I32(-3)), json!(-3));
+
+    let v128 = val_from_json(
+        ValType::V128,
+        &json!("340282366920938463463374607431768211455"),
+    )?;
+    assert_eq!(v128.unwrap_v128(), u128::max_value());
+    assert_eq!(val_to_json(&v128), json!(u128::max_value().to_string()));
+    Ok(())
+}
+
+#[test]
+fn floats() -> Result<()> {
+    assert_eq!(val_from_json(ValType::F64, &json!(0.1))?.unwrap_f64(), 0.1);
+    assert_eq!(val_from_json(ValType::F32, &json!(0.1))?.unwrap_f32(), 0.1);
+    assert_eq!(val_from_json(ValType::F32, &json!(2))?.unwrap_f32(), 2.0);
+    assert!(val_from_json(ValType::F64, &json!("NaN"))?
+        .unwrap_f64()
+        .is_nan());
+    assert_eq!(
+        val_from_json(ValType::F32, &json!("-inf"))?.unwrap_f32(),
+        f32::NEG_INFINITY
+    );
+    assert!(val_from_json(ValType::F64, &json!("1.5")).is_err());
+
+    assert_eq!(val_to_json(&Val::F64(f64::NAN.to_bits())), json!("NaN"));
+    assert_eq!(
+        val_to_json(&Val::F32(f32::INFINITY.to_bits())),
+        json!("inf")
+    );
+    assert_eq!(val_to_json(&Val::F64(1.25f64.to_bits())), json!(1.25));
+    Ok(())
+}
+
+#[test]
+fn references() -> Result<()> {
+    assert!(val_from_json(ValType::ExternRef, &json!(null))?
+        .unwrap_externref()
+        .is_none());
+    assert!(val_from_json(ValType::FuncRef, &json!(null))?
+        .unwrap_funcref()
+        .is_none());
+    let err = val_from_json(ValType::FuncRef, &json!(1)).unwrap_err();
+    assert_eq!(err.to_string(), "cannot convert JSON value `1` to funcref");
+    assert_eq!(val_to_json(&Val::ExternRef(None)), json!(null));
+    Ok(())
+}
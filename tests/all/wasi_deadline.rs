@@ -0,0 +1,127 @@
+use anyhow::Result;
+use std::thread;
+use std::time::{Duration, Instant};
+use wasi_common::{MockClock, WasiCtxBuilder};
+use wasmtime::*;
+use wasmtime_wasi::Wasi;
+
+// Sleeps for a second with `poll_oneoff`, stores its errno at 256, and then
+// calls a function, whose prologue is where an interrupt is delivered.
+const SLEEPER: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "poll_oneoff"
+            (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func $after_sleep)
+        (func (export "sleep")
+            ;; A relative `CLOCK_MONOTONIC` subscription for one second.
+            (i32.store8 (i32.const 8) (i32.const 0))
+            (i32.store (i32.const 16) (i32.const 1))
+            (i64.store (i32.const 24) (i64.const 1000000000))
+            (i32.store (i32.const 256)
+                (call $poll_oneoff (i32.const 0) (i32.const 64) (i32.const 1) (i32.const 128)))
+            (call $after_sleep))
+    )
+"#;
+
+const ERRNO_SUCCESS: i32 = 0;
+const ERRNO_INTR: i32 = 27;
+
+fn sleeper(store: &Store, share_deadline: bool, clock: Option<MockClock>) -> Result<Instance> {
+    let mut builder = WasiCtxBuilder::new();
+    if let Some(clock) = clock {
+        builder.monotonic_clock(clock);
+    }
+    if share_deadline {
+        let handle = store.interrupt_handle()?;
+        let interrupt = store.interrupt_handle()?;
+        builder.deadline(move || handle.deadline(), move || interrupt.interrupt());
+    }
+    let mut linker = Linker::new(store);
+    Wasi::new(store, builder.build()?).add_to_linker(&mut linker)?;
+    linker.instantiate(&Module::new(store.engine(), SLEEPER)?)
+}
+
+fn errno(instance: &Instance) -> i32 {
+    let memory = instance.get_memory("memory").unwrap();
+    unsafe { *(memory.data_ptr().add(256) as *const i32) }
+}
+
+fn interruptable_store() -> Store {
+    let mut config = Config::new();
+    config.interruptable(true);
+    Store::new(&Engine::new(&config))
+}
+
+#[test]
+fn host_deadline_cuts_guest_timer_short() -> Result<()> {
+    let store = interruptable_store();
+    let instance = sleeper(&store, true, None)?;
+    let sleep = instance.get_func("sleep").unwrap().get0::<()>()?;
+
+    let start = Instant::now();
+    store.set_deadline(start + Duration::from_millis(100))?;
+    let trap = sleep().unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::Interrupt));
+    assert!(
+        start.elapsed() < Duration::from_millis(900),
+        "the guest timer shouldn't have run its course"
+    );
+    assert_eq!(errno(&instance), ERRNO_INTR);
+    Ok(())
+}
+
+#[test]
+fn guest_timer_before_host_deadline() -> Result<()> {
+    let store = interruptable_store();
+    let instance = sleeper(&store, true, None)?;
+    let sleep = instance.get_func("sleep").unwrap().get0::<()>()?;
+
+    store.set_deadline(Instant::now() + Duration::from_secs(60))?;
+    sleep()?;
+    assert_eq!(errno(&instance), ERRNO_SUCCESS);
+    store.clear_deadline();
+    assert_eq!(store.deadline(), None);
+    Ok(())
+}
+
+#[test]
+fn unshared_deadline_waits_for_guest_timer() -> Result<()> {
+    let store = interruptable_store();
+    let instance = sleeper(&store, false, None)?;
+    let sleep = instance.get_func("sleep").unwrap().get0::<()>()?;
+
+    // Without the deadline, the interrupt is only delivered once the guest
+    // wakes up.
+    let start = Instant::now();
+    store.set_deadline(start + Duration::from_millis(100))?;
+    let trap = sleep().unwrap_err();
+    assert_eq!(trap.trap_code(), Some(TrapCode::Interrupt));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(errno(&instance), ERRNO_SUCCESS);
+    Ok(())
+}
+
+#[test]
+fn deadline_shares_installed_clock() -> Result<()> {
+    let store = interruptable_store();
+    let clock = MockClock::new(0);
+    let instance = sleeper(&store, true, Some(clock.clone()))?;
+    let sleep = instance.get_func("sleep").unwrap().get0::<()>()?;
+
+    // Moving the guest's clock past the host deadline, but not past the
+    // guest's timer, interrupts the guest before the deadline passes in real
+    // time.
+    let start = Instant::now();
+    store.set_deadline(start + Duration::from_millis(500))?;
+    let advance = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        clock.advance(Duration::from_millis(600));
+    });
+    let trap = sleep().unwrap_err();
+    advance.join().unwrap();
+    assert_eq!(trap.trap_code(), Some(TrapCode::Interrupt));
+    assert!(start.elapsed() < Duration::from_millis(400));
+    assert_eq!(errno(&instance), ERRNO_INTR);
+    Ok(())
+}
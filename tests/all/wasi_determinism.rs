@@ -0,0 +1,171 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use wasi_common::{MockClock, SeededRandom};
+use wasmtime::*;
+
+const SNAPSHOT_1: &str = "wasi_snapshot_preview1";
+const SNAPSHOT_0: &str = "wasi_unstable";
+
+const CLOCKID_REALTIME: i32 = 0;
+const CLOCKID_MONOTONIC: i32 = 1;
+const ERRNO_SUCCESS: i32 = 0;
+
+/// A guest for `snapshot` which reads the clocks, asks for random bytes and
+/// sleeps, the subscription of which is laid out differently by snapshot 0.
+fn guest(snapshot: &str) -> String {
+    let clock = if snapshot == SNAPSHOT_0 { 152 } else { 144 };
+    format!(
+        r#"
+            (module
+                (import "{m}" "clock_time_get"
+                    (func $clock_time_get (param i32 i64 i32) (result i32)))
+                (import "{m}" "random_get"
+                    (func $random_get (param i32 i32) (result i32)))
+                (import "{m}" "poll_oneoff"
+                    (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+
+                ;; Returns the time of clock `id`.
+                (func (export "now") (param $id i32) (result i64)
+                    (if (call $clock_time_get (local.get $id) (i64.const 0) (i32.const 0))
+                        (then unreachable))
+                    (i64.load (i32.const 0)))
+
+                ;; Fills the 32 bytes at 64 with random bytes.
+                (func (export "random")
+                    (if (call $random_get (i32.const 64) (i32.const 32))
+                        (then unreachable)))
+
+                ;; Sleeps for `ns` on the monotonic clock, returning the errno.
+                (func (export "sleep") (param $ns i64) (result i32)
+                    (i64.store (i32.const 128) (i64.const 0))
+                    (i32.store8 (i32.const 136) (i32.const 0))
+                    (i32.store (i32.const {id}) (i32.const 1))
+                    (i64.store (i32.const {timeout}) (local.get $ns))
+                    (i64.store (i32.const {precision}) (i64.const 0))
+                    (i32.store16 (i32.const {flags}) (i32.const 0))
+                    (call $poll_oneoff (i32.const 128) (i32.const 256) (i32.const 1) (i32.const 512)))
+            )
+        "#,
+        m = snapshot,
+        id = clock,
+        timeout = clock + 8,
+        precision = clock + 16,
+        flags = clock + 24,
+    )
+}
+
+fn instantiate(
+    store: &Store,
+    snapshot: &str,
+    monotonic: MockClock,
+    wall: MockClock,
+    seed: u64,
+) -> Result<Instance> {
+    let mut linker = Linker::new(store);
+    if snapshot == SNAPSHOT_0 {
+        let ctx = wasi_common::old::snapshot_0::WasiCtxBuilder::new()
+            .monotonic_clock(monotonic)
+            .wall_clock(wall)
+            .random(SeededRandom::new(seed))
+            .build()?;
+        wasmtime_wasi::old::snapshot_0::Wasi::new(store, ctx).add_to_linker(&mut linker)?;
+    } else {
+        let ctx = wasi_common::WasiCtxBuilder::new()
+            .monotonic_clock(monotonic)
+            .wall_clock(wall)
+            .random(SeededRandom::new(seed))
+            .build()?;
+        wasmtime_wasi::Wasi::new(store, ctx).add_to_linker(&mut linker)?;
+    }
+    let module = Module::new(store.engine(), guest(snapshot))?;
+    linker.instantiate(&module)
+}
+
+/// Runs the guest once, returning everything it observed.
+fn observe(snapshot: &str, seed: u64) -> Result<(Vec<i64>, Vec<u8>)> {
+    let store = Store::default();
+    let monotonic = MockClock::new(1_000).with_tick(Duration::from_millis(1));
+    let wall = MockClock::new(1_600_000_000_000_000_000);
+    let instance = instantiate(&store, snapshot, monotonic, wall, seed)?;
+    let now = instance.get_func("now").unwrap().get1::<i32, i64>()?;
+    let random = instance.get_func("random").unwrap().get0::<()>()?;
+    let sleep = instance.get_func("sleep").unwrap().get1::<i64, i32>()?;
+
+    let mut times = vec![now(CLOCKID_MONOTONIC)?, now(CLOCKID_MONOTONIC)?];
+    assert_eq!(sleep(10_000_000)?, ERRNO_SUCCESS);
+    times.push(now(CLOCKID_MONOTONIC)?);
+    times.push(now(CLOCKID_REALTIME)?);
+    random()?;
+    let memory = instance.get_memory("memory").unwrap();
+    let bytes = unsafe { memory.data_unchecked()[64..96].to_vec() };
+    Ok((times, bytes))
+}
+
+#[test]
+fn runs_are_reproducible() -> Result<()> {
+    for snapshot in &[SNAPSHOT_1, SNAPSHOT_0] {
+        let (times, bytes) = observe(snapshot, 42)?;
+        assert_eq!(times[..2], [1_000, 1_001_000]);
+        // Sleeping moves the clock to the deadline, and then reads it.
+        assert!(
+            times[2] >= 12_002_000,
+            "bad time after sleeping: {:?}",
+            times
+        );
+        assert_eq!(times[3], 1_600_000_000_000_000_000);
+        assert_eq!(observe(snapshot, 42)?, (times, bytes.clone()));
+        assert_ne!(observe(snapshot, 43)?.1, bytes);
+    }
+    Ok(())
+}
+
+#[test]
+fn sleep_waits_for_mock_clock() -> Result<()> {
+    for snapshot in &[SNAPSHOT_1, SNAPSHOT_0] {
+        let store = Store::default();
+        let monotonic = MockClock::new(0);
+        let instance = instantiate(&store, snapshot, monotonic.clone(), MockClock::new(0), 0)?;
+        let sleep = instance.get_func("sleep").unwrap().get1::<i64, i32>()?;
+
+        let advanced = Arc::new(AtomicBool::new(false));
+        let advancer = {
+            let advanced = advanced.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                advanced.store(true, SeqCst);
+                monotonic.advance(Duration::from_secs(60));
+            })
+        };
+        let start = Instant::now();
+        assert_eq!(sleep(60_000_000_000)?, ERRNO_SUCCESS);
+        assert!(
+            advanced.load(SeqCst),
+            "woke up before the clock was advanced"
+        );
+        assert!(start.elapsed() < Duration::from_secs(30));
+        advancer.join().unwrap();
+    }
+    Ok(())
+}
+
+#[test]
+fn sleep_skips_ticking_clock_ahead() -> Result<()> {
+    for snapshot in &[SNAPSHOT_1, SNAPSHOT_0] {
+        let store = Store::default();
+        let monotonic = MockClock::new(0).with_tick(Duration::from_nanos(1));
+        let instance = instantiate(&store, snapshot, monotonic, MockClock::new(0), 0)?;
+        let now = instance.get_func("now").unwrap().get1::<i32, i64>()?;
+        let sleep = instance.get_func("sleep").unwrap().get1::<i64, i32>()?;
+
+        // An hour passes for the guest without anyone waiting for it.
+        let start = Instant::now();
+        assert_eq!(sleep(3_600_000_000_000)?, ERRNO_SUCCESS);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(now(CLOCKID_MONOTONIC)? >= 3_600_000_000_000);
+    }
+    Ok(())
+}
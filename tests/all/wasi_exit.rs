@@ -0,0 +1,63 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn guest(snapshot: &str) -> String {
+    format!(
+        r#"
+            (module
+                (import "{}" "proc_exit" (func $proc_exit (param i32)))
+                (memory (export "memory") 1)
+                (global $calls (mut i32) (i32.const 0))
+
+                (func (export "exit") (param i32)
+                    (global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+                    (call $proc_exit (local.get 0))
+                    unreachable)
+
+                (func (export "calls") (result i32)
+                    global.get $calls))
+        "#,
+        snapshot
+    )
+}
+
+fn instantiate(store: &Store, snapshot: &str) -> Result<Instance> {
+    let mut linker = Linker::new(store);
+    if snapshot == "wasi_unstable" {
+        let ctx = wasi_common::old::snapshot_0::WasiCtxBuilder::new().build()?;
+        wasmtime_wasi::old::snapshot_0::Wasi::new(store, ctx).add_to_linker(&mut linker)?;
+    } else {
+        let ctx = wasi_common::WasiCtxBuilder::new().build()?;
+        wasmtime_wasi::Wasi::new(store, ctx).add_to_linker(&mut linker)?;
+    }
+    linker.instantiate(&Module::new(store.engine(), guest(snapshot))?)
+}
+
+#[test]
+fn proc_exit_is_an_exit_status() -> Result<()> {
+    for snapshot in &["wasi_snapshot_preview1", "wasi_unstable"] {
+        let store = Store::default();
+        let instance = instantiate(&store, snapshot)?;
+        let exit = instance.get_func("exit").unwrap();
+        let calls = instance.get_func("calls").unwrap().get0::<i32>()?;
+
+        for status in &[0, 1, 125] {
+            let err = exit.call(&[Val::I32(*status)]).unwrap_err();
+            let trap = err.downcast_ref::<Trap>().unwrap();
+            assert_eq!(trap.i32_exit_status(), Some(*status), "{}", snapshot);
+            assert_eq!(trap.trap_code(), None);
+        }
+
+        // Statuses outside of WASI's range are errors rather than exits.
+        for status in &[126, -1] {
+            let err = exit.call(&[Val::I32(*status)]).unwrap_err();
+            let trap = err.downcast_ref::<Trap>().unwrap();
+            assert_eq!(trap.i32_exit_status(), None);
+            assert!(trap.to_string().contains("invalid exit status"), "{}", trap);
+        }
+
+        // Exiting unwinds cleanly, so the instance is still usable after.
+        assert_eq!(calls()?, 5);
+    }
+    Ok(())
+}
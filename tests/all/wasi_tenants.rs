@@ -0,0 +1,202 @@
+use anyhow::Result;
+use std::fs;
+use std::sync::{Arc, Barrier, RwLock};
+use std::thread;
+use tempfile::TempDir;
+use wasi_common::virtfs::pipe::WritePipe;
+use wasi_common::{preopen_dir, WasiCtxBuilder};
+use wasmtime::*;
+use wasmtime_wasi::Wasi;
+
+const TENANT: &str = r#"
+    (module
+        (import "wasi_snapshot_preview1" "args_sizes_get"
+            (func $args_sizes_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "args_get"
+            (func $args_get (param i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+        (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 32) "out.txt")
+
+        ;; Points the iovec at 16 to argv[1], which is the last argument.
+        (func $load_arg
+            (drop (call $args_sizes_get (i32.const 0) (i32.const 4)))
+            (drop (call $args_get (i32.const 64) (i32.const 256)))
+            (i32.store (i32.const 16) (i32.load (i32.const 68)))
+            (i32.store (i32.const 20)
+                (i32.sub
+                    (i32.sub
+                        (i32.add (i32.const 256) (i32.load (i32.const 4)))
+                        (i32.load (i32.const 68)))
+                    (i32.const 1))))
+
+        ;; Writes argv[1] to `fd`, returning the errno.
+        (func $write_fd (export "write_fd") (param $fd i32) (result i32)
+            (call $load_arg)
+            (call $fd_write (local.get $fd) (i32.const 16) (i32.const 1) (i32.const 24)))
+
+        ;; Writes argv[1] to stdout.
+        (func (export "_start")
+            (drop (call $write_fd (i32.const 1))))
+
+        ;; Creates `out.txt` in the preopened directory, returning its fd or -1.
+        (func (export "open") (result i32)
+            (if (call $path_open
+                    (i32.const 3) (i32.const 0) (i32.const 32) (i32.const 7)
+                    ;; O_CREAT | O_TRUNC, with the right to fd_write.
+                    (i32.const 9) (i64.const 0x40) (i64.const 0)
+                    (i32.const 0) (i32.const 28))
+                (then (return (i32.const -1))))
+            (i32.load (i32.const 28)))
+    )
+"#;
+
+const ERRNO_SUCCESS: i32 = 0;
+const ERRNO_BADF: i32 = 8;
+const STDOUT_FD: i32 = 1;
+
+/// One instance of `TENANT` with its own `WasiCtx`, preopen and stdout.
+struct Tenant {
+    name: String,
+    instance: Instance,
+    stdout: Arc<RwLock<Vec<u8>>>,
+    dir: TempDir,
+}
+
+impl Tenant {
+    fn new(store: &Store, module: &Module, name: String) -> Result<Tenant> {
+        let dir = tempfile::tempdir()?;
+        let stdout = Arc::new(RwLock::new(Vec::new()));
+        let mut builder = WasiCtxBuilder::new();
+        builder
+            .arg("tenant")
+            .arg(&name)
+            .stdout(WritePipe::from_shared(stdout.clone()))
+            .preopened_dir(preopen_dir(dir.path())?, ".");
+
+        // Each tenant gets its own `Linker`, so that its imports resolve to
+        // functions bound to its own `WasiCtx`.
+        let mut linker = Linker::new(store);
+        Wasi::new(store, builder.build()?).add_to_linker(&mut linker)?;
+        let instance = linker.instantiate(module)?;
+        Ok(Tenant {
+            name,
+            instance,
+            stdout,
+            dir,
+        })
+    }
+
+    fn write_fd(&self, fd: i32) -> Result<i32> {
+        let write_fd = self.instance.get_func("write_fd").unwrap();
+        Ok(write_fd.get1::<i32, i32>()?(fd)?)
+    }
+
+    fn open(&self) -> Result<i32> {
+        let open = self.instance.get_func("open").unwrap();
+        Ok(open.get0::<i32>()?()?)
+    }
+
+    fn stdout(&self) -> String {
+        String::from_utf8(self.stdout.read().unwrap().clone()).unwrap()
+    }
+
+    fn file(&self) -> Result<String> {
+        Ok(fs::read_to_string(self.dir.path().join("out.txt"))?)
+    }
+}
+
+#[test]
+fn many_tenants_in_one_store() -> Result<()> {
+    let store = Store::default();
+    let module = Module::new(store.engine(), TENANT)?;
+    let tenants = (0..100)
+        .map(|i| Tenant::new(&store, &module, format!("tenant-{}.", i)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Calls into the tenants are interleaved.
+    for _ in 0..3 {
+        for tenant in &tenants {
+            assert_eq!(tenant.write_fd(STDOUT_FD)?, ERRNO_SUCCESS);
+        }
+    }
+
+    // An fd opened by one tenant doesn't exist for any other.
+    let fd = tenants[0].open()?;
+    assert!(fd > 3, "bad fd {}", fd);
+    assert_eq!(tenants[1].write_fd(fd)?, ERRNO_BADF);
+    assert_eq!(tenants[0].write_fd(fd)?, ERRNO_SUCCESS);
+    for tenant in &tenants[1..] {
+        let fd = tenant.open()?;
+        assert_eq!(tenant.write_fd(fd)?, ERRNO_SUCCESS);
+    }
+
+    for tenant in &tenants {
+        assert_eq!(tenant.stdout(), tenant.name.repeat(3));
+        assert_eq!(tenant.file()?, tenant.name);
+    }
+    Ok(())
+}
+
+#[test]
+fn tenants_across_stores() -> Result<()> {
+    // The engine and the compiled module are shared, everything else is
+    // per-store.
+    let engine = Engine::default();
+    let module = Module::new(&engine, TENANT)?;
+    let stores = [Store::new(&engine), Store::new(&engine)];
+    let a = Tenant::new(&stores[0], &module, "a".to_string())?;
+    let b = Tenant::new(&stores[1], &module, "b".to_string())?;
+    let c = Tenant::new(&stores[1], &module, "c".to_string())?;
+
+    for tenant in [&a, &b, &c, &a, &b, &a].iter() {
+        assert_eq!(tenant.write_fd(STDOUT_FD)?, ERRNO_SUCCESS);
+    }
+    let fd = a.open()?;
+    assert_eq!(b.write_fd(fd)?, ERRNO_BADF);
+    assert_eq!(a.write_fd(fd)?, ERRNO_SUCCESS);
+
+    assert_eq!(a.stdout(), "aaa");
+    assert_eq!(b.stdout(), "bb");
+    assert_eq!(c.stdout(), "c");
+    assert_eq!(a.file()?, "a");
+    assert!(b.file().is_err());
+    Ok(())
+}
+
+#[test]
+fn tenants_on_threads() -> Result<()> {
+    // The module is compiled once and each thread instantiates it into a
+    // store of its own, with its own `WasiCtx`.
+    let engine = Engine::default();
+    let module = Module::new(&engine, TENANT)?;
+    let barrier = Arc::new(Barrier::new(8));
+    let threads = (0..8)
+        .map(|i| {
+            let engine = engine.clone();
+            let module = module.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || -> Result<()> {
+                let store = Store::new(&engine);
+                let tenant = Tenant::new(&store, &module, format!("thread-{}.", i))?;
+                let start = tenant.instance.get_func("_start").unwrap().get0::<()>()?;
+                barrier.wait();
+                for _ in 0..100 {
+                    start()?;
+                }
+                let fd = tenant.open()?;
+                assert_eq!(tenant.write_fd(fd)?, ERRNO_SUCCESS);
+                assert_eq!(tenant.stdout(), tenant.name.repeat(100));
+                assert_eq!(tenant.file()?, tenant.name);
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+    Ok(())
+}
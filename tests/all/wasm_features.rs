@@ -0,0 +1,130 @@
+use anyhow::Result;
+use wasmtime::*;
+
+fn config(f: impl FnOnce(&mut Config)) -> Engine {
+    let mut config = Config::new();
+    f(&mut config);
+    Engine::new(&config)
+}
+
+fn assert_disabled(engine: &Engine, wat: &str, message: &str) -> Result<()> {
+    let err = Module::validate(engine, &wat::parse_str(wat)?).unwrap_err();
+    let err = err.downcast_ref::<ValidationError>().unwrap();
+    assert!(err.message().contains(message), "bad error: {}", err);
+    Ok(())
+}
+
+#[test]
+fn multi_value() -> Result<()> {
+    let wat = r#"
+        (module
+            (func (export "pair") (param i32) (result i32 i64)
+                local.get 0
+                local.get 0
+                i64.extend_i32_s))
+    "#;
+    let engine = config(|c| {
+        c.wasm_multi_value(true);
+    });
+    let store = Store::new(&engine);
+    let instance = Instance::new(&store, &Module::new(&engine, wat)?, &[])?;
+    let results = instance.get_func("pair").unwrap().call(&[Val::I32(-3)])?;
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].unwrap_i32(), -3);
+    assert_eq!(results[1].unwrap_i64(), -3);
+
+    let engine = config(|c| {
+        c.wasm_multi_value(false);
+    });
+    assert_disabled(&engine, wat, "multiple values")
+}
+
+#[test]
+fn bulk_memory() -> Result<()> {
+    let wat = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "fill") (param i32 i32 i32)
+                (memory.fill (local.get 0) (local.get 1) (local.get 2))))
+    "#;
+    let engine = config(|c| {
+        c.wasm_bulk_memory(true);
+    });
+    let store = Store::new(&engine);
+    let instance = Instance::new(&store, &Module::new(&engine, wat)?, &[])?;
+    let fill = instance
+        .get_func("fill")
+        .unwrap()
+        .get3::<i32, i32, i32, ()>()?;
+    fill(8, 0xab, 4)?;
+    let memory = instance.get_memory("memory").unwrap();
+    assert_eq!(
+        unsafe { &memory.data_unchecked()[7..13] },
+        &[0, 0xab, 0xab, 0xab, 0xab, 0]
+    );
+
+    let engine = config(|c| {
+        c.wasm_bulk_memory(false);
+    });
+    assert_disabled(&engine, wat, "bulk memory support is not enabled")
+}
+
+#[test]
+fn reference_types() -> Result<()> {
+    let wat = r#"
+        (module
+            (func (export "id") (param externref) (result externref)
+                local.get 0))
+    "#;
+    // Reference types are only supported by the code generator on x86-64.
+    if cfg!(target_arch = "x86_64") {
+        let engine = config(|c| {
+            c.wasm_reference_types(true);
+        });
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &Module::new(&engine, wat)?, &[])?;
+        let arg = Val::ExternRef(Some(ExternRef::new(42_u32)));
+        let results = instance.get_func("id").unwrap().call(&[arg])?;
+        let result = results[0].unwrap_externref().unwrap();
+        assert_eq!(result.data().downcast_ref::<u32>(), Some(&42));
+    }
+
+    let engine = config(|c| {
+        c.wasm_reference_types(false);
+    });
+    assert_disabled(&engine, wat, "reference types support is not enabled")?;
+
+    // Reference types can't be left enabled without bulk memory, which they
+    // depend on.
+    let engine = config(|c| {
+        c.wasm_reference_types(true).wasm_bulk_memory(false);
+    });
+    assert_disabled(&engine, wat, "reference types support is not enabled")
+}
+
+#[test]
+fn simd() -> Result<()> {
+    let wat = r#"
+        (module
+            (func (export "sum") (param i32 i32) (result i32)
+                (i32x4.extract_lane 0
+                    (i32x4.add
+                        (i32x4.splat (local.get 0))
+                        (i32x4.splat (local.get 1))))))
+    "#;
+    // SIMD is only supported by the code generator on x86-64.
+    if cfg!(target_arch = "x86_64") {
+        let engine = config(|c| {
+            c.wasm_simd(true);
+        });
+        let store = Store::new(&engine);
+        let instance = Instance::new(&store, &Module::new(&engine, wat)?, &[])?;
+        let sum = instance.get_func("sum").unwrap().get2::<i32, i32, i32>()?;
+        assert_eq!(sum(40, 2)?, 42);
+    }
+
+    let engine = config(|c| {
+        c.wasm_simd(false);
+    });
+    assert_disabled(&engine, wat, "SIMD support is not enabled")
+}
@@ -19,8 +19,13 @@ fn run_wast(wast: &str, strategy: Strategy) -> anyhow::Result<()> {
     // by reference types.
     let reftypes = simd || wast.iter().any(|s| s == "reference-types");
 
+    // Start from the spec-mode preset and only enable the proposals that the
+    // test's directory requires. Multi-value has been merged into the core
+    // spec testsuite so it's always enabled.
     let mut cfg = Config::new();
-    cfg.wasm_simd(simd)
+    cfg.strict_spec_mode()
+        .wasm_multi_value(true)
+        .wasm_simd(simd)
         .wasm_bulk_memory(bulk_mem)
         .wasm_reference_types(reftypes)
         .wasm_multi_memory(multi_memory)
@@ -0,0 +1,84 @@
+// Limiting the memory compilation takes needs `CompilationAllocator` as the
+// global allocator, which is why this is a test of its own rather than part
+// of `tests/all`.
+
+use std::alloc::System;
+use wasmtime::*;
+
+#[global_allocator]
+static GLOBAL: CompilationAllocator = CompilationAllocator::new(System);
+
+const LIMIT: usize = 1 << 20;
+
+// Few instructions, but thousands of locals live across thousands of blocks,
+// which takes a lot of memory to put into SSA form.
+fn expensive_wat() -> String {
+    let mut wat = format!("(module (func (local {})", "i32 ".repeat(10_000));
+    for _ in 0..1_000 {
+        wat.push_str("(block (br_if 0 (local.get 0)))");
+    }
+    wat.push_str("))");
+    wat
+}
+
+fn assert_memory_limit(err: anyhow::Error) {
+    let err = format!("{:?}", err);
+    assert!(
+        err.contains(&format!(
+            "Compilation memory exceeds the limit of {} bytes",
+            LIMIT
+        )),
+        "bad error: {}",
+        err
+    );
+}
+
+fn limit() -> anyhow::Result<()> {
+    let mut config = Config::new();
+    config.compilation_memory_limit(LIMIT);
+    let engine = Engine::new(&config);
+
+    Module::new(
+        &engine,
+        "(module (func (export \"run\") (result i32) i32.const 1))",
+    )?;
+    assert_memory_limit(Module::new(&engine, &expensive_wat()).unwrap_err());
+
+    // The engine is still usable afterwards.
+    Module::new(&engine, "(module (func))")?;
+    Ok(())
+}
+
+fn limit_with_cache() -> anyhow::Result<()> {
+    let td = tempfile::TempDir::new()?;
+    let config_path = td.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        &format!(
+            "
+                [cache]
+                enabled = true
+                directory = '{}'
+            ",
+            td.path().join("cache").display()
+        ),
+    )?;
+    let wat = expensive_wat();
+
+    // Without a limit the module compiles and is cached.
+    let mut config = Config::new();
+    config.cache_config_load(&config_path)?;
+    Module::new(&Engine::new(&config), &wat)?;
+
+    // The cached module isn't used once there's a limit it exceeds.
+    config.compilation_memory_limit(LIMIT);
+    assert_memory_limit(Module::new(&Engine::new(&config), &wat).unwrap_err());
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    limit()?;
+    limit_with_cache()?;
+    println!("ok");
+    Ok(())
+}
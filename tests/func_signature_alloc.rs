@@ -0,0 +1,54 @@
+// Dynamic binding layers query a function's signature on every call, so doing
+// so shouldn't allocate. This installs a counting global allocator to check
+// that, which is why it's a test of its own rather than part of `tests/all`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use wasmtime::*;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn main() -> anyhow::Result<()> {
+    let store = Store::default();
+    let module = Module::new(
+        store.engine(),
+        r#"
+            (module
+                (func (export "f") (param i32 i64 f32) (result f64)
+                    f64.const 0)
+            )
+        "#,
+    )?;
+    let instance = Instance::new(&store, &module, &[])?;
+    let f = instance.get_func("f").unwrap();
+
+    let before = ALLOCATIONS.load(SeqCst);
+    for _ in 0..1000 {
+        assert_eq!(f.param_arity(), 3);
+        assert_eq!(f.result_arity(), 1);
+        assert_eq!(f.param(1), Some(ValType::I64));
+        assert_eq!(f.param(3), None);
+        let ty = f.ty();
+        assert_eq!(ty.params().len(), 3);
+    }
+    let after = ALLOCATIONS.load(SeqCst);
+    assert_eq!(after - before, 0, "signature queries allocated");
+    println!("ok");
+    Ok(())
+}
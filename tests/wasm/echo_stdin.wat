@@ -0,0 +1,36 @@
+(module
+  (import "wasi_snapshot_preview1" "fd_read"
+    (func $__wasi_fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $__wasi_fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit"
+    (func $__wasi_proc_exit (param i32)))
+  (func $_start
+    (block $done
+      (loop $copy
+        ;; Read up to 1024 bytes from stdin into the buffer at 1024.
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 1024))
+        (if (call $__wasi_fd_read
+              (i32.const 0)
+              (i32.const 0)
+              (i32.const 1)
+              (i32.const 16))
+          (then (call $__wasi_proc_exit (i32.const 1))))
+        (br_if $done (i32.eqz (i32.load (i32.const 16))))
+        ;; Write back whatever was read.
+        (i32.store (i32.const 4) (i32.load (i32.const 16)))
+        (if (call $__wasi_fd_write
+              (i32.const 1)
+              (i32.const 0)
+              (i32.const 1)
+              (i32.const 16))
+          (then (call $__wasi_proc_exit (i32.const 1))))
+        (br $copy)
+      )
+    )
+  )
+  (memory 1)
+  (export "memory" (memory 0))
+  (export "_start" (func $_start))
+)
@@ -0,0 +1,5 @@
+(module
+    (func (export "scale") (param f32 f64) (result f64)
+        (f64.mul (f64.promote_f32 (local.get 0)) (local.get 1))
+    )
+)
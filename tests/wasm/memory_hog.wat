@@ -0,0 +1,10 @@
+(module
+    (memory 1)
+    (func (export "_start")
+        ;; Grow a page at a time for as long as growing succeeds.
+        (loop $grow
+            (br_if $grow
+                (i32.ne (memory.grow (i32.const 1)) (i32.const -1))))
+        unreachable
+    )
+)
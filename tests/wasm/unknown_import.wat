@@ -0,0 +1,5 @@
+(module
+  (import "env" "missing" (func $missing))
+  (func (export "run") (result i32) i32.const 42)
+  (func (export "call_missing") call $missing)
+)